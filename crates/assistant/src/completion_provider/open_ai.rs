@@ -300,6 +300,8 @@ impl AuthenticationPrompt {
             font_features: settings.ui_font.features.clone(),
             font_size: rems(0.875).into(),
             font_weight: settings.ui_font.weight,
+            font_stretch: settings.ui_font.stretch,
+            font_simulations: settings.ui_font.simulations,
             font_style: FontStyle::Normal,
             line_height: relative(1.3),
             background_color: None,
@@ -23,7 +23,15 @@ pub fn init(app_state: &Arc<AppState>, cx: &mut AppContext) {
                     .log_err();
             }
 
+            #[cfg(target_os = "windows")]
+            cx.update(|cx| workspace::notifications::system_notifications::forget(TOAST_ID, cx))
+                .log_err();
+
             if let Some(incoming_call) = incoming_call {
+                #[cfg(target_os = "windows")]
+                cx.update(|cx| show_toast(&incoming_call, app_state.clone(), cx))
+                    .log_err();
+
                 let unique_screens = cx.update(|cx| cx.displays()).unwrap();
                 let window_size = gpui::Size {
                     width: px(400.),
@@ -54,6 +62,35 @@ pub fn init(app_state: &Arc<AppState>, cx: &mut AppContext) {
     .detach();
 }
 
+#[cfg(target_os = "windows")]
+const TOAST_ID: &str = "incoming-call";
+
+/// Also shows the call through the system, which gets noticed while Zed is in the background.
+#[cfg(target_os = "windows")]
+fn show_toast(call: &IncomingCall, app_state: Weak<AppState>, cx: &mut AppContext) {
+    if cx.is_app_active() {
+        return;
+    }
+    let notification = gpui::SystemNotification {
+        id: TOAST_ID.into(),
+        title: format!("{} is calling you", call.calling_user.github_login).into(),
+        body: Default::default(),
+        actions: vec!["Accept".into(), "Decline".into()],
+    };
+    let state = IncomingCallNotificationState::new(call.clone(), app_state);
+    workspace::notifications::system_notifications::show(
+        notification,
+        move |action, cx| match action {
+            Some(0) => state.respond(true, cx),
+            Some(_) => state.respond(false, cx),
+            // clicking the toast brings Zed forward, where the call can be answered
+            None => cx.activate(true),
+        },
+        cx,
+    )
+    .log_err();
+}
+
 struct IncomingCallNotificationState {
     call: IncomingCall,
     app_state: Weak<AppState>,
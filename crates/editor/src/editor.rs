@@ -55,6 +55,8 @@ use debounced_delay::DebouncedDelay;
 use display_map::*;
 pub use display_map::{DisplayPoint, FoldPlaceholder};
 pub use editor_settings::{CurrentLineHighlight, EditorSettings};
+#[cfg(target_os = "windows")]
+pub use mouse_context_menu::refresh_default_browser;
 use element::LineWithInvisibles;
 pub use element::{
     CursorLayout, EditorElement, HighlightedRange, HighlightedRangeLine, PointForPosition,
@@ -263,6 +265,8 @@ pub fn init_settings(cx: &mut AppContext) {
 
 pub fn init(cx: &mut AppContext) {
     init_settings(cx);
+    #[cfg(target_os = "windows")]
+    refresh_default_browser(cx);
 
     workspace::register_project_item::<Editor>(cx);
     workspace::register_followable_item::<Editor>(cx);
@@ -12116,6 +12120,8 @@ impl Render for Editor {
                 font_features: settings.ui_font.features.clone(),
                 font_size: rems(0.875).into(),
                 font_weight: settings.ui_font.weight,
+                font_stretch: settings.ui_font.stretch,
+                font_simulations: settings.ui_font.simulations,
                 font_style: FontStyle::Normal,
                 line_height: relative(settings.buffer_line_height.value()),
                 background_color: None,
@@ -12129,6 +12135,8 @@ impl Render for Editor {
                 font_features: settings.buffer_font.features.clone(),
                 font_size: settings.buffer_font_size(cx).into(),
                 font_weight: settings.buffer_font.weight,
+                font_stretch: settings.buffer_font.stretch,
+                font_simulations: settings.buffer_font.simulations,
                 font_style: FontStyle::Normal,
                 line_height: relative(settings.buffer_line_height.value()),
                 background_color: None,
@@ -12150,7 +12158,7 @@ impl Render for Editor {
                 background,
                 local_player: cx.theme().players().local(),
                 text: text_style,
-                scrollbar_width: EditorElement::SCROLLBAR_WIDTH,
+                scrollbar_width: EditorElement::scrollbar_width(cx),
                 syntax: cx.theme().syntax().clone(),
                 status: cx.theme().status().clone(),
                 inlay_hints_style: HighlightStyle {
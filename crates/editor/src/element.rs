@@ -17,7 +17,7 @@ use crate::{
     hunk_status,
     items::BufferSearchHighlights,
     mouse_context_menu::{self, MouseContextMenu},
-    scroll::scroll_amount::ScrollAmount,
+    scroll::{scroll_amount::ScrollAmount, ScrollbarAutoHide},
     CodeActionsMenu, CursorShape, DisplayPoint, DisplayRow, DocumentHighlightRead,
     DocumentHighlightWrite, Editor, EditorMode, EditorSettings, EditorSnapshot, EditorStyle,
     ExpandExcerpts, GutterDimensions, HalfPageDown, HalfPageUp, HoveredCursor, HunkToExpand,
@@ -29,13 +29,13 @@ use collections::{BTreeMap, HashMap};
 use git::{blame::BlameEntry, diff::DiffHunkStatus, Oid};
 use gpui::{
     anchored, deferred, div, fill, outline, point, px, quad, relative, size, svg,
-    transparent_black, Action, AnchorCorner, AnyElement, AvailableSpace, Bounds, ClipboardItem,
-    ContentMask, Corners, CursorStyle, DispatchPhase, Edges, Element, ElementInputHandler, Entity,
-    FontId, GlobalElementId, Hitbox, Hsla, InteractiveElement, IntoElement, Length,
-    ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad,
-    ParentElement, Pixels, ScrollDelta, ScrollWheelEvent, ShapedLine, SharedString, Size,
-    StatefulInteractiveElement, Style, Styled, TextRun, TextStyle, TextStyleRefinement, View,
-    ViewContext, WeakView, WindowContext,
+    transparent_black, Action, AnchorCorner, AnyElement, AppContext, AvailableSpace, Bounds,
+    ClipboardItem, ContentMask, Corners, CursorStyle, DispatchPhase, Edges, Element,
+    ElementInputHandler, Entity, FontId, GlobalElementId, Hitbox, Hsla, InteractiveElement,
+    IntoElement, Length, ModifiersChangedEvent, MouseButton, MouseDeltaEvent, MouseDownEvent,
+    MouseMoveEvent, MouseUpEvent, PaintQuad, ParentElement, Pixels, ScrollDelta, ScrollWheelEvent,
+    ShapedLine, SharedString, Size, StatefulInteractiveElement, Style, Styled, TextRun, TextStyle,
+    TextStyleRefinement, View, ViewContext, WeakView, WindowContext,
 };
 use itertools::Itertools;
 use language::language_settings::{
@@ -144,6 +144,18 @@ type DisplayRowDelta = u32;
 impl EditorElement {
     pub(crate) const SCROLLBAR_WIDTH: Pixels = px(13.);
 
+    /// Overlay scrollbars keep the editor's own width, classic ones follow the system metrics.
+    pub(crate) fn scrollbar_width(cx: &AppContext) -> Pixels {
+        let auto_hide = cx
+            .try_global::<ScrollbarAutoHide>()
+            .map_or_else(|| cx.should_auto_hide_scrollbars(), |auto_hide| auto_hide.0);
+        if auto_hide {
+            Self::SCROLLBAR_WIDTH
+        } else {
+            cx.system_scrollbar_width().unwrap_or(Self::SCROLLBAR_WIDTH)
+        }
+    }
+
     pub fn new(editor: &View<Editor>, style: EditorStyle) -> Self {
         Self {
             editor: editor.clone(),
@@ -3306,6 +3318,29 @@ impl EditorElement {
             }
         });
 
+        // the cursor stops at the edges of the screen, past which the relative movement of the
+        // mouse keeps the thumb going
+        cx.on_mouse_event({
+            let editor = self.editor.clone();
+            move |event: &MouseDeltaEvent, phase, cx| {
+                if phase == DispatchPhase::Capture
+                    || event.pressed_button != Some(MouseButton::Left)
+                    || !is_mouse_pinned_vertically(event.delta, cx)
+                {
+                    return;
+                }
+
+                editor.update(cx, |editor, cx| {
+                    if editor.scroll_manager.is_dragging_scrollbar() {
+                        let mut position = editor.scroll_position(cx);
+                        position.y = (position.y + event.delta.y / row_height).max(0.0);
+                        editor.set_scroll_position(position, cx);
+                        cx.stop_propagation();
+                    }
+                })
+            }
+        });
+
         if self.editor.read(cx).scroll_manager.is_dragging_scrollbar() {
             cx.on_mouse_event({
                 let editor = self.editor.clone();
@@ -3563,18 +3598,27 @@ impl EditorElement {
                     .map(|row| {
                         let line_layout =
                             &layout.position_map.line_layouts[row.minus(start_row) as usize];
+                        let line_start = if row == range.start.row() {
+                            range.start.column() as usize
+                        } else {
+                            0
+                        };
+                        let line_end = if row == range.end.row() {
+                            range.end.column() as usize
+                        } else {
+                            line_layout.len
+                        };
+                        let selection_x = line_layout.selection_x_range(line_start..line_end);
                         HighlightedRangeLine {
                             start_x: if row == range.start.row() {
-                                layout.content_origin.x
-                                    + line_layout.x_for_index(range.start.column() as usize)
+                                layout.content_origin.x + selection_x.start
                                     - layout.position_map.scroll_pixel_position.x
                             } else {
                                 layout.content_origin.x
                                     - layout.position_map.scroll_pixel_position.x
                             },
                             end_x: if row == range.end.row() {
-                                layout.content_origin.x
-                                    + line_layout.x_for_index(range.end.column() as usize)
+                                layout.content_origin.x + selection_x.end
                                     - layout.position_map.scroll_pixel_position.x
                             } else {
                                 layout.content_origin.x + line_layout.width + line_end_overshoot
@@ -4264,8 +4308,14 @@ impl LineWithInvisibles {
             };
 
             let x_offset = self.x_for_index(token_offset);
-            let invisible_offset =
-                (layout.position_map.em_width - invisible_symbol.width).max(Pixels::ZERO) / 2.0;
+            // Whitespace is centered on the space the shaped glyph takes up, which isn't
+            // necessarily an em wide. Tabs are expanded to several columns and keep their
+            // symbol at the start.
+            let token_width = match invisible {
+                Invisible::Tab { .. } => layout.position_map.em_width,
+                Invisible::Whitespace { .. } => self.x_for_index(token_end_offset) - x_offset,
+            };
+            let invisible_offset = (token_width - invisible_symbol.width).max(Pixels::ZERO) / 2.0;
             let origin = content_origin
                 + gpui::point(
                     x_offset + invisible_offset - layout.position_map.scroll_pixel_position.x,
@@ -4370,6 +4420,52 @@ impl LineWithInvisibles {
         fragment_start_x
     }
 
+    /// Returns where a highlight of the given range starts and ends on this line. The edges come
+    /// from the selection boxes of the text runs, so highlights don't leave a gap where a run
+    /// switches to an italic or fallback font.
+    pub fn selection_x_range(&self, range: Range<usize>) -> Range<Pixels> {
+        let mut selection_x: Option<Range<Pixels>> = None;
+        let mut extend = |x: Range<Pixels>| {
+            selection_x = Some(match selection_x.take() {
+                Some(selection_x) => selection_x.start..x.end,
+                None => x,
+            });
+        };
+        let mut fragment_start_x = Pixels::ZERO;
+        let mut fragment_start_index = 0;
+
+        for fragment in &self.fragments {
+            match fragment {
+                LineFragment::Text(shaped_line) => {
+                    let fragment_end_index = fragment_start_index + shaped_line.len;
+                    let start = range.start.max(fragment_start_index) - fragment_start_index;
+                    let end = range
+                        .end
+                        .min(fragment_end_index)
+                        .saturating_sub(fragment_start_index);
+                    for selection_box in shaped_line.selection_boxes(start..end) {
+                        extend(
+                            fragment_start_x + selection_box.x.start
+                                ..fragment_start_x + selection_box.x.end,
+                        );
+                    }
+                    fragment_start_x += shaped_line.width;
+                    fragment_start_index = fragment_end_index;
+                }
+                LineFragment::Element { len, size, .. } => {
+                    let fragment_end_index = fragment_start_index + len;
+                    if range.start < fragment_end_index && fragment_start_index < range.end {
+                        extend(fragment_start_x..fragment_start_x + size.width);
+                    }
+                    fragment_start_x += size.width;
+                    fragment_start_index = fragment_end_index;
+                }
+            }
+        }
+
+        selection_x.unwrap_or_else(|| self.x_for_index(range.start)..self.x_for_index(range.end))
+    }
+
     pub fn index_for_x(&self, x: Pixels) -> Option<usize> {
         let mut fragment_start_x = Pixels::ZERO;
         let mut fragment_start_index = 0;
@@ -4619,7 +4715,7 @@ impl Element for EditorElement {
                     let text_width = bounds.size.width - gutter_dimensions.width;
 
                     let right_margin = if snapshot.mode == EditorMode::Full {
-                        EditorElement::SCROLLBAR_WIDTH
+                        self.style.scrollbar_width
                     } else {
                         px(0.)
                     };
@@ -5785,6 +5881,27 @@ fn scale_horizontal_mouse_autoscroll_delta(delta: Pixels) -> f32 {
     (delta.pow(1.2) / 300.0).into()
 }
 
+/// Whether the mouse moved up or down while the cursor couldn't follow, being at the top or
+/// bottom edge of the display.
+fn is_mouse_pinned_vertically(delta: gpui::Point<Pixels>, cx: &WindowContext) -> bool {
+    let Some(display) = cx.display() else {
+        return false;
+    };
+    let cursor_y = cx.bounds().origin.y + cx.mouse_position().y;
+    is_pinned_between(
+        cursor_y,
+        delta.y,
+        display.bounds().top(),
+        display.bounds().bottom(),
+    )
+}
+
+fn is_pinned_between(position: Pixels, delta: Pixels, start: Pixels, end: Pixels) -> bool {
+    // the cursor can't reach the end itself, which is past the last pixel
+    (delta < Pixels::ZERO && position <= start)
+        || (delta > Pixels::ZERO && position >= end - px(1.))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -6264,6 +6381,17 @@ mod tests {
             .cloned()
             .collect()
     }
+
+    #[test]
+    fn test_is_pinned_between() {
+        let (start, end) = (px(0.), px(1080.));
+        assert!(is_pinned_between(px(1079.), px(3.), start, end));
+        assert!(is_pinned_between(px(0.), px(-3.), start, end));
+        // moving away from the edge moves the cursor too
+        assert!(!is_pinned_between(px(1079.), px(-3.), start, end));
+        assert!(!is_pinned_between(px(0.), px(3.), start, end));
+        assert!(!is_pinned_between(px(500.), px(3.), start, end));
+    }
 }
 
 pub fn register_action<T: Action>(
@@ -1,11 +1,42 @@
 use crate::{
     Copy, CopyPermalinkToLine, Cut, DisplayPoint, Editor, EditorMode, FindAllReferences,
-    GoToDefinition, GoToImplementation, GoToTypeDefinition, Paste, Rename, RevealInFinder,
-    SelectMode, ToggleCodeActions,
+    GoToDefinition, GoToImplementation, GoToTypeDefinition, OpenPermalinkToLine, Paste, Rename,
+    RevealInFinder, SelectMode, ToggleCodeActions,
+};
+use gpui::{
+    prelude::FluentBuilder, AppContext, DismissEvent, Pixels, Point, Subscription, View,
+    ViewContext,
 };
-use gpui::{DismissEvent, Pixels, Point, Subscription, View, ViewContext};
 use workspace::OpenInTerminal;
 
+/// The name of the user's default browser, which permalinks are opened in.
+#[cfg(target_os = "windows")]
+struct DefaultBrowser(Option<gpui::SharedString>);
+
+#[cfg(target_os = "windows")]
+impl gpui::Global for DefaultBrowser {}
+
+/// Looks up the default browser again, such as after the user picked a different one.
+#[cfg(target_os = "windows")]
+pub fn refresh_default_browser(cx: &mut AppContext) {
+    let name = cx
+        .default_handler(gpui::DefaultHandlerKind::Https)
+        .map(|handler| handler.name);
+    cx.set_global(DefaultBrowser(name));
+}
+
+#[cfg(target_os = "windows")]
+fn open_permalink_label(cx: &AppContext) -> Option<String> {
+    let name = cx.try_global::<DefaultBrowser>()?.0.as_ref()?;
+    Some(format!("Open Permalink in {}", name))
+}
+
+// other platforms can't tell which browser the permalink would open in
+#[cfg(not(target_os = "windows"))]
+fn open_permalink_label(_: &AppContext) -> Option<String> {
+    None
+}
+
 pub struct MouseContextMenu {
     pub(crate) position: Point<Pixels>,
     pub(crate) context_menu: View<ui::ContextMenu>,
@@ -72,6 +103,7 @@ pub fn deploy_context_menu(
         });
 
         let focus = cx.focused();
+        let open_permalink_label = open_permalink_label(cx);
         ui::ContextMenu::build(cx, |menu, _cx| {
             let builder = menu
                 .action("Rename Symbol", Box::new(Rename))
@@ -92,7 +124,10 @@ pub fn deploy_context_menu(
                 .separator()
                 .action("Reveal in Finder", Box::new(RevealInFinder))
                 .action("Open in Terminal", Box::new(OpenInTerminal))
-                .action("Copy Permalink", Box::new(CopyPermalinkToLine));
+                .action("Copy Permalink", Box::new(CopyPermalinkToLine))
+                .when_some(open_permalink_label, |menu, label| {
+                    menu.action(label, Box::new(OpenPermalinkToLine))
+                });
             match focus {
                 Some(focus) => builder.context(focus),
                 None => builder,
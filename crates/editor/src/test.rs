@@ -28,7 +28,9 @@ pub fn marked_display_snapshot(
         family: "Zed Plex Mono".into(),
         features: FontFeatures::default(),
         weight: FontWeight::default(),
+        stretch: Default::default(),
         style: FontStyle::default(),
+        simulations: Default::default(),
     };
     let font_size: Pixels = 14usize.into();
 
@@ -1,5 +1,5 @@
 use client::telemetry;
-use gpui::{AppContext, Task};
+use gpui::{Task, WindowContext};
 use human_bytes::human_bytes;
 use release_channel::{AppCommitSha, AppVersion, ReleaseChannel};
 use serde::Serialize;
@@ -15,10 +15,11 @@ pub struct SystemSpecs {
     memory: u64,
     architecture: &'static str,
     commit_sha: Option<String>,
+    render_target: Option<String>,
 }
 
 impl SystemSpecs {
-    pub fn new(cx: &AppContext) -> Task<Self> {
+    pub fn new(cx: &WindowContext) -> Task<Self> {
         let app_version = AppVersion::global(cx).to_string();
         let release_channel = ReleaseChannel::global(cx);
         let os_name = telemetry::os_name();
@@ -33,6 +34,16 @@ impl SystemSpecs {
             }
             _ => None,
         };
+        let render_target = cx.render_target_info().map(|info| {
+            let mut description = format!("{} surface", info.surface_format);
+            if let Some(color_depth) = info.display_color_depth {
+                description.push_str(&format!(", {}-bit display", color_depth));
+            }
+            if info.dithering {
+                description.push_str(", dithered");
+            }
+            description
+        });
 
         cx.background_executor().spawn(async move {
             let os_version = telemetry::os_version();
@@ -44,6 +55,7 @@ impl SystemSpecs {
                 memory,
                 architecture,
                 commit_sha,
+                render_target,
             }
         })
     }
@@ -61,12 +73,16 @@ impl Display for SystemSpecs {
             }
         );
         let system_specs = [
-            app_version_information,
-            os_information,
-            format!("Memory: {}", human_bytes(self.memory as f64)),
-            format!("Architecture: {}", self.architecture),
+            Some(app_version_information),
+            Some(os_information),
+            Some(format!("Memory: {}", human_bytes(self.memory as f64))),
+            Some(format!("Architecture: {}", self.architecture)),
+            self.render_target
+                .as_ref()
+                .map(|render_target| format!("Renderer: {}", render_target)),
         ]
         .into_iter()
+        .flatten()
         .collect::<Vec<String>>()
         .join("\n");
 
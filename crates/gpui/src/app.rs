@@ -11,7 +11,11 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use derive_more::{Deref, DerefMut};
-use futures::{channel::oneshot, future::LocalBoxFuture, Future};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::LocalBoxFuture,
+    Future, StreamExt,
+};
 use slotmap::SlotMap;
 use smol::future::FutureExt;
 use time::UtcOffset;
@@ -28,11 +32,12 @@ use util::ResultExt;
 use crate::{
     current_platform, init_app_menus, Action, ActionRegistry, Any, AnyView, AnyWindowHandle,
     AssetCache, AssetSource, BackgroundExecutor, ClipboardItem, Context, DispatchPhase, DisplayId,
-    Entity, EventEmitter, ForegroundExecutor, Global, KeyBinding, Keymap, Keystroke, LayoutId,
-    Menu, MenuItem, OwnedMenu, PathPromptOptions, Pixels, Platform, PlatformDisplay, Point,
-    PromptBuilder, PromptHandle, PromptLevel, Render, RenderablePromptHandle, Reservation,
-    SharedString, SubscriberSet, Subscription, SvgRenderer, Task, TextSystem, View, ViewContext,
-    Window, WindowAppearance, WindowContext, WindowHandle, WindowId,
+    Entity, EventEmitter, ForegroundExecutor, Global, KeyBinding, KeyBindingResolution, KeyContext,
+    KeyboardMapper, Keymap, Keystroke, LayoutId, Menu, MenuItem, OwnedMenu, PathPromptFilter,
+    PathPromptOptions, Pixels, Platform, PlatformDisplay, Point, PromptBuilder, PromptHandle,
+    PromptLevel, Render, RenderablePromptHandle, Reservation, SharedString, SubscriberSet,
+    Subscription, SvgRenderer, SystemAccentColor, Task, TextSystem, View, ViewContext, Window,
+    WindowAppearance, WindowContext, WindowHandle, WindowId,
 };
 
 mod async_context;
@@ -168,6 +173,101 @@ impl App {
         self
     }
 
+    /// Invokes a handler when the secure desktop (UAC prompts, the ctrl+alt+del screen or the
+    /// lock screen) is shown or dismissed. Rendering is paused while it is active.
+    #[cfg(target_os = "windows")]
+    pub fn on_secure_desktop_change<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(bool, &mut AppContext),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_secure_desktop_change(Box::new(move |active| {
+                if let Some(app) = this.upgrade() {
+                    callback(active, &mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
+    /// Invokes a handler when the user locks or unlocks the workstation, which is only
+    /// reported while at least one window is open.
+    #[cfg(target_os = "windows")]
+    pub fn on_session_lock_change<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(bool, &mut AppContext),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_session_lock_change(Box::new(move |locked| {
+                if let Some(app) = this.upgrade() {
+                    callback(locked, &mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
+    /// Invokes a handler when the user clicks the icon added with [`AppContext::set_tray_icon`].
+    #[cfg(target_os = "windows")]
+    pub fn on_tray_icon_click<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(&mut AppContext),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_tray_icon_click(Box::new(move || {
+                if let Some(app) = this.upgrade() {
+                    callback(&mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
+    /// Invokes a handler when the user clicks a notification shown with
+    /// [`AppContext::show_notification`], with its id and the index of the button that was
+    /// clicked, if any.
+    #[cfg(target_os = "windows")]
+    pub fn on_notification_activated<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(SharedString, Option<usize>, &mut AppContext),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_notification_activated(Box::new(move |id, action| {
+                if let Some(app) = this.upgrade() {
+                    callback(id, action, &mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
+    /// Invokes a handler when the user picks a different default browser or terminal.
+    /// Use [`AppContext::default_handler`] to look up the new defaults.
+    #[cfg(target_os = "windows")]
+    pub fn on_default_handlers_change<F>(&self, mut callback: F) -> &Self
+    where
+        F: 'static + FnMut(&mut AppContext),
+    {
+        let this = Rc::downgrade(&self.0);
+        self.0
+            .borrow_mut()
+            .platform
+            .on_default_handlers_change(Box::new(move || {
+                if let Some(app) = this.upgrade() {
+                    callback(&mut app.borrow_mut());
+                }
+            }));
+        self
+    }
+
     /// Returns a handle to the [`BackgroundExecutor`] associated with this app, which can be used to spawn futures in the background.
     pub fn background_executor(&self) -> BackgroundExecutor {
         self.0.borrow().background_executor.clone()
@@ -187,11 +287,22 @@ impl App {
     pub fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf> {
         self.0.borrow().path_for_auxiliary_executable(name)
     }
+
+    /// Returns whether loopback ports of other processes can be connected to, which
+    /// AppContainers don't allow unless they're exempted from loopback isolation.
+    #[cfg(target_os = "windows")]
+    pub fn is_loopback_available(&self) -> bool {
+        self.0.borrow().platform.is_loopback_available()
+    }
 }
 
 type Handler = Box<dyn FnMut(&mut AppContext) -> bool + 'static>;
 type Listener = Box<dyn FnMut(&dyn Any, &mut AppContext) -> bool + 'static>;
 type KeystrokeObserver = Box<dyn FnMut(&KeystrokeEvent, &mut WindowContext) + 'static>;
+type AppActiveObserver = Box<dyn FnMut(bool, &mut AppContext) + 'static>;
+type AccentColorObserver = Box<dyn FnMut(&mut AppContext) + 'static>;
+type DisplaysObserver = Box<dyn FnMut(&mut AppContext) + 'static>;
+type KeyboardLayoutObserver = Box<dyn FnMut(&mut AppContext) + 'static>;
 type QuitHandler = Box<dyn FnOnce(&mut AppContext) -> LocalBoxFuture<'static, ()> + 'static>;
 type ReleaseListener = Box<dyn FnOnce(&mut dyn Any, &mut AppContext) + 'static>;
 type NewViewListener = Box<dyn FnMut(AnyView, &mut WindowContext) + 'static>;
@@ -229,9 +340,14 @@ pub struct AppContext {
     // TypeId is the type of the event that the listener callback expects
     pub(crate) event_listeners: SubscriberSet<EntityId, (TypeId, Listener)>,
     pub(crate) keystroke_observers: SubscriberSet<(), KeystrokeObserver>,
+    pub(crate) keystroke_diagnostics: Option<VecDeque<KeystrokeDiagnostic>>,
     pub(crate) release_listeners: SubscriberSet<EntityId, ReleaseListener>,
     pub(crate) global_observers: SubscriberSet<TypeId, Handler>,
     pub(crate) quit_observers: SubscriberSet<(), QuitHandler>,
+    pub(crate) app_active_observers: SubscriberSet<(), AppActiveObserver>,
+    pub(crate) accent_color_observers: SubscriberSet<(), AccentColorObserver>,
+    pub(crate) displays_observers: SubscriberSet<(), DisplaysObserver>,
+    pub(crate) keyboard_layout_observers: SubscriberSet<(), KeyboardLayoutObserver>,
     pub(crate) layout_id_buffer: Vec<LayoutId>, // We recycle this memory across layout requests.
     pub(crate) propagate_event: bool,
     pub(crate) prompt_builder: Option<PromptBuilder>,
@@ -284,8 +400,13 @@ impl AppContext {
                 event_listeners: SubscriberSet::new(),
                 release_listeners: SubscriberSet::new(),
                 keystroke_observers: SubscriberSet::new(),
+                keystroke_diagnostics: None,
                 global_observers: SubscriberSet::new(),
                 quit_observers: SubscriberSet::new(),
+                app_active_observers: SubscriberSet::new(),
+                accent_color_observers: SubscriberSet::new(),
+                displays_observers: SubscriberSet::new(),
+                keyboard_layout_observers: SubscriberSet::new(),
                 layout_id_buffer: Default::default(),
                 propagate_event: true,
                 prompt_builder: Some(PromptBuilder::Default),
@@ -301,6 +422,64 @@ impl AppContext {
             }
         }));
 
+        platform.on_app_active_status_change(Box::new({
+            let cx = Rc::downgrade(&app);
+            move |active| {
+                if let Some(cx) = cx.upgrade() {
+                    cx.borrow_mut().update(|cx| {
+                        cx.app_active_observers.clone().retain(&(), |callback| {
+                            callback(active, cx);
+                            true
+                        });
+                    });
+                }
+            }
+        }));
+
+        platform.on_accent_color_change(Box::new({
+            let cx = Rc::downgrade(&app);
+            move || {
+                if let Some(cx) = cx.upgrade() {
+                    cx.borrow_mut().update(|cx| {
+                        cx.accent_color_observers.clone().retain(&(), |callback| {
+                            callback(cx);
+                            true
+                        });
+                    });
+                }
+            }
+        }));
+
+        platform.on_displays_change(Box::new({
+            let cx = Rc::downgrade(&app);
+            move || {
+                if let Some(cx) = cx.upgrade() {
+                    cx.borrow_mut().update(|cx| {
+                        cx.displays_observers.clone().retain(&(), |callback| {
+                            callback(cx);
+                            true
+                        });
+                    });
+                }
+            }
+        }));
+
+        platform.on_keyboard_layout_change(Box::new({
+            let cx = Rc::downgrade(&app);
+            move || {
+                if let Some(cx) = cx.upgrade() {
+                    cx.borrow_mut().update(|cx| {
+                        cx.keyboard_layout_observers
+                            .clone()
+                            .retain(&(), |callback| {
+                                callback(cx);
+                                true
+                            });
+                    });
+                }
+            }
+        }));
+
         app
     }
 
@@ -338,6 +517,23 @@ impl AppContext {
         self.pending_effects.push_back(Effect::Refresh);
     }
 
+    /// Loads fonts on a background thread the first time they are used, instead of blocking
+    /// the frame that uses them. Text is laid out with a fallback font in the meantime, and
+    /// all windows are refreshed once the font is available.
+    pub fn enable_background_font_loading(&mut self) {
+        let mut font_loaded = self
+            .text_system
+            .load_fonts_in_background(self.background_executor.clone());
+        self.spawn(|mut cx| async move {
+            while font_loaded.next().await.is_some() {
+                if cx.update(|cx| cx.refresh()).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
     pub(crate) fn update<R>(&mut self, update: impl FnOnce(&mut Self) -> R) -> R {
         self.pending_updates += 1;
         let result = update(self);
@@ -459,6 +655,19 @@ impl AppContext {
             .collect()
     }
 
+    /// Returns a handle to the open window with the given ID, if there is one.
+    pub fn window_for_id(&self, id: WindowId) -> Option<AnyWindowHandle> {
+        self.windows
+            .contains_key(id)
+            .then(|| self.window_handles.get(&id).copied())
+            .flatten()
+    }
+
+    /// Returns the IDs of all open windows in the application.
+    pub fn window_ids(&self) -> Vec<WindowId> {
+        self.windows.keys().collect()
+    }
+
     /// Returns a handle to the window that is currently focused at the platform level, if one exists.
     pub fn active_window(&self) -> Option<AnyWindowHandle> {
         self.platform.active_window()
@@ -517,6 +726,14 @@ impl AppContext {
         self.platform.displays()
     }
 
+    /// Register a callback to be invoked when a display is connected or disconnected, or the
+    /// bounds of one change. Use [`AppContext::displays`] to look up the new arrangement.
+    pub fn observe_displays(&mut self, f: impl FnMut(&mut AppContext) + 'static) -> Subscription {
+        let (subscription, activate) = self.displays_observers.insert((), Box::new(f));
+        activate();
+        subscription
+    }
+
     /// Returns the primary display that will be used for new windows.
     pub fn primary_display(&self) -> Option<Rc<dyn PlatformDisplay>> {
         self.platform.primary_display()
@@ -612,19 +829,30 @@ impl AppContext {
     /// Displays a platform modal for selecting paths.
     /// When one or more paths are selected, they'll be relayed asynchronously via the returned oneshot channel.
     /// If cancelled, a `None` will be relayed instead.
+    /// When `filters` aren't empty, only the files matching one of them are listed, and the user
+    /// can switch between them.
     pub fn prompt_for_paths(
         &self,
         options: PathPromptOptions,
+        filters: Vec<PathPromptFilter>,
     ) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
-        self.platform.prompt_for_paths(options)
+        self.platform.prompt_for_paths(options, filters)
     }
 
     /// Displays a platform modal for selecting a new path where a file can be saved.
-    /// The provided directory will be used to set the initial location.
+    /// The provided directory will be used to set the initial location, and the suggested name
+    /// to fill in the file name. On Windows, names typed without an extension get the first
+    /// extension of the first filter.
     /// When a path is selected, it is relayed asynchronously via the returned oneshot channel.
     /// If cancelled, a `None` will be relayed instead.
-    pub fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
-        self.platform.prompt_for_new_path(directory)
+    pub fn prompt_for_new_path(
+        &self,
+        directory: &Path,
+        suggested_name: Option<&str>,
+        filters: Vec<PathPromptFilter>,
+    ) -> oneshot::Receiver<Option<PathBuf>> {
+        self.platform
+            .prompt_for_new_path(directory, suggested_name, filters)
     }
 
     /// Reveals the specified path at the platform level, such as in Finder on macOS.
@@ -632,11 +860,138 @@ impl AppContext {
         self.platform.reveal_path(path)
     }
 
+    /// Returns whether the secure desktop (UAC prompts, the ctrl+alt+del screen or the lock
+    /// screen) is currently shown.
+    #[cfg(target_os = "windows")]
+    pub fn is_secure_desktop_active(&self) -> bool {
+        self.platform.is_secure_desktop_active()
+    }
+
+    /// Returns whether the workstation is locked.
+    #[cfg(target_os = "windows")]
+    pub fn is_session_locked(&self) -> bool {
+        self.platform.is_session_locked()
+    }
+
+    /// Replaces the entries shown when right-clicking the app's taskbar button.
+    #[cfg(target_os = "windows")]
+    pub fn set_jump_list(&self, jump_list: crate::JumpList) {
+        self.platform.set_jump_list(jump_list);
+    }
+
+    /// Shows an icon for the app in the notification area of the taskbar, or updates it.
+    /// Right-clicking it shows the menu, whose actions are dispatched like the ones of the app
+    /// menus. While the icon is shown, closing the last window doesn't quit the app.
+    #[cfg(target_os = "windows")]
+    pub fn set_tray_icon(&self, tooltip: &str, menu: Vec<MenuItem>) {
+        self.platform.set_tray_icon(tooltip, menu);
+    }
+
+    /// Removes the icon added with [`AppContext::set_tray_icon`]. The app quits when it has no
+    /// windows left, as it was only kept running by the icon.
+    #[cfg(target_os = "windows")]
+    pub fn remove_tray_icon(&self) {
+        self.platform.remove_tray_icon();
+    }
+
+    /// Sets the AppUserModelID of the process, which the taskbar groups its windows by, before
+    /// any window is opened. Notifications are shown under this ID, so it has to be the one the
+    /// installer gave the app's Start menu shortcut.
+    #[cfg(target_os = "windows")]
+    pub fn set_app_user_model_id(&self, id: &str) {
+        self.platform.set_app_user_model_id(id);
+    }
+
+    /// Shows a notification through the system, which is listed in its notification center
+    /// until it's dismissed. Fails when no AppUserModelID was set, in which case the app should
+    /// notify the user some other way.
+    #[cfg(target_os = "windows")]
+    pub fn show_notification(&self, notification: crate::SystemNotification) -> Result<()> {
+        self.platform.show_notification(notification)
+    }
+
+    /// Returns the icon the shell displays for files of the same type as the given path.
+    #[cfg(target_os = "windows")]
+    pub fn file_type_icon(&self, path: &Path) -> Option<Arc<crate::ImageData>> {
+        crate::file_type_icon(path)
+    }
+
+    /// Returns the name and icon of the application the user picked to handle the given kind
+    /// of target, such as their default browser.
+    #[cfg(target_os = "windows")]
+    pub fn default_handler(
+        &self,
+        kind: crate::DefaultHandlerKind,
+    ) -> Option<crate::DefaultHandler> {
+        self.platform.default_handler(kind)
+    }
+
+    /// Returns the platform features that are turned off because the app runs in a restricted
+    /// sandbox, such as an AppContainer, so they can be included in diagnostics.
+    #[cfg(target_os = "windows")]
+    pub fn degraded_platform_features(&self) -> Vec<crate::DegradedFeature> {
+        self.platform.degraded_features()
+    }
+
+    /// Returns how often the system timer resolution has been raised to keep short timers,
+    /// like the ones driving animations, on time.
+    #[cfg(target_os = "windows")]
+    pub fn timer_resolution_statistics(&self) -> crate::TimerResolutionStatistics {
+        self.platform.timer_resolution_statistics()
+    }
+
+    /// Creates a cursor from images drawn for different scale factors, which can then be
+    /// shown with [`CursorStyle::Custom`].
+    pub fn register_custom_cursor(
+        &self,
+        images: Vec<crate::CustomCursorImage>,
+    ) -> Result<crate::CustomCursorId> {
+        self.platform.register_custom_cursor(images)
+    }
+
+    /// Returns a [`KeyboardMapper`] for the keyboard layout that is currently active.
+    pub fn keyboard_mapper(&self) -> Box<dyn KeyboardMapper> {
+        self.platform.keyboard_mapper()
+    }
+
+    /// Register a callback to be invoked when the user switches to another keyboard layout.
+    /// Bindings to physical keys are resolved when they're bound, so they have to be bound
+    /// again to follow the new layout.
+    pub fn observe_keyboard_layout(
+        &mut self,
+        f: impl FnMut(&mut AppContext) + 'static,
+    ) -> Subscription {
+        let (subscription, activate) = self.keyboard_layout_observers.insert((), Box::new(f));
+        activate();
+        subscription
+    }
+
+    /// Returns every registered binding that can't be typed as written on the keyboard
+    /// layout that is currently active, along with how it resolves.
+    pub fn unresolved_key_bindings(&self) -> Vec<(KeyBinding, KeyBindingResolution)> {
+        let keyboard_mapper = self.keyboard_mapper();
+        self.keymap
+            .borrow()
+            .bindings()
+            .filter_map(|binding| {
+                let resolution = binding.resolution(keyboard_mapper.as_ref());
+                (resolution != KeyBindingResolution::Resolvable)
+                    .then(|| (binding.clone(), resolution))
+            })
+            .collect()
+    }
+
     /// Returns whether the user has configured scrollbars to auto-hide at the platform level.
     pub fn should_auto_hide_scrollbars(&self) -> bool {
         self.platform.should_auto_hide_scrollbars()
     }
 
+    /// Returns the width of the platform's classic scrollbars, as configured in the system
+    /// settings. Overlay scrollbars, see [`Self::should_auto_hide_scrollbars`], are usually thinner.
+    pub fn system_scrollbar_width(&self) -> Option<Pixels> {
+        self.platform.scrollbar_width()
+    }
+
     /// Restart the application.
     pub fn restart(&self, binary_path: Option<PathBuf>) {
         self.platform.restart(binary_path)
@@ -848,6 +1203,39 @@ impl AppContext {
         self.foreground_executor.spawn(f(self.to_async()))
     }
 
+    /// Creates a channel that can be sent to from any thread. Its messages are passed to
+    /// `handler` on the main thread, one at a time and in the order they were sent. They
+    /// stop being handled once the returned task is dropped or the app quits.
+    pub fn foreground_channel<T: 'static>(
+        &self,
+        mut handler: impl FnMut(T, &mut AppContext) + 'static,
+    ) -> (mpsc::UnboundedSender<T>, Task<()>) {
+        let (tx, mut rx) = mpsc::unbounded();
+        let task = self.spawn(|mut cx| async move {
+            while let Some(message) = rx.next().await {
+                if cx.update(|cx| handler(message, cx)).is_err() {
+                    break;
+                }
+            }
+        });
+        (tx, task)
+    }
+
+    /// Like [`Self::foreground_channel`], but the messages are handled on the thread pool.
+    /// They are still handled one at a time and in order, though not always on the same thread.
+    pub fn background_channel<T: Send + 'static>(
+        &self,
+        mut handler: impl FnMut(T) + Send + 'static,
+    ) -> (mpsc::UnboundedSender<T>, Task<()>) {
+        let (tx, mut rx) = mpsc::unbounded();
+        let task = self.background_executor.spawn(async move {
+            while let Some(message) = rx.next().await {
+                handler(message);
+            }
+        });
+        (tx, task)
+    }
+
     /// Schedules the given function to be run at the end of the current effect cycle, allowing entities
     /// that are currently on the stack to be returned to the app.
     pub fn defer(&mut self, f: impl FnOnce(&mut AppContext) + 'static) {
@@ -1038,6 +1426,74 @@ impl AppContext {
         inner(&mut self.keystroke_observers, Box::new(f))
     }
 
+    /// Start or stop recording how each keystroke is dispatched. While enabled, the
+    /// most recent keystrokes can be inspected with [`Self::keystroke_diagnostics`].
+    pub fn set_keystroke_diagnostics_enabled(&mut self, enabled: bool) {
+        if enabled {
+            self.keystroke_diagnostics.get_or_insert_with(VecDeque::new);
+        } else {
+            self.keystroke_diagnostics = None;
+        }
+    }
+
+    /// Returns whether keystroke diagnostics are being recorded.
+    pub fn keystroke_diagnostics_enabled(&self) -> bool {
+        self.keystroke_diagnostics.is_some()
+    }
+
+    /// Returns how the most recent keystrokes were dispatched, oldest first.
+    pub fn keystroke_diagnostics(&self) -> Vec<KeystrokeDiagnostic> {
+        self.keystroke_diagnostics
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn record_keystroke_diagnostic(&mut self, diagnostic: KeystrokeDiagnostic) {
+        const MAX_KEYSTROKE_DIAGNOSTICS: usize = 200;
+
+        if let Some(diagnostics) = self.keystroke_diagnostics.as_mut() {
+            if diagnostics.len() == MAX_KEYSTROKE_DIAGNOSTICS {
+                diagnostics.pop_front();
+            }
+            diagnostics.push_back(diagnostic);
+        }
+    }
+
+    /// Returns whether one of the application's windows is the foreground window.
+    pub fn is_app_active(&self) -> bool {
+        self.platform.is_app_active()
+    }
+
+    /// Register a callback to be invoked when the application becomes active or inactive as a
+    /// whole. Unlike [`ViewContext::observe_window_activation`], this isn't invoked when the
+    /// focus moves between the application's own windows.
+    pub fn observe_app_active_status(
+        &mut self,
+        f: impl FnMut(bool, &mut AppContext) + 'static,
+    ) -> Subscription {
+        let (subscription, activate) = self.app_active_observers.insert((), Box::new(f));
+        activate();
+        subscription
+    }
+
+    /// Returns the accent color of the operating system, if it has one.
+    pub fn system_accent_color(&self) -> Option<SystemAccentColor> {
+        self.platform.accent_color()
+    }
+
+    /// Register a callback to be invoked when the user changes the accent color of the
+    /// operating system.
+    pub fn observe_system_accent_color(
+        &mut self,
+        f: impl FnMut(&mut AppContext) + 'static,
+    ) -> Subscription {
+        let (subscription, activate) = self.accent_color_observers.insert((), Box::new(f));
+        activate();
+        subscription
+    }
+
     /// Register key bindings.
     pub fn bind_keys(&mut self, bindings: impl IntoIterator<Item = KeyBinding>) {
         self.keymap.borrow_mut().add_bindings(bindings);
@@ -1452,3 +1908,100 @@ pub struct KeystrokeEvent {
     /// The action that was resolved for the keystroke, if any
     pub action: Option<Box<dyn Action>>,
 }
+
+/// How a keystroke was dispatched, recorded while keystroke diagnostics are enabled.
+/// See [`AppContext::set_keystroke_diagnostics_enabled`].
+#[derive(Clone, Debug)]
+pub struct KeystrokeDiagnostic {
+    /// The keystroke that occurred
+    pub keystroke: Keystroke,
+
+    /// The key contexts from the root of the window to the focused element
+    pub context_stack: Vec<KeyContext>,
+
+    /// The names of the actions bound to the keystroke in these contexts, in the order they were tried
+    pub bindings: Vec<SharedString>,
+
+    /// What stopped the keystroke's propagation
+    pub resolution: KeystrokeResolution,
+
+    /// Notes added by the handlers while the keystroke was dispatched,
+    /// see [`WindowContext::annotate_keystroke`]
+    pub notes: Vec<String>,
+}
+
+/// What stopped the propagation of a keystroke.
+#[derive(Clone, Debug)]
+pub enum KeystrokeResolution {
+    /// The keystroke is part of a multi-stroke binding that is still pending
+    Pending,
+
+    /// The action with the given name handled the keystroke
+    Action(SharedString),
+
+    /// A key listener handled the keystroke, within the given key context
+    KeyListener(Option<KeyContext>),
+
+    /// Nothing handled the keystroke, so it was left to the platform's text input
+    Unhandled,
+}
+
+#[cfg(test)]
+mod tests {
+    // For compatibility with the test macro
+    use crate as gpui;
+    use gpui::TestAppContext;
+    use parking_lot::Mutex;
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    #[crate::test]
+    fn test_channels(cx: &mut TestAppContext) {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let (tx, _task) = cx.update(|cx| {
+            cx.foreground_channel({
+                let received = received.clone();
+                move |message: usize, _| received.borrow_mut().push(message)
+            })
+        });
+        let background_received = Arc::new(Mutex::new(Vec::new()));
+        let (background_tx, _background_task) = cx.update(|cx| {
+            cx.background_channel({
+                let received = background_received.clone();
+                move |message: usize| received.lock().push(message)
+            })
+        });
+
+        // senders on other threads interleave, but each one's messages stay in order
+        for sender in 0..3 {
+            let tx = tx.clone();
+            let background_tx = background_tx.clone();
+            cx.executor()
+                .spawn(async move {
+                    for ix in 0..5 {
+                        tx.unbounded_send(sender * 10 + ix).unwrap();
+                        background_tx.unbounded_send(sender * 10 + ix).unwrap();
+                    }
+                })
+                .detach();
+        }
+        cx.run_until_parked();
+
+        for received in [
+            received.borrow().clone(),
+            background_received.lock().clone(),
+        ] {
+            assert_eq!(received.len(), 15);
+            for sender in 0..3 {
+                let messages = received
+                    .iter()
+                    .copied()
+                    .filter(|message| message / 10 == sender)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    messages,
+                    (0..5).map(|ix| sender * 10 + ix).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+}
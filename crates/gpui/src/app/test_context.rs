@@ -754,7 +754,10 @@ impl VisualTestContext {
 
     /// Simulate a modifiers changed event
     pub fn simulate_modifiers_change(&mut self, modifiers: Modifiers) {
-        self.simulate_event(ModifiersChangedEvent { modifiers })
+        self.simulate_event(ModifiersChangedEvent {
+            modifiers,
+            ..Default::default()
+        })
     }
 
     /// Simulates the user resizing the window to the new size.
@@ -1,6 +1,6 @@
 use crate::{
-    point, seal::Sealed, Empty, IntoElement, Keystroke, Modifiers, Pixels, Point, Render,
-    ViewContext,
+    point, seal::Sealed, Capslock, Empty, IntoElement, Keystroke, Modifiers, Pixels, Point, Render,
+    SharedString, ViewContext,
 };
 use smallvec::SmallVec;
 use std::{any::Any, fmt::Debug, ops::Deref, path::PathBuf};
@@ -55,6 +55,9 @@ impl KeyEvent for KeyUpEvent {}
 pub struct ModifiersChangedEvent {
     /// The new state of the modifier keys
     pub modifiers: Modifiers,
+
+    /// The new state of the capslock key
+    pub capslock: Capslock,
 }
 
 impl Sealed for ModifiersChangedEvent {}
@@ -226,6 +229,37 @@ impl MouseMoveEvent {
     }
 }
 
+/// A relative mouse movement from the platform, reported while the pointer is locked, and
+/// on Windows also while a mouse button is held down, so drags keep moving at the edges of
+/// the screen. See [`crate::WindowContext::lock_pointer`].
+#[derive(Clone, Debug, Default)]
+pub struct MouseDeltaEvent {
+    /// How far the mouse was moved.
+    pub delta: Point<Pixels>,
+
+    /// The mouse button that was pressed, if any.
+    pub pressed_button: Option<MouseButton>,
+
+    /// The modifiers that were held down when the mouse was moved.
+    pub modifiers: Modifiers,
+}
+
+impl Sealed for MouseDeltaEvent {}
+impl InputEvent for MouseDeltaEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::MouseDelta(self)
+    }
+}
+impl MouseEvent for MouseDeltaEvent {}
+
+impl Deref for MouseDeltaEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
 /// A mouse wheel event from the platform
 #[derive(Clone, Debug, Default)]
 pub struct ScrollWheelEvent {
@@ -306,6 +340,96 @@ impl ScrollDelta {
     }
 }
 
+/// What a pen did to produce a [`PenEvent`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PenPhase {
+    /// The pen touched the screen.
+    Down,
+    /// The pen moved, either on the screen or hovering above it.
+    #[default]
+    Move,
+    /// The pen was lifted from the screen.
+    Up,
+}
+
+/// A pen (stylus) event from the platform, with the details that mouse events don't carry.
+///
+/// Platforms still report the pen as a mouse afterwards, unless a listener prevents the
+/// default behavior of this event.
+#[derive(Clone, Debug, Default)]
+pub struct PenEvent {
+    /// What the pen did.
+    pub phase: PenPhase,
+
+    /// The position of the pen tip on the window.
+    pub position: Point<Pixels>,
+
+    /// How hard the pen is pressed against the screen, from 0 to 1.
+    /// This is 0 while the pen hovers above the screen.
+    pub pressure: f32,
+
+    /// How far the pen is tilted along the x and y axes, in degrees from -90 to 90.
+    pub tilt: Point<f32>,
+
+    /// Whether the button on the barrel of the pen is held down.
+    pub barrel_button: bool,
+
+    /// Whether the pen is used as an eraser, by flipping it or holding its eraser button.
+    pub eraser: bool,
+
+    /// The modifiers that were held down when the pen was used.
+    pub modifiers: Modifiers,
+}
+
+impl Sealed for PenEvent {}
+impl InputEvent for PenEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::Pen(self)
+    }
+}
+impl MouseEvent for PenEvent {}
+
+impl Deref for PenEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
+/// A pinch gesture on a touchpad or touchscreen, usually used to zoom.
+#[derive(Clone, Debug, Default)]
+pub struct PinchEvent {
+    /// The position of the center of the gesture on the window.
+    pub position: Point<Pixels>,
+
+    /// How much the content should be magnified since the last event, as a fraction of its
+    /// current scale. Positive values zoom in and negative values zoom out.
+    pub delta: f32,
+
+    /// The modifiers that were held down during the gesture.
+    pub modifiers: Modifiers,
+
+    /// The phase of the gesture.
+    pub phase: TouchPhase,
+}
+
+impl Sealed for PinchEvent {}
+impl InputEvent for PinchEvent {
+    fn to_platform_input(self) -> PlatformInput {
+        PlatformInput::Pinch(self)
+    }
+}
+impl MouseEvent for PinchEvent {}
+
+impl Deref for PinchEvent {
+    type Target = Modifiers;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modifiers
+    }
+}
+
 /// A mouse exit event from the platform, generated when the mouse leaves the window.
 #[derive(Clone, Debug, Default)]
 pub struct MouseExitEvent {
@@ -351,6 +475,24 @@ impl Render for ExternalPaths {
     }
 }
 
+/// Text dragged into the window from another application.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalText(pub(crate) SharedString);
+
+impl ExternalText {
+    /// The text that is being dragged.
+    pub fn text(&self) -> &SharedString {
+        &self.0
+    }
+}
+
+impl Render for ExternalText {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+        // the platform will render a preview of the dragged text
+        Empty
+    }
+}
+
 /// A file drop event from the platform, generated when files are dragged and dropped onto the window.
 #[derive(Debug, Clone)]
 pub enum FileDropEvent {
@@ -361,6 +503,13 @@ pub enum FileDropEvent {
         /// The paths of the files that are being dragged.
         paths: ExternalPaths,
     },
+    /// Text from another application has entered the window.
+    TextEntered {
+        /// The position of the mouse relative to the window.
+        position: Point<Pixels>,
+        /// The text that is being dragged.
+        text: ExternalText,
+    },
     /// The files are being dragged over the window
     Pending {
         /// The position of the mouse relative to the window.
@@ -398,10 +547,16 @@ pub enum PlatformInput {
     MouseUp(MouseUpEvent),
     /// The mouse was moved.
     MouseMove(MouseMoveEvent),
+    /// The mouse was moved while the pointer is locked.
+    MouseDelta(MouseDeltaEvent),
     /// The mouse exited the window.
     MouseExited(MouseExitEvent),
     /// The scroll wheel was used.
     ScrollWheel(ScrollWheelEvent),
+    /// A pen was used.
+    Pen(PenEvent),
+    /// A pinch gesture was made.
+    Pinch(PinchEvent),
     /// Files were dragged and dropped onto the window.
     FileDrop(FileDropEvent),
 }
@@ -415,8 +570,11 @@ impl PlatformInput {
             PlatformInput::MouseDown(event) => Some(event),
             PlatformInput::MouseUp(event) => Some(event),
             PlatformInput::MouseMove(event) => Some(event),
+            PlatformInput::MouseDelta(event) => Some(event),
             PlatformInput::MouseExited(event) => Some(event),
             PlatformInput::ScrollWheel(event) => Some(event),
+            PlatformInput::Pen(event) => Some(event),
+            PlatformInput::Pinch(event) => Some(event),
             PlatformInput::FileDrop(event) => Some(event),
         }
     }
@@ -429,8 +587,11 @@ impl PlatformInput {
             PlatformInput::MouseDown(_) => None,
             PlatformInput::MouseUp(_) => None,
             PlatformInput::MouseMove(_) => None,
+            PlatformInput::MouseDelta(_) => None,
             PlatformInput::MouseExited(_) => None,
             PlatformInput::ScrollWheel(_) => None,
+            PlatformInput::Pen(_) => None,
+            PlatformInput::Pinch(_) => None,
             PlatformInput::FileDrop(_) => None,
         }
     }
@@ -441,7 +602,8 @@ mod test {
 
     use crate::{
         self as gpui, div, FocusHandle, InteractiveElement, IntoElement, KeyBinding, Keystroke,
-        ParentElement, Render, TestAppContext, VisualContext,
+        KeystrokeResolution, ParentElement, Render, TestAppContext, VisualContext,
+        VisualTestContext,
     };
 
     struct TestView {
@@ -459,6 +621,7 @@ mod test {
                     .key_context("parent")
                     .on_key_down(cx.listener(|this, _, cx| {
                         cx.stop_propagation();
+                        cx.annotate_keystroke(|| "handled by the test view".to_string());
                         this.saw_key_down = true
                     }))
                     .on_action(
@@ -508,4 +671,105 @@ mod test {
             })
             .unwrap();
     }
+
+    #[gpui::test]
+    fn test_keystroke_diagnostics(cx: &mut TestAppContext) {
+        let window = cx.update(|cx| {
+            cx.open_window(Default::default(), |cx| {
+                cx.new_view(|cx| TestView {
+                    saw_key_down: false,
+                    saw_action: false,
+                    focus_handle: cx.focus_handle(),
+                })
+            })
+            .unwrap()
+        });
+
+        cx.update(|cx| {
+            cx.bind_keys(vec![KeyBinding::new("ctrl-g", TestAction, Some("parent"))]);
+            cx.set_keystroke_diagnostics_enabled(true);
+        });
+
+        window
+            .update(cx, |test_view, cx| cx.focus(&test_view.focus_handle))
+            .unwrap();
+
+        cx.dispatch_keystroke(*window, Keystroke::parse("a").unwrap());
+        cx.dispatch_keystroke(*window, Keystroke::parse("ctrl-g").unwrap());
+
+        let diagnostics = cx.update(|cx| cx.keystroke_diagnostics());
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].keystroke, Keystroke::parse("a").unwrap());
+        assert!(diagnostics[0].bindings.is_empty());
+        assert!(matches!(
+            &diagnostics[0].resolution,
+            KeystrokeResolution::KeyListener(Some(context)) if context.contains("parent")
+        ));
+        assert_eq!(diagnostics[0].notes, ["handled by the test view"]);
+
+        assert_eq!(
+            diagnostics[1].keystroke,
+            Keystroke::parse("ctrl-g").unwrap()
+        );
+        assert_eq!(diagnostics[1].bindings, ["test::TestAction"]);
+        assert!(matches!(
+            &diagnostics[1].resolution,
+            KeystrokeResolution::Action(name) if name == "test::TestAction"
+        ));
+        assert!(diagnostics[1].notes.is_empty());
+
+        cx.update(|cx| cx.set_keystroke_diagnostics_enabled(false));
+        cx.dispatch_keystroke(*window, Keystroke::parse("a").unwrap());
+        assert!(cx.update(|cx| cx.keystroke_diagnostics()).is_empty());
+    }
+
+    #[gpui::test]
+    fn test_pending_keystrokes_cancelled_on_deactivation(cx: &mut TestAppContext) {
+        let window = cx.update(|cx| {
+            cx.open_window(Default::default(), |cx| {
+                cx.new_view(|cx| TestView {
+                    saw_key_down: false,
+                    saw_action: false,
+                    focus_handle: cx.focus_handle(),
+                })
+            })
+            .unwrap()
+        });
+        cx.update(|cx| {
+            cx.bind_keys(vec![KeyBinding::new(
+                "ctrl-g x",
+                TestAction,
+                Some("parent"),
+            )]);
+        });
+        window
+            .update(cx, |test_view, cx| {
+                cx.activate_window();
+                cx.focus(&test_view.focus_handle)
+            })
+            .unwrap();
+        let cx = &mut VisualTestContext::from_window(*window, cx);
+        cx.run_until_parked();
+
+        cx.dispatch_keystroke(*window, Keystroke::parse("ctrl-g").unwrap());
+        assert!(window
+            .update(cx, |_, cx| cx.pending_input_keystrokes().is_some())
+            .unwrap());
+
+        cx.deactivate_window();
+        assert!(window
+            .update(cx, |_, cx| cx.pending_input_keystrokes().is_none())
+            .unwrap());
+
+        // the end of the chord is a keystroke of its own once the window is back
+        window.update(cx, |_, cx| cx.activate_window()).unwrap();
+        cx.run_until_parked();
+        cx.dispatch_keystroke(*window, Keystroke::parse("x").unwrap());
+        let test_view = window.root(cx).unwrap();
+        cx.update(|cx| {
+            assert!(test_view.read(cx).saw_key_down);
+            assert!(!test_view.read(cx).saw_action);
+        });
+    }
 }
@@ -122,7 +122,8 @@ impl Keymap {
 mod tests {
     use super::*;
     use crate as gpui;
-    use gpui::actions;
+    use gpui::{actions, KeyboardMapper, Modifiers, PhysicalKey};
+    use std::{cell::RefCell, rc::Rc};
 
     actions!(
         keymap_test,
@@ -155,6 +156,109 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_physical_key_bindings() {
+        struct GermanKeyboardMapper;
+
+        impl KeyboardMapper for GermanKeyboardMapper {
+            fn character_for_physical_key(&self, key: PhysicalKey) -> Option<String> {
+                match key {
+                    PhysicalKey::BracketLeft => Some("ü".into()),
+                    _ => None,
+                }
+            }
+        }
+
+        let binding = KeyBinding::new("ctrl-code:BracketLeft", ActionAlpha {}, None);
+        assert_eq!(binding.keystrokes()[0].key, "[");
+
+        let binding = KeyBinding::load_with_keyboard_mapper(
+            "ctrl-code:BracketLeft ctrl-code:Slash",
+            Box::new(ActionAlpha {}),
+            None,
+            &GermanKeyboardMapper,
+        )
+        .unwrap();
+        assert_eq!(binding.keystrokes()[0].key, "ü");
+        assert!(binding.keystrokes()[0].modifiers.control);
+        // keys the mapper can't resolve fall back to the US layout
+        assert_eq!(binding.keystrokes()[1].key, "/");
+
+        // characters are never remapped
+        let binding = KeyBinding::load_with_keyboard_mapper(
+            "ctrl-[",
+            Box::new(ActionAlpha {}),
+            None,
+            &GermanKeyboardMapper,
+        )
+        .unwrap();
+        assert_eq!(binding.keystrokes()[0].key, "[");
+
+        assert!(KeyBinding::load("ctrl-code:Unknown", Box::new(ActionAlpha {}), None).is_err());
+    }
+
+    #[test]
+    fn test_key_binding_resolution() {
+        struct GermanKeyboardMapper;
+
+        impl KeyboardMapper for GermanKeyboardMapper {
+            fn character_for_physical_key(&self, key: PhysicalKey) -> Option<String> {
+                match key {
+                    PhysicalKey::Slash => Some("-".into()),
+                    _ => None,
+                }
+            }
+
+            fn modifiers_for_character(&self, character: &str) -> Option<Modifiers> {
+                match character {
+                    "/" => None,
+                    "[" => Some(Modifiers {
+                        control: true,
+                        alt: true,
+                        ..Default::default()
+                    }),
+                    _ => Some(Modifiers::default()),
+                }
+            }
+        }
+
+        let mapper = GermanKeyboardMapper;
+        let binding = KeyBinding::new("ctrl-k ctrl-/", ActionAlpha {}, None);
+        let suggestion = Keystroke::parse("ctrl--").unwrap();
+        assert_eq!(
+            binding.resolution(&mapper),
+            KeyBindingResolution::Unavailable {
+                keystroke: Keystroke::parse("ctrl-/").unwrap(),
+                suggestion: Some(suggestion.clone()),
+            }
+        );
+
+        let binding = KeyBinding::new("ctrl-[", ActionAlpha {}, None);
+        assert!(matches!(
+            binding.resolution(&mapper),
+            KeyBindingResolution::RequiresModifier { .. }
+        ));
+        // only unavailable keys are affected by the fallback
+        assert!(binding
+            .with_fallback(KeyBindingFallback::Disable, &mapper)
+            .is_some());
+
+        assert_eq!(
+            KeyBinding::new("enter", ActionAlpha {}, None).resolution(&mapper),
+            KeyBindingResolution::Resolvable
+        );
+
+        let binding = KeyBinding::new("ctrl-k ctrl-/", ActionAlpha {}, None);
+        let remapped = binding
+            .clone()
+            .with_fallback(KeyBindingFallback::Remap, &mapper)
+            .unwrap();
+        assert_eq!(remapped.keystrokes()[1], suggestion);
+        assert!(binding
+            .with_fallback(KeyBindingFallback::Disable, &mapper)
+            .is_none());
+    }
+
     #[test]
     fn test_keymap_disabled() {
         let bindings = [
@@ -180,4 +284,44 @@ mod tests {
         // binding is globally disabled
         assert!(!keymap.binding_enabled(&bindings[1], &[KeyContext::parse("barf").unwrap()]));
     }
+
+    #[test]
+    fn test_numpad_key_fallback() {
+        let mut keymap = Keymap::default();
+        keymap.add_bindings([
+            KeyBinding::new("ctrl-1", ActionAlpha {}, None),
+            KeyBinding::new("ctrl-k ctrl-2", ActionBeta {}, None),
+            KeyBinding::new("ctrl-numpad3", ActionGamma {}, None),
+            KeyBinding::new("ctrl-3", ActionDelta {}, None),
+        ]);
+        let mut matcher = KeystrokeMatcher::new(Rc::new(RefCell::new(keymap)));
+        let mut match_keystroke = |source| {
+            let result = matcher.match_keystroke(&Keystroke::parse(source).unwrap(), &[]);
+            (
+                result
+                    .bindings
+                    .first()
+                    .map(|binding| binding.action().name().to_string()),
+                result.pending,
+            )
+        };
+
+        // unbound numpad keys match the bindings of the keys they stand in for
+        assert_eq!(
+            match_keystroke("ctrl-numpad1"),
+            (Some("keymap_test::ActionAlpha".to_string()), false)
+        );
+        assert_eq!(match_keystroke("ctrl-k"), (None, true));
+        assert_eq!(
+            match_keystroke("ctrl-numpad2"),
+            (Some("keymap_test::ActionBeta".to_string()), false)
+        );
+        // bindings of the numpad key itself take precedence
+        assert_eq!(
+            match_keystroke("ctrl-numpad3"),
+            (Some("keymap_test::ActionGamma".to_string()), false)
+        );
+        // keys that type their character don't fall back
+        assert_eq!(match_keystroke("numpad1"), (None, false));
+    }
 }
@@ -1,5 +1,10 @@
-use crate::{Action, KeyBindingContextPredicate, KeyMatch, Keystroke};
+use crate::{
+    Action, KeyBindingContextPredicate, KeyMatch, KeyboardMapper, Keystroke, Modifiers,
+    PhysicalKey, UsKeyboardMapper,
+};
 use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 /// A keybinding and its associated metadata, from the keymap.
@@ -27,6 +32,17 @@ impl KeyBinding {
 
     /// Load a keybinding from the given raw data.
     pub fn load(keystrokes: &str, action: Box<dyn Action>, context: Option<&str>) -> Result<Self> {
+        Self::load_with_keyboard_mapper(keystrokes, action, context, &UsKeyboardMapper)
+    }
+
+    /// Load a keybinding from the given raw data, resolving physical keys with the given
+    /// [`KeyboardMapper`].
+    pub fn load_with_keyboard_mapper(
+        keystrokes: &str,
+        action: Box<dyn Action>,
+        context: Option<&str>,
+        keyboard_mapper: &dyn KeyboardMapper,
+    ) -> Result<Self> {
         let context = if let Some(context) = context {
             Some(KeyBindingContextPredicate::parse(context)?)
         } else {
@@ -35,7 +51,7 @@ impl KeyBinding {
 
         let keystrokes = keystrokes
             .split_whitespace()
-            .map(Keystroke::parse)
+            .map(|source| Keystroke::parse_with_keyboard_mapper(source, keyboard_mapper))
             .collect::<Result<_>>()?;
 
         Ok(Self {
@@ -68,6 +84,121 @@ impl KeyBinding {
     pub fn action(&self) -> &dyn Action {
         self.action.as_ref()
     }
+
+    /// Check whether this binding can be typed on the keyboard layout described by the
+    /// given [`KeyboardMapper`]. The first keystroke that can't be typed as written is
+    /// reported.
+    pub fn resolution(&self, keyboard_mapper: &dyn KeyboardMapper) -> KeyBindingResolution {
+        for keystroke in &self.keystrokes {
+            let resolution = keystroke_resolution(keystroke, keyboard_mapper);
+            if resolution != KeyBindingResolution::Resolvable {
+                return resolution;
+            }
+        }
+        KeyBindingResolution::Resolvable
+    }
+
+    /// Apply the given [`KeyBindingFallback`] to this binding. Returns `None` if the
+    /// binding should be dropped.
+    pub fn with_fallback(
+        mut self,
+        fallback: KeyBindingFallback,
+        keyboard_mapper: &dyn KeyboardMapper,
+    ) -> Option<Self> {
+        if fallback == KeyBindingFallback::Keep {
+            return Some(self);
+        }
+
+        for keystroke in self.keystrokes.iter_mut() {
+            match keystroke_resolution(keystroke, keyboard_mapper) {
+                KeyBindingResolution::Unavailable {
+                    suggestion: Some(suggestion),
+                    ..
+                } if fallback == KeyBindingFallback::Remap => *keystroke = suggestion,
+                KeyBindingResolution::Unavailable { .. } => return None,
+                _ => {}
+            }
+        }
+        Some(self)
+    }
+}
+
+/// Whether the keystrokes of a [`KeyBinding`] can be typed on the current keyboard layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyBindingResolution {
+    /// Every keystroke can be typed as written.
+    Resolvable,
+
+    /// The keystroke's key is only reachable by also holding `modifiers`, e.g. with
+    /// AltGr on many European layouts.
+    RequiresModifier {
+        /// The keystroke as written in the binding
+        keystroke: Keystroke,
+        /// The modifiers the layout needs to produce the key
+        modifiers: Modifiers,
+    },
+
+    /// No key in the layout produces the keystroke's key.
+    Unavailable {
+        /// The keystroke as written in the binding
+        keystroke: Keystroke,
+        /// The same keystroke on the key that produces the binding's key on a US
+        /// layout, if the binding's key is a layout-dependent one.
+        suggestion: Option<Keystroke>,
+    },
+}
+
+/// What to do with bindings whose keys aren't available on the current keyboard layout.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyBindingFallback {
+    /// Load the binding as written, even though it can't be triggered.
+    #[default]
+    Keep,
+    /// Move the binding to the key at the same position on a US layout, if there is one.
+    Remap,
+    /// Drop the binding.
+    Disable,
+}
+
+fn keystroke_resolution(
+    keystroke: &Keystroke,
+    keyboard_mapper: &dyn KeyboardMapper,
+) -> KeyBindingResolution {
+    // named keys such as `enter` or `f1` are the same on every layout
+    if keystroke.key.chars().count() != 1 {
+        return KeyBindingResolution::Resolvable;
+    }
+
+    match keyboard_mapper.modifiers_for_character(&keystroke.key) {
+        // shift is part of how the key is written in the binding, e.g. `ctrl-{`
+        Some(modifiers) if !modifiers.control && !modifiers.alt => KeyBindingResolution::Resolvable,
+        Some(modifiers) => KeyBindingResolution::RequiresModifier {
+            keystroke: keystroke.clone(),
+            modifiers,
+        },
+        None => {
+            let suggestion = PhysicalKey::ALL
+                .iter()
+                .find(|key| key.us_character() == keystroke.key)
+                .and_then(|key| keyboard_mapper.character_for_physical_key(*key))
+                .filter(|character| {
+                    *character != keystroke.key
+                        && keyboard_mapper
+                            .modifiers_for_character(character)
+                            .map_or(false, |modifiers| !modifiers.control && !modifiers.alt)
+                })
+                .map(|character| Keystroke {
+                    modifiers: keystroke.modifiers,
+                    key: character,
+                    ime_key: None,
+                });
+            KeyBindingResolution::Unavailable {
+                keystroke: keystroke.clone(),
+                suggestion,
+            }
+        }
+    }
 }
 
 impl std::fmt::Debug for KeyBinding {
@@ -1,4 +1,4 @@
-use crate::{KeyBinding, KeyContext, Keymap, KeymapVersion, Keystroke};
+use crate::{numpad_fallback_keystroke, KeyBinding, KeyContext, Keymap, KeymapVersion, Keystroke};
 use smallvec::SmallVec;
 use std::{cell::RefCell, rc::Rc};
 
@@ -72,6 +72,14 @@ impl KeystrokeMatcher {
             }
         }
 
+        if bindings.is_empty() && pending_key.is_none() {
+            // numpad keys that no binding uses act like the keys they stand in for
+            if let Some(fallback) = numpad_fallback_keystroke(keystroke) {
+                drop(keymap);
+                return self.match_keystroke(&fallback, context_stack);
+            }
+        }
+
         if bindings.is_empty() && pending_key.is_none() && !self.pending_keystrokes.is_empty() {
             drop(keymap);
             self.pending_keystrokes.remove(0);
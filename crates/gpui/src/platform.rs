@@ -2,6 +2,7 @@
 #![cfg_attr(windows, allow(dead_code))]
 
 mod app_menu;
+mod keyboard;
 mod keystroke;
 
 #[cfg(not(target_os = "macos"))]
@@ -24,10 +25,10 @@ mod windows;
 
 use crate::{
     point, Action, AnyWindowHandle, AsyncWindowContext, BackgroundExecutor, Bounds, DevicePixels,
-    DispatchEventResult, Font, FontId, FontMetrics, FontRun, ForegroundExecutor, GlyphId, Keymap,
-    LineLayout, Pixels, PlatformInput, Point, RenderGlyphParams, RenderImageParams,
-    RenderSvgParams, Scene, SharedString, Size, Task, TaskLabel, WindowContext,
-    DEFAULT_WINDOW_SIZE,
+    DispatchEventResult, Font, FontId, FontMetrics, FontRun, ForegroundExecutor, GlyphId, Hsla,
+    ImageData, Keymap, LineLayout, LineSpacing, ParagraphLayout, Pixels, PlatformInput, Point,
+    RenderGlyphParams, RenderImageParams, RenderSvgParams, Scene, SharedString, Size, Task,
+    TaskLabel, TextOrientation, WindowContext, DEFAULT_WINDOW_SIZE,
 };
 use anyhow::Result;
 use async_task::Runnable;
@@ -41,6 +42,7 @@ use std::hash::{Hash, Hasher};
 use std::time::{Duration, Instant};
 use std::{
     fmt::{self, Debug},
+    num::NonZeroU32,
     ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
@@ -49,6 +51,7 @@ use std::{
 use uuid::Uuid;
 
 pub use app_menu::*;
+pub use keyboard::*;
 pub use keystroke::*;
 
 #[cfg(not(target_os = "macos"))]
@@ -63,6 +66,8 @@ pub(crate) use test::*;
 use time::UtcOffset;
 #[cfg(target_os = "windows")]
 pub(crate) use windows::*;
+#[cfg(target_os = "windows")]
+pub use windows::{NativePreview, TaskbarEdge, TaskbarPosition};
 
 #[cfg(target_os = "macos")]
 pub(crate) fn current_platform() -> Rc<dyn Platform> {
@@ -119,6 +124,9 @@ pub(crate) trait Platform: 'static {
 
     fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>>;
     fn primary_display(&self) -> Option<Rc<dyn PlatformDisplay>>;
+    /// Invoked when a display is connected, disconnected or rearranged, on platforms that
+    /// report it.
+    fn on_displays_change(&self, _callback: Box<dyn FnMut()>) {}
     fn active_window(&self) -> Option<AnyWindowHandle>;
 
     fn open_window(
@@ -137,8 +145,14 @@ pub(crate) trait Platform: 'static {
     fn prompt_for_paths(
         &self,
         options: PathPromptOptions,
+        filters: Vec<PathPromptFilter>,
     ) -> oneshot::Receiver<Option<Vec<PathBuf>>>;
-    fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>>;
+    fn prompt_for_new_path(
+        &self,
+        directory: &Path,
+        suggested_name: Option<&str>,
+        filters: Vec<PathPromptFilter>,
+    ) -> oneshot::Receiver<Option<PathBuf>>;
     fn reveal_path(&self, path: &Path);
 
     fn on_quit(&self, callback: Box<dyn FnMut()>);
@@ -163,7 +177,21 @@ pub(crate) trait Platform: 'static {
     fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf>;
 
     fn set_cursor_style(&self, style: CursorStyle);
+    fn register_custom_cursor(&self, _images: Vec<CustomCursorImage>) -> Result<CustomCursorId> {
+        Err(anyhow::anyhow!(
+            "custom cursors are not supported on this platform"
+        ))
+    }
     fn should_auto_hide_scrollbars(&self) -> bool;
+    /// The width of the platform's classic, always visible scrollbars, if it has a standard one.
+    fn scrollbar_width(&self) -> Option<Pixels> {
+        None
+    }
+    fn keyboard_mapper(&self) -> Box<dyn KeyboardMapper> {
+        Box::new(UsKeyboardMapper)
+    }
+    /// Invoked when the user switches to another keyboard layout, on platforms that report it.
+    fn on_keyboard_layout_change(&self, _callback: Box<dyn FnMut()>) {}
 
     #[cfg(target_os = "linux")]
     fn write_to_primary(&self, item: ClipboardItem);
@@ -175,6 +203,67 @@ pub(crate) trait Platform: 'static {
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>>;
     fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>>;
     fn delete_credentials(&self, url: &str) -> Task<Result<()>>;
+
+    #[cfg(target_os = "windows")]
+    fn on_secure_desktop_change(&self, _callback: Box<dyn FnMut(bool)>) {}
+    #[cfg(target_os = "windows")]
+    fn is_secure_desktop_active(&self) -> bool {
+        false
+    }
+    #[cfg(target_os = "windows")]
+    fn on_session_lock_change(&self, _callback: Box<dyn FnMut(bool)>) {}
+    #[cfg(target_os = "windows")]
+    fn is_session_locked(&self) -> bool {
+        false
+    }
+    #[cfg(target_os = "windows")]
+    fn set_jump_list(&self, _jump_list: JumpList) {}
+    #[cfg(target_os = "windows")]
+    fn set_tray_icon(&self, _tooltip: &str, _menu: Vec<MenuItem>) {}
+    #[cfg(target_os = "windows")]
+    fn remove_tray_icon(&self) {}
+    #[cfg(target_os = "windows")]
+    fn on_tray_icon_click(&self, _callback: Box<dyn FnMut()>) {}
+    #[cfg(target_os = "windows")]
+    fn set_app_user_model_id(&self, _id: &str) {}
+    #[cfg(target_os = "windows")]
+    fn show_notification(&self, _notification: SystemNotification) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "notifications are not supported on this platform"
+        ))
+    }
+    #[cfg(target_os = "windows")]
+    fn on_notification_activated(&self, _callback: Box<dyn FnMut(SharedString, Option<usize>)>) {}
+
+    fn on_app_active_status_change(&self, _callback: Box<dyn FnMut(bool)>) {}
+    fn is_app_active(&self) -> bool {
+        true
+    }
+
+    /// The accent color the user picked for the system, on platforms that let them pick one.
+    fn accent_color(&self) -> Option<SystemAccentColor> {
+        None
+    }
+    fn on_accent_color_change(&self, _callback: Box<dyn FnMut()>) {}
+
+    #[cfg(target_os = "windows")]
+    fn default_handler(&self, _kind: DefaultHandlerKind) -> Option<DefaultHandler> {
+        None
+    }
+    #[cfg(target_os = "windows")]
+    fn on_default_handlers_change(&self, _callback: Box<dyn FnMut()>) {}
+    #[cfg(target_os = "windows")]
+    fn degraded_features(&self) -> Vec<DegradedFeature> {
+        Vec::new()
+    }
+    #[cfg(target_os = "windows")]
+    fn is_loopback_available(&self) -> bool {
+        true
+    }
+    #[cfg(target_os = "windows")]
+    fn timer_resolution_statistics(&self) -> TimerResolutionStatistics {
+        TimerResolutionStatistics::default()
+    }
 }
 
 /// A handle to a platform's display, e.g. a monitor or laptop screen.
@@ -220,8 +309,12 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn display(&self) -> Option<Rc<dyn PlatformDisplay>>;
     fn mouse_position(&self) -> Point<Pixels>;
     fn modifiers(&self) -> Modifiers;
+    fn capslock(&self) -> Capslock {
+        Capslock::default()
+    }
     fn set_input_handler(&mut self, input_handler: PlatformInputHandler);
     fn take_input_handler(&mut self) -> Option<PlatformInputHandler>;
+    fn set_text_input_focused(&self, _focused: bool) {}
     fn prompt(
         &self,
         level: PromptLevel,
@@ -230,11 +323,27 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
         answers: &[&str],
     ) -> Option<oneshot::Receiver<usize>>;
     fn activate(&self);
+    fn request_activation(&self) {
+        self.activate();
+    }
+    fn request_user_attention(&self, _flash_count: Option<u32>) {}
     fn is_active(&self) -> bool;
     fn set_title(&mut self, title: &str);
     fn set_app_id(&mut self, app_id: &str);
     fn set_background_appearance(&mut self, background_appearance: WindowBackgroundAppearance);
     fn set_edited(&mut self, edited: bool);
+    fn set_min_size(&self, _size: Option<Size<Pixels>>) {}
+    fn set_max_size(&self, _size: Option<Size<Pixels>>) {}
+    fn set_corner_preference(&self, _preference: WindowCornerPreference) {}
+    fn set_opacity(&self, _opacity: f32) {}
+    fn set_level(&self, _level: WindowLevel) {}
+    fn set_progress(&self, _progress: WindowProgress) {}
+    fn set_badge(&self, _badge: Option<WindowBadge>) {}
+    fn set_titlebar_appears_transparent(&self, _appears_transparent: bool) {}
+    fn titlebar_appears_transparent(&self) -> bool {
+        false
+    }
+    fn set_frame_appearance(&self, _appearance: Option<WindowAppearance>) {}
     fn show_character_palette(&self);
     fn minimize(&self);
     fn zoom(&self);
@@ -251,13 +360,57 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn draw(&self, scene: &Scene);
     fn completed_frame(&self) {}
     fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas>;
+    fn render_target_info(&self) -> Option<RenderTargetInfo> {
+        None
+    }
+    fn present_statistics(&self) -> Option<PresentStatistics> {
+        None
+    }
+    fn render_to_image(
+        &self,
+        _scene: &Scene,
+        _size: Size<DevicePixels>,
+    ) -> Result<image::RgbaImage> {
+        Err(anyhow::anyhow!(
+            "rendering to an image is not supported on this platform"
+        ))
+    }
+    fn start_external_drag(
+        &self,
+        _data: ExternalDragData,
+        _on_complete: Box<dyn FnOnce(ExternalDragOutcome)>,
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "dragging out of the window is not supported on this platform"
+        ))
+    }
 
     #[cfg(target_os = "windows")]
     fn get_raw_handle(&self) -> windows::HWND;
+    #[cfg(target_os = "windows")]
+    fn set_window_menu(&self, _items: Vec<WindowMenuItem>) {}
+    #[cfg(target_os = "windows")]
+    fn set_window_menu_item_checked(&self, _action: &dyn Action, _checked: bool) {}
+    #[cfg(target_os = "windows")]
+    fn on_window_menu_action(&self, _callback: Box<dyn FnMut(&dyn Action)>) {}
+    #[cfg(target_os = "windows")]
+    fn set_thumbnail_buttons(&self, _buttons: Vec<ThumbnailButton>) {}
+    #[cfg(target_os = "windows")]
+    fn window_placement(&self) -> Option<WindowPlacement> {
+        None
+    }
+    #[cfg(target_os = "windows")]
+    fn set_window_placement(&self, _placement: WindowPlacement) {}
 
     fn show_window_menu(&self, position: Point<Pixels>);
     fn start_system_move(&self);
     fn should_render_window_controls(&self) -> bool;
+    fn lock_pointer(&self) {}
+    fn unlock_pointer(&self) {}
+    fn is_pointer_locked(&self) -> bool {
+        false
+    }
+    fn set_cursor_visible(&self, _visible: bool) {}
 
     #[cfg(any(test, feature = "test-support"))]
     fn as_test(&mut self) -> Option<&mut TestWindow> {
@@ -291,6 +444,7 @@ pub(crate) trait PlatformTextSystem: Send + Sync {
     fn all_font_families(&self) -> Vec<String>;
     fn font_id(&self, descriptor: &Font) -> Result<FontId>;
     fn font_metrics(&self, font_id: FontId) -> FontMetrics;
+    fn is_monospace(&self, font_id: FontId) -> bool;
     fn typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>>;
     fn advance(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Size<f32>>;
     fn glyph_for_char(&self, font_id: FontId, ch: char) -> Option<GlyphId>;
@@ -300,7 +454,62 @@ pub(crate) trait PlatformTextSystem: Send + Sync {
         params: &RenderGlyphParams,
         raster_bounds: Bounds<DevicePixels>,
     ) -> Result<(Size<DevicePixels>, Vec<u8>)>;
+    /// Rasterizes many glyphs in one go, returning the results in the same order. Platforms
+    /// that can share the setup of a render target between glyphs override this.
+    fn rasterize_glyphs(
+        &self,
+        glyphs: &[(RenderGlyphParams, Bounds<DevicePixels>)],
+    ) -> Vec<Result<(Size<DevicePixels>, Vec<u8>)>> {
+        glyphs
+            .iter()
+            .map(|(params, raster_bounds)| self.rasterize_glyph(params, *raster_bounds))
+            .collect()
+    }
     fn layout_line(&self, text: &str, font_size: Pixels, runs: &[FontRun]) -> LineLayout;
+    /// Platforms without vertical text support lay out every line horizontally.
+    fn layout_line_with_orientation(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        _orientation: TextOrientation,
+    ) -> LineLayout {
+        self.layout_line(text, font_size, runs)
+    }
+    /// Lays out a paragraph, breaking it into lines at newlines and wherever a line would
+    /// grow wider than `max_width`. Platforms that can't break text themselves shape each
+    /// line once and wrap it at the positions of its glyphs.
+    fn layout_paragraph(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        max_width: Pixels,
+    ) -> ParagraphLayout {
+        ParagraphLayout::wrap_lines(text, runs, max_width, |line, runs| {
+            self.layout_line(line, font_size, runs)
+        })
+    }
+    /// Returns the character boundary closest to the given x coordinate in the line, the
+    /// shaped glyph positions are used on platforms that can't hit-test text.
+    fn index_for_x(&self, text: &str, font_size: Pixels, runs: &[FontRun], x: Pixels) -> usize {
+        self.layout_line(text, font_size, runs)
+            .closest_index_for_x(x)
+    }
+    /// Returns the x coordinate of the caret at the given index in the line.
+    fn x_for_index(&self, text: &str, font_size: Pixels, runs: &[FontRun], index: usize) -> Pixels {
+        self.layout_line(text, font_size, runs).x_for_index(index)
+    }
+    /// Sets the distance between tab stops in multiples of the advance of a space, platforms
+    /// that shape tabs as ordinary glyphs ignore it.
+    fn set_tab_size(&self, _tab_size: NonZeroU32) {}
+    /// Sets how lines starting in the given font family are spaced, platforms that always use
+    /// the metrics of the fonts ignore it.
+    fn set_line_spacing(&self, _font_family: &str, _line_spacing: LineSpacing) {}
+    /// Returns how lines starting in the given font are spaced.
+    fn line_spacing(&self, _font_id: FontId) -> LineSpacing {
+        LineSpacing::default()
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -681,7 +890,7 @@ pub enum WindowKind {
 ///
 /// On macOS, this corresponds to named [`NSAppearance`](https://developer.apple.com/documentation/appkit/nsappearance)
 /// values.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum WindowAppearance {
     /// A light appearance.
     ///
@@ -710,6 +919,168 @@ impl Default for WindowAppearance {
     }
 }
 
+/// The accent color of the operating system, with the shades it derives from it.
+///
+/// On Windows, these are the `Accent`, `AccentLight1` to `AccentLight3` and
+/// `AccentDark1` to `AccentDark3` [`UIColorType`](https://learn.microsoft.com/en-us/uwp/api/windows.ui.viewmanagement.uicolortype) values.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SystemAccentColor {
+    /// The accent color itself.
+    pub accent: Hsla,
+
+    /// Lighter shades of the accent color, from the closest to the lightest.
+    /// These read well on dark backgrounds.
+    pub light: [Hsla; 3],
+
+    /// Darker shades of the accent color, from the closest to the darkest.
+    /// These read well on light backgrounds.
+    pub dark: [Hsla; 3],
+}
+
+/// The kinds of targets whose default application can be looked up with
+/// [`AppContext::default_handler`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DefaultHandlerKind {
+    /// The application that opens `http` URLs, usually the default browser.
+    Http,
+    /// The application that opens `https` URLs, usually the default browser.
+    Https,
+    /// The terminal that hosts console applications.
+    Terminal,
+}
+
+/// The application the user picked to handle a [`DefaultHandlerKind`].
+#[derive(Clone, Debug)]
+pub struct DefaultHandler {
+    /// The name of the application, as the system displays it.
+    pub name: SharedString,
+
+    /// The icon of the application, if it could be loaded.
+    pub icon: Option<Arc<ImageData>>,
+}
+
+/// The entries shown when right-clicking the app's taskbar button. See
+/// [`AppContext::set_jump_list`].
+#[derive(Clone, Debug, Default)]
+pub struct JumpList {
+    /// Things the app can do, like opening a new window.
+    pub tasks: Vec<JumpListItem>,
+
+    /// Things opened recently, most recent first. Only as many as the system has room for
+    /// are shown.
+    pub recent: Vec<JumpListItem>,
+}
+
+/// An entry of a [`JumpList`], which launches the app with the given arguments. When the app is
+/// already running, it's up to the new process to hand them over to the running one.
+#[derive(Clone, Debug)]
+pub struct JumpListItem {
+    /// The text of the entry.
+    pub title: SharedString,
+
+    /// The tooltip of the entry.
+    pub description: Option<SharedString>,
+
+    /// The command line arguments the app is launched with.
+    pub arguments: Vec<String>,
+}
+
+/// A notification shown by the system, outside of the app's windows. See
+/// [`AppContext::show_notification`].
+#[derive(Clone, Debug)]
+pub struct SystemNotification {
+    /// Identifies the notification when it's clicked. Showing a notification replaces the
+    /// one with the same id.
+    pub id: SharedString,
+
+    /// The first line of the notification, in bold.
+    pub title: SharedString,
+
+    /// The text below the title.
+    pub body: SharedString,
+
+    /// The labels of the buttons of the notification. Windows shows at most five.
+    pub actions: Vec<SharedString>,
+}
+
+/// A platform feature that is turned off because the process runs in a sandbox that doesn't
+/// grant the access it needs. See [`AppContext::degraded_platform_features`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DegradedFeature {
+    /// What stopped working.
+    pub feature: SharedString,
+
+    /// Why the feature is unavailable.
+    pub reason: SharedString,
+}
+
+/// How often the system timer resolution has been raised for short timers, which keeps
+/// animations smooth at the cost of battery. See [`AppContext::timer_resolution_statistics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimerResolutionStatistics {
+    /// How many times the resolution has been raised.
+    pub activations: u64,
+
+    /// How long the resolution has been raised for in total.
+    pub active_duration: Duration,
+
+    /// Whether the resolution is raised right now.
+    pub active: bool,
+}
+
+/// What a window offers to other applications when something is dragged out of it.
+/// See [`WindowContext::start_external_drag`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExternalDragData {
+    /// Files and directories, which file managers copy or link to.
+    pub paths: Vec<PathBuf>,
+
+    /// Plain text, for applications that accept text.
+    pub text: Option<String>,
+}
+
+/// How a drag out of the window ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalDragOutcome {
+    /// The drag was cancelled, or dropped somewhere that didn't accept it.
+    Cancelled,
+    /// The dragged data was copied.
+    Copied,
+    /// A link to the dragged files was created.
+    Linked,
+}
+
+/// Describes the surface a window is rendered into, for diagnostics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderTargetInfo {
+    /// The pixel format of the surface, as named by the renderer.
+    pub surface_format: SharedString,
+
+    /// The number of bits per pixel of the display the window is presented on, if known.
+    pub display_color_depth: Option<u32>,
+
+    /// Whether the renderer dithers its output because the display has too few colors.
+    pub dithering: bool,
+}
+
+/// How the frames of a window have been presented, for diagnosing dropped frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PresentStatistics {
+    /// The number of frames the window has presented.
+    pub present_count: u64,
+
+    /// The number of frames the compositor dropped since the window was opened. Compositors
+    /// only report this for the whole desktop, so frames of other windows are included.
+    pub dropped_frames: u64,
+
+    /// The time from the window's most recent present to the following vertical blank,
+    /// if known.
+    pub present_latency: Option<Duration>,
+
+    /// How long the most recent keyboard or mouse input waited to be handled, if known.
+    pub input_latency: Option<Duration>,
+}
+
 /// The appearance of the background of the window itself, when there is
 /// no content or the content is transparent.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -731,6 +1102,90 @@ pub enum WindowBackgroundAppearance {
     Blurred,
 }
 
+/// Where a window is stacked relative to the windows of other apps.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WindowLevel {
+    /// Stacked with the windows of other apps as they're activated.
+    #[default]
+    Normal,
+    /// Kept above the windows of other apps while this app is active.
+    Floating,
+    /// Kept above the windows of other apps at all times.
+    AlwaysOnTop,
+}
+
+/// The progress of a long running operation, shown on the window's taskbar button.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum WindowProgress {
+    /// No progress is shown.
+    #[default]
+    None,
+    /// An operation is running, but how far along it is isn't known.
+    Indeterminate,
+    /// An operation is running, with the given fraction of it done, from 0 to 1.
+    Normal(f32),
+    /// An operation has failed after getting the given fraction of the way, from 0 to 1.
+    Error(f32),
+}
+
+/// A badge drawn over the window's taskbar button, or over the dock icon on macOS.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowBadge {
+    /// A count of things waiting for the user, like unread messages. A count of 0 clears the badge.
+    Count(usize),
+    /// A dot marking that something is going on, like a debugging session.
+    Dot,
+}
+
+/// Where a window is and how it's shown, which can be saved to reopen the window the same way.
+/// See [`WindowContext::window_placement`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowPlacement {
+    /// The bounds of the window when it's neither maximized nor minimized, in device pixels.
+    pub normal_bounds: Bounds<DevicePixels>,
+
+    /// Whether the window is maximized, or is maximized again when it's restored from being
+    /// minimized.
+    pub maximized: bool,
+
+    /// Whether the window is minimized.
+    pub minimized: bool,
+
+    /// The display the window is on, which stays the same across restarts.
+    pub display_uuid: Option<Uuid>,
+}
+
+/// A button in the toolbar below the window's thumbnail, which the taskbar shows when hovering
+/// the window's button. See [`WindowContext::set_thumbnail_buttons`].
+pub struct ThumbnailButton {
+    /// The icon of the button, with its pixels in BGRA order. The taskbar draws it 16 pixels
+    /// wide at 100% scale.
+    pub icon: Arc<ImageData>,
+
+    /// The tooltip of the button.
+    pub tooltip: SharedString,
+
+    /// The action dispatched to the focused element of the window when the button is clicked.
+    pub action: Box<dyn Action>,
+
+    /// Whether the button can be clicked.
+    pub enabled: bool,
+}
+
+/// How the corners of a window are rounded, on platforms that let windows pick.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WindowCornerPreference {
+    /// Whatever the system does for the kind of window.
+    #[default]
+    Default,
+    /// Square corners.
+    Square,
+    /// Rounded corners.
+    Round,
+    /// Corners with a smaller radius, like those of menus and tooltips.
+    RoundSmall,
+}
+
 /// The options that can be configured for a file dialog prompt
 #[derive(Copy, Clone, Debug)]
 pub struct PathPromptOptions {
@@ -742,6 +1197,15 @@ pub struct PathPromptOptions {
     pub multiple: bool,
 }
 
+/// A kind of file that can be picked in a file dialog, like "Rust" for `.rs` files.
+#[derive(Clone, Debug)]
+pub struct PathPromptFilter {
+    /// The name of the kind of file, as listed in the dialog.
+    pub name: SharedString,
+    /// The extensions of the files, without the leading dot. Every file matches when empty.
+    pub extensions: Vec<SharedString>,
+}
+
 /// What kind of prompt styling to show
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PromptLevel {
@@ -832,6 +1296,45 @@ pub enum CursorStyle {
     /// A cursor indicating that the operation will result in a context menu
     /// corresponds to the CSS cursor value `context-menu`
     ContextualMenu,
+
+    /// A cursor created from images with [`crate::AppContext::register_custom_cursor`],
+    /// shown as the arrow on platforms that don't support them
+    /// corresponds to the CSS cursor value `url(...)`
+    Custom(CustomCursorId),
+}
+
+/// Identifies a cursor registered with [`crate::AppContext::register_custom_cursor`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CustomCursorId(pub(crate) usize);
+
+/// One resolution of a custom cursor. The image closest to a display's scale factor is
+/// shown on it, so a cursor stays sharp when a window moves between displays.
+#[derive(Clone, Debug)]
+pub struct CustomCursorImage {
+    /// The scale factor of the displays this image is drawn for.
+    pub scale_factor: f32,
+    /// The size of the image.
+    pub size: Size<DevicePixels>,
+    /// The point of the image the cursor clicks at, from its top left corner.
+    pub hotspot: Point<DevicePixels>,
+    /// The pixels of the image as 8-bit RGBA, row by row from the top.
+    pub rgba: Vec<u8>,
+}
+
+impl CustomCursorImage {
+    /// Decodes an image file, such as a PNG loaded from the app's assets.
+    pub fn decode(bytes: &[u8], scale_factor: f32, hotspot: Point<DevicePixels>) -> Result<Self> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        Ok(Self {
+            scale_factor,
+            size: Size {
+                width: DevicePixels(image.width() as i32),
+                height: DevicePixels(image.height() as i32),
+            },
+            hotspot,
+            rgba: image.into_raw(),
+        })
+    }
 }
 
 impl Default for CursorStyle {
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::{Action, AppContext, Platform};
+use crate::{Action, AppContext, Platform, SharedString};
 use util::ResultExt;
 
 /// A menu of the application, either a main menu or a submenu
@@ -141,6 +141,51 @@ impl Clone for OwnedMenuItem {
     }
 }
 
+/// An item appended to a window's system menu, the menu opened from the window icon
+/// or with Alt+Space. Currently only supported on Windows.
+pub enum WindowMenuItem {
+    /// A separator between items
+    Separator,
+
+    /// An action that can be performed
+    Action {
+        /// The name of this menu item
+        name: SharedString,
+
+        /// the action to perform when this menu item is selected
+        action: Box<dyn Action>,
+
+        /// Whether this menu item is initially displayed with a checkmark
+        checked: bool,
+    },
+}
+
+impl WindowMenuItem {
+    /// Creates a new window menu item that is a separator
+    pub fn separator() -> Self {
+        Self::Separator
+    }
+
+    /// Creates a new window menu item that invokes an action
+    pub fn action(name: impl Into<SharedString>, action: impl Action) -> Self {
+        Self::Action {
+            name: name.into(),
+            action: Box::new(action),
+            checked: false,
+        }
+    }
+
+    /// Creates a new window menu item that invokes an action and displays a checkmark
+    /// reflecting the given state
+    pub fn toggle(name: impl Into<SharedString>, action: impl Action, checked: bool) -> Self {
+        Self::Action {
+            name: name.into(),
+            action: Box::new(action),
+            checked,
+        }
+    }
+}
+
 // TODO: As part of the global selections refactor, these should
 // be moved to GPUI-provided actions that make this association
 // without leaking the platform details to GPUI users
@@ -33,6 +33,21 @@ struct BladeAtlasState {
 unsafe impl Send for BladeAtlasState {}
 
 impl BladeAtlasState {
+    fn new(gpu: &Arc<gpu::Context>) -> Self {
+        Self {
+            gpu: Arc::clone(gpu),
+            upload_belt: BufferBelt::new(BufferBeltDescriptor {
+                memory: gpu::Memory::Upload,
+                min_chunk_size: 0x10000,
+                alignment: 64, // Vulkan `optimalBufferCopyOffsetAlignment` on Intel XE
+            }),
+            storage: BladeAtlasStorage::default(),
+            tiles_by_key: Default::default(),
+            initializations: Vec::new(),
+            uploads: Vec::new(),
+        }
+    }
+
     fn destroy(&mut self) {
         self.storage.destroy(&self.gpu);
         self.upload_belt.destroy(&self.gpu);
@@ -46,24 +61,22 @@ pub struct BladeTextureInfo {
 
 impl BladeAtlas {
     pub(crate) fn new(gpu: &Arc<gpu::Context>) -> Self {
-        BladeAtlas(Mutex::new(BladeAtlasState {
-            gpu: Arc::clone(gpu),
-            upload_belt: BufferBelt::new(BufferBeltDescriptor {
-                memory: gpu::Memory::Upload,
-                min_chunk_size: 0x10000,
-                alignment: 64, // Vulkan `optimalBufferCopyOffsetAlignment` on Intel XE
-            }),
-            storage: BladeAtlasStorage::default(),
-            tiles_by_key: Default::default(),
-            initializations: Vec::new(),
-            uploads: Vec::new(),
-        }))
+        BladeAtlas(Mutex::new(BladeAtlasState::new(gpu)))
     }
 
     pub(crate) fn destroy(&self) {
         self.0.lock().destroy();
     }
 
+    /// Moves the atlas to a new GPU context, after the old one was lost. Every tile is
+    /// dropped, so they're rasterized again the next time they're needed.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub(crate) fn reset(&self, gpu: &Arc<gpu::Context>) {
+        let mut lock = self.0.lock();
+        lock.destroy();
+        *lock = BladeAtlasState::new(gpu);
+    }
+
     pub(crate) fn clear_textures(&self, texture_kind: AtlasTextureKind) {
         let mut lock = self.0.lock();
         let textures = &mut lock.storage[texture_kind];
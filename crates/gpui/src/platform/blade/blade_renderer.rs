@@ -4,8 +4,8 @@
 use super::{BladeAtlas, PATH_TEXTURE_FORMAT};
 use crate::{
     AtlasTextureKind, AtlasTile, Bounds, ContentMask, DevicePixels, Hsla, MonochromeSprite, Path,
-    PathId, PathVertex, PolychromeSprite, PrimitiveBatch, Quad, ScaledPixels, Scene, Shadow, Size,
-    Underline,
+    PathId, PathVertex, PolychromeSprite, PrimitiveBatch, Quad, RenderTargetInfo, ScaledPixels,
+    Scene, Shadow, Size, Underline,
 };
 use bytemuck::{Pod, Zeroable};
 use collections::HashMap;
@@ -16,9 +16,23 @@ use std::{ffi::c_void, ptr::NonNull};
 
 use blade_graphics as gpu;
 use blade_util::{BufferBelt, BufferBeltDescriptor};
-use std::{mem, sync::Arc};
+use std::{
+    mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 const MAX_FRAME_TIME_MS: u32 = 1000;
+/// Windows resets a GPU that hangs for two seconds, which removes its device, so a frame that
+/// still isn't done well past that was lost with the device rather than being slow.
+const DEVICE_REMOVED_TIMEOUT: Duration = Duration::from_secs(5);
+/// The surface formats the pipelines can draw to and frames can be read back from.
+const SUPPORTED_SURFACE_FORMATS: &[gpu::TextureFormat] = &[
+    gpu::TextureFormat::Bgra8UnormSrgb,
+    gpu::TextureFormat::Bgra8Unorm,
+    gpu::TextureFormat::Rgba8UnormSrgb,
+    gpu::TextureFormat::Rgba8Unorm,
+];
 
 #[cfg(target_os = "macos")]
 pub type Context = ();
@@ -84,7 +98,7 @@ pub unsafe fn new_renderer(
 struct GlobalParams {
     viewport_size: [f32; 2],
     premultiplied_alpha: u32,
-    pad: u32,
+    dither_levels: u32,
 }
 
 //Note: we can't use `Bounds` directly here because
@@ -335,6 +349,17 @@ impl BladePipelines {
             }),
         }
     }
+
+    fn destroy(&mut self, gpu: &gpu::Context) {
+        gpu.destroy_render_pipeline(&mut self.quads);
+        gpu.destroy_render_pipeline(&mut self.shadows);
+        gpu.destroy_render_pipeline(&mut self.path_rasterization);
+        gpu.destroy_render_pipeline(&mut self.paths);
+        gpu.destroy_render_pipeline(&mut self.underlines);
+        gpu.destroy_render_pipeline(&mut self.mono_sprites);
+        gpu.destroy_render_pipeline(&mut self.poly_sprites);
+        gpu.destroy_render_pipeline(&mut self.surfaces);
+    }
 }
 
 pub struct BladeSurfaceConfig {
@@ -345,7 +370,15 @@ pub struct BladeSurfaceConfig {
 pub struct BladeRenderer {
     gpu: Arc<gpu::Context>,
     surface_config: gpu::SurfaceConfig,
+    surface_format: gpu::TextureFormat,
     alpha_mode: gpu::AlphaMode,
+    display_color_depth: Option<u32>,
+    /// The number of levels per color channel shadows are dithered to, zero when the display
+    /// has enough of them
+    dither_levels: u32,
+    /// Set once the GPU stopped responding, after which nothing is drawn until the renderer
+    /// recovers on a new context
+    device_lost: bool,
     command_encoder: gpu::CommandEncoder,
     last_sync_point: Option<gpu::SyncPoint>,
     pipelines: BladePipelines,
@@ -359,7 +392,16 @@ pub struct BladeRenderer {
 
 impl BladeRenderer {
     pub fn new(gpu: Arc<gpu::Context>, config: BladeSurfaceConfig) -> Self {
-        let surface_config = gpu::SurfaceConfig {
+        let atlas = Arc::new(BladeAtlas::new(&gpu));
+        Self::with_atlas(gpu, config, atlas)
+    }
+
+    fn with_atlas(
+        gpu: Arc<gpu::Context>,
+        config: BladeSurfaceConfig,
+        atlas: Arc<BladeAtlas>,
+    ) -> Self {
+        let mut surface_config = gpu::SurfaceConfig {
             size: config.size,
             usage: gpu::TextureUsage::TARGET,
             display_sync: gpu::DisplaySync::Recent,
@@ -367,7 +409,7 @@ impl BladeRenderer {
             allow_exclusive_full_screen: false,
             transparent: config.transparent,
         };
-        let surface_info = gpu.resize(surface_config);
+        let surface_info = Self::configure_surface(&gpu, &mut surface_config);
 
         let command_encoder = gpu.create_command_encoder(gpu::CommandEncoderDesc {
             name: "main",
@@ -379,7 +421,6 @@ impl BladeRenderer {
             min_chunk_size: 0x1000,
             alignment: 0x40, // Vulkan `minStorageBufferOffsetAlignment` on Intel Xe
         });
-        let atlas = Arc::new(BladeAtlas::new(&gpu));
         let atlas_sampler = gpu.create_sampler(gpu::SamplerDesc {
             name: "atlas",
             mag_filter: gpu::FilterMode::Linear,
@@ -396,7 +437,11 @@ impl BladeRenderer {
         Self {
             gpu,
             surface_config,
+            surface_format: surface_info.format,
             alpha_mode: surface_info.alpha,
+            display_color_depth: None,
+            dither_levels: 0,
+            device_lost: false,
             command_encoder,
             last_sync_point: None,
             pipelines,
@@ -409,10 +454,41 @@ impl BladeRenderer {
         }
     }
 
+    /// Configures the surface, falling back to the default color space when the swapchain
+    /// doesn't support a format for the preferred one, like the 16-bit surfaces of some
+    /// virtual machines.
+    fn configure_surface(gpu: &gpu::Context, config: &mut gpu::SurfaceConfig) -> gpu::SurfaceInfo {
+        let surface_info = gpu.resize(*config);
+        if SUPPORTED_SURFACE_FORMATS.contains(&surface_info.format)
+            || matches!(config.color_space, gpu::ColorSpace::Srgb)
+        {
+            return surface_info;
+        }
+        log::warn!(
+            "surface format {:?} is unsupported, falling back to the default format",
+            surface_info.format
+        );
+        config.color_space = gpu::ColorSpace::Srgb;
+        let surface_info = gpu.resize(*config);
+        log::info!("using surface format {:?}", surface_info.format);
+        surface_info
+    }
+
     fn wait_for_gpu(&mut self) {
         if let Some(last_sp) = self.last_sync_point.take() {
-            if !self.gpu.wait_for(&last_sp, MAX_FRAME_TIME_MS) {
-                panic!("GPU hung");
+            let started = Instant::now();
+            while !self.gpu.wait_for(&last_sp, MAX_FRAME_TIME_MS) {
+                if !cfg!(target_os = "windows") {
+                    panic!("GPU hung");
+                }
+                // like when its driver was updated, or it was reset after a hang
+                if started.elapsed() >= DEVICE_REMOVED_TIMEOUT {
+                    log::error!("GPU device lost");
+                    self.device_lost = true;
+                    return;
+                }
+                // like the first frame after resuming
+                log::warn!("GPU is slow to finish a frame, waiting for it");
             }
         }
     }
@@ -427,7 +503,12 @@ impl BladeRenderer {
         if gpu_size != self.surface_config.size {
             self.wait_for_gpu();
             self.surface_config.size = gpu_size;
-            self.gpu.resize(self.surface_config);
+            let surface_info = Self::configure_surface(&self.gpu, &mut self.surface_config);
+            if surface_info.format != self.surface_format {
+                self.pipelines.destroy(&self.gpu);
+                self.pipelines = BladePipelines::new(&self.gpu, surface_info);
+                self.surface_format = surface_info.format;
+            }
         }
     }
 
@@ -435,12 +516,83 @@ impl BladeRenderer {
         if transparent != self.surface_config.transparent {
             self.wait_for_gpu();
             self.surface_config.transparent = transparent;
-            let surface_info = self.gpu.resize(self.surface_config);
+            let surface_info = Self::configure_surface(&self.gpu, &mut self.surface_config);
+            self.pipelines.destroy(&self.gpu);
             self.pipelines = BladePipelines::new(&self.gpu, surface_info);
+            self.surface_format = surface_info.format;
             self.alpha_mode = surface_info.alpha;
         }
     }
 
+    /// Configures the surface again, as its swapchain can be lost while the system sleeps.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn reset_surface(&mut self) {
+        self.wait_for_gpu();
+        let surface_info = Self::configure_surface(&self.gpu, &mut self.surface_config);
+        // the pipelines are built for the surface format, which the new swapchain may not share
+        if surface_info.format != self.surface_format {
+            self.pipelines.destroy(&self.gpu);
+            self.pipelines = BladePipelines::new(&self.gpu, surface_info);
+        }
+        self.surface_format = surface_info.format;
+        self.alpha_mode = surface_info.alpha;
+    }
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost
+    }
+
+    /// Starts over on a new GPU context once the device was lost. The atlas is kept, as the
+    /// window holds on to it, but it's emptied along with everything else of the old device.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn recover(&mut self, gpu: Arc<gpu::Context>) {
+        self.atlas.reset(&gpu);
+        let config = BladeSurfaceConfig {
+            size: self.surface_config.size,
+            transparent: self.surface_config.transparent,
+        };
+        let mut renderer = Self::with_atlas(gpu, config, self.atlas.clone());
+        renderer.display_color_depth = self.display_color_depth;
+        renderer.dither_levels = self.dither_levels;
+        let mut lost = std::mem::replace(self, renderer);
+        lost.destroy_resources();
+    }
+
+    /// Adapts to the number of bits per pixel of the display the surface is presented on.
+    /// The surface itself keeps its format, so on displays with fewer than 8 bits per channel
+    /// the compositor reduces the colors and shadows are dithered to hide the banding.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn update_color_depth(&mut self, bits_per_pixel: u32) {
+        let dither_levels = match bits_per_pixel {
+            // unknown, or at least 8 bits per channel
+            0 | 24.. => 0,
+            // 5 or 6 bits per channel
+            15..=23 => 32,
+            // palettized, which approximates colors with a small color cube
+            _ => 6,
+        };
+        if dither_levels != 0 && dither_levels != self.dither_levels {
+            log::warn!(
+                "display has {} bits per pixel, dithering {:?} surface to {} levels per channel",
+                bits_per_pixel,
+                self.surface_format,
+                dither_levels
+            );
+        }
+        self.display_color_depth = (bits_per_pixel > 0).then_some(bits_per_pixel);
+        self.dither_levels = dither_levels;
+    }
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn render_target_info(&self) -> RenderTargetInfo {
+        RenderTargetInfo {
+            surface_format: format!("{:?}", self.surface_format).into(),
+            display_color_depth: self.display_color_depth,
+            dithering: self.dither_levels != 0,
+        }
+    }
+
     #[cfg_attr(target_os = "macos", allow(dead_code))]
     pub fn viewport_size(&self) -> gpu::Extent {
         self.surface_config.size
@@ -492,7 +644,7 @@ impl BladeRenderer {
             let globals = GlobalParams {
                 viewport_size: [tex_info.size.width as f32, tex_info.size.height as f32],
                 premultiplied_alpha: 0,
-                pad: 0,
+                dither_levels: 0,
             };
 
             let vertex_buf = unsafe { self.instance_belt.alloc_typed(&vertices, &self.gpu) };
@@ -520,11 +672,21 @@ impl BladeRenderer {
     pub fn destroy(&mut self) {
         self.wait_for_gpu();
         self.atlas.destroy();
+        self.destroy_resources();
+    }
+
+    /// Destroys everything but the atlas, which a recovered renderer keeps.
+    fn destroy_resources(&mut self) {
         self.instance_belt.destroy(&self.gpu);
         self.gpu.destroy_command_encoder(&mut self.command_encoder);
+        self.pipelines.destroy(&self.gpu);
+        self.gpu.destroy_sampler(self.atlas_sampler);
     }
 
     pub fn draw(&mut self, scene: &Scene) {
+        if self.device_lost {
+            return;
+        }
         self.command_encoder.start();
         self.atlas.before_frame(&mut self.command_encoder);
         self.rasterize_paths(scene.paths());
@@ -544,12 +706,120 @@ impl BladeRenderer {
                 gpu::AlphaMode::Ignored | gpu::AlphaMode::PostMultiplied => 0,
                 gpu::AlphaMode::PreMultiplied => 1,
             },
-            pad: 0,
+            dither_levels: self.dither_levels,
         };
 
+        self.draw_batches(scene, frame.texture_view(), globals);
+
+        self.command_encoder.present(frame);
+        let sync_point = self.gpu.submit(&mut self.command_encoder);
+
+        profiling::scope!("finish");
+        self.instance_belt.flush(&sync_point);
+        self.atlas.after_frame(&sync_point);
+        self.atlas.clear_textures(AtlasTextureKind::Path);
+
+        self.wait_for_gpu();
+        self.last_sync_point = Some(sync_point);
+    }
+
+    /// Draws the scene into an offscreen texture of the given size and reads it back, for
+    /// rendering elements into images without presenting them.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub fn render_to_image(
+        &mut self,
+        scene: &Scene,
+        size: Size<DevicePixels>,
+    ) -> anyhow::Result<image::RgbaImage> {
+        if self.device_lost {
+            return Err(anyhow::anyhow!(
+                "can't render to an image after losing the GPU"
+            ));
+        }
+        let extent = gpu::Extent {
+            width: size.width.0.max(1) as u32,
+            height: size.height.0.max(1) as u32,
+            depth: 1,
+        };
+        // the pipelines are built for the surface format, so the offscreen target shares it
+        let format = self.surface_format;
+        let texture = self.gpu.create_texture(gpu::TextureDesc {
+            name: "offscreen",
+            format,
+            size: extent,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            dimension: gpu::TextureDimension::D2,
+            usage: gpu::TextureUsage::TARGET | gpu::TextureUsage::COPY,
+        });
+        let texture_view = self.gpu.create_texture_view(gpu::TextureViewDesc {
+            name: "offscreen",
+            texture,
+            format,
+            dimension: gpu::ViewDimension::D2,
+            subresources: &Default::default(),
+        });
+        let bytes_per_row = extent.width * 4;
+        let readback = self.gpu.create_buffer(gpu::BufferDesc {
+            name: "offscreen readback",
+            size: (bytes_per_row * extent.height) as u64,
+            memory: gpu::Memory::Shared,
+        });
+
+        self.wait_for_gpu();
+        self.command_encoder.start();
+        self.atlas.before_frame(&mut self.command_encoder);
+        self.rasterize_paths(scene.paths());
+        self.command_encoder.init_texture(texture);
+        let globals = GlobalParams {
+            viewport_size: [extent.width as f32, extent.height as f32],
+            // images hold straight alpha
+            premultiplied_alpha: 0,
+            dither_levels: 0,
+        };
+        self.draw_batches(scene, texture_view, globals);
+        if let mut transfer = self.command_encoder.transfer() {
+            transfer.copy_texture_to_buffer(
+                gpu::TexturePiece {
+                    texture,
+                    mip_level: 0,
+                    array_layer: 0,
+                    origin: [0, 0, 0],
+                },
+                readback.into(),
+                bytes_per_row,
+                extent,
+            );
+        }
+        let sync_point = self.gpu.submit(&mut self.command_encoder);
+        self.instance_belt.flush(&sync_point);
+        self.atlas.after_frame(&sync_point);
+        self.atlas.clear_textures(AtlasTextureKind::Path);
+        self.last_sync_point = Some(sync_point);
+        self.wait_for_gpu();
+
+        let len = (bytes_per_row * extent.height) as usize;
+        let mut pixels = unsafe { std::slice::from_raw_parts(readback.data(), len) }.to_vec();
+        self.gpu.destroy_buffer(readback);
+        self.gpu.destroy_texture_view(texture_view);
+        self.gpu.destroy_texture(texture);
+
+        if matches!(
+            format,
+            gpu::TextureFormat::Bgra8Unorm | gpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        image::RgbaImage::from_raw(extent.width, extent.height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("offscreen image has the wrong size"))
+    }
+
+    fn draw_batches(&mut self, scene: &Scene, target: gpu::TextureView, globals: GlobalParams) {
         if let mut pass = self.command_encoder.render(gpu::RenderTargetSet {
             colors: &[gpu::RenderTarget {
-                view: frame.texture_view(),
+                view: target,
                 init_op: gpu::InitOp::Clear(gpu::TextureColor::TransparentBlack),
                 finish_op: gpu::FinishOp::Store,
             }],
@@ -741,16 +1011,5 @@ impl BladeRenderer {
                 }
             }
         }
-
-        self.command_encoder.present(frame);
-        let sync_point = self.gpu.submit(&mut self.command_encoder);
-
-        profiling::scope!("finish");
-        self.instance_belt.flush(&sync_point);
-        self.atlas.after_frame(&sync_point);
-        self.atlas.clear_textures(AtlasTextureKind::Path);
-
-        self.wait_for_gpu();
-        self.last_sync_point = Some(sync_point);
     }
 }
@@ -1,7 +1,7 @@
 struct GlobalParams {
     viewport_size: vec2<f32>,
     premultiplied_alpha: u32,
-    pad: u32,
+    dither_levels: u32,
 }
 
 var<uniform> globals: GlobalParams;
@@ -197,6 +197,24 @@ fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
     return vec4<f32>(color.rgb * multiplier, alpha);
 }
 
+// Displays with few levels per channel, such as 16-bit remote sessions, turn smooth
+// gradients into visible bands. Ordered dithering trades them for a fine pattern.
+fn dither(color: vec4<f32>, position: vec2<f32>) -> vec4<f32> {
+    if (globals.dither_levels == 0u) {
+        return color;
+    }
+    var bayer = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    let cell = vec2<u32>(position) % vec2<u32>(4u);
+    let threshold = (bayer[cell.y * 4u + cell.x] + 0.5) / 16.0 - 0.5;
+    let level = 1.0 / f32(globals.dither_levels - 1u);
+    return vec4<f32>(saturate(color.rgb + threshold * level), color.a);
+}
+
 // --- quads --- //
 
 struct Quad {
@@ -360,7 +378,7 @@ fn fs_shadow(input: ShadowVarying) -> @location(0) vec4<f32> {
         y += step;
     }
 
-    return blend_color(input.color, alpha);
+    return dither(blend_color(input.color, alpha), input.position.xy);
 }
 
 // --- path rasterization --- //
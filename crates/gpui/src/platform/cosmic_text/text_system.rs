@@ -1,7 +1,7 @@
 use crate::{
-    point, size, Bounds, DevicePixels, Font, FontFeatures, FontId, FontMetrics, FontRun, FontStyle,
-    FontWeight, GlyphId, LineLayout, Pixels, PlatformTextSystem, Point, RenderGlyphParams,
-    ShapedGlyph, SharedString, Size,
+    point, px, size, Bounds, DecorationMetrics, DevicePixels, Font, FontFeatures, FontId,
+    FontMetrics, FontRun, FontStyle, FontWeight, GlyphId, LineLayout, Pixels, PlatformTextSystem,
+    Point, RenderGlyphParams, ShapedGlyph, SharedString, Size, TextSystemError, OS2_TABLE_TAG,
 };
 use anyhow::{anyhow, Context, Ok, Result};
 use collections::HashMap;
@@ -110,17 +110,26 @@ impl PlatformTextSystem for CosmicTextSystem {
 
         let ix =
             font_kit::matching::find_best_match(&candidate_properties, &font_into_properties(font))
-                .context("requested font family contains no font matching the other parameters")?;
+                .map_err(|_| TextSystemError::FontNotFound(font.family.clone()))?;
 
         Ok(candidates[ix])
     }
 
+    fn is_monospace(&self, font_id: FontId) -> bool {
+        let lock = self.0.read();
+        let font_id = lock.loaded_fonts_store[font_id.0].id();
+        lock.font_system
+            .db()
+            .face(font_id)
+            .map_or(false, |face| face.monospaced)
+    }
+
     fn font_metrics(&self, font_id: FontId) -> FontMetrics {
-        let metrics = self.0.read().loaded_fonts_store[font_id.0]
-            .as_swash()
-            .metrics(&[]);
+        let lock = self.0.read();
+        let font = lock.loaded_fonts_store[font_id.0].as_swash();
+        let metrics = font.metrics(&[]);
 
-        FontMetrics {
+        let mut font_metrics = FontMetrics {
             units_per_em: metrics.units_per_em as u32,
             ascent: metrics.ascent,
             descent: -metrics.descent, // todo(linux) confirm this is correct
@@ -129,12 +138,18 @@ impl PlatformTextSystem for CosmicTextSystem {
             underline_thickness: metrics.stroke_size,
             cap_height: metrics.cap_height,
             x_height: metrics.x_height,
+            superscript_size: 0.,
+            superscript_offset: 0.,
+            subscript_size: 0.,
+            subscript_offset: 0.,
             // todo(linux): Compute this correctly
             bounding_box: Bounds {
                 origin: point(0.0, 0.0),
                 size: size(metrics.max_width, metrics.ascent + metrics.descent),
             },
-        }
+        };
+        font_metrics.read_script_metrics(font.table(OS2_TABLE_TAG));
+        font_metrics
     }
 
     fn typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
@@ -295,6 +310,7 @@ impl CosmicTextSystemState {
                 .0,
             )
             .clone()
+            .ok_or(TextSystemError::MissingGlyph)
             .with_context(|| format!("no image for {params:?} in font {font:?}"))?;
         Ok(Bounds {
             origin: point(image.placement.left.into(), (-image.placement.top).into()),
@@ -309,7 +325,7 @@ impl CosmicTextSystemState {
         glyph_bounds: Bounds<DevicePixels>,
     ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
         if glyph_bounds.size.width.0 == 0 || glyph_bounds.size.height.0 == 0 {
-            Err(anyhow!("glyph bounds are empty"))
+            Err(TextSystemError::MissingGlyph.into())
         } else {
             // todo(linux) handle subpixel variants
             let bitmap_size = glyph_bounds.size;
@@ -329,6 +345,7 @@ impl CosmicTextSystemState {
                     .0,
                 )
                 .clone()
+                .ok_or(TextSystemError::MissingGlyph)
                 .with_context(|| format!("no image for {params:?} in font {font:?}"))?;
 
             if params.is_emoji {
@@ -368,6 +385,17 @@ impl CosmicTextSystemState {
         }
     }
 
+    fn decoration_metrics(&self, font_id: FontId, font_size: Pixels) -> DecorationMetrics {
+        let metrics = self.loaded_fonts_store[font_id.0].as_swash().metrics(&[]);
+        let scale = font_size.0 / metrics.units_per_em as f32;
+        DecorationMetrics {
+            underline_offset: px(-metrics.underline_offset * scale),
+            underline_thickness: px(metrics.stroke_size * scale),
+            strikethrough_offset: px(-metrics.strikeout_offset * scale),
+            strikethrough_thickness: px(metrics.stroke_size * scale),
+        }
+    }
+
     #[profiling::function]
     fn layout_line(&mut self, text: &str, font_size: Pixels, font_runs: &[FontRun]) -> LineLayout {
         let mut attrs_list = AttrsList::new(Attrs::new());
@@ -426,17 +454,24 @@ impl CosmicTextSystemState {
                 is_emoji,
             });
 
-            runs.push(crate::ShapedRun { font_id, glyphs });
+            runs.push(crate::ShapedRun {
+                font_id,
+                glyphs,
+                decorations: Some(self.decoration_metrics(font_id, font_size)),
+            });
         }
 
-        LineLayout {
+        let mut layout = LineLayout {
             font_size,
             width: layout.w.into(),
             ascent: layout.max_ascent.into(),
             descent: layout.max_descent.into(),
             runs,
             len: text.len(),
-        }
+            ..Default::default()
+        };
+        layout.measure_whitespace(text);
+        layout
     }
 }
 
@@ -509,7 +544,17 @@ fn font_into_properties(font: &crate::Font) -> font_kit::properties::Properties
             crate::FontStyle::Oblique => font_kit::properties::Style::Oblique,
         },
         weight: font_kit::properties::Weight(font.weight.0),
-        stretch: Default::default(),
+        stretch: match font.stretch {
+            crate::FontStretch::UltraCondensed => font_kit::properties::Stretch::ULTRA_CONDENSED,
+            crate::FontStretch::ExtraCondensed => font_kit::properties::Stretch::EXTRA_CONDENSED,
+            crate::FontStretch::Condensed => font_kit::properties::Stretch::CONDENSED,
+            crate::FontStretch::SemiCondensed => font_kit::properties::Stretch::SEMI_CONDENSED,
+            crate::FontStretch::Normal => font_kit::properties::Stretch::NORMAL,
+            crate::FontStretch::SemiExpanded => font_kit::properties::Stretch::SEMI_EXPANDED,
+            crate::FontStretch::Expanded => font_kit::properties::Stretch::EXPANDED,
+            crate::FontStretch::ExtraExpanded => font_kit::properties::Stretch::EXTRA_EXPANDED,
+            crate::FontStretch::UltraExpanded => font_kit::properties::Stretch::ULTRA_EXPANDED,
+        },
     }
 }
 
@@ -0,0 +1,150 @@
+use crate::Modifiers;
+use anyhow::anyhow;
+
+/// A key referred to by a keybinding, either by the character it produces or
+/// by its physical position on the keyboard.
+///
+/// In keystroke strings a physical key is written with a `code:` prefix followed
+/// by its [`PhysicalKey`] name, e.g. `ctrl-code:BracketLeft`. Anything else is
+/// interpreted as a character.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KeyEquivalent {
+    /// The key that produces the given character in the current keyboard layout
+    Character(String),
+
+    /// The key at the given position, whatever character it produces
+    Physical(PhysicalKey),
+}
+
+impl KeyEquivalent {
+    /// Parse the key component of a keystroke string.
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        match source.strip_prefix("code:") {
+            Some(code) => PhysicalKey::from_code(code)
+                .map(Self::Physical)
+                .ok_or_else(|| anyhow!("Invalid physical key `{}`", code)),
+            None => Ok(Self::Character(source.to_string())),
+        }
+    }
+
+    /// Resolve this key to the character that keystrokes generated by the platform
+    /// will carry for it in the current keyboard layout.
+    pub fn resolve(&self, keyboard_mapper: &dyn KeyboardMapper) -> String {
+        match self {
+            Self::Character(character) => character.clone(),
+            Self::Physical(key) => keyboard_mapper
+                .character_for_physical_key(*key)
+                .unwrap_or_else(|| key.us_character().to_string()),
+        }
+    }
+}
+
+/// The keys whose character depends on the keyboard layout, named after their
+/// [UI Events `code`](https://www.w3.org/TR/uievents-code/) values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicalKey {
+    /// The `` ` `` key on a US layout
+    Backquote,
+    /// The `-` key on a US layout
+    Minus,
+    /// The `=` key on a US layout
+    Equal,
+    /// The `[` key on a US layout
+    BracketLeft,
+    /// The `]` key on a US layout
+    BracketRight,
+    /// The `\` key on a US layout
+    Backslash,
+    /// The `;` key on a US layout
+    Semicolon,
+    /// The `'` key on a US layout
+    Quote,
+    /// The `,` key on a US layout
+    Comma,
+    /// The `.` key on a US layout
+    Period,
+    /// The `/` key on a US layout
+    Slash,
+    /// The key between the left shift and `Z` on ISO layouts
+    IntlBackslash,
+}
+
+impl PhysicalKey {
+    /// Every layout-dependent key, in the order they appear on a US keyboard.
+    pub const ALL: [Self; 12] = [
+        Self::Backquote,
+        Self::Minus,
+        Self::Equal,
+        Self::BracketLeft,
+        Self::BracketRight,
+        Self::Backslash,
+        Self::Semicolon,
+        Self::Quote,
+        Self::Comma,
+        Self::Period,
+        Self::Slash,
+        Self::IntlBackslash,
+    ];
+
+    /// Returns the physical key with the given `code` name, if any.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let key = match code {
+            "Backquote" => Self::Backquote,
+            "Minus" => Self::Minus,
+            "Equal" => Self::Equal,
+            "BracketLeft" => Self::BracketLeft,
+            "BracketRight" => Self::BracketRight,
+            "Backslash" => Self::Backslash,
+            "Semicolon" => Self::Semicolon,
+            "Quote" => Self::Quote,
+            "Comma" => Self::Comma,
+            "Period" => Self::Period,
+            "Slash" => Self::Slash,
+            "IntlBackslash" => Self::IntlBackslash,
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    /// Returns the character this key produces on a US layout.
+    pub fn us_character(&self) -> &'static str {
+        match self {
+            Self::Backquote => "`",
+            Self::Minus => "-",
+            Self::Equal => "=",
+            Self::BracketLeft => "[",
+            Self::BracketRight => "]",
+            Self::Backslash | Self::IntlBackslash => "\\",
+            Self::Semicolon => ";",
+            Self::Quote => "'",
+            Self::Comma => ",",
+            Self::Period => ".",
+            Self::Slash => "/",
+        }
+    }
+}
+
+/// Maps physical keys to the characters they produce in the current keyboard layout.
+pub trait KeyboardMapper {
+    /// Returns the unshifted character produced by the given key, if the platform
+    /// is able to tell.
+    fn character_for_physical_key(&self, key: PhysicalKey) -> Option<String>;
+
+    /// Returns the modifiers that have to be held to type the given character, or
+    /// `None` if no key in the current layout produces it.
+    ///
+    /// Platforms that can't tell report every character as typeable without modifiers.
+    fn modifiers_for_character(&self, _character: &str) -> Option<Modifiers> {
+        Some(Modifiers::default())
+    }
+}
+
+/// A [`KeyboardMapper`] for the US layout, used on platforms that can't query
+/// the current layout.
+pub struct UsKeyboardMapper;
+
+impl KeyboardMapper for UsKeyboardMapper {
+    fn character_for_physical_key(&self, key: PhysicalKey) -> Option<String> {
+        Some(key.us_character().to_string())
+    }
+}
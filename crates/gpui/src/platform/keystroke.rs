@@ -1,3 +1,4 @@
+use crate::{KeyEquivalent, KeyboardMapper, UsKeyboardMapper};
 use anyhow::anyhow;
 use serde::Deserialize;
 use smallvec::SmallVec;
@@ -58,7 +59,17 @@ impl Keystroke {
     /// [ctrl-][alt-][shift-][cmd-][fn-]key[->ime_key]
     /// ime_key syntax is only used for generating test events,
     /// when matching a key with an ime_key set will be matched without it.
+    /// Physical keys (`code:BracketLeft`) are resolved as if the US layout were active.
     pub fn parse(source: &str) -> anyhow::Result<Self> {
+        Self::parse_with_keyboard_mapper(source, &UsKeyboardMapper)
+    }
+
+    /// Like [`Keystroke::parse`], but resolves physical keys to the character they
+    /// produce with the given [`KeyboardMapper`].
+    pub fn parse_with_keyboard_mapper(
+        source: &str,
+        keyboard_mapper: &dyn KeyboardMapper,
+    ) -> anyhow::Result<Self> {
         let mut control = false;
         let mut alt = false;
         let mut shift = false;
@@ -116,6 +127,7 @@ impl Keystroke {
         }
 
         let key = key.ok_or_else(|| anyhow!("Invalid keystroke `{}`", source))?;
+        let key = KeyEquivalent::parse(&key)?.resolve(keyboard_mapper);
 
         Ok(Keystroke {
             modifiers: Modifiers {
@@ -154,7 +166,8 @@ impl Keystroke {
             self.ime_key = match self.key.as_str() {
                 "space" => Some(" ".into()),
                 "tab" => Some("\t".into()),
-                "enter" => Some("\n".into()),
+                "enter" | "numpadenter" => Some("\n".into()),
+                key if key.starts_with("numpad") => numpad_key_text(key).map(Into::into),
                 key if !is_printable_key(key) => None,
                 key => {
                     if self.modifiers.shift {
@@ -169,6 +182,47 @@ impl Keystroke {
     }
 }
 
+/// The text typed by a numpad key, such as `numpad1` or `numpadadd`, while num lock is on.
+pub(crate) fn numpad_key_text(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "numpad0" => "0",
+        "numpad1" => "1",
+        "numpad2" => "2",
+        "numpad3" => "3",
+        "numpad4" => "4",
+        "numpad5" => "5",
+        "numpad6" => "6",
+        "numpad7" => "7",
+        "numpad8" => "8",
+        "numpad9" => "9",
+        "numpaddecimal" => ".",
+        "numpadadd" => "+",
+        "numpadsubtract" => "-",
+        "numpadmultiply" => "*",
+        "numpaddivide" => "/",
+        _ => return None,
+    })
+}
+
+/// The keystroke a numpad key stands in for when no binding uses it. Only enter and keys
+/// held with a modifier that keeps them from typing fall back, as the others type their
+/// character anyway.
+pub(crate) fn numpad_fallback_keystroke(keystroke: &Keystroke) -> Option<Keystroke> {
+    let key = if keystroke.key == "numpadenter" {
+        "enter"
+    } else if keystroke.modifiers.control || keystroke.modifiers.alt || keystroke.modifiers.platform
+    {
+        numpad_key_text(&keystroke.key)?
+    } else {
+        return None;
+    };
+    Some(Keystroke {
+        modifiers: keystroke.modifiers,
+        key: key.to_string(),
+        ime_key: None,
+    })
+}
+
 fn is_printable_key(key: &str) -> bool {
     match key {
         "up" | "down" | "left" | "right" | "pageup" | "pagedown" | "home" | "end" | "delete"
@@ -245,6 +299,13 @@ pub struct Modifiers {
     pub function: bool,
 }
 
+/// The state of the capslock key at some point in time
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Hash)]
+pub struct Capslock {
+    /// The capslock key is on
+    pub on: bool,
+}
+
 impl Modifiers {
     /// Returns true if any modifier key is pressed
     pub fn modified(&self) -> bool {
@@ -366,3 +427,34 @@ impl Modifiers {
             && (other.function || !self.function)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{numpad_fallback_keystroke, numpad_key_text, Keystroke};
+
+    #[test]
+    fn test_numpad_key_text() {
+        assert_eq!(numpad_key_text("numpad0"), Some("0"));
+        assert_eq!(numpad_key_text("numpad9"), Some("9"));
+        assert_eq!(numpad_key_text("numpaddecimal"), Some("."));
+        assert_eq!(numpad_key_text("numpaddivide"), Some("/"));
+        // enter types a newline rather than text
+        assert_eq!(numpad_key_text("numpadenter"), None);
+        assert_eq!(numpad_key_text("1"), None);
+    }
+
+    #[test]
+    fn test_numpad_fallback_keystroke() {
+        let parse = |source| Keystroke::parse(source).unwrap();
+        let fallback = |source| numpad_fallback_keystroke(&parse(source));
+        assert_eq!(fallback("numpadenter"), Some(parse("enter")));
+        assert_eq!(fallback("shift-numpadenter"), Some(parse("shift-enter")));
+        assert_eq!(fallback("ctrl-numpad1"), Some(parse("ctrl-1")));
+        assert_eq!(fallback("alt-numpadadd"), Some(parse("alt-+")));
+        assert_eq!(fallback("cmd-numpad1"), Some(parse("cmd-1")));
+        // without those modifiers the key types its character
+        assert_eq!(fallback("numpad1"), None);
+        assert_eq!(fallback("shift-numpad1"), None);
+        assert_eq!(fallback("ctrl-1"), None);
+    }
+}
@@ -19,7 +19,7 @@ use std::{
 };
 
 use anyhow::anyhow;
-use ashpd::desktop::file_chooser::{OpenFileRequest, SaveFileRequest};
+use ashpd::desktop::file_chooser::{FileFilter, OpenFileRequest, SaveFileRequest};
 use async_task::Runnable;
 use calloop::channel::Channel;
 use calloop::{EventLoop, LoopHandle, LoopSignal};
@@ -37,9 +37,9 @@ use crate::platform::linux::wayland::WaylandClient;
 use crate::{
     px, Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CosmicTextSystem, CursorStyle,
     DisplayId, ForegroundExecutor, Keymap, Keystroke, LinuxDispatcher, Menu, MenuItem, Modifiers,
-    OwnedMenu, PathPromptOptions, Pixels, Platform, PlatformDisplay, PlatformInputHandler,
-    PlatformTextSystem, PlatformWindow, Point, PromptLevel, Result, SemanticVersion, SharedString,
-    Size, Task, WindowAppearance, WindowOptions, WindowParams,
+    OwnedMenu, PathPromptFilter, PathPromptOptions, Pixels, Platform, PlatformDisplay,
+    PlatformInputHandler, PlatformTextSystem, PlatformWindow, Point, PromptLevel, Result,
+    SemanticVersion, SharedString, Size, Task, WindowAppearance, WindowOptions, WindowParams,
 };
 
 use super::x11::X11Client;
@@ -253,6 +253,7 @@ impl<P: LinuxClient + 'static> Platform for P {
     fn prompt_for_paths(
         &self,
         options: PathPromptOptions,
+        filters: Vec<PathPromptFilter>,
     ) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
         let (done_tx, done_rx) = oneshot::channel();
         self.foreground_executor()
@@ -271,7 +272,11 @@ impl<P: LinuxClient + 'static> Platform for P {
                     }
                 };
 
-                let result = OpenFileRequest::default()
+                let result = filters
+                    .iter()
+                    .fold(OpenFileRequest::default(), |request, filter| {
+                        request.filter(file_filter(filter))
+                    })
                     .modal(true)
                     .title(title)
                     .accept_label("Select")
@@ -295,15 +300,26 @@ impl<P: LinuxClient + 'static> Platform for P {
         done_rx
     }
 
-    fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
+    fn prompt_for_new_path(
+        &self,
+        directory: &Path,
+        suggested_name: Option<&str>,
+        filters: Vec<PathPromptFilter>,
+    ) -> oneshot::Receiver<Option<PathBuf>> {
         let (done_tx, done_rx) = oneshot::channel();
         let directory = directory.to_owned();
+        let suggested_name = suggested_name.map(ToString::to_string);
         self.foreground_executor()
             .spawn(async move {
-                let result = SaveFileRequest::default()
+                let result = filters
+                    .iter()
+                    .fold(SaveFileRequest::default(), |request, filter| {
+                        request.filter(file_filter(filter))
+                    })
                     .modal(true)
                     .title("Select new path")
                     .accept_label("Accept")
+                    .current_name(suggested_name.as_deref())
                     .send()
                     .await
                     .ok()
@@ -490,6 +506,17 @@ impl<P: LinuxClient + 'static> Platform for P {
     fn add_recent_document(&self, _path: &Path) {}
 }
 
+fn file_filter(filter: &PathPromptFilter) -> FileFilter {
+    // a filter without extensions lists every file
+    if filter.extensions.is_empty() {
+        return FileFilter::new(filter.name.as_ref()).glob("*");
+    }
+    filter.extensions.iter().fold(
+        FileFilter::new(filter.name.as_ref()),
+        |file_filter, extension| file_filter.glob(&format!("*.{}", extension)),
+    )
+}
+
 pub(super) fn open_uri_internal(uri: &str, activation_token: Option<&str>) {
     let mut last_err = None;
     for mut command in open::commands(uri) {
@@ -565,6 +592,7 @@ impl CursorStyle {
             CursorStyle::DragLink => Shape::Alias,
             CursorStyle::DragCopy => Shape::Copy,
             CursorStyle::ContextualMenu => Shape::ContextMenu,
+            CursorStyle::Custom(_) => Shape::Default,
         }
     }
 
@@ -592,6 +620,7 @@ impl CursorStyle {
             CursorStyle::DragLink => "alias",
             CursorStyle::DragCopy => "copy",
             CursorStyle::ContextualMenu => "context-menu",
+            CursorStyle::Custom(_) => "arrow",
         }
         .to_string()
     }
@@ -611,6 +640,24 @@ impl Keystroke {
             Keysym::Next => "pagedown".to_owned(),
             Keysym::ISO_Left_Tab => "tab".to_owned(),
 
+            // with num lock off, the numpad keys move the cursor instead
+            Keysym::KP_0 => "numpad0".to_owned(),
+            Keysym::KP_1 => "numpad1".to_owned(),
+            Keysym::KP_2 => "numpad2".to_owned(),
+            Keysym::KP_3 => "numpad3".to_owned(),
+            Keysym::KP_4 => "numpad4".to_owned(),
+            Keysym::KP_5 => "numpad5".to_owned(),
+            Keysym::KP_6 => "numpad6".to_owned(),
+            Keysym::KP_7 => "numpad7".to_owned(),
+            Keysym::KP_8 => "numpad8".to_owned(),
+            Keysym::KP_9 => "numpad9".to_owned(),
+            Keysym::KP_Decimal => "numpaddecimal".to_owned(),
+            Keysym::KP_Add => "numpadadd".to_owned(),
+            Keysym::KP_Subtract => "numpadsubtract".to_owned(),
+            Keysym::KP_Multiply => "numpadmultiply".to_owned(),
+            Keysym::KP_Divide => "numpaddivide".to_owned(),
+            Keysym::KP_Enter => "numpadenter".to_owned(),
+
             Keysym::comma => ",".to_owned(),
             Keysym::period => ".".to_owned(),
             Keysym::less => "<".to_owned(),
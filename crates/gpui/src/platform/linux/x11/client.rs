@@ -543,7 +543,10 @@ impl X11Client {
 
                     let focused_window = self.get_window(focused_window_id)?;
                     focused_window.handle_input(PlatformInput::ModifiersChanged(
-                        ModifiersChangedEvent { modifiers },
+                        ModifiersChangedEvent {
+                            modifiers,
+                            ..Default::default()
+                        },
                     ));
                 }
             }
@@ -1,8 +1,8 @@
 use crate::{
-    platform::mac::NSStringExt, point, px, KeyDownEvent, KeyUpEvent, Keystroke, Modifiers,
-    ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseExitEvent, MouseMoveEvent,
-    MouseUpEvent, NavigationDirection, Pixels, PlatformInput, ScrollDelta, ScrollWheelEvent,
-    TouchPhase,
+    platform::mac::NSStringExt, point, px, Capslock, KeyDownEvent, KeyUpEvent, Keystroke,
+    Modifiers, ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseExitEvent, MouseMoveEvent,
+    MouseUpEvent, NavigationDirection, PinchEvent, Pixels, PlatformInput, ScrollDelta,
+    ScrollWheelEvent, TouchPhase,
 };
 use cocoa::{
     appkit::{NSEvent, NSEventModifierFlags, NSEventPhase, NSEventType},
@@ -82,6 +82,13 @@ unsafe fn read_modifiers(native_event: id) -> Modifiers {
     }
 }
 
+unsafe fn read_capslock(native_event: id) -> Capslock {
+    let modifiers = native_event.modifierFlags();
+    Capslock {
+        on: modifiers.contains(NSEventModifierFlags::NSAlphaShiftKeyMask),
+    }
+}
+
 impl PlatformInput {
     pub(crate) unsafe fn from_native(
         native_event: id,
@@ -101,6 +108,7 @@ impl PlatformInput {
         match event_type {
             NSEventType::NSFlagsChanged => Some(Self::ModifiersChanged(ModifiersChangedEvent {
                 modifiers: read_modifiers(native_event),
+                capslock: read_capslock(native_event),
             })),
             NSEventType::NSKeyDown => Some(Self::KeyDown(KeyDownEvent {
                 keystroke: parse_keystroke(native_event),
@@ -190,6 +198,26 @@ impl PlatformInput {
                     modifiers: read_modifiers(native_event),
                 })
             }),
+            NSEventType::NSEventTypeMagnify => window_height.map(|window_height| {
+                let phase = match native_event.phase() {
+                    NSEventPhase::NSEventPhaseMayBegin | NSEventPhase::NSEventPhaseBegan => {
+                        TouchPhase::Started
+                    }
+                    NSEventPhase::NSEventPhaseEnded | NSEventPhase::NSEventPhaseCancelled => {
+                        TouchPhase::Ended
+                    }
+                    _ => TouchPhase::Moved,
+                };
+                Self::Pinch(PinchEvent {
+                    position: point(
+                        px(native_event.locationInWindow().x as f32),
+                        window_height - px(native_event.locationInWindow().y as f32),
+                    ),
+                    delta: native_event.magnification() as f32,
+                    modifiers: read_modifiers(native_event),
+                    phase,
+                })
+            }),
             NSEventType::NSLeftMouseDragged
             | NSEventType::NSRightMouseDragged
             | NSEventType::NSOtherMouseDragged => {
@@ -316,6 +344,8 @@ unsafe fn parse_keystroke(native_event: id) -> Keystroke {
             }
         }
     };
+    // numpad keys produce the same characters as the keys they stand in for
+    let key = numpad_key(native_event.keyCode()).map_or(key, ToString::to_string);
 
     Keystroke {
         modifiers: Modifiers {
@@ -330,6 +360,28 @@ unsafe fn parse_keystroke(native_event: id) -> Keystroke {
     }
 }
 
+fn numpad_key(code: CGKeyCode) -> Option<&'static str> {
+    Some(match code {
+        0x52 => "numpad0",
+        0x53 => "numpad1",
+        0x54 => "numpad2",
+        0x55 => "numpad3",
+        0x56 => "numpad4",
+        0x57 => "numpad5",
+        0x58 => "numpad6",
+        0x59 => "numpad7",
+        0x5b => "numpad8",
+        0x5c => "numpad9",
+        0x41 => "numpaddecimal",
+        0x45 => "numpadadd",
+        0x4e => "numpadsubtract",
+        0x43 => "numpadmultiply",
+        0x4b => "numpaddivide",
+        0x4c => "numpadenter",
+        _ => return None,
+    })
+}
+
 fn chars_for_modified_key(code: CGKeyCode, cmd: bool, shift: bool) -> String {
     // Ideally, we would use `[NSEvent charactersByApplyingModifiers]` but that
     // always returns an empty string with certain keyboards, e.g. Japanese. Synthesizing
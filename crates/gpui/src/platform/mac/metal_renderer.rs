@@ -14,6 +14,7 @@ use cocoa::{
 use collections::HashMap;
 use core_foundation::base::TCFType;
 use foreign_types::ForeignType;
+use image::RgbaImage;
 use media::core_video::CVMetalTextureCache;
 use metal::{CAMetalLayer, CommandQueue, MTLPixelFormat, MTLResourceOptions, NSRange};
 use objc::{self, msg_send, sel, sel_impl};
@@ -306,11 +307,37 @@ impl MetalRenderer {
             return;
         };
 
+        let Some(command_buffer) =
+            self.encode_scene(scene, drawable.texture(), viewport_size, layer.is_opaque())
+        else {
+            return;
+        };
+        if self.presents_with_transaction {
+            command_buffer.commit();
+            command_buffer.wait_until_scheduled();
+            drawable.present();
+        } else {
+            command_buffer.present_drawable(drawable);
+            command_buffer.commit();
+        }
+    }
+
+    /// Encodes the scene into a command buffer drawing to `target`, growing the instance
+    /// buffer and trying again while the scene doesn't fit in it. The instance buffer goes
+    /// back to the pool once the GPU is done with the command buffer, which is left to the
+    /// caller to commit.
+    fn encode_scene(
+        &mut self,
+        scene: &Scene,
+        target: &metal::TextureRef,
+        viewport_size: Size<DevicePixels>,
+        opaque: bool,
+    ) -> Option<metal::CommandBuffer> {
         loop {
             let mut instance_buffer = self.instance_buffer_pool.lock().acquire(&self.device);
 
             let command_buffer =
-                self.draw_primitives(scene, &mut instance_buffer, drawable, viewport_size);
+                self.draw_primitives(scene, &mut instance_buffer, target, viewport_size, opaque);
 
             match command_buffer {
                 Ok(command_buffer) => {
@@ -323,16 +350,7 @@ impl MetalRenderer {
                     });
                     let block = block.copy();
                     command_buffer.add_completed_handler(&block);
-
-                    if self.presents_with_transaction {
-                        command_buffer.commit();
-                        command_buffer.wait_until_scheduled();
-                        drawable.present();
-                    } else {
-                        command_buffer.present_drawable(drawable);
-                        command_buffer.commit();
-                    }
-                    return;
+                    return Some(command_buffer);
                 }
                 Err(err) => {
                     log::error!(
@@ -343,7 +361,7 @@ impl MetalRenderer {
                     let buffer_size = instance_buffer_pool.buffer_size;
                     if buffer_size >= 256 * 1024 * 1024 {
                         log::error!("instance buffer size grew too large: {}", buffer_size);
-                        break;
+                        return None;
                     }
                     instance_buffer_pool.reset(buffer_size * 2);
                     log::info!(
@@ -355,12 +373,57 @@ impl MetalRenderer {
         }
     }
 
+    /// Draws the scene into an offscreen texture of the given size and reads it back, for
+    /// rendering elements into images without presenting them. Only the device is drawn
+    /// with, so it works without a drawable, like while the window is minimized or hidden.
+    pub fn render_to_image(
+        &mut self,
+        scene: &Scene,
+        size: Size<DevicePixels>,
+    ) -> Result<RgbaImage> {
+        let width = size.width.0.max(1) as u64;
+        let height = size.height.0.max(1) as u64;
+        let texture_descriptor = metal::TextureDescriptor::new();
+        texture_descriptor.set_width(width);
+        texture_descriptor.set_height(height);
+        texture_descriptor.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+        texture_descriptor.set_storage_mode(metal::MTLStorageMode::Managed);
+        texture_descriptor.set_usage(metal::MTLTextureUsage::RenderTarget);
+        let texture = self.device.new_texture(&texture_descriptor);
+
+        let command_buffer = self
+            .encode_scene(scene, &texture, size, false)
+            .ok_or_else(|| anyhow!("scene too large to render to an image"))?;
+        // managed textures have to be synchronized before the CPU can read them
+        let blit_encoder = command_buffer.new_blit_command_encoder();
+        blit_encoder.synchronize_resource(&texture);
+        blit_encoder.end_encoding();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        let bytes_per_row = width * 4;
+        let mut pixels = vec![0u8; (bytes_per_row * height) as usize];
+        texture.get_bytes(
+            pixels.as_mut_ptr() as *mut c_void,
+            bytes_per_row,
+            metal::MTLRegion::new_2d(0, 0, width, height),
+            0,
+        );
+        // BGRA to RGBA
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        RgbaImage::from_raw(width as u32, height as u32, pixels)
+            .ok_or_else(|| anyhow!("offscreen image has the wrong size"))
+    }
+
     fn draw_primitives(
         &mut self,
         scene: &Scene,
         instance_buffer: &mut InstanceBuffer,
-        drawable: &metal::MetalDrawableRef,
+        target: &metal::TextureRef,
         viewport_size: Size<DevicePixels>,
+        opaque: bool,
     ) -> Result<metal::CommandBuffer> {
         let command_queue = self.command_queue.clone();
         let command_buffer = command_queue.new_command_buffer();
@@ -381,10 +444,10 @@ impl MetalRenderer {
             .object_at(0)
             .unwrap();
 
-        color_attachment.set_texture(Some(drawable.texture()));
+        color_attachment.set_texture(Some(target));
         color_attachment.set_load_action(metal::MTLLoadAction::Clear);
         color_attachment.set_store_action(metal::MTLStoreAction::Store);
-        let alpha = if self.layer.is_opaque() { 1. } else { 0. };
+        let alpha = if opaque { 1. } else { 0. };
         color_attachment.set_clear_color(metal::MTLClearColor::new(0., 0., 0., alpha));
         let command_encoder = command_buffer.new_render_command_encoder(render_pass_descriptor);
 
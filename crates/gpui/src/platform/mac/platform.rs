@@ -1,9 +1,9 @@
 use super::{events::key_to_native, BoolExt};
 use crate::{
     Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, ForegroundExecutor,
-    Keymap, MacDispatcher, MacDisplay, MacTextSystem, MacWindow, Menu, MenuItem, PathPromptOptions,
-    Platform, PlatformDisplay, PlatformTextSystem, PlatformWindow, Result, SemanticVersion, Task,
-    WindowAppearance, WindowParams,
+    Keymap, MacDispatcher, MacDisplay, MacTextSystem, MacWindow, Menu, MenuItem, PathPromptFilter,
+    PathPromptOptions, Platform, PlatformDisplay, PlatformTextSystem, PlatformWindow, Result,
+    SemanticVersion, Task, WindowAppearance, WindowParams,
 };
 use anyhow::anyhow;
 use block::ConcreteBlock;
@@ -602,6 +602,7 @@ impl Platform for MacPlatform {
     fn prompt_for_paths(
         &self,
         options: PathPromptOptions,
+        filters: Vec<PathPromptFilter>,
     ) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
         let (done_tx, done_rx) = oneshot::channel();
         self.foreground_executor()
@@ -613,6 +614,7 @@ impl Platform for MacPlatform {
                     panel.setAllowsMultipleSelection_(options.multiple.to_objc());
                     panel.setCanCreateDirectories(true.to_objc());
                     panel.setResolvesAliases_(false.to_objc());
+                    set_allowed_file_types(panel, &filters);
                     let done_tx = Cell::new(Some(done_tx));
                     let block = ConcreteBlock::new(move |response: NSModalResponse| {
                         let result = if response == NSModalResponse::NSModalResponseOk {
@@ -643,8 +645,14 @@ impl Platform for MacPlatform {
         done_rx
     }
 
-    fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
+    fn prompt_for_new_path(
+        &self,
+        directory: &Path,
+        suggested_name: Option<&str>,
+        filters: Vec<PathPromptFilter>,
+    ) -> oneshot::Receiver<Option<PathBuf>> {
         let directory = directory.to_owned();
+        let suggested_name = suggested_name.map(ToString::to_string);
         let (done_tx, done_rx) = oneshot::channel();
         self.foreground_executor()
             .spawn(async move {
@@ -653,6 +661,10 @@ impl Platform for MacPlatform {
                     let path = ns_string(directory.to_string_lossy().as_ref());
                     let url = NSURL::fileURLWithPath_isDirectory_(nil, path, true.to_objc());
                     panel.setDirectoryURL(url);
+                    if let Some(name) = suggested_name {
+                        let _: () = msg_send![panel, setNameFieldStringValue: ns_string(&name)];
+                    }
+                    set_allowed_file_types(panel, &filters);
 
                     let done_tx = Cell::new(Some(done_tx));
                     let block = ConcreteBlock::new(move |response: NSModalResponse| {
@@ -813,6 +825,7 @@ impl Platform for MacPlatform {
                 CursorStyle::DragLink => msg_send![class!(NSCursor), dragLinkCursor],
                 CursorStyle::DragCopy => msg_send![class!(NSCursor), dragCopyCursor],
                 CursorStyle::ContextualMenu => msg_send![class!(NSCursor), contextualMenuCursor],
+                CursorStyle::Custom(_) => msg_send![class!(NSCursor), arrowCursor],
             };
 
             let old_cursor: id = msg_send![class!(NSCursor), currentCursor];
@@ -1159,6 +1172,20 @@ unsafe fn ns_string(string: &str) -> id {
     NSString::alloc(nil).init_str(string).autorelease()
 }
 
+/// Panels can't switch between filters, so they list the files matching any of them.
+unsafe fn set_allowed_file_types(panel: id, filters: &[PathPromptFilter]) {
+    // a filter without extensions lists every file
+    if filters.is_empty() || filters.iter().any(|filter| filter.extensions.is_empty()) {
+        return;
+    }
+    let extensions = filters
+        .iter()
+        .flat_map(|filter| filter.extensions.iter())
+        .map(|extension| ns_string(extension.as_ref()))
+        .collect::<Vec<_>>();
+    let _: () = msg_send![panel, setAllowedFileTypes: NSArray::arrayWithObjects(nil, &extensions)];
+}
+
 unsafe fn ns_url_to_path(url: id) -> Result<PathBuf> {
     let path: *mut c_char = msg_send![url, fileSystemRepresentation];
     if path.is_null() {
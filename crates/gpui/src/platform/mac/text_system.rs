@@ -1,7 +1,8 @@
 use crate::{
     point, px, size, Bounds, DevicePixels, Font, FontFeatures, FontId, FontMetrics, FontRun,
-    FontStyle, FontWeight, GlyphId, LineLayout, Pixels, PlatformTextSystem, Point,
-    RenderGlyphParams, Result, ShapedGlyph, ShapedRun, SharedString, Size, SUBPIXEL_VARIANTS,
+    FontStretch, FontStyle, FontWeight, GlyphId, LineLayout, Pixels, PlatformTextSystem, Point,
+    RenderGlyphParams, Result, ShapedGlyph, ShapedRun, SharedString, Size, TextSystemError,
+    OS2_TABLE_TAG, SUBPIXEL_VARIANTS,
 };
 use anyhow::anyhow;
 use cocoa::appkit::{CGFloat, CGPoint};
@@ -30,7 +31,7 @@ use font_kit::{
     handle::Handle,
     hinting::HintingOptions,
     metrics::Metrics,
-    properties::{Style as FontkitStyle, Weight as FontkitWeight},
+    properties::{Stretch as FontkitStretch, Style as FontkitStyle, Weight as FontkitWeight},
     source::SystemSource,
     sources::mem::MemSource,
 };
@@ -142,9 +143,10 @@ impl PlatformTextSystem for MacTextSystem {
                 &font_kit::properties::Properties {
                     style: font.style.into(),
                     weight: font.weight.into(),
-                    stretch: Default::default(),
+                    stretch: font.stretch.into(),
                 },
-            )?;
+            )
+            .map_err(|_| TextSystemError::FontNotFound(font.family.clone()))?;
 
             let font_id = candidates[ix];
             lock.font_selections.insert(font.clone(), font_id);
@@ -153,7 +155,15 @@ impl PlatformTextSystem for MacTextSystem {
     }
 
     fn font_metrics(&self, font_id: FontId) -> FontMetrics {
-        self.0.read().fonts[font_id.0].metrics().into()
+        let lock = self.0.read();
+        let font = &lock.fonts[font_id.0];
+        let mut metrics = FontMetrics::from(font.metrics());
+        metrics.read_script_metrics(font.load_font_table(OS2_TABLE_TAG).as_deref());
+        metrics
+    }
+
+    fn is_monospace(&self, font_id: FontId) -> bool {
+        self.0.read().fonts[font_id.0].is_monospace()
     }
 
     fn typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
@@ -351,7 +361,7 @@ impl MacTextSystemState {
         glyph_bounds: Bounds<DevicePixels>,
     ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
         if glyph_bounds.size.width.0 == 0 || glyph_bounds.size.height.0 == 0 {
-            Err(anyhow!("glyph bounds are empty"))
+            Err(TextSystemError::MissingGlyph.into())
         } else {
             // Add an extra pixel when the subpixel variant isn't zero to make room for anti-aliasing.
             let mut bitmap_size = glyph_bounds.size;
@@ -484,6 +494,8 @@ impl MacTextSystemState {
                     .unwrap()
             };
             let font_id = self.id_for_native_font(font);
+            let decorations =
+                FontMetrics::from(self.fonts[font_id.0].metrics()).decoration_metrics(font_size);
 
             let mut ix_converter = StringIndexConverter::new(text);
             let mut glyphs = SmallVec::new();
@@ -503,18 +515,25 @@ impl MacTextSystemState {
                 });
             }
 
-            runs.push(ShapedRun { font_id, glyphs })
+            runs.push(ShapedRun {
+                font_id,
+                glyphs,
+                decorations: Some(decorations),
+            })
         }
 
         let typographic_bounds = line.get_typographic_bounds();
-        LineLayout {
+        let mut layout = LineLayout {
             runs,
             font_size,
             width: typographic_bounds.width.into(),
             ascent: typographic_bounds.ascent.into(),
             descent: typographic_bounds.descent.into(),
             len: text.len(),
-        }
+            ..Default::default()
+        };
+        layout.measure_whitespace(text);
+        layout
     }
 }
 
@@ -568,6 +587,11 @@ impl From<Metrics> for FontMetrics {
             underline_thickness: metrics.underline_thickness,
             cap_height: metrics.cap_height,
             x_height: metrics.x_height,
+            // font-kit doesn't expose these, see `FontMetrics::read_script_metrics`
+            superscript_size: 0.,
+            superscript_offset: 0.,
+            subscript_size: 0.,
+            subscript_offset: 0.,
             bounding_box: metrics.bounding_box.into(),
         }
     }
@@ -624,6 +648,22 @@ impl From<FontWeight> for FontkitWeight {
     }
 }
 
+impl From<FontStretch> for FontkitStretch {
+    fn from(stretch: FontStretch) -> Self {
+        match stretch {
+            FontStretch::UltraCondensed => FontkitStretch::ULTRA_CONDENSED,
+            FontStretch::ExtraCondensed => FontkitStretch::EXTRA_CONDENSED,
+            FontStretch::Condensed => FontkitStretch::CONDENSED,
+            FontStretch::SemiCondensed => FontkitStretch::SEMI_CONDENSED,
+            FontStretch::Normal => FontkitStretch::NORMAL,
+            FontStretch::SemiExpanded => FontkitStretch::SEMI_EXPANDED,
+            FontStretch::Expanded => FontkitStretch::EXPANDED,
+            FontStretch::ExtraExpanded => FontkitStretch::EXTRA_EXPANDED,
+            FontStretch::UltraExpanded => FontkitStretch::ULTRA_EXPANDED,
+        }
+    }
+}
+
 impl From<FontStyle> for FontkitStyle {
     fn from(style: FontStyle) -> Self {
         match style {
@@ -675,7 +715,7 @@ mod lenient_font_attributes {
 
 #[cfg(test)]
 mod tests {
-    use crate::{font, px, FontRun, GlyphId, MacTextSystem, PlatformTextSystem};
+    use crate::{font, px, FontFeatures, FontRun, GlyphId, MacTextSystem, PlatformTextSystem};
 
     #[test]
     fn test_layout_line_bom_char() {
@@ -685,9 +725,10 @@ mod tests {
         let mut style = FontRun {
             font_id,
             len: line.len(),
+            features: FontFeatures::default(),
         };
 
-        let layout = fonts.layout_line(line, px(16.), &[style]);
+        let layout = fonts.layout_line(line, px(16.), &[style.clone()]);
         assert_eq!(layout.len, line.len());
         assert!(layout.runs.is_empty());
 
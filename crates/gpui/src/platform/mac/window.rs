@@ -4,7 +4,8 @@ use crate::{
     ExternalPaths, FileDropEvent, ForegroundExecutor, KeyDownEvent, Keystroke, Modifiers,
     ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels,
     PlatformAtlas, PlatformDisplay, PlatformInput, PlatformWindow, Point, PromptLevel, Size, Timer,
-    WindowAppearance, WindowBackgroundAppearance, WindowBounds, WindowKind, WindowParams,
+    WindowAppearance, WindowBackgroundAppearance, WindowBadge, WindowBounds, WindowKind,
+    WindowLevel, WindowParams,
 };
 use block::ConcreteBlock;
 use cocoa::{
@@ -58,8 +59,16 @@ const NSWindowStyleMaskNonactivatingPanel: NSWindowStyleMask =
 #[allow(non_upper_case_globals)]
 const NSNormalWindowLevel: NSInteger = 0;
 #[allow(non_upper_case_globals)]
+const NSFloatingWindowLevel: NSInteger = 3;
+#[allow(non_upper_case_globals)]
+const NSStatusWindowLevel: NSInteger = 25;
+#[allow(non_upper_case_globals)]
 const NSPopUpWindowLevel: NSInteger = 101;
 #[allow(non_upper_case_globals)]
+const NSCriticalRequest: NSInteger = 0;
+#[allow(non_upper_case_globals)]
+const NSInformationalRequest: NSInteger = 10;
+#[allow(non_upper_case_globals)]
 const NSTrackingMouseEnteredAndExited: NSUInteger = 0x01;
 #[allow(non_upper_case_globals)]
 const NSTrackingMouseMoved: NSUInteger = 0x02;
@@ -147,6 +156,10 @@ unsafe fn build_classes() {
             sel!(scrollWheel:),
             handle_view_event as extern "C" fn(&Object, Sel, id),
         );
+        decl.add_method(
+            sel!(magnifyWithEvent:),
+            handle_view_event as extern "C" fn(&Object, Sel, id),
+        );
         decl.add_method(
             sel!(flagsChanged:),
             handle_view_event as extern "C" fn(&Object, Sel, id),
@@ -997,6 +1010,50 @@ impl PlatformWindow for MacWindow {
         self.0.lock().move_traffic_light();
     }
 
+    fn set_opacity(&self, opacity: f32) {
+        unsafe {
+            let window = self.0.lock().native_window;
+            let _: () = msg_send![window, setAlphaValue: opacity.clamp(0., 1.) as f64];
+        }
+    }
+
+    fn set_level(&self, level: WindowLevel) {
+        let level = match level {
+            WindowLevel::Normal => NSNormalWindowLevel,
+            WindowLevel::Floating => NSFloatingWindowLevel,
+            WindowLevel::AlwaysOnTop => NSStatusWindowLevel,
+        };
+        unsafe { self.0.lock().native_window.setLevel_(level) };
+    }
+
+    // the dock tile belongs to the whole app, so the last window to set a badge wins
+    fn set_badge(&self, badge: Option<WindowBadge>) {
+        unsafe {
+            let label = match badge {
+                Some(WindowBadge::Count(count)) if count > 0 => ns_string(&count.to_string()),
+                Some(WindowBadge::Dot) => ns_string("•"),
+                _ => nil,
+            };
+            let app = NSApplication::sharedApplication(nil);
+            let dock_tile: id = msg_send![app, dockTile];
+            let _: () = msg_send![dock_tile, setBadgeLabel: label];
+        }
+    }
+
+    // the dock icon can't bounce a given number of times, but an informational request
+    // bounces it once while a critical one keeps bouncing until the app is activated
+    fn request_user_attention(&self, flash_count: Option<u32>) {
+        let request_type = if flash_count.is_some() {
+            NSInformationalRequest
+        } else {
+            NSCriticalRequest
+        };
+        unsafe {
+            let app = NSApplication::sharedApplication(nil);
+            let _: NSInteger = msg_send![app, requestUserAttention: request_type];
+        }
+    }
+
     fn show_character_palette(&self) {
         let this = self.0.lock();
         let window = this.native_window;
@@ -1093,6 +1150,14 @@ impl PlatformWindow for MacWindow {
         self.0.lock().renderer.sprite_atlas().clone()
     }
 
+    fn render_to_image(
+        &self,
+        scene: &crate::Scene,
+        size: Size<crate::DevicePixels>,
+    ) -> anyhow::Result<image::RgbaImage> {
+        self.0.lock().renderer.render_to_image(scene, size)
+    }
+
     fn show_window_menu(&self, _position: Point<Pixels>) {}
 
     fn start_system_move(&self) {}
@@ -1399,13 +1464,17 @@ extern "C" fn handle_view_event(this: &Object, _: Sel, native_event: id) {
                 lock.synthetic_drag_counter += 1;
             }
 
-            PlatformInput::ModifiersChanged(ModifiersChangedEvent { modifiers }) => {
+            PlatformInput::ModifiersChanged(ModifiersChangedEvent {
+                modifiers,
+                capslock,
+            }) => {
                 // Only raise modifiers changed event when they have actually changed
                 if let Some(PlatformInput::ModifiersChanged(ModifiersChangedEvent {
                     modifiers: prev_modifiers,
+                    capslock: prev_capslock,
                 })) = &lock.previous_modifiers_changed_event
                 {
-                    if prev_modifiers == modifiers {
+                    if prev_modifiers == modifiers && prev_capslock == capslock {
                         return;
                     }
                 }
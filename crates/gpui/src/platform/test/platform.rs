@@ -216,6 +216,7 @@ impl Platform for TestPlatform {
     fn prompt_for_paths(
         &self,
         _options: crate::PathPromptOptions,
+        _filters: Vec<crate::PathPromptFilter>,
     ) -> oneshot::Receiver<Option<Vec<std::path::PathBuf>>> {
         unimplemented!()
     }
@@ -223,6 +224,8 @@ impl Platform for TestPlatform {
     fn prompt_for_new_path(
         &self,
         directory: &std::path::Path,
+        _suggested_name: Option<&str>,
+        _filters: Vec<crate::PathPromptFilter>,
     ) -> oneshot::Receiver<Option<std::path::PathBuf>> {
         let (tx, rx) = oneshot::channel();
         self.prompts
@@ -1,18 +1,50 @@
+mod backdrop;
+mod custom_cursor;
+mod default_handlers;
 mod direct_write;
 mod dispatcher;
 mod display;
+mod drag_source;
 mod events;
+mod file_dialog;
+mod frame;
+mod jump_list;
+mod keyboard;
 mod platform;
+mod preview;
+mod sandbox;
 mod system_settings;
+mod taskbar;
+mod timer_resolution;
+mod toast;
+mod touch_keyboard;
+mod tray;
 mod util;
 mod window;
 
+pub(crate) use backdrop::*;
+pub(crate) use custom_cursor::*;
+pub(crate) use default_handlers::*;
 pub(crate) use direct_write::*;
 pub(crate) use dispatcher::*;
 pub(crate) use display::*;
+pub(crate) use drag_source::*;
 pub(crate) use events::*;
+pub(crate) use file_dialog::*;
+pub(crate) use frame::*;
+pub(crate) use jump_list::*;
+pub(crate) use keyboard::*;
 pub(crate) use platform::*;
+pub use preview::NativePreview;
+pub(crate) use preview::*;
+pub(crate) use sandbox::*;
 pub(crate) use system_settings::*;
+pub use system_settings::{TaskbarEdge, TaskbarPosition};
+pub(crate) use taskbar::*;
+pub(crate) use timer_resolution::*;
+pub(crate) use toast::*;
+pub(crate) use touch_keyboard::*;
+pub(crate) use tray::*;
 pub(crate) use util::*;
 pub(crate) use window::*;
 
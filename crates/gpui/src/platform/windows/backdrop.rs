@@ -0,0 +1,95 @@
+use std::{ffi::c_void, sync::OnceLock};
+
+use ::util::ResultExt;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::{Dwm::*, Gdi::*},
+        System::LibraryLoader::*,
+    },
+};
+
+use crate::*;
+
+// `SetWindowCompositionAttribute` is undocumented, but it's the only way to get a blurred
+// backdrop on Windows 10, and Windows 11 still supports it.
+// https://github.com/riverar/sample-win32-acrylicblur
+const WCA_ACCENT_POLICY: u32 = 19;
+const ACCENT_DISABLED: u32 = 0;
+const ACCENT_ENABLE_ACRYLICBLURBEHIND: u32 = 4;
+// the tint drawn over the blur, as ABGR. It's kept nearly transparent, since the app draws
+// its own translucent background over it.
+const ACRYLIC_TINT: u32 = 0x0100_0000;
+
+#[repr(C)]
+struct AccentPolicy {
+    accent_state: u32,
+    accent_flags: u32,
+    gradient_color: u32,
+    animation_id: u32,
+}
+
+#[repr(C)]
+struct WindowCompositionAttributeData {
+    attribute: u32,
+    data: *mut c_void,
+    size: usize,
+}
+
+type SetWindowCompositionAttribute =
+    unsafe extern "system" fn(HWND, *mut WindowCompositionAttributeData) -> BOOL;
+
+/// Lets the compositor show what's behind the transparent parts of the window, which it
+/// otherwise fills with black, and blurs it for [`WindowBackgroundAppearance::Blurred`].
+pub(crate) fn set_window_backdrop(hwnd: HWND, appearance: WindowBackgroundAppearance) {
+    // an empty blur region makes the compositor blend the window by its alpha channel,
+    // without blurring anything by itself
+    unsafe {
+        let region = CreateRectRgn(0, 0, -1, -1);
+        let blur_behind = DWM_BLURBEHIND {
+            dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
+            fEnable: (appearance != WindowBackgroundAppearance::Opaque).into(),
+            hRgnBlur: region,
+            fTransitionOnMaximized: FALSE,
+        };
+        DwmEnableBlurBehindWindow(hwnd, &blur_behind).log_err();
+        DeleteObject(region);
+    }
+    let accent_state = if appearance == WindowBackgroundAppearance::Blurred {
+        ACCENT_ENABLE_ACRYLICBLURBEHIND
+    } else {
+        ACCENT_DISABLED
+    };
+    set_accent_policy(hwnd, accent_state);
+}
+
+fn set_accent_policy(hwnd: HWND, accent_state: u32) {
+    static SET_WINDOW_COMPOSITION_ATTRIBUTE: OnceLock<Option<SetWindowCompositionAttribute>> =
+        OnceLock::new();
+    let Some(set_window_composition_attribute) =
+        SET_WINDOW_COMPOSITION_ATTRIBUTE.get_or_init(|| unsafe {
+            let user32 = GetModuleHandleW(w!("user32.dll")).log_err()?;
+            let address = GetProcAddress(user32, s!("SetWindowCompositionAttribute"))?;
+            Some(std::mem::transmute::<_, SetWindowCompositionAttribute>(
+                address,
+            ))
+        })
+    else {
+        return;
+    };
+    let mut policy = AccentPolicy {
+        accent_state,
+        accent_flags: 0,
+        gradient_color: ACRYLIC_TINT,
+        animation_id: 0,
+    };
+    let mut data = WindowCompositionAttributeData {
+        attribute: WCA_ACCENT_POLICY,
+        data: &mut policy as *mut AccentPolicy as *mut c_void,
+        size: std::mem::size_of::<AccentPolicy>(),
+    };
+    if !unsafe { set_window_composition_attribute(hwnd, &mut data) }.as_bool() {
+        log::error!("unable to set the backdrop of window {:?}", hwnd);
+    }
+}
@@ -0,0 +1,77 @@
+use ::util::ResultExt;
+use anyhow::{anyhow, ensure, Result};
+use parking_lot::Mutex;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::*;
+
+/// The images of every registered cursor, indexed by [`CustomCursorId`] and sorted by scale
+/// factor. The first image stands for the whole cursor when the platform posts
+/// `CURSOR_STYLE_CHANGED`, and each window swaps it for the image matching its own scale.
+static CUSTOM_CURSORS: Mutex<Vec<Vec<(f32, HCURSOR)>>> = Mutex::new(Vec::new());
+
+pub(crate) fn register_custom_cursor(mut images: Vec<CustomCursorImage>) -> Result<CustomCursorId> {
+    ensure!(
+        !images.is_empty(),
+        "a custom cursor needs at least one image"
+    );
+    images.sort_by(|a, b| a.scale_factor.total_cmp(&b.scale_factor));
+    let mut cursors = Vec::with_capacity(images.len());
+    for image in &images {
+        match create_cursor(image) {
+            Ok(cursor) => cursors.push((image.scale_factor, cursor)),
+            Err(error) => {
+                for (_, cursor) in cursors {
+                    unsafe { DestroyCursor(cursor) }.log_err();
+                }
+                return Err(error);
+            }
+        }
+    }
+    let mut registry = CUSTOM_CURSORS.lock();
+    registry.push(cursors);
+    Ok(CustomCursorId(registry.len() - 1))
+}
+
+pub(crate) fn custom_cursor(id: CustomCursorId) -> Option<HCURSOR> {
+    CUSTOM_CURSORS.lock().get(id.0).map(|cursors| cursors[0].1)
+}
+
+/// Picks the image of a custom cursor to show at the given scale factor: the smallest one
+/// drawn for at least that scale, as shrinking an image looks better than blowing it up.
+/// Stock cursors are returned as they are, since the system scales those itself.
+pub(crate) fn scaled_cursor(cursor: HCURSOR, scale_factor: f32) -> HCURSOR {
+    let registry = CUSTOM_CURSORS.lock();
+    let Some(cursors) = registry.iter().find(|cursors| cursors[0].1 == cursor) else {
+        return cursor;
+    };
+    cursors
+        .iter()
+        .find(|(scale, _)| *scale >= scale_factor)
+        .or(cursors.last())
+        .map_or(cursor, |(_, cursor)| *cursor)
+}
+
+fn create_cursor(image: &CustomCursorImage) -> Result<HCURSOR> {
+    let width = image.size.width.0;
+    let height = image.size.height.0;
+    ensure!(
+        width > 0 && height > 0 && image.rgba.len() == (width * height * 4) as usize,
+        "cursor image of {}x{} pixels has {} bytes",
+        width,
+        height,
+        image.rgba.len()
+    );
+    let bgra = image
+        .rgba
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+        .collect::<Vec<_>>();
+    let hotspot = (
+        image.hotspot.x.0.clamp(0, width - 1) as u32,
+        image.hotspot.y.0.clamp(0, height - 1) as u32,
+    );
+    create_icon_from_bgra(width, height, &bgra, Some(hotspot))
+        .map(|cursor| HCURSOR(cursor.0))
+        .map_err(|error| anyhow!("unable to create a custom cursor: {}", error))
+}
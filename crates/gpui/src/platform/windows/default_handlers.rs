@@ -0,0 +1,244 @@
+use std::{path::PathBuf, thread::JoinHandle};
+
+use ::util::ResultExt;
+use anyhow::{anyhow, Result};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        System::{Registry::*, Threading::*},
+        UI::{Shell::*, WindowsAndMessaging::*},
+    },
+};
+
+use crate::*;
+
+pub(crate) const URL_ASSOCIATIONS_KEY: PCWSTR =
+    w!("Software\\Microsoft\\Windows\\Shell\\Associations\\UrlAssociations");
+const CONSOLE_KEY: PCWSTR = w!("Console");
+const CONSOLE_STARTUP_KEY: PCWSTR = w!("Console\\%%Startup");
+
+// https://learn.microsoft.com/en-us/windows/terminal/install#set-your-default-terminal-application
+const WINDOWS_TERMINAL_DELEGATES: &[&str] = &[
+    "{E12CFF52-A866-4C77-9A90-F570A7AA2C6B}",
+    "{86633F1F-6454-40EC-89CE-DA4EBA977EE2}",
+];
+const CONHOST_DELEGATE: &str = "{B23D10C0-E52E-411E-9D5B-C09FDF709C7D}";
+
+/// The registry values that decide which application handles each [`DefaultHandlerKind`],
+/// used to tell whether a change notification actually changed a default.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct DefaultHandlerIds {
+    http: Option<String>,
+    https: Option<String>,
+    terminal: Option<String>,
+}
+
+impl DefaultHandlerIds {
+    pub(crate) fn current() -> Self {
+        Self {
+            http: url_prog_id("http"),
+            https: url_prog_id("https"),
+            terminal: read_user_string(CONSOLE_STARTUP_KEY, w!("DelegationTerminal")),
+        }
+    }
+}
+
+pub(crate) fn default_handler(kind: DefaultHandlerKind) -> Option<DefaultHandler> {
+    match kind {
+        DefaultHandlerKind::Http => url_handler(w!("http")),
+        DefaultHandlerKind::Https => url_handler(w!("https")),
+        DefaultHandlerKind::Terminal => Some(terminal_handler()),
+    }
+}
+
+/// Watches the registry keys holding the user's default browser and terminal, posting
+/// `DEFAULT_HANDLERS_CHANGED` to the given thread whenever one of them is written to, until
+/// `stop_event` is set. The event has to outlive the returned thread.
+pub(crate) fn watch_default_handlers(thread_id: u32, stop_event: HANDLE) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let keys = [URL_ASSOCIATIONS_KEY, CONSOLE_KEY]
+            .into_iter()
+            .filter_map(|subkey| {
+                let mut key = HKEY::default();
+                unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, subkey, 0, KEY_NOTIFY, &mut key) }
+                    .ok()
+                    .log_err()?;
+                let event = unsafe { CreateEventW(None, false, false, None) }.log_err()?;
+                Some((key, event))
+            })
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return;
+        }
+        let mut events = keys.iter().map(|(_, event)| *event).collect::<Vec<_>>();
+        events.push(stop_event);
+        loop {
+            // notifications only fire once, so they're registered again after every change
+            for (key, event) in &keys {
+                unsafe {
+                    RegNotifyChangeKeyValue(
+                        *key,
+                        true,
+                        REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                        *event,
+                        true,
+                    )
+                }
+                .ok()
+                .log_err();
+            }
+            let result = unsafe { WaitForMultipleObjects(&events, false, INFINITE) };
+            if result.0 == WAIT_OBJECT_0.0 + keys.len() as u32 {
+                break;
+            }
+            if result.0 >= WAIT_OBJECT_0.0 + events.len() as u32 {
+                log::error!("unable to wait for default handler changes: {:?}", result);
+                break;
+            }
+            unsafe {
+                PostThreadMessageW(thread_id, DEFAULT_HANDLERS_CHANGED, WPARAM(0), LPARAM(0))
+            }
+            .log_err();
+        }
+        for (key, event) in keys {
+            unsafe {
+                RegCloseKey(key).ok().log_err();
+                CloseHandle(event).log_err();
+            }
+        }
+    })
+}
+
+fn url_handler(scheme: PCWSTR) -> Option<DefaultHandler> {
+    let name = assoc_query_string(scheme, ASSOCSTR_FRIENDLYAPPNAME).log_err()?;
+    let icon = assoc_query_string(scheme, ASSOCSTR_EXECUTABLE)
+        .log_err()
+        .and_then(|path| file_icon(&PathBuf::from(path)));
+    Some(DefaultHandler {
+        name: name.into(),
+        icon,
+    })
+}
+
+fn terminal_handler() -> DefaultHandler {
+    let windows_terminal = std::env::var_os("LOCALAPPDATA")
+        .map(|path| PathBuf::from(path).join("Microsoft\\WindowsApps\\wt.exe"))
+        .filter(|path| path.exists());
+    let delegate = read_user_string(CONSOLE_STARTUP_KEY, w!("DelegationTerminal"));
+    let uses_windows_terminal = match delegate.as_deref() {
+        Some(CONHOST_DELEGATE) => false,
+        Some(delegate) if WINDOWS_TERMINAL_DELEGATES.contains(&delegate) => true,
+        // "Let Windows decide" picks Windows Terminal whenever it is installed
+        _ => windows_terminal.is_some(),
+    };
+    if let Some(path) = windows_terminal.filter(|_| uses_windows_terminal) {
+        DefaultHandler {
+            name: "Windows Terminal".into(),
+            icon: file_icon(&path),
+        }
+    } else {
+        let conhost = std::env::var_os("SystemRoot")
+            .map(|path| PathBuf::from(path).join("System32\\conhost.exe"));
+        DefaultHandler {
+            name: "Windows Console Host".into(),
+            icon: conhost.and_then(|path| file_icon(&path)),
+        }
+    }
+}
+
+fn url_prog_id(scheme: &str) -> Option<String> {
+    let subkey = HSTRING::from(format!(
+        "Software\\Microsoft\\Windows\\Shell\\Associations\\UrlAssociations\\{}\\UserChoice",
+        scheme
+    ));
+    read_user_string(PCWSTR(subkey.as_ptr()), w!("ProgId"))
+}
+
+fn read_user_string(subkey: PCWSTR, value: PCWSTR) -> Option<String> {
+    let mut size = 0u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey,
+            value,
+            RRF_RT_REG_SZ,
+            None,
+            None,
+            Some(&mut size),
+        )
+    }
+    .ok()
+    .ok()?;
+    let mut buffer = vec![0u16; size as usize / 2];
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey,
+            value,
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+    }
+    .ok()
+    .ok()?;
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+fn assoc_query_string(scheme: PCWSTR, query: ASSOCSTR) -> Result<String> {
+    let mut len = 0u32;
+    unsafe {
+        AssocQueryStringW(
+            ASSOCF_IS_PROTOCOL,
+            query,
+            scheme,
+            PCWSTR::null(),
+            PWSTR::null(),
+            &mut len,
+        )
+    };
+    if len == 0 {
+        return Err(anyhow!("no application is associated with the scheme"));
+    }
+    let mut buffer = vec![0u16; len as usize];
+    unsafe {
+        AssocQueryStringW(
+            ASSOCF_IS_PROTOCOL,
+            query,
+            scheme,
+            PCWSTR::null(),
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        )
+    }
+    .ok()?;
+    // the returned length includes the null terminator
+    Ok(String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{watch_default_handlers, DefaultHandlerIds};
+    use windows::Win32::{
+        Foundation::CloseHandle,
+        System::Threading::{CreateEventW, GetCurrentThreadId, SetEvent},
+    };
+
+    #[test]
+    fn test_default_handlers_watcher_stops() {
+        let stop_event = unsafe { CreateEventW(None, true, false, None) }.unwrap();
+        let watcher = watch_default_handlers(unsafe { GetCurrentThreadId() }, stop_event);
+        unsafe { SetEvent(stop_event) }.unwrap();
+        watcher.join().unwrap();
+        unsafe { CloseHandle(stop_event) }.unwrap();
+    }
+
+    #[test]
+    fn test_default_handler_ids() {
+        // reading the registry again without a change in between doesn't report one
+        assert_eq!(DefaultHandlerIds::current(), DefaultHandlerIds::current());
+    }
+}
@@ -1,10 +1,18 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    mem::ManuallyDrop,
+    num::NonZeroU32,
+    ops::{Index, Range},
+    sync::{Arc, OnceLock},
+};
 
 use ::util::ResultExt;
 use anyhow::{anyhow, Result};
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use itertools::Itertools;
-use parking_lot::{RwLock, RwLockUpgradableReadGuard};
+use parking_lot::{Mutex, RwLock};
 use smallvec::SmallVec;
 use windows::{
     core::*,
@@ -29,12 +37,13 @@ use crate::*;
 struct FontInfo {
     font_family: String,
     font_face: IDWriteFontFace3,
-    features: IDWriteTypography,
     is_system_font: bool,
     is_emoji: bool,
+    /// The `CPAL` table of the font, read the first time a palette is picked by its type
+    cpal: OnceLock<Option<Vec<u8>>>,
 }
 
-pub(crate) struct DirectWriteTextSystem(RwLock<DirectWriteState>);
+pub(crate) struct DirectWriteTextSystem(DirectWriteState);
 
 struct DirectWriteComponent {
     locale: String,
@@ -45,25 +54,44 @@ struct DirectWriteComponent {
     builder: IDWriteFontSetBuilder1,
     text_renderer: Arc<TextRendererWrapper>,
     render_context: GlyphRenderContext,
+    font_fallback: Option<IDWriteFontFallback>,
 }
 
 struct GlyphRenderContext {
     params: IDWriteRenderingParams3,
-    dc_target: ID2D1DeviceContext4,
+    // glyphs are measured at the DPI of their window, which is set on the target itself
+    dc_target: Mutex<ID2D1DeviceContext>,
 }
 
 // All use of the IUnknown methods should be "thread-safe".
 unsafe impl Sync for DirectWriteComponent {}
 unsafe impl Send for DirectWriteComponent {}
 
+/// Lines are shaped and glyphs rasterized from several threads at once, so the state is split
+/// up: the components never change, fonts are only ever appended and can be read without a
+/// lock, and the font collections, caches and settings are each behind a lock of their own.
 struct DirectWriteState {
     components: DirectWriteComponent,
     system_ui_font_name: SharedString,
-    system_font_collection: IDWriteFontCollection1,
-    custom_font_collection: IDWriteFontCollection1,
-    fonts: Vec<FontInfo>,
-    font_selections: HashMap<Font, FontId>,
-    font_id_by_identifier: HashMap<FontIdentifier, FontId>,
+    system_font_collection: RwLock<IDWriteFontCollection1>,
+    custom_font_collection: RwLock<IDWriteFontCollection1>,
+    /// The length and hash of every font file added to the custom collection
+    added_font_data: Mutex<HashSet<(usize, u64)>>,
+    fonts: AppendOnlyVec<FontInfo>,
+    font_selections: RwLock<HashMap<Font, FontId>>,
+    font_id_by_identifier: RwLock<HashMap<FontIdentifier, FontId>>,
+    typographies: RwLock<HashMap<FontFeatures, IDWriteTypography>>,
+    tab_size: RwLock<NonZeroU32>,
+    /// How lines are spaced, by the font family they start in
+    line_spacing: RwLock<HashMap<String, LineSpacing>>,
+}
+
+/// A vector that can be appended to through a shared reference. Elements never move once
+/// they are pushed, so reading them doesn't take a lock.
+struct AppendOnlyVec<T> {
+    // every chunk is twice as long as the one before it
+    chunks: [OnceLock<Box<[OnceLock<T>]>>; APPEND_ONLY_VEC_CHUNKS],
+    len: Mutex<usize>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -71,6 +99,7 @@ struct FontIdentifier {
     postscript_name: String,
     weight: i32,
     style: i32,
+    simulations: i32,
 }
 
 impl DirectWriteComponent {
@@ -92,6 +121,7 @@ impl DirectWriteComponent {
             let locale = String::from_utf16_lossy(&locale_vec);
             let text_renderer = Arc::new(TextRendererWrapper::new(&locale));
             let render_context = GlyphRenderContext::new(&factory, &d2d1_factory)?;
+            let font_fallback = create_font_fallback(&factory).log_err();
 
             Ok(DirectWriteComponent {
                 locale,
@@ -102,6 +132,7 @@ impl DirectWriteComponent {
                 builder,
                 text_renderer,
                 render_context,
+                font_fallback,
             })
         }
     }
@@ -132,12 +163,15 @@ impl GlyphRenderContext {
                     DXGI_FORMAT_B8G8R8A8_UNORM,
                     D2D1_ALPHA_MODE_PREMULTIPLIED,
                 ))?;
-                let target = target.cast::<ID2D1DeviceContext4>()?;
+                let target = target.cast::<ID2D1DeviceContext>()?;
                 target.SetTextRenderingParams(&params);
                 target
             };
 
-            Ok(Self { params, dc_target })
+            Ok(Self {
+                params,
+                dc_target: Mutex::new(dc_target),
+            })
         }
     }
 }
@@ -160,64 +194,125 @@ impl DirectWriteTextSystem {
         };
         let system_ui_font_name = get_system_ui_font_name();
 
-        Ok(Self(RwLock::new(DirectWriteState {
+        Ok(Self(DirectWriteState {
             components,
             system_ui_font_name,
-            system_font_collection,
-            custom_font_collection,
-            fonts: Vec::new(),
-            font_selections: HashMap::default(),
-            font_id_by_identifier: HashMap::default(),
-        })))
+            system_font_collection: RwLock::new(system_font_collection),
+            custom_font_collection: RwLock::new(custom_font_collection),
+            added_font_data: Mutex::default(),
+            fonts: AppendOnlyVec::new(),
+            font_selections: RwLock::default(),
+            font_id_by_identifier: RwLock::default(),
+            typographies: RwLock::default(),
+            tab_size: RwLock::new(DEFAULT_TAB_SIZE),
+            line_spacing: RwLock::default(),
+        }))
+    }
+}
+
+impl<T> AppendOnlyVec<T> {
+    fn new() -> Self {
+        Self {
+            chunks: std::array::from_fn(|_| OnceLock::new()),
+            len: Mutex::new(0),
+        }
+    }
+
+    /// Appends an element, returning its index.
+    fn push(&self, value: T) -> usize {
+        let mut len = self.len.lock();
+        let index = *len;
+        let (chunk_ix, offset) = Self::location(index);
+        let chunk = self.chunks[chunk_ix].get_or_init(|| {
+            (0..APPEND_ONLY_VEC_FIRST_CHUNK_LEN << chunk_ix)
+                .map(|_| OnceLock::new())
+                .collect()
+        });
+        if chunk[offset].set(value).is_err() {
+            unreachable!("elements are only pushed while holding the length");
+        }
+        *len += 1;
+        index
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        let (chunk_ix, offset) = Self::location(index);
+        self.chunks.get(chunk_ix)?.get()?.get(offset)?.get()
+    }
+
+    /// Returns the chunk holding the element at the given index, and its offset in the chunk.
+    fn location(index: usize) -> (usize, usize) {
+        let chunk_ix = (index / APPEND_ONLY_VEC_FIRST_CHUNK_LEN + 1).ilog2() as usize;
+        let chunk_start = APPEND_ONLY_VEC_FIRST_CHUNK_LEN * ((1 << chunk_ix) - 1);
+        (chunk_ix, index - chunk_start)
+    }
+}
+
+impl<T> Index<usize> for AppendOnlyVec<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index)
+            .unwrap_or_else(|| panic!("no element at index {index}"))
     }
 }
 
 impl PlatformTextSystem for DirectWriteTextSystem {
     fn add_fonts(&self, fonts: Vec<Cow<'static, [u8]>>) -> Result<()> {
-        self.0.write().add_fonts(fonts)
+        self.0.add_fonts(fonts).map_err(text_system_error)
     }
 
     fn all_font_names(&self) -> Vec<String> {
-        self.0.read().all_font_names()
+        self.0.all_font_names()
     }
 
     fn all_font_families(&self) -> Vec<String> {
-        self.0.read().all_font_families()
+        self.0.all_font_families()
     }
 
     fn font_id(&self, font: &Font) -> Result<FontId> {
-        let lock = self.0.upgradable_read();
-        if let Some(font_id) = lock.font_selections.get(font) {
-            Ok(*font_id)
-        } else {
-            let mut lock = RwLockUpgradableReadGuard::upgrade(lock);
-            let font_id = lock.select_font(font);
-            lock.font_selections.insert(font.clone(), font_id);
-            Ok(font_id)
+        if let Some(font_id) = self.0.font_selections.read().get(font) {
+            return Ok(*font_id);
         }
+        let font_id = self.0.select_font(font)?;
+        self.0.font_selections.write().insert(font.clone(), font_id);
+        Ok(font_id)
     }
 
     fn font_metrics(&self, font_id: FontId) -> FontMetrics {
-        self.0.read().font_metrics(font_id)
+        self.0.font_metrics(font_id)
+    }
+
+    fn is_monospace(&self, font_id: FontId) -> bool {
+        unsafe {
+            self.0.fonts[font_id.0]
+                .font_face
+                .IsMonospacedFont()
+                .as_bool()
+        }
     }
 
     fn typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
-        self.0.read().get_typographic_bounds(font_id, glyph_id)
+        self.0
+            .get_typographic_bounds(font_id, glyph_id)
+            .map_err(text_system_error)
     }
 
     fn advance(&self, font_id: FontId, glyph_id: GlyphId) -> anyhow::Result<Size<f32>> {
-        self.0.read().get_advance(font_id, glyph_id)
+        self.0
+            .get_advance(font_id, glyph_id)
+            .map_err(text_system_error)
     }
 
     fn glyph_for_char(&self, font_id: FontId, ch: char) -> Option<GlyphId> {
-        self.0.read().glyph_for_char(font_id, ch)
+        self.0.glyph_for_char(font_id, ch)
     }
 
     fn glyph_raster_bounds(
         &self,
         params: &RenderGlyphParams,
     ) -> anyhow::Result<Bounds<DevicePixels>> {
-        self.0.read().raster_bounds(params)
+        self.0.raster_bounds(params).map_err(text_system_error)
     }
 
     fn rasterize_glyph(
@@ -225,50 +320,116 @@ impl PlatformTextSystem for DirectWriteTextSystem {
         params: &RenderGlyphParams,
         raster_bounds: Bounds<DevicePixels>,
     ) -> anyhow::Result<(Size<DevicePixels>, Vec<u8>)> {
-        self.0.read().rasterize_glyph(params, raster_bounds)
+        self.0
+            .rasterize_glyph(params, raster_bounds)
+            .map_err(text_system_error)
+    }
+
+    fn rasterize_glyphs(
+        &self,
+        glyphs: &[(RenderGlyphParams, Bounds<DevicePixels>)],
+    ) -> Vec<anyhow::Result<(Size<DevicePixels>, Vec<u8>)>> {
+        self.0
+            .rasterize_glyphs(glyphs)
+            .into_iter()
+            .map(|result| result.map_err(text_system_error))
+            .collect()
     }
 
     fn layout_line(&self, text: &str, font_size: Pixels, runs: &[FontRun]) -> LineLayout {
+        self.layout_line_with_orientation(text, font_size, runs, TextOrientation::Horizontal)
+    }
+
+    fn layout_line_with_orientation(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        orientation: TextOrientation,
+    ) -> LineLayout {
         self.0
-            .write()
-            .layout_line(text, font_size, runs)
+            .layout_line(text, font_size, runs, orientation)
             .log_err()
             .unwrap_or(LineLayout {
                 font_size,
                 ..Default::default()
             })
     }
+
+    fn layout_paragraph(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        max_width: Pixels,
+    ) -> ParagraphLayout {
+        self.0
+            .layout_paragraph(text, font_size, runs, max_width)
+            .log_err()
+            .unwrap_or_default()
+    }
+
+    fn index_for_x(&self, text: &str, font_size: Pixels, runs: &[FontRun], x: Pixels) -> usize {
+        let index = self.0.index_for_x(text, font_size, runs, x).log_err();
+        index.unwrap_or_else(|| {
+            self.layout_line(text, font_size, runs)
+                .closest_index_for_x(x)
+        })
+    }
+
+    fn x_for_index(&self, text: &str, font_size: Pixels, runs: &[FontRun], index: usize) -> Pixels {
+        let x = self.0.x_for_index(text, font_size, runs, index).log_err();
+        x.unwrap_or_else(|| self.layout_line(text, font_size, runs).x_for_index(index))
+    }
+
+    fn set_tab_size(&self, tab_size: NonZeroU32) {
+        *self.0.tab_size.write() = tab_size;
+    }
+
+    fn set_line_spacing(&self, font_family: &str, line_spacing: LineSpacing) {
+        self.0
+            .line_spacing
+            .write()
+            .insert(font_family.to_string(), line_spacing);
+    }
+
+    fn line_spacing(&self, font_id: FontId) -> LineSpacing {
+        self.0.line_spacing(font_id)
+    }
 }
 
 impl DirectWriteState {
-    fn add_fonts(&mut self, fonts: Vec<Cow<'static, [u8]>>) -> Result<()> {
+    fn add_fonts(&self, fonts: Vec<Cow<'static, [u8]>>) -> Result<()> {
+        // the lock also keeps other threads from using the font set builder
+        let mut custom_font_collection = self.custom_font_collection.write();
+        let mut added_font_data = self.added_font_data.lock();
+        let mut added_any = false;
         for font_data in fonts {
-            match font_data {
-                Cow::Borrowed(data) => unsafe {
-                    let font_file = self
-                        .components
-                        .in_memory_loader
-                        .CreateInMemoryFontFileReference(
-                            &self.components.factory,
-                            data.as_ptr() as _,
-                            data.len() as _,
-                            None,
-                        )?;
-                    self.components.builder.AddFontFile(&font_file)?;
-                },
-                Cow::Owned(data) => unsafe {
-                    let font_file = self
-                        .components
-                        .in_memory_loader
-                        .CreateInMemoryFontFileReference(
-                            &self.components.factory,
-                            data.as_ptr() as _,
-                            data.len() as _,
-                            None,
-                        )?;
-                    self.components.builder.AddFontFile(&font_file)?;
-                },
+            // embedded fonts are added again by every window and renderer that initializes
+            let mut hasher = DefaultHasher::new();
+            font_data.hash(&mut hasher);
+            let key = (font_data.len(), hasher.finish());
+            if added_font_data.contains(&key) {
+                continue;
+            }
+            // without an owner the loader keeps a copy of the data
+            unsafe {
+                let font_file = self
+                    .components
+                    .in_memory_loader
+                    .CreateInMemoryFontFileReference(
+                        &self.components.factory,
+                        font_data.as_ptr() as _,
+                        font_data.len() as _,
+                        None,
+                    )?;
+                self.components.builder.AddFontFile(&font_file)?;
             }
+            added_font_data.insert(key);
+            added_any = true;
+        }
+        if !added_any {
+            return Ok(());
         }
         let set = unsafe { self.components.builder.CreateFontSet()? };
         let collection = unsafe {
@@ -276,74 +437,95 @@ impl DirectWriteState {
                 .factory
                 .CreateFontCollectionFromFontSet(&set)?
         };
-        self.custom_font_collection = collection;
+        *custom_font_collection = collection;
 
         Ok(())
     }
 
-    unsafe fn generate_font_features(
-        &self,
-        font_features: &FontFeatures,
-    ) -> Result<IDWriteTypography> {
-        let direct_write_features = self.components.factory.CreateTypography()?;
-        apply_font_features(&direct_write_features, font_features)?;
-        Ok(direct_write_features)
+    /// Returns the collection fonts are looked up in, which can be replaced while it's in use.
+    fn font_collection(&self, is_system_font: bool) -> IDWriteFontCollection1 {
+        if is_system_font {
+            self.system_font_collection.read().clone()
+        } else {
+            self.custom_font_collection.read().clone()
+        }
+    }
+
+    unsafe fn get_typography(&self, font_features: &FontFeatures) -> Result<IDWriteTypography> {
+        if let Some(typography) = self.typographies.read().get(font_features) {
+            return Ok(typography.clone());
+        }
+        let typography = self.components.factory.CreateTypography()?;
+        apply_font_features(&typography, font_features)?;
+        self.typographies
+            .write()
+            .insert(font_features.clone(), typography.clone());
+        Ok(typography)
     }
 
+    /// Looks the family up in one of the font collections, matching the weight, stretch and
+    /// style of the given font and synthesizing only the faces it allows.
     unsafe fn get_font_id_from_font_collection(
-        &mut self,
+        &self,
         family_name: &str,
-        font_weight: FontWeight,
-        font_style: FontStyle,
-        font_features: &FontFeatures,
+        target_font: &Font,
         is_system_font: bool,
     ) -> Option<FontId> {
-        let collection = if is_system_font {
-            &self.system_font_collection
-        } else {
-            &self.custom_font_collection
-        };
+        let collection = self.font_collection(is_system_font);
         let fontset = collection.GetFontSet().log_err()?;
         let font = fontset
             .GetMatchingFonts(
                 &HSTRING::from(family_name),
-                font_weight.into(),
-                DWRITE_FONT_STRETCH_NORMAL,
-                font_style.into(),
+                target_font.weight.into(),
+                target_font.stretch.into(),
+                target_font.style.into(),
             )
             .log_err()?;
+        let allowed_simulations: DWRITE_FONT_SIMULATIONS = target_font.simulations.into();
         let total_number = font.GetFontCount();
         for index in 0..total_number {
             let Some(font_face_ref) = font.GetFontFaceReference(index).log_err() else {
                 continue;
             };
-            let Some(font_face) = font_face_ref.CreateFontFace().log_err() else {
+            // The matching fonts come with the simulations that bring them closest to the
+            // requested weight and style, drop the ones that aren't allowed.
+            let simulations = font_face_ref.GetSimulations();
+            let font_face = if simulations.0 & !allowed_simulations.0 != 0 {
+                font_face_ref.CreateFontFaceWithSimulations(DWRITE_FONT_SIMULATIONS(
+                    simulations.0 & allowed_simulations.0,
+                ))
+            } else {
+                font_face_ref.CreateFontFace()
+            };
+            let Some(font_face) = font_face.log_err() else {
                 continue;
             };
             let Some(identifier) = get_font_identifier(&font_face, &self.components.locale) else {
                 continue;
             };
+            // features are applied per run when laying out text, so fonts that only
+            // differ in their features share the same font face. The lock is held until the
+            // font is added, so two threads can't both add it.
+            let mut font_id_by_identifier = self.font_id_by_identifier.write();
+            if let Some(font_id) = font_id_by_identifier.get(&identifier) {
+                return Some(*font_id);
+            }
             let is_emoji = font_face.IsColorFont().as_bool();
-            let Some(direct_write_features) = self.generate_font_features(font_features).log_err()
-            else {
-                continue;
-            };
             let font_info = FontInfo {
                 font_family: family_name.to_owned(),
                 font_face,
                 is_system_font,
-                features: direct_write_features,
                 is_emoji,
+                cpal: OnceLock::new(),
             };
-            let font_id = FontId(self.fonts.len());
-            self.fonts.push(font_info);
-            self.font_id_by_identifier.insert(identifier, font_id);
+            let font_id = FontId(self.fonts.push(font_info));
+            font_id_by_identifier.insert(identifier, font_id);
             return Some(font_id);
         }
         None
     }
 
-    unsafe fn update_system_font_collection(&mut self) {
+    unsafe fn update_system_font_collection(&self) {
         let mut collection = std::mem::zeroed();
         if self
             .components
@@ -352,67 +534,151 @@ impl DirectWriteState {
             .log_err()
             .is_some()
         {
-            self.system_font_collection = collection.unwrap();
+            *self.system_font_collection.write() = collection.unwrap();
         }
     }
 
-    fn select_font(&mut self, target_font: &Font) -> FontId {
-        unsafe {
-            if target_font.family == ".SystemUIFont" {
-                let family = self.system_ui_font_name.clone();
-                self.find_font_id(
-                    family.as_ref(),
-                    target_font.weight,
-                    target_font.style,
-                    &target_font.features,
-                )
-                .unwrap()
-            } else {
-                self.find_font_id(
-                    target_font.family.as_ref(),
-                    target_font.weight,
-                    target_font.style,
-                    &target_font.features,
-                )
-                .unwrap_or_else(|| {
-                    let family = self.system_ui_font_name.clone();
-                    log::error!("{} not found, use {} instead.", target_font.family, family);
-                    self.get_font_id_from_font_collection(
-                        family.as_ref(),
-                        target_font.weight,
-                        target_font.style,
-                        &target_font.features,
-                        true,
-                    )
-                    .unwrap()
-                })
+    fn select_font(&self, target_font: &Font) -> Result<FontId> {
+        let family = if target_font.family == ".SystemUIFont" {
+            self.system_ui_font_name.clone()
+        } else {
+            target_font.family.clone()
+        };
+        if let Some(font_id) = unsafe { self.find_font_id(family.as_ref(), target_font) } {
+            return Ok(font_id);
+        }
+        if let Some(font) = unsafe { self.resolve_font_name(family.as_ref(), target_font) } {
+            if let Some(font_id) = unsafe { self.find_font_id(font.family.as_ref(), &font) } {
+                return Ok(font_id);
             }
         }
+
+        let fallbacks = [
+            self.system_ui_font_name.clone(),
+            SharedString::from(DEFAULT_UI_FONT_FAMILY),
+            SharedString::from(DEFAULT_MONOSPACE_FONT_FAMILY),
+        ];
+        for fallback in fallbacks {
+            if fallback == family {
+                continue;
+            }
+            if let Some(font_id) = unsafe {
+                self.get_font_id_from_font_collection(fallback.as_ref(), target_font, true)
+            } {
+                log::error!("font {} not found, using {} instead", family, fallback);
+                return Ok(font_id);
+            }
+        }
+        Err(TextSystemError::FontNotFound(family).into())
     }
 
-    unsafe fn find_font_id(
-        &mut self,
-        family_name: &str,
-        weight: FontWeight,
-        style: FontStyle,
-        features: &FontFeatures,
-    ) -> Option<FontId> {
+    unsafe fn find_font_id(&self, family_name: &str, target_font: &Font) -> Option<FontId> {
         // try to find target font in custom font collection first
-        self.get_font_id_from_font_collection(family_name, weight, style, features, false)
-            .or_else(|| {
-                self.get_font_id_from_font_collection(family_name, weight, style, features, true)
-            })
+        self.get_font_id_from_font_collection(family_name, target_font, false)
+            .or_else(|| self.get_font_id_from_font_collection(family_name, target_font, true))
             .or_else(|| {
                 self.update_system_font_collection();
-                self.get_font_id_from_font_collection(family_name, weight, style, features, true)
+                self.get_font_id_from_font_collection(family_name, target_font, true)
             })
     }
 
+    /// Resolves names that don't match a family name as is: the names of single faces
+    /// (e.g. "Consolas Bold" or "Consolas-Bold"), family names in other languages
+    /// (e.g. "맑은 고딕"), and family names followed by style words.
+    unsafe fn resolve_font_name(&self, name: &str, target_font: &Font) -> Option<Font> {
+        for property in [
+            DWRITE_FONT_PROPERTY_ID_FULL_NAME,
+            DWRITE_FONT_PROPERTY_ID_POSTSCRIPT_NAME,
+        ] {
+            if let Some(font) = self.find_font_by_property(property, name) {
+                return Some(font);
+            }
+        }
+        for property in [
+            DWRITE_FONT_PROPERTY_ID_WEIGHT_STRETCH_STYLE_FAMILY_NAME,
+            DWRITE_FONT_PROPERTY_ID_TYPOGRAPHIC_FAMILY_NAME,
+            DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME,
+        ] {
+            if let Some(font) = self.find_font_by_property(property, name) {
+                return Some(Font {
+                    weight: target_font.weight,
+                    stretch: target_font.stretch,
+                    style: target_font.style,
+                    simulations: target_font.simulations,
+                    ..font
+                });
+            }
+        }
+        let (family, weight, style) =
+            strip_style_suffixes(name, target_font.weight, target_font.style)?;
+        let font = self
+            .find_font_by_property(
+                DWRITE_FONT_PROPERTY_ID_WEIGHT_STRETCH_STYLE_FAMILY_NAME,
+                family,
+            )
+            .unwrap_or_else(|| Font {
+                family: family.to_owned().into(),
+                features: FontFeatures::default(),
+                weight,
+                stretch: target_font.stretch,
+                style,
+                simulations: target_font.simulations,
+            });
+        Some(Font {
+            weight,
+            stretch: target_font.stretch,
+            style,
+            simulations: target_font.simulations,
+            ..font
+        })
+    }
+
+    /// Returns the first font with the given property, whose value is compared in every
+    /// language the font provides it in.
+    unsafe fn find_font_by_property(
+        &self,
+        property_id: DWRITE_FONT_PROPERTY_ID,
+        value: &str,
+    ) -> Option<Font> {
+        let value = HSTRING::from(value);
+        let property = DWRITE_FONT_PROPERTY {
+            propertyId: property_id,
+            propertyValue: PCWSTR(value.as_ptr()),
+            // an empty locale matches the value in any language
+            localeName: w!(""),
+        };
+        for collection in [self.font_collection(false), self.font_collection(true)] {
+            let Some(fontset) = collection.GetFontSet().log_err() else {
+                continue;
+            };
+            let Some(fonts) = fontset.GetMatchingFonts2(&[property]).log_err() else {
+                continue;
+            };
+            if fonts.GetFontCount() == 0 {
+                continue;
+            }
+            let Some(font_face) = fonts
+                .GetFontFaceReference(0)
+                .and_then(|font_face_ref| font_face_ref.CreateFontFace())
+                .log_err()
+            else {
+                continue;
+            };
+            if let Some((_, font, _)) =
+                get_font_identifier_and_font_struct(&font_face, &self.components.locale)
+            {
+                return Some(font);
+            }
+        }
+        None
+    }
+
     fn layout_line(
-        &mut self,
+        &self,
         text: &str,
         font_size: Pixels,
         font_runs: &[FontRun],
+        orientation: TextOrientation,
     ) -> Result<LineLayout> {
         if font_runs.is_empty() {
             return Ok(LineLayout {
@@ -422,91 +688,25 @@ impl DirectWriteState {
         }
         unsafe {
             let text_renderer = self.components.text_renderer.clone();
-            let text_wide = text.encode_utf16().collect_vec();
-
-            let mut utf8_offset = 0usize;
-            let mut utf16_offset = 0u32;
-            let text_layout = {
-                let first_run = &font_runs[0];
-                let font_info = &self.fonts[first_run.font_id.0];
-                let collection = if font_info.is_system_font {
-                    &self.system_font_collection
-                } else {
-                    &self.custom_font_collection
-                };
-                let format = self.components.factory.CreateTextFormat(
-                    &HSTRING::from(&font_info.font_family),
-                    collection,
-                    font_info.font_face.GetWeight(),
-                    font_info.font_face.GetStyle(),
-                    DWRITE_FONT_STRETCH_NORMAL,
-                    font_size.0,
-                    &HSTRING::from(&self.components.locale),
-                )?;
-
-                let layout = self.components.factory.CreateTextLayout(
-                    &text_wide,
-                    &format,
-                    f32::INFINITY,
-                    f32::INFINITY,
-                )?;
-                let current_text = &text[utf8_offset..(utf8_offset + first_run.len)];
-                utf8_offset += first_run.len;
-                let current_text_utf16_length = current_text.encode_utf16().count() as u32;
-                let text_range = DWRITE_TEXT_RANGE {
-                    startPosition: utf16_offset,
-                    length: current_text_utf16_length,
-                };
-                layout.SetTypography(&font_info.features, text_range)?;
-                utf16_offset += current_text_utf16_length;
-
-                layout
-            };
-
-            let mut first_run = true;
-            let mut ascent = Pixels::default();
-            let mut descent = Pixels::default();
-            for run in font_runs {
-                if first_run {
-                    first_run = false;
-                    let mut metrics = vec![DWRITE_LINE_METRICS::default(); 4];
-                    let mut line_count = 0u32;
-                    text_layout.GetLineMetrics(Some(&mut metrics), &mut line_count as _)?;
-                    ascent = px(metrics[0].baseline);
-                    descent = px(metrics[0].height - metrics[0].baseline);
-                    continue;
-                }
-                let font_info = &self.fonts[run.font_id.0];
-                let current_text = &text[utf8_offset..(utf8_offset + run.len)];
-                utf8_offset += run.len;
-                let current_text_utf16_length = current_text.encode_utf16().count() as u32;
-
-                let collection = if font_info.is_system_font {
-                    &self.system_font_collection
-                } else {
-                    &self.custom_font_collection
-                };
-                let text_range = DWRITE_TEXT_RANGE {
-                    startPosition: utf16_offset,
-                    length: current_text_utf16_length,
-                };
-                utf16_offset += current_text_utf16_length;
-                text_layout.SetFontCollection(collection, text_range)?;
-                text_layout
-                    .SetFontFamilyName(&HSTRING::from(&font_info.font_family), text_range)?;
-                text_layout.SetFontSize(font_size.0, text_range)?;
-                text_layout.SetFontStyle(font_info.font_face.GetStyle(), text_range)?;
-                text_layout.SetFontWeight(font_info.font_face.GetWeight(), text_range)?;
-                text_layout.SetTypography(&font_info.features, text_range)?;
-            }
+            let text_wide = Utf16Text::new(text);
+            let (text_layout, ascent, descent) =
+                self.create_text_layout(&text_wide, font_size, font_runs, orientation)?;
+            // Decorations are painted by us, these only make DirectWrite report the
+            // underline and strikethrough metrics of each run to the text renderer.
+            let whole_text = text_wide.range(0..text.len());
+            text_layout.SetUnderline(true, whole_text)?;
+            text_layout.SetStrikethrough(true, whole_text)?;
 
             let mut runs = Vec::new();
             let renderer_context = RendererContext {
                 text_system: self,
-                index_converter: StringIndexConverter::new(text),
+                text: &text_wide,
                 runs: &mut runs,
-                utf16_index: 0,
+                run_origins: Vec::new(),
                 width: 0.0,
+                vertical: orientation == TextOrientation::Vertical,
+                underlines: Vec::new(),
+                strikethroughs: Vec::new(),
             };
             text_layout.Draw(
                 Some(&renderer_context as *const _ as _),
@@ -515,15 +715,374 @@ impl DirectWriteState {
                 0.0,
             )?;
             let width = px(renderer_context.width);
+            let RendererContext {
+                run_origins,
+                vertical,
+                underlines,
+                strikethroughs,
+                ..
+            } = renderer_context;
+            DecorationSpan::apply(
+                &mut runs,
+                &run_origins,
+                &underlines,
+                &strikethroughs,
+                vertical,
+            );
 
-            Ok(LineLayout {
+            let mut layout = LineLayout {
                 font_size,
                 width,
                 ascent,
                 descent,
                 runs,
                 len: text.len(),
-            })
+                ..Default::default()
+            };
+            if !vertical {
+                layout.measure_whitespace(text);
+                // DirectWrite measures the trailing whitespace itself, which also accounts for
+                // the width of whitespace that isn't drawn with a glyph of its own.
+                let mut metrics = DWRITE_TEXT_METRICS::default();
+                if text_layout.GetMetrics(&mut metrics).log_err().is_some() {
+                    layout.trailing_whitespace_width =
+                        px(metrics.widthIncludingTrailingWhitespace - metrics.width);
+                }
+            }
+            Ok(layout)
+        }
+    }
+
+    fn layout_paragraph(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        font_runs: &[FontRun],
+        max_width: Pixels,
+    ) -> Result<ParagraphLayout> {
+        if font_runs.is_empty() {
+            return Ok(ParagraphLayout::default());
+        }
+        unsafe {
+            let text_renderer = self.components.text_renderer.clone();
+            let text_wide = Utf16Text::new(text);
+            let (text_layout, _, _) = self.create_text_layout(
+                &text_wide,
+                font_size,
+                font_runs,
+                TextOrientation::Horizontal,
+            )?;
+            text_layout.SetMaxWidth(max_width.0)?;
+            text_layout.SetWordWrapping(DWRITE_WORD_WRAPPING_WRAP)?;
+            let whole_text = text_wide.range(0..text.len());
+            text_layout.SetUnderline(true, whole_text)?;
+            text_layout.SetStrikethrough(true, whole_text)?;
+
+            let mut runs = Vec::new();
+            let renderer_context = RendererContext {
+                text_system: self,
+                text: &text_wide,
+                runs: &mut runs,
+                run_origins: Vec::new(),
+                width: 0.0,
+                vertical: false,
+                underlines: Vec::new(),
+                strikethroughs: Vec::new(),
+            };
+            text_layout.Draw(
+                Some(&renderer_context as *const _ as _),
+                &text_renderer.0,
+                0.0,
+                0.0,
+            )?;
+            let RendererContext {
+                run_origins,
+                width: total_advance,
+                underlines,
+                strikethroughs,
+                ..
+            } = renderer_context;
+            DecorationSpan::apply(&mut runs, &run_origins, &underlines, &strikethroughs, false);
+
+            // The renderer positions glyphs as if the whole paragraph was a single line, move
+            // every run to where DirectWrite placed it and remember where it ends.
+            let run_ends = (0..runs.len())
+                .map(|ix| {
+                    let start = runs[ix].glyphs[0].position.x.0;
+                    let end = runs
+                        .get(ix + 1)
+                        .map_or(total_advance, |next| next.glyphs[0].position.x.0);
+                    run_origins[ix].x + end - start
+                })
+                .collect_vec();
+            for (run, origin) in runs.iter_mut().zip(&run_origins) {
+                let shift = px(origin.x) - run.glyphs[0].position.x;
+                for glyph in &mut run.glyphs {
+                    glyph.position.x += shift;
+                }
+            }
+
+            let mut paragraph = ParagraphLayout::default();
+            let mut runs = runs.into_iter().zip(run_ends).peekable();
+            let mut line_start = 0;
+            let mut next_line_start_utf16 = 0;
+            for metrics in line_metrics(&text_layout)? {
+                next_line_start_utf16 += metrics.length as usize;
+                let line_end =
+                    text_wide.utf8_ix(next_line_start_utf16 - metrics.newlineLength as usize);
+                let next_line_start = text_wide.utf8_ix(next_line_start_utf16);
+
+                let mut line = LineLayout {
+                    font_size,
+                    ascent: px(metrics.baseline),
+                    descent: px(metrics.height - metrics.baseline),
+                    len: line_end - line_start,
+                    ..Default::default()
+                };
+                while let Some((mut run, run_end)) =
+                    runs.next_if(|(run, _)| run.glyphs[0].index < next_line_start)
+                {
+                    // newlines don't belong to the text of the line
+                    run.glyphs.retain(|glyph| glyph.index < line_end);
+                    for glyph in &mut run.glyphs {
+                        glyph.index -= line_start;
+                    }
+                    line.width = line.width.max(px(run_end));
+                    if !run.glyphs.is_empty() {
+                        line.runs.push(run);
+                    }
+                }
+                line.measure_whitespace(&text[line_start..line_end]);
+                paragraph.lines.push(line);
+                paragraph.line_starts.push(line_start);
+                line_start = next_line_start;
+            }
+            Ok(paragraph)
+        }
+    }
+
+    /// Creates a text layout for the line, returning it together with the ascent and descent
+    /// of its first run.
+    unsafe fn create_text_layout(
+        &self,
+        text_wide: &Utf16Text,
+        font_size: Pixels,
+        font_runs: &[FontRun],
+        orientation: TextOrientation,
+    ) -> Result<(IDWriteTextLayout, Pixels, Pixels)> {
+        let mut utf8_offset = 0usize;
+        let text_layout = {
+            let first_run = &font_runs[0];
+            let typography = self.get_typography(&first_run.features)?;
+            let font_info = &self.fonts[first_run.font_id.0];
+            let collection = self.font_collection(font_info.is_system_font);
+            let format = self.components.factory.CreateTextFormat(
+                &HSTRING::from(&font_info.font_family),
+                &collection,
+                font_info.font_face.GetWeight(),
+                font_info.font_face.GetStyle(),
+                font_info.font_face.GetStretch(),
+                font_size.0,
+                &HSTRING::from(&self.components.locale),
+            )?;
+            if orientation == TextOrientation::Vertical {
+                // The flow direction has to be moved off the vertical axis before the
+                // reading direction can take its place.
+                format.SetFlowDirection(DWRITE_FLOW_DIRECTION_RIGHT_TO_LEFT)?;
+                format.SetReadingDirection(DWRITE_READING_DIRECTION_TOP_TO_BOTTOM)?;
+                // Glyphs are rasterized upright, so horizontal scripts are stacked rather than
+                // set sideways, which would need their glyphs rotated.
+                format
+                    .cast::<IDWriteTextFormat1>()?
+                    .SetVerticalGlyphOrientation(DWRITE_VERTICAL_GLYPH_ORIENTATION_STACKED)?;
+            }
+            if let Some(font_fallback) = self.components.font_fallback.as_ref() {
+                format
+                    .cast::<IDWriteTextFormat1>()?
+                    .SetFontFallback(font_fallback)?;
+            }
+            // Without an explicit tab stop DirectWrite advances tabs to multiples of four
+            // times the font size, rather than to columns of spaces.
+            if let Some(tab_stop) = self.tab_stop(first_run.font_id, font_size) {
+                format.SetIncrementalTabStop(tab_stop.0)?;
+            }
+            self.apply_line_spacing(&format, first_run.font_id, font_size)?;
+
+            let layout = self.components.factory.CreateTextLayout(
+                &text_wide.units,
+                &format,
+                f32::INFINITY,
+                f32::INFINITY,
+            )?;
+            let text_range = text_wide.range(utf8_offset..utf8_offset + first_run.len);
+            utf8_offset += first_run.len;
+            layout.SetTypography(&typography, text_range)?;
+
+            layout
+        };
+
+        let mut first_run = true;
+        let mut ascent = Pixels::default();
+        let mut descent = Pixels::default();
+        for run in font_runs {
+            if first_run {
+                first_run = false;
+                let metrics = line_metrics(&text_layout)?;
+                ascent = px(metrics[0].baseline);
+                descent = px(metrics[0].height - metrics[0].baseline);
+                continue;
+            }
+            let typography = self.get_typography(&run.features)?;
+            let font_info = &self.fonts[run.font_id.0];
+            let text_range = text_wide.range(utf8_offset..utf8_offset + run.len);
+            utf8_offset += run.len;
+
+            let collection = self.font_collection(font_info.is_system_font);
+            text_layout.SetFontCollection(&collection, text_range)?;
+            text_layout.SetFontFamilyName(&HSTRING::from(&font_info.font_family), text_range)?;
+            text_layout.SetFontSize(font_size.0, text_range)?;
+            text_layout.SetFontStyle(font_info.font_face.GetStyle(), text_range)?;
+            text_layout.SetFontWeight(font_info.font_face.GetWeight(), text_range)?;
+            text_layout.SetFontStretch(font_info.font_face.GetStretch(), text_range)?;
+            text_layout.SetTypography(&typography, text_range)?;
+        }
+        Ok((text_layout, ascent, descent))
+    }
+
+    fn line_spacing(&self, font_id: FontId) -> LineSpacing {
+        self.line_spacing
+            .read()
+            .get(&self.fonts[font_id.0].font_family)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Applies the line spacing of the given font to the format of a line starting in it.
+    unsafe fn apply_line_spacing(
+        &self,
+        format: &IDWriteTextFormat,
+        font_id: FontId,
+        font_size: Pixels,
+    ) -> Result<()> {
+        let line_spacing = self.line_spacing(font_id);
+        let line_gap_usage = if line_spacing.trim_line_gap {
+            DWRITE_FONT_LINE_GAP_USAGE_DISABLED
+        } else {
+            DWRITE_FONT_LINE_GAP_USAGE_DEFAULT
+        };
+        let (method, height, baseline) = match line_spacing.method {
+            LineSpacingMethod::Default if !line_spacing.trim_line_gap => return Ok(()),
+            LineSpacingMethod::Default => (DWRITE_LINE_SPACING_METHOD_DEFAULT, 0.0, 0.0),
+            // both are factors applied to what the fonts ask for
+            LineSpacingMethod::Proportional(factor) => {
+                (DWRITE_LINE_SPACING_METHOD_PROPORTIONAL, factor, factor)
+            }
+            LineSpacingMethod::Uniform(factor) => {
+                let metrics = self.font_metrics(font_id);
+                let height = font_size * factor;
+                let ascent = metrics.ascent(font_size);
+                let descent = -metrics.descent(font_size);
+                let baseline = (height - ascent - descent) / 2. + ascent;
+                (DWRITE_LINE_SPACING_METHOD_UNIFORM, height.0, baseline.0)
+            }
+        };
+        // Trimming the line gap needs Windows 10 1709
+        if let Ok(format) = format.cast::<IDWriteTextFormat3>() {
+            format.SetLineSpacing(&DWRITE_LINE_SPACING {
+                method,
+                height,
+                baseline,
+                // split the extra space of proportional spacing evenly around the line
+                leadingBefore: 0.5,
+                fontLineGapUsage: line_gap_usage,
+            })?;
+        } else {
+            format.SetLineSpacing(method, height, baseline)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the distance between tab stops for a line starting in the given font.
+    fn tab_stop(&self, font_id: FontId, font_size: Pixels) -> Option<Pixels> {
+        let space = self.glyph_for_char(font_id, ' ')?;
+        let advance = self.get_advance(font_id, space).log_err()?;
+        let units_per_em = self.font_metrics(font_id).units_per_em as f32;
+        Some(font_size * (advance.width / units_per_em) * self.tab_size.read().get() as f32)
+    }
+
+    fn index_for_x(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        font_runs: &[FontRun],
+        x: Pixels,
+    ) -> Result<usize> {
+        if font_runs.is_empty() {
+            return Ok(0);
+        }
+        unsafe {
+            let text_wide = Utf16Text::new(text);
+            let (text_layout, _, _) = self.create_text_layout(
+                &text_wide,
+                font_size,
+                font_runs,
+                TextOrientation::Horizontal,
+            )?;
+            let mut is_trailing_hit = BOOL::default();
+            let mut is_inside = BOOL::default();
+            let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+            text_layout.HitTestPoint(
+                x.0,
+                0.0,
+                &mut is_trailing_hit,
+                &mut is_inside,
+                &mut metrics,
+            )?;
+            // a hit on the trailing half of a cluster places the caret after it
+            let mut utf16_index = metrics.textPosition as usize;
+            if is_trailing_hit.as_bool() {
+                utf16_index += metrics.length as usize;
+            }
+            Ok(text_wide.utf8_ix(utf16_index))
+        }
+    }
+
+    fn x_for_index(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        font_runs: &[FontRun],
+        index: usize,
+    ) -> Result<Pixels> {
+        if font_runs.is_empty() || text.is_empty() {
+            return Ok(px(0.));
+        }
+        unsafe {
+            let text_wide = Utf16Text::new(text);
+            let (text_layout, _, _) = self.create_text_layout(
+                &text_wide,
+                font_size,
+                font_runs,
+                TextOrientation::Horizontal,
+            )?;
+            let utf16_index = text_wide.utf16_ix(index);
+            // the end of the line is the trailing edge of its last cluster
+            let (position, is_trailing_hit) = if utf16_index < text_wide.units.len() {
+                (utf16_index, false)
+            } else {
+                (text_wide.units.len() - 1, true)
+            };
+            let mut x = 0.0;
+            let mut y = 0.0;
+            let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+            text_layout.HitTestTextPosition(
+                position as u32,
+                is_trailing_hit,
+                &mut x,
+                &mut y,
+                &mut metrics,
+            )?;
+            Ok(px(x))
         }
     }
 
@@ -542,6 +1101,10 @@ impl DirectWriteState {
                 underline_thickness: metrics.Base.underlineThickness as _,
                 cap_height: metrics.Base.capHeight as _,
                 x_height: metrics.Base.xHeight as _,
+                superscript_size: metrics.superscriptSizeY as _,
+                superscript_offset: metrics.superscriptPositionY as _,
+                subscript_size: metrics.subscriptSizeY as _,
+                subscript_offset: metrics.subscriptPositionY as _,
                 bounding_box: Bounds {
                     origin: Point {
                         x: metrics.glyphBoxLeft as _,
@@ -557,7 +1120,7 @@ impl DirectWriteState {
     }
 
     fn raster_bounds(&self, params: &RenderGlyphParams) -> Result<Bounds<DevicePixels>> {
-        let render_target = &self.components.render_context.dc_target;
+        let render_target = self.components.render_context.dc_target.lock();
         unsafe {
             render_target.SetUnitMode(D2D1_UNIT_MODE_DIPS);
             render_target.SetDpi(96.0 * params.scale_factor, 96.0 * params.scale_factor);
@@ -567,7 +1130,7 @@ impl DirectWriteState {
         let advance = [0.0f32];
         let offset = [DWRITE_GLYPH_OFFSET::default()];
         let glyph_run = DWRITE_GLYPH_RUN {
-            fontFace: unsafe { std::mem::transmute_copy(&font.font_face) },
+            fontFace: borrowed_font_face(&font.font_face),
             fontEmSize: params.font_size.0,
             glyphCount: 1,
             glyphIndices: glyph_id.as_ptr(),
@@ -576,13 +1139,48 @@ impl DirectWriteState {
             isSideways: BOOL(0),
             bidiLevel: 0,
         };
-        let bounds = unsafe {
+        let baseline_origin = D2D_POINT_2F { x: 0.0, y: 0.0 };
+        let mut bounds = unsafe {
             render_target.GetGlyphRunWorldBounds(
-                D2D_POINT_2F { x: 0.0, y: 0.0 },
+                baseline_origin,
                 &glyph_run,
                 DWRITE_MEASURING_MODE_NATURAL,
             )?
         };
+        if params.is_emoji {
+            // The layers of a color glyph can extend past the outline of the base glyph, the
+            // bitmap we rasterize into is sized from these bounds so they have to cover every
+            // layer or large emoji get clipped.
+            unsafe {
+                if let Ok(enumerator) = self.components.factory.TranslateColorGlyphRun(
+                    baseline_origin,
+                    &glyph_run as _,
+                    None,
+                    DWRITE_GLYPH_IMAGE_FORMATS_COLR
+                        | DWRITE_GLYPH_IMAGE_FORMATS_SVG
+                        | DWRITE_GLYPH_IMAGE_FORMATS_PNG
+                        | DWRITE_GLYPH_IMAGE_FORMATS_JPEG
+                        | DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8,
+                    DWRITE_MEASURING_MODE_NATURAL,
+                    None,
+                    color_palette_index(font, params.palette.selection),
+                ) {
+                    while enumerator.MoveNext().is_ok() {
+                        let Ok(color_glyph) = enumerator.GetCurrentRun() else {
+                            break;
+                        };
+                        let color_glyph = &*color_glyph;
+                        if let Ok(layer_bounds) = render_target.GetGlyphRunWorldBounds(
+                            baseline_origin,
+                            &color_glyph.Base.glyphRun,
+                            color_glyph.measuringMode,
+                        ) {
+                            bounds = union_rect(bounds, layer_bounds);
+                        }
+                    }
+                }
+            }
+        }
 
         if bounds.right < bounds.left {
             Ok(Bounds {
@@ -590,15 +1188,14 @@ impl DirectWriteState {
                 size: size(0.into(), 0.into()),
             })
         } else {
+            // Round outwards so the bitmap covers every pixel the glyph touches.
+            let left = (bounds.left * params.scale_factor).floor() as i32;
+            let top = (bounds.top * params.scale_factor).floor() as i32;
+            let right = (bounds.right * params.scale_factor).ceil() as i32;
+            let bottom = (bounds.bottom * params.scale_factor).ceil() as i32;
             Ok(Bounds {
-                origin: point(
-                    ((bounds.left * params.scale_factor).ceil() as i32).into(),
-                    ((bounds.top * params.scale_factor).ceil() as i32).into(),
-                ),
-                size: size(
-                    (((bounds.right - bounds.left) * params.scale_factor).ceil() as i32).into(),
-                    (((bounds.bottom - bounds.top) * params.scale_factor).ceil() as i32).into(),
-                ),
+                origin: point(left.into(), top.into()),
+                size: size((right - left).into(), (bottom - top).into()),
             })
         }
     }
@@ -622,7 +1219,7 @@ impl DirectWriteState {
         glyph_bounds: Bounds<DevicePixels>,
     ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
         if glyph_bounds.size.width.0 == 0 || glyph_bounds.size.height.0 == 0 {
-            return Err(anyhow!("glyph bounds are empty"));
+            return Err(TextSystemError::MissingGlyph.into());
         }
 
         let font_info = &self.fonts[params.font_id.0];
@@ -633,7 +1230,7 @@ impl DirectWriteState {
             ascenderOffset: glyph_bounds.origin.y.0 as f32 / params.scale_factor,
         }];
         let glyph_run = DWRITE_GLYPH_RUN {
-            fontFace: unsafe { std::mem::transmute_copy(&font_info.font_face) },
+            fontFace: borrowed_font_face(&font_info.font_face),
             fontEmSize: params.font_size.0,
             glyphCount: 1,
             glyphIndices: glyph_id.as_ptr(),
@@ -643,15 +1240,7 @@ impl DirectWriteState {
             bidiLevel: 0,
         };
 
-        // Add an extra pixel when the subpixel variant isn't zero to make room for anti-aliasing.
-        let mut bitmap_size = glyph_bounds.size;
-        if params.subpixel_variant.x > 0 {
-            bitmap_size.width += DevicePixels(1);
-        }
-        if params.subpixel_variant.y > 0 {
-            bitmap_size.height += DevicePixels(1);
-        }
-        let bitmap_size = bitmap_size;
+        let bitmap_size = glyph_bitmap_size(params, glyph_bounds);
 
         let total_bytes;
         let bitmap_format;
@@ -702,9 +1291,10 @@ impl DirectWriteState {
                 y: subpixel_shift.y / params.scale_factor,
             };
 
-            // This `cast()` action here should never fail since we are running on Win10+, and
-            // ID2D1DeviceContext4 requires Win8+
-            let render_target = render_target.cast::<ID2D1DeviceContext4>().unwrap();
+            let render_target = render_target.cast::<ID2D1DeviceContext>()?;
+            // Bitmap and SVG color glyphs can only be drawn on Windows 10 1607 and later, older
+            // versions draw the layers of COLR glyphs alone.
+            let color_target = render_target.cast::<ID2D1DeviceContext4>().ok();
             render_target.SetUnitMode(D2D1_UNIT_MODE_DIPS);
             render_target.SetDpi(
                 bitmap_dpi * params.scale_factor,
@@ -713,47 +1303,80 @@ impl DirectWriteState {
             render_target.SetTextRenderingParams(&self.components.render_context.params);
             render_target.BeginDraw();
 
-            if params.is_emoji {
-                // WARN: only DWRITE_GLYPH_IMAGE_FORMATS_COLR has been tested
-                let enumerator = self.components.factory.TranslateColorGlyphRun(
-                    baseline_origin,
-                    &glyph_run as _,
-                    None,
+            let color_glyphs = if params.is_emoji {
+                let image_formats = if color_target.is_some() {
                     DWRITE_GLYPH_IMAGE_FORMATS_COLR
                         | DWRITE_GLYPH_IMAGE_FORMATS_SVG
                         | DWRITE_GLYPH_IMAGE_FORMATS_PNG
                         | DWRITE_GLYPH_IMAGE_FORMATS_JPEG
-                        | DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8,
-                    DWRITE_MEASURING_MODE_NATURAL,
-                    None,
-                    0,
-                )?;
+                        | DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8
+                } else {
+                    DWRITE_GLYPH_IMAGE_FORMATS_COLR
+                };
+                // WARN: only DWRITE_GLYPH_IMAGE_FORMATS_COLR has been tested
+                // Glyphs without color data in any of these formats are drawn like any other.
+                self.components
+                    .factory
+                    .TranslateColorGlyphRun(
+                        baseline_origin,
+                        &glyph_run as _,
+                        None,
+                        image_formats,
+                        DWRITE_MEASURING_MODE_NATURAL,
+                        None,
+                        color_palette_index(font_info, params.palette.selection),
+                    )
+                    .ok()
+            } else {
+                None
+            };
+            if let Some(enumerator) = color_glyphs {
+                let foreground = params.palette.foreground.map(|color| {
+                    let color = color.to_rgb();
+                    D2D1_COLOR_F {
+                        r: color.r,
+                        g: color.g,
+                        b: color.b,
+                        a: color.a,
+                    }
+                });
                 while enumerator.MoveNext().is_ok() {
                     let Ok(color_glyph) = enumerator.GetCurrentRun() else {
                         break;
                     };
                     let color_glyph = &*color_glyph;
-                    let brush_color = translate_color(&color_glyph.Base.runColor);
+                    // Layers without a palette entry are painted in the color of the text.
+                    let brush_color = match foreground {
+                        Some(foreground)
+                            if color_glyph.Base.paletteIndex == FOREGROUND_PALETTE_INDEX =>
+                        {
+                            foreground
+                        }
+                        _ => translate_color(&color_glyph.Base.runColor),
+                    };
                     brush.SetColor(&brush_color);
-                    match color_glyph.glyphImageFormat {
-                        DWRITE_GLYPH_IMAGE_FORMATS_PNG
-                        | DWRITE_GLYPH_IMAGE_FORMATS_JPEG
-                        | DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8 => render_target
-                            .DrawColorBitmapGlyphRun(
-                                color_glyph.glyphImageFormat,
-                                baseline_origin,
-                                &color_glyph.Base.glyphRun,
-                                color_glyph.measuringMode,
-                                D2D1_COLOR_BITMAP_GLYPH_SNAP_OPTION_DEFAULT,
-                            ),
-                        DWRITE_GLYPH_IMAGE_FORMATS_SVG => render_target.DrawSvgGlyphRun(
+                    match (color_glyph.glyphImageFormat, &color_target) {
+                        (
+                            DWRITE_GLYPH_IMAGE_FORMATS_PNG
+                            | DWRITE_GLYPH_IMAGE_FORMATS_JPEG
+                            | DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8,
+                            Some(color_target),
+                        ) => color_target.DrawColorBitmapGlyphRun(
+                            color_glyph.glyphImageFormat,
                             baseline_origin,
                             &color_glyph.Base.glyphRun,
-                            &brush,
-                            None,
-                            color_glyph.Base.paletteIndex as u32,
                             color_glyph.measuringMode,
+                            D2D1_COLOR_BITMAP_GLYPH_SNAP_OPTION_DEFAULT,
                         ),
+                        (DWRITE_GLYPH_IMAGE_FORMATS_SVG, Some(color_target)) => color_target
+                            .DrawSvgGlyphRun(
+                                baseline_origin,
+                                &color_glyph.Base.glyphRun,
+                                &brush,
+                                None,
+                                color_glyph.Base.paletteIndex as u32,
+                                color_glyph.measuringMode,
+                            ),
                         _ => render_target.DrawGlyphRun(
                             baseline_origin,
                             &color_glyph.Base.glyphRun,
@@ -798,6 +1421,156 @@ impl DirectWriteState {
         }
     }
 
+    fn rasterize_glyphs(
+        &self,
+        glyphs: &[(RenderGlyphParams, Bounds<DevicePixels>)],
+    ) -> Vec<Result<(Size<DevicePixels>, Vec<u8>)>> {
+        let mut results = Vec::with_capacity(glyphs.len());
+        let mut start = 0;
+        while start < glyphs.len() {
+            let (first, first_bounds) = &glyphs[start];
+            if !can_rasterize_in_strip(first, *first_bounds) {
+                results.push(self.rasterize_glyph(first, *first_bounds));
+                start += 1;
+                continue;
+            }
+
+            // Glyphs sharing a font, size and scale factor are drawn side by side into one
+            // bitmap, so the render target and the downscaling pass are set up only once.
+            let mut end = start + 1;
+            let mut strip_width = glyph_bitmap_size(first, *first_bounds).width.0;
+            while let Some((params, bounds)) = glyphs.get(end) {
+                let width = glyph_bitmap_size(params, *bounds).width.0;
+                if !can_rasterize_in_strip(params, *bounds)
+                    || params.font_id != first.font_id
+                    || params.font_size != first.font_size
+                    || params.scale_factor != first.scale_factor
+                    || strip_width + GLYPH_STRIP_GAP + width > MAX_GLYPH_STRIP_WIDTH
+                {
+                    break;
+                }
+                strip_width += GLYPH_STRIP_GAP + width;
+                end += 1;
+            }
+
+            let batch = &glyphs[start..end];
+            match self.rasterize_glyph_strip(batch) {
+                Ok(bitmaps) => results.extend(bitmaps.into_iter().map(Ok)),
+                Err(error) => {
+                    log::error!("failed to rasterize glyphs in a batch: {:?}", error);
+                    results.extend(
+                        batch
+                            .iter()
+                            .map(|(params, bounds)| self.rasterize_glyph(params, *bounds)),
+                    );
+                }
+            }
+            start = end;
+        }
+        results
+    }
+
+    fn rasterize_glyph_strip(
+        &self,
+        glyphs: &[(RenderGlyphParams, Bounds<DevicePixels>)],
+    ) -> Result<Vec<(Size<DevicePixels>, Vec<u8>)>> {
+        let first = &glyphs[0].0;
+        let font_info = &self.fonts[first.font_id.0];
+        let scale_factor = first.scale_factor;
+
+        let mut slots = Vec::with_capacity(glyphs.len());
+        let mut strip_width = 0;
+        let mut strip_height = 0;
+        for (params, bounds) in glyphs {
+            if strip_width > 0 {
+                strip_width += GLYPH_STRIP_GAP;
+            }
+            let bitmap_size = glyph_bitmap_size(params, *bounds);
+            slots.push((strip_width, bitmap_size));
+            strip_width += bitmap_size.width.0;
+            strip_height = strip_height.max(bitmap_size.height.0);
+        }
+
+        unsafe {
+            // Like single glyphs, the strip is drawn at twice its size and scaled down.
+            let bitmap = self.components.bitmap_factory.CreateBitmap(
+                strip_width as u32 * 2,
+                strip_height as u32 * 2,
+                &GUID_WICPixelFormat8bppAlpha,
+                WICBitmapCacheOnLoad,
+            )?;
+            let render_target_property =
+                get_render_target_property(DXGI_FORMAT_A8_UNORM, D2D1_ALPHA_MODE_STRAIGHT);
+            let render_target = self
+                .components
+                .d2d1_factory
+                .CreateWicBitmapRenderTarget(&bitmap, &render_target_property)?;
+            let brush = render_target.CreateSolidColorBrush(&BRUSH_COLOR, None)?;
+            let render_target = render_target.cast::<ID2D1DeviceContext>()?;
+            render_target.SetUnitMode(D2D1_UNIT_MODE_DIPS);
+            render_target.SetDpi(192.0 * scale_factor, 192.0 * scale_factor);
+            render_target.SetTextRenderingParams(&self.components.render_context.params);
+            render_target.BeginDraw();
+
+            for ((params, bounds), (x, _)) in glyphs.iter().zip(&slots) {
+                let glyph_id = [params.glyph_id.0 as u16];
+                let advance = [bounds.size.width.0 as f32];
+                let offset = [DWRITE_GLYPH_OFFSET {
+                    advanceOffset: -bounds.origin.x.0 as f32 / scale_factor,
+                    ascenderOffset: bounds.origin.y.0 as f32 / scale_factor,
+                }];
+                let glyph_run = DWRITE_GLYPH_RUN {
+                    fontFace: borrowed_font_face(&font_info.font_face),
+                    fontEmSize: params.font_size.0,
+                    glyphCount: 1,
+                    glyphIndices: glyph_id.as_ptr(),
+                    glyphAdvances: advance.as_ptr(),
+                    glyphOffsets: offset.as_ptr(),
+                    isSideways: BOOL(0),
+                    bidiLevel: 0,
+                };
+                let subpixel_shift = params
+                    .subpixel_variant
+                    .map(|v| v as f32 / SUBPIXEL_VARIANTS as f32);
+                let baseline_origin = D2D_POINT_2F {
+                    x: (*x as f32 + subpixel_shift.x) / scale_factor,
+                    y: subpixel_shift.y / scale_factor,
+                };
+                render_target.DrawGlyphRun(
+                    baseline_origin,
+                    &glyph_run,
+                    None,
+                    &brush,
+                    DWRITE_MEASURING_MODE_NATURAL,
+                );
+            }
+            render_target.EndDraw(None, None)?;
+
+            let scaler = self.components.bitmap_factory.CreateBitmapScaler()?;
+            scaler.Initialize(
+                &bitmap,
+                strip_width as u32,
+                strip_height as u32,
+                WICBitmapInterpolationModeHighQualityCubic,
+            )?;
+            slots
+                .into_iter()
+                .map(|(x, bitmap_size)| {
+                    let rect = WICRect {
+                        X: x,
+                        Y: 0,
+                        Width: bitmap_size.width.0,
+                        Height: bitmap_size.height.0,
+                    };
+                    let mut raw_data =
+                        vec![0u8; bitmap_size.width.0 as usize * bitmap_size.height.0 as usize];
+                    scaler.CopyPixels(&rect, bitmap_size.width.0 as u32, &mut raw_data)?;
+                    Ok((bitmap_size, raw_data))
+                })
+                .collect()
+        }
+    }
+
     fn get_typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
         unsafe {
             let font = &self.fonts[font_id.0].font_face;
@@ -849,16 +1622,16 @@ impl DirectWriteState {
 
     fn all_font_names(&self) -> Vec<String> {
         let mut result =
-            get_font_names_from_collection(&self.system_font_collection, &self.components.locale);
+            get_font_names_from_collection(&self.font_collection(true), &self.components.locale);
         result.extend(get_font_names_from_collection(
-            &self.custom_font_collection,
+            &self.font_collection(false),
             &self.components.locale,
         ));
         result
     }
 
     fn all_font_families(&self) -> Vec<String> {
-        get_font_names_from_collection(&self.system_font_collection, &self.components.locale)
+        get_font_names_from_collection(&self.font_collection(true), &self.components.locale)
     }
 }
 
@@ -896,11 +1669,66 @@ impl TextRenderer {
 }
 
 struct RendererContext<'t, 'a, 'b> {
-    text_system: &'t mut DirectWriteState,
-    index_converter: StringIndexConverter<'a>,
+    text_system: &'t DirectWriteState,
+    text: &'a Utf16Text,
     runs: &'b mut Vec<ShapedRun>,
-    utf16_index: usize,
+    /// The baseline origin of each of the runs, in DIPs relative to the layout
+    run_origins: Vec<Point<f32>>,
     width: f32,
+    /// Whether the glyphs advance along the y axis
+    vertical: bool,
+    underlines: Vec<DecorationSpan>,
+    strikethroughs: Vec<DecorationSpan>,
+}
+
+/// An underline or strikethrough reported by DirectWrite, in DIPs relative to the baseline
+/// of the line. The span is measured along the direction the glyphs advance in.
+struct DecorationSpan {
+    start: f32,
+    end: f32,
+    /// The position of the line's baseline across the direction the glyphs advance in,
+    /// which tells the lines of a paragraph apart
+    baseline: f32,
+    offset: f32,
+    thickness: f32,
+}
+
+impl DecorationSpan {
+    fn find(spans: &[DecorationSpan], position: f32, baseline: f32) -> Option<&DecorationSpan> {
+        spans.iter().find(|span| {
+            span.start <= position && position < span.end && (span.baseline - baseline).abs() < 0.5
+        })
+    }
+
+    /// Attaches the decorations drawn under each run to it, given the baseline origins of
+    /// the runs.
+    fn apply(
+        runs: &mut [ShapedRun],
+        run_origins: &[Point<f32>],
+        underlines: &[DecorationSpan],
+        strikethroughs: &[DecorationSpan],
+        vertical: bool,
+    ) {
+        for (run, origin) in runs.iter_mut().zip(run_origins) {
+            let (start, baseline) = if vertical {
+                (origin.y, origin.x)
+            } else {
+                (origin.x, origin.y)
+            };
+            if let Some((underline, strikethrough)) = DecorationSpan::find(
+                underlines, start, baseline,
+            )
+            .zip(DecorationSpan::find(strikethroughs, start, baseline))
+            {
+                run.decorations = Some(DecorationMetrics {
+                    underline_offset: px(underline.offset),
+                    underline_thickness: px(underline.thickness),
+                    strikethrough_offset: px(strikethrough.offset),
+                    strikethrough_thickness: px(strikethrough.thickness),
+                });
+            }
+        }
+    }
 }
 
 #[allow(non_snake_case)]
@@ -943,8 +1771,8 @@ impl IDWriteTextRenderer_Impl for TextRenderer {
     fn DrawGlyphRun(
         &self,
         clientdrawingcontext: *const ::core::ffi::c_void,
-        _baselineoriginx: f32,
-        _baselineoriginy: f32,
+        baselineoriginx: f32,
+        baselineoriginy: f32,
         _measuringmode: DWRITE_MEASURING_MODE,
         glyphrun: *const DWRITE_GLYPH_RUN,
         glyphrundescription: *const DWRITE_GLYPH_RUN_DESCRIPTION,
@@ -957,7 +1785,6 @@ impl IDWriteTextRenderer_Impl for TextRenderer {
                 return Ok(());
             }
             let desc = &*glyphrundescription;
-            let utf16_length_per_glyph = desc.stringLength as usize / glyph_count;
             let context =
                 &mut *(clientdrawingcontext as *const RendererContext as *mut RendererContext);
 
@@ -966,70 +1793,124 @@ impl IDWriteTextRenderer_Impl for TextRenderer {
             }
 
             let font_face = glyphrun.fontFace.as_ref().unwrap();
-            // This `cast()` action here should never fail since we are running on Win10+, and
-            // `IDWriteFontFace3` requires Win10
-            let font_face = &font_face.cast::<IDWriteFontFace3>().unwrap();
+            // Fonts that are only available through older interfaces can't be looked up, skip
+            // their glyphs but keep the positions of the following runs intact.
+            let Ok(font_face) = &font_face.cast::<IDWriteFontFace3>() else {
+                for index in 0..glyph_count {
+                    context.width += *glyphrun.glyphAdvances.add(index);
+                }
+                return Ok(());
+            };
             let Some((font_identifier, font_struct, is_emoji)) =
                 get_font_identifier_and_font_struct(font_face, &self.locale)
             else {
                 return Ok(());
             };
 
-            let font_id = if let Some(id) = context
+            let font_id = context
                 .text_system
                 .font_id_by_identifier
+                .read()
                 .get(&font_identifier)
-            {
-                *id
+                .copied();
+            let font_id = if let Some(id) = font_id {
+                id
             } else {
-                context.text_system.select_font(&font_struct)
+                let Some(font_id) = context.text_system.select_font(&font_struct).log_err() else {
+                    // keep the positions of the following runs intact
+                    for index in 0..glyph_count {
+                        context.width += *glyphrun.glyphAdvances.add(index);
+                    }
+                    return Ok(());
+                };
+                font_id
             };
+            let cluster_map =
+                std::slice::from_raw_parts(desc.clusterMap, desc.stringLength as usize);
+            let glyph_offsets = glyph_text_offsets(cluster_map, glyph_count);
             let mut glyphs = SmallVec::new();
             for index in 0..glyph_count {
                 let id = GlyphId(*glyphrun.glyphIndices.add(index) as u32);
-                context
-                    .index_converter
-                    .advance_to_utf16_ix(context.utf16_index);
+                let utf16_index = desc.textPosition as usize + glyph_offsets[index];
+                let position = if context.vertical {
+                    point(px(0.0), px(context.width))
+                } else {
+                    point(px(context.width), px(0.0))
+                };
                 glyphs.push(ShapedGlyph {
                     id,
-                    position: point(px(context.width), px(0.0)),
-                    index: context.index_converter.utf8_ix,
+                    position,
+                    index: context.text.utf8_ix(utf16_index),
                     is_emoji,
                 });
-                context.utf16_index += utf16_length_per_glyph;
                 context.width += *glyphrun.glyphAdvances.add(index);
             }
-            context.runs.push(ShapedRun { font_id, glyphs });
+            context.runs.push(ShapedRun {
+                font_id,
+                glyphs,
+                decorations: None,
+            });
+            context
+                .run_origins
+                .push(point(baselineoriginx, baselineoriginy));
         }
         Ok(())
     }
 
     fn DrawUnderline(
         &self,
-        _clientdrawingcontext: *const ::core::ffi::c_void,
-        _baselineoriginx: f32,
-        _baselineoriginy: f32,
-        _underline: *const DWRITE_UNDERLINE,
+        clientdrawingcontext: *const ::core::ffi::c_void,
+        baselineoriginx: f32,
+        baselineoriginy: f32,
+        underline: *const DWRITE_UNDERLINE,
         _clientdrawingeffect: Option<&windows::core::IUnknown>,
     ) -> windows::core::Result<()> {
-        Err(windows::core::Error::new(
-            E_NOTIMPL,
-            "DrawUnderline unimplemented",
-        ))
+        unsafe {
+            let underline = &*underline;
+            let context =
+                &mut *(clientdrawingcontext as *const RendererContext as *mut RendererContext);
+            let (start, baseline) = if context.vertical {
+                (baselineoriginy, baselineoriginx)
+            } else {
+                (baselineoriginx, baselineoriginy)
+            };
+            context.underlines.push(DecorationSpan {
+                start,
+                end: start + underline.width,
+                baseline,
+                offset: underline.offset,
+                thickness: underline.thickness,
+            });
+        }
+        Ok(())
     }
 
     fn DrawStrikethrough(
         &self,
-        _clientdrawingcontext: *const ::core::ffi::c_void,
-        _baselineoriginx: f32,
-        _baselineoriginy: f32,
-        _strikethrough: *const DWRITE_STRIKETHROUGH,
+        clientdrawingcontext: *const ::core::ffi::c_void,
+        baselineoriginx: f32,
+        baselineoriginy: f32,
+        strikethrough: *const DWRITE_STRIKETHROUGH,
         _clientdrawingeffect: Option<&windows::core::IUnknown>,
     ) -> windows::core::Result<()> {
-        Err(windows::core::Error::new(
-            E_NOTIMPL,
-            "DrawStrikethrough unimplemented",
-        ))
+        unsafe {
+            let strikethrough = &*strikethrough;
+            let context =
+                &mut *(clientdrawingcontext as *const RendererContext as *mut RendererContext);
+            let (start, baseline) = if context.vertical {
+                (baselineoriginy, baselineoriginx)
+            } else {
+                (baselineoriginx, baselineoriginy)
+            };
+            context.strikethroughs.push(DecorationSpan {
+                start,
+                end: start + strikethrough.width,
+                baseline,
+                offset: strikethrough.offset,
+                thickness: strikethrough.thickness,
+            });
+        }
+        Ok(())
     }
 
     fn DrawInlineObject(
@@ -1049,41 +1930,51 @@ impl IDWriteTextRenderer_Impl for TextRenderer {
     }
 }
 
-struct StringIndexConverter<'a> {
-    text: &'a str,
-    utf8_ix: usize,
-    utf16_ix: usize,
+/// A line encoded as UTF-16 for DirectWrite, together with the UTF-8 offset of each of
+/// its code units so positions can be mapped back and forth without re-encoding.
+struct Utf16Text {
+    units: Vec<u16>,
+    /// One entry per code unit plus one for the end of the text. Both units of a
+    /// surrogate pair map to the start of their character.
+    utf8_offsets: Vec<usize>,
 }
 
-impl<'a> StringIndexConverter<'a> {
-    fn new(text: &'a str) -> Self {
+impl Utf16Text {
+    fn new(text: &str) -> Self {
+        let mut units = Vec::with_capacity(text.len());
+        let mut utf8_offsets = Vec::with_capacity(text.len() + 1);
+        let mut buffer = [0; 2];
+        for (ix, c) in text.char_indices() {
+            let encoded = c.encode_utf16(&mut buffer);
+            units.extend_from_slice(encoded);
+            utf8_offsets.extend(std::iter::repeat(ix).take(encoded.len()));
+        }
+        utf8_offsets.push(text.len());
         Self {
-            text,
-            utf8_ix: 0,
-            utf16_ix: 0,
+            units,
+            utf8_offsets,
         }
     }
 
-    fn advance_to_utf8_ix(&mut self, utf8_target: usize) {
-        for (ix, c) in self.text[self.utf8_ix..].char_indices() {
-            if self.utf8_ix + ix >= utf8_target {
-                self.utf8_ix += ix;
-                return;
-            }
-            self.utf16_ix += c.len_utf16();
-        }
-        self.utf8_ix = self.text.len();
+    /// Returns the UTF-8 offset of the given code unit, or the end of the text if it's
+    /// out of bounds.
+    fn utf8_ix(&self, utf16_ix: usize) -> usize {
+        self.utf8_offsets[utf16_ix.min(self.units.len())]
     }
 
-    fn advance_to_utf16_ix(&mut self, utf16_target: usize) {
-        for (ix, c) in self.text[self.utf8_ix..].char_indices() {
-            if self.utf16_ix >= utf16_target {
-                self.utf8_ix += ix;
-                return;
-            }
-            self.utf16_ix += c.len_utf16();
+    /// Returns the first code unit of the character at or after the given UTF-8 offset.
+    fn utf16_ix(&self, utf8_ix: usize) -> usize {
+        self.utf8_offsets
+            .partition_point(|&offset| offset < utf8_ix)
+    }
+
+    fn range(&self, utf8_range: Range<usize>) -> DWRITE_TEXT_RANGE {
+        let start = self.utf16_ix(utf8_range.start);
+        let end = self.utf16_ix(utf8_range.end);
+        DWRITE_TEXT_RANGE {
+            startPosition: start as u32,
+            length: (end - start) as u32,
         }
-        self.utf8_ix = self.text.len();
     }
 }
 
@@ -1120,6 +2011,89 @@ impl From<DWRITE_FONT_WEIGHT> for FontWeight {
     }
 }
 
+impl From<FontStretch> for DWRITE_FONT_STRETCH {
+    fn from(value: FontStretch) -> Self {
+        match value {
+            FontStretch::UltraCondensed => DWRITE_FONT_STRETCH_ULTRA_CONDENSED,
+            FontStretch::ExtraCondensed => DWRITE_FONT_STRETCH_EXTRA_CONDENSED,
+            FontStretch::Condensed => DWRITE_FONT_STRETCH_CONDENSED,
+            FontStretch::SemiCondensed => DWRITE_FONT_STRETCH_SEMI_CONDENSED,
+            FontStretch::Normal => DWRITE_FONT_STRETCH_NORMAL,
+            FontStretch::SemiExpanded => DWRITE_FONT_STRETCH_SEMI_EXPANDED,
+            FontStretch::Expanded => DWRITE_FONT_STRETCH_EXPANDED,
+            FontStretch::ExtraExpanded => DWRITE_FONT_STRETCH_EXTRA_EXPANDED,
+            FontStretch::UltraExpanded => DWRITE_FONT_STRETCH_ULTRA_EXPANDED,
+        }
+    }
+}
+
+impl From<DWRITE_FONT_STRETCH> for FontStretch {
+    fn from(value: DWRITE_FONT_STRETCH) -> Self {
+        match value.0 {
+            1 => FontStretch::UltraCondensed,
+            2 => FontStretch::ExtraCondensed,
+            3 => FontStretch::Condensed,
+            4 => FontStretch::SemiCondensed,
+            6 => FontStretch::SemiExpanded,
+            7 => FontStretch::Expanded,
+            8 => FontStretch::ExtraExpanded,
+            9 => FontStretch::UltraExpanded,
+            // DWRITE_FONT_STRETCH_UNDEFINED and DWRITE_FONT_STRETCH_NORMAL
+            _ => FontStretch::Normal,
+        }
+    }
+}
+
+impl From<FontSimulations> for DWRITE_FONT_SIMULATIONS {
+    fn from(value: FontSimulations) -> Self {
+        match value {
+            FontSimulations::All => DWRITE_FONT_SIMULATIONS(
+                DWRITE_FONT_SIMULATIONS_BOLD.0 | DWRITE_FONT_SIMULATIONS_OBLIQUE.0,
+            ),
+            FontSimulations::None => DWRITE_FONT_SIMULATIONS_NONE,
+            FontSimulations::SyntheticBold => DWRITE_FONT_SIMULATIONS_BOLD,
+            FontSimulations::SyntheticOblique => DWRITE_FONT_SIMULATIONS_OBLIQUE,
+        }
+    }
+}
+
+/// The simulations a face was created with, which are the only ones a font selecting the
+/// face again may allow.
+impl From<DWRITE_FONT_SIMULATIONS> for FontSimulations {
+    fn from(value: DWRITE_FONT_SIMULATIONS) -> Self {
+        let bold = value.0 & DWRITE_FONT_SIMULATIONS_BOLD.0 != 0;
+        let oblique = value.0 & DWRITE_FONT_SIMULATIONS_OBLIQUE.0 != 0;
+        match (bold, oblique) {
+            (true, true) => FontSimulations::All,
+            (true, false) => FontSimulations::SyntheticBold,
+            (false, true) => FontSimulations::SyntheticOblique,
+            (false, false) => FontSimulations::None,
+        }
+    }
+}
+
+impl From<windows::core::Error> for TextSystemError {
+    fn from(error: windows::core::Error) -> Self {
+        match error.code() {
+            // returned by `EndDraw` once the device behind the render target is gone
+            D2DERR_RECREATE_TARGET => TextSystemError::DeviceLost,
+            code => TextSystemError::InvalidParams { hresult: code.0 },
+        }
+    }
+}
+
+/// Replaces a failed COM call anywhere in the error's chain with a [`TextSystemError`], so
+/// callers can match on the failure without parsing `HRESULT`s out of the message.
+fn text_system_error(error: anyhow::Error) -> anyhow::Error {
+    match error.downcast_ref::<windows::core::Error>() {
+        Some(com_error) => {
+            let text_system_error = TextSystemError::from(com_error.clone());
+            error.context(text_system_error)
+        }
+        None => error,
+    }
+}
+
 fn get_font_names_from_collection(
     collection: &IDWriteFontCollection1,
     locale: &str,
@@ -1152,32 +2126,68 @@ fn get_font_identifier_and_font_struct(
     let localized_family_name = unsafe { font_face.GetFamilyNames().log_err() }?;
     let family_name = get_name(localized_family_name, locale).log_err()?;
     let weight = unsafe { font_face.GetWeight() };
+    let stretch = unsafe { font_face.GetStretch() };
     let style = unsafe { font_face.GetStyle() };
+    let simulations = unsafe { font_face.GetSimulations() };
     let identifier = FontIdentifier {
         postscript_name,
         weight: weight.0,
         style: style.0,
+        simulations: simulations.0,
     };
     let font_struct = Font {
         family: family_name.into(),
         features: FontFeatures::default(),
         weight: weight.into(),
+        stretch: stretch.into(),
         style: style.into(),
+        simulations: simulations.into(),
     };
     let is_emoji = unsafe { font_face.IsColorFont().as_bool() };
     Some((identifier, font_struct, is_emoji))
 }
 
+/// Splits style words off the end of a font name like "Consolas Bold Italic", returning the
+/// remaining family name together with the weight and style the words describe.
+fn strip_style_suffixes(
+    name: &str,
+    mut weight: FontWeight,
+    mut style: FontStyle,
+) -> Option<(&str, FontWeight, FontStyle)> {
+    let name = name.trim_end();
+    let mut family = name;
+    while let Some((rest, word)) = family.rsplit_once([' ', '-']) {
+        match word.to_ascii_lowercase().as_str() {
+            "regular" | "normal" | "book" | "roman" => {}
+            "italic" => style = FontStyle::Italic,
+            "oblique" => style = FontStyle::Oblique,
+            "thin" | "hairline" => weight = FontWeight::THIN,
+            "extralight" | "ultralight" => weight = FontWeight::EXTRA_LIGHT,
+            "light" => weight = FontWeight::LIGHT,
+            "medium" => weight = FontWeight::MEDIUM,
+            "semibold" | "demibold" => weight = FontWeight::SEMIBOLD,
+            "bold" => weight = FontWeight::BOLD,
+            "extrabold" | "ultrabold" => weight = FontWeight::EXTRA_BOLD,
+            "black" | "heavy" => weight = FontWeight::BLACK,
+            _ => break,
+        }
+        family = rest.trim_end();
+    }
+    (family.len() < name.len() && !family.is_empty()).then_some((family, weight, style))
+}
+
 #[inline]
 fn get_font_identifier(font_face: &IDWriteFontFace3, locale: &str) -> Option<FontIdentifier> {
     let weight = unsafe { font_face.GetWeight().0 };
     let style = unsafe { font_face.GetStyle().0 };
+    let simulations = unsafe { font_face.GetSimulations().0 };
     get_postscript_name(font_face, locale)
         .log_err()
         .map(|postscript_name| FontIdentifier {
             postscript_name,
             weight,
             style,
+            simulations,
         })
 }
 
@@ -1215,22 +2225,16 @@ fn apply_font_features(
     let mut feature_clig = make_direct_write_feature("clig", 1);
     let mut feature_calt = make_direct_write_feature("calt", 1);
 
+    // The value is passed through as is, so features with alternates (e.g. `cv01`
+    // or `aalt`) can select a specific one instead of just being toggled.
     for (tag, value) in tag_values {
-        if tag.as_str() == "liga" && *value == 0 {
-            feature_liga.parameter = 0;
-            continue;
-        }
-        if tag.as_str() == "clig" && *value == 0 {
-            feature_clig.parameter = 0;
-            continue;
-        }
-        if tag.as_str() == "calt" && *value == 0 {
-            feature_calt.parameter = 0;
-            continue;
-        }
-
-        unsafe {
-            direct_write_features.AddFontFeature(make_direct_write_feature(&tag, *value))?;
+        match tag.as_str() {
+            "liga" => feature_liga.parameter = *value,
+            "clig" => feature_clig.parameter = *value,
+            "calt" => feature_calt.parameter = *value,
+            _ => unsafe {
+                direct_write_features.AddFontFeature(make_direct_write_feature(&tag, *value))?;
+            },
         }
     }
     unsafe {
@@ -1242,6 +2246,78 @@ fn apply_font_features(
     Ok(())
 }
 
+/// The symbol fonts that ship with Windows, tried in order for the private use areas.
+const SYMBOL_FALLBACK_FAMILIES: [PCWSTR; 3] = [
+    w!("Segoe Fluent Icons"),
+    w!("Segoe MDL2 Assets"),
+    w!("Segoe UI Symbol"),
+];
+
+/// Creates the system font fallback, extended to look for icons in the private use areas,
+/// like the ones patched into Nerd Fonts, in the symbol fonts of Windows. The system
+/// fallback leaves these code points to the font they are requested in.
+unsafe fn create_font_fallback(factory: &IDWriteFactory5) -> Result<IDWriteFontFallback> {
+    let builder = factory.CreateFontFallbackBuilder()?;
+    let private_use_areas = [
+        DWRITE_UNICODE_RANGE {
+            first: 0xE000,
+            last: 0xF8FF,
+        },
+        DWRITE_UNICODE_RANGE {
+            first: 0xF0000,
+            last: 0xFFFFD,
+        },
+        DWRITE_UNICODE_RANGE {
+            first: 0x100000,
+            last: 0x10FFFD,
+        },
+    ];
+    let families = SYMBOL_FALLBACK_FAMILIES.map(|family| family.as_ptr());
+    builder.AddMapping(&private_use_areas, &families, None, None, None, 1.0)?;
+    builder.AddMappings(&factory.GetSystemFontFallback()?)?;
+    Ok(builder.CreateFontFallback()?)
+}
+
+/// Returns the offset of the text each glyph of a run belongs to, relative to the start
+/// of the run, given the run's cluster map. The cluster map holds the index of the first
+/// glyph of the cluster each UTF-16 code unit is part of, so a ligature maps several code
+/// units to one glyph and a combining sequence may map them to several glyphs. Glyphs
+/// after the first one of a cluster are assigned the start of that cluster.
+fn glyph_text_offsets(cluster_map: &[u16], glyph_count: usize) -> Vec<usize> {
+    let mut offsets = vec![None; glyph_count];
+    for (offset, glyph) in cluster_map.iter().enumerate().rev() {
+        if let Some(glyph_offset) = offsets.get_mut(*glyph as usize) {
+            *glyph_offset = Some(offset);
+        }
+    }
+    let mut cluster_start = 0;
+    offsets
+        .into_iter()
+        .map(|offset| {
+            if let Some(offset) = offset {
+                cluster_start = offset;
+            }
+            cluster_start
+        })
+        .collect()
+}
+
+fn union_rect(a: D2D_RECT_F, b: D2D_RECT_F) -> D2D_RECT_F {
+    // empty bounds are reported with `right` less than `left`
+    if b.right < b.left {
+        return a;
+    }
+    if a.right < a.left {
+        return b;
+    }
+    D2D_RECT_F {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
+    }
+}
+
 #[inline]
 fn make_direct_write_feature(feature_name: &str, parameter: u32) -> DWRITE_FONT_FEATURE {
     let tag = make_direct_write_tag(feature_name);
@@ -1299,6 +2375,82 @@ fn get_name(string: IDWriteLocalizedStrings, locale: &str) -> Result<String> {
     Ok(String::from_utf16_lossy(&name_vec[..name_length]))
 }
 
+/// Resolves the index of the palette the color glyphs of a font are painted with, falling
+/// back to the first palette when the font doesn't have the requested one.
+fn color_palette_index(font_info: &FontInfo, selection: FontPaletteSelection) -> u32 {
+    let font_face = &font_info.font_face;
+    let palette_type = match selection {
+        FontPaletteSelection::Default => return 0,
+        FontPaletteSelection::Index(index) => {
+            let index = index as u32;
+            return if index < unsafe { font_face.GetColorPaletteCount() } {
+                index
+            } else {
+                0
+            };
+        }
+        FontPaletteSelection::LightBackground => CPAL_USABLE_WITH_LIGHT_BACKGROUND,
+        FontPaletteSelection::DarkBackground => CPAL_USABLE_WITH_DARK_BACKGROUND,
+    };
+    font_info
+        .cpal
+        .get_or_init(|| unsafe { read_font_table(font_face, make_open_type_tag("CPAL")) })
+        .as_deref()
+        .and_then(|cpal| cpal_palette_with_type(cpal, palette_type))
+        .map_or(0, |index| index as u32)
+}
+
+unsafe fn read_font_table(font_face: &IDWriteFontFace3, tag: u32) -> Option<Vec<u8>> {
+    let mut data = std::ptr::null_mut();
+    let mut size = 0;
+    let mut context = std::ptr::null_mut();
+    let mut exists = BOOL(0);
+    font_face
+        .TryGetFontTable(tag, &mut data, &mut size, &mut context, &mut exists)
+        .log_err()?;
+    if !exists.as_bool() {
+        return None;
+    }
+    let table = std::slice::from_raw_parts(data as *const u8, size as usize).to_vec();
+    font_face.ReleaseFontTable(context);
+    Some(table)
+}
+
+/// Finds the first palette of a `CPAL` table whose type has the given flag set. Palette types
+/// were added in version 1 of the table, fonts with an older table have none to pick from.
+fn cpal_palette_with_type(cpal: &[u8], palette_type: u32) -> Option<u16> {
+    let read_u16 = |offset: usize| {
+        cpal.get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    let read_u32 = |offset: usize| {
+        cpal.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    if read_u16(0)? < 1 {
+        return None;
+    }
+    let palette_count = read_u16(4)?;
+    // The offset follows the header and the index of the first color of each palette.
+    let types_offset = read_u32(12 + palette_count as usize * 2)? as usize;
+    if types_offset == 0 {
+        return None;
+    }
+    (0..palette_count).find(|ix| {
+        read_u32(types_offset + *ix as usize * 4).is_some_and(|types| types & palette_type != 0)
+    })
+}
+
+/// Refers to the font face from a glyph run without taking a reference to it, glyph runs never
+/// release their font face.
+fn borrowed_font_face(font_face: &IDWriteFontFace3) -> ManuallyDrop<Option<IDWriteFontFace>> {
+    // every version of the interface extends the first one, so the pointer is valid for it
+    ManuallyDrop::new(Some(unsafe {
+        IDWriteFontFace::from_raw(font_face.as_raw())
+    }))
+}
+
 #[inline]
 fn translate_color(color: &DWRITE_COLOR_F) -> D2D1_COLOR_F {
     D2D1_COLOR_F {
@@ -1333,6 +2485,34 @@ fn get_system_ui_font_name() -> SharedString {
     }
 }
 
+// Add an extra pixel when the subpixel variant isn't zero to make room for anti-aliasing.
+fn glyph_bitmap_size(
+    params: &RenderGlyphParams,
+    glyph_bounds: Bounds<DevicePixels>,
+) -> Size<DevicePixels> {
+    let mut bitmap_size = glyph_bounds.size;
+    if params.subpixel_variant.x > 0 {
+        bitmap_size.width += DevicePixels(1);
+    }
+    if params.subpixel_variant.y > 0 {
+        bitmap_size.height += DevicePixels(1);
+    }
+    bitmap_size
+}
+
+fn can_rasterize_in_strip(params: &RenderGlyphParams, glyph_bounds: Bounds<DevicePixels>) -> bool {
+    !params.is_emoji && glyph_bounds.size.width.0 > 0 && glyph_bounds.size.height.0 > 0
+}
+
+unsafe fn line_metrics(text_layout: &IDWriteTextLayout) -> Result<Vec<DWRITE_LINE_METRICS>> {
+    let mut line_count = 0u32;
+    // Fails with a buffer that is too small, after reporting the number of lines.
+    text_layout.GetLineMetrics(None, &mut line_count as _).ok();
+    let mut metrics = vec![DWRITE_LINE_METRICS::default(); line_count as usize];
+    text_layout.GetLineMetrics(Some(&mut metrics), &mut line_count as _)?;
+    Ok(metrics)
+}
+
 #[inline]
 fn get_render_target_property(
     pixel_format: DXGI_FORMAT,
@@ -1351,10 +2531,205 @@ fn get_render_target_property(
     }
 }
 
+const APPEND_ONLY_VEC_FIRST_CHUNK_LEN: usize = 16;
+const APPEND_ONLY_VEC_CHUNKS: usize = 32;
+const DEFAULT_TAB_SIZE: NonZeroU32 = match NonZeroU32::new(4) {
+    Some(tab_size) => tab_size,
+    None => unreachable!(),
+};
+// Glyphs in a strip are kept apart so the downscaling filter doesn't bleed between them.
+const GLYPH_STRIP_GAP: i32 = 4;
+// In device pixels, the strip is rasterized at twice this width.
+const MAX_GLYPH_STRIP_WIDTH: i32 = 4096;
 const DEFAULT_LOCALE_NAME: PCWSTR = windows::core::w!("en-US");
+// fonts that ship with Windows, used when the requested font is missing
+const DEFAULT_UI_FONT_FAMILY: &str = "Segoe UI";
+const DEFAULT_MONOSPACE_FONT_FAMILY: &str = "Cascadia Mono";
+const CPAL_USABLE_WITH_LIGHT_BACKGROUND: u32 = 0x1;
+const CPAL_USABLE_WITH_DARK_BACKGROUND: u32 = 0x2;
+// the palette index of color glyph layers that are painted in the color of the text
+const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
 const BRUSH_COLOR: D2D1_COLOR_F = D2D1_COLOR_F {
     r: 1.0,
     g: 1.0,
     b: 1.0,
     a: 1.0,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::DirectWriteTextSystem;
+    use super::{
+        cpal_palette_with_type, glyph_text_offsets, strip_style_suffixes, AppendOnlyVec, Utf16Text,
+        CPAL_USABLE_WITH_DARK_BACKGROUND, CPAL_USABLE_WITH_LIGHT_BACKGROUND,
+    };
+    use crate::{
+        font, px, FontFeatures, FontRun, FontSimulations, FontStyle, FontWeight,
+        PlatformTextSystem, TextOrientation,
+    };
+    use windows::Win32::Graphics::DirectWrite::DWRITE_FONT_SIMULATIONS;
+
+    #[test]
+    fn test_glyph_text_offsets() {
+        // one glyph per character
+        assert_eq!(glyph_text_offsets(&[0, 1, 2], 3), vec![0, 1, 2]);
+        // "->" shaped as a ligature, followed by a space
+        assert_eq!(glyph_text_offsets(&[0, 0, 1], 2), vec![0, 2]);
+        // a surrogate pair followed by a character decomposed into two glyphs
+        assert_eq!(glyph_text_offsets(&[0, 0, 1], 3), vec![0, 2, 2]);
+    }
+
+    #[test]
+    fn test_strip_style_suffixes() {
+        assert_eq!(
+            strip_style_suffixes("Consolas Bold", FontWeight::NORMAL, FontStyle::Normal),
+            Some(("Consolas", FontWeight::BOLD, FontStyle::Normal))
+        );
+        assert_eq!(
+            strip_style_suffixes(
+                "Cascadia Code-SemiBold Italic",
+                FontWeight::NORMAL,
+                FontStyle::Normal
+            ),
+            Some(("Cascadia Code", FontWeight::SEMIBOLD, FontStyle::Italic))
+        );
+        assert_eq!(
+            strip_style_suffixes("Segoe UI", FontWeight::NORMAL, FontStyle::Normal),
+            None
+        );
+        assert_eq!(
+            strip_style_suffixes("Bold", FontWeight::NORMAL, FontStyle::Normal),
+            None
+        );
+    }
+
+    #[test]
+    fn test_font_simulations() {
+        for simulations in [
+            FontSimulations::All,
+            FontSimulations::None,
+            FontSimulations::SyntheticBold,
+            FontSimulations::SyntheticOblique,
+        ] {
+            let dwrite_simulations: DWRITE_FONT_SIMULATIONS = simulations.into();
+            assert_eq!(FontSimulations::from(dwrite_simulations), simulations);
+        }
+    }
+
+    #[test]
+    fn test_append_only_vec() {
+        let vec = AppendOnlyVec::new();
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let vec = &vec;
+                scope.spawn(move || {
+                    for value in 0..100 {
+                        let index = vec.push(thread * 100 + value);
+                        assert_eq!(vec[index], thread * 100 + value);
+                    }
+                });
+            }
+        });
+
+        let mut values = (0..400).map(|index| vec[index]).collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, (0..400).collect::<Vec<_>>());
+        assert_eq!(vec.get(400), None);
+    }
+
+    #[test]
+    fn test_cpal_palette_with_type() {
+        // a version 1 table with three palettes of one color each
+        let mut cpal = Vec::new();
+        for value in [1u16, 1, 3, 3] {
+            cpal.extend(value.to_be_bytes());
+        }
+        cpal.extend(42u32.to_be_bytes());
+        for index in [0u16, 1, 2] {
+            cpal.extend(index.to_be_bytes());
+        }
+        let types_offset = cpal.len() as u32 + 12;
+        cpal.extend(types_offset.to_be_bytes());
+        cpal.extend(0u32.to_be_bytes());
+        cpal.extend(0u32.to_be_bytes());
+        for palette_type in [0u32, CPAL_USABLE_WITH_DARK_BACKGROUND, 0] {
+            cpal.extend(palette_type.to_be_bytes());
+        }
+        // the color records
+        cpal.extend([0; 12]);
+
+        assert_eq!(
+            cpal_palette_with_type(&cpal, CPAL_USABLE_WITH_DARK_BACKGROUND),
+            Some(1)
+        );
+        assert_eq!(
+            cpal_palette_with_type(&cpal, CPAL_USABLE_WITH_LIGHT_BACKGROUND),
+            None
+        );
+
+        // version 0 tables don't record palette types
+        cpal[1] = 0;
+        assert_eq!(
+            cpal_palette_with_type(&cpal, CPAL_USABLE_WITH_DARK_BACKGROUND),
+            None
+        );
+    }
+
+    #[test]
+    fn test_utf16_text() {
+        // `é` is two bytes and one unit, `😀` is four bytes and a surrogate pair
+        let text = Utf16Text::new("aé😀b");
+        assert_eq!(text.units.len(), 5);
+        assert_eq!(text.utf8_offsets, [0, 1, 3, 3, 7, 8]);
+
+        assert_eq!(text.utf8_ix(2), 3);
+        assert_eq!(text.utf8_ix(4), 7);
+        assert_eq!(text.utf8_ix(10), 8);
+        assert_eq!(text.utf16_ix(3), 2);
+        assert_eq!(text.utf16_ix(7), 4);
+        assert_eq!(text.utf16_ix(8), 5);
+
+        let range = text.range(1..7);
+        assert_eq!((range.startPosition, range.length), (1, 3));
+    }
+
+    #[test]
+    fn test_vertical_layout() {
+        let text_system = DirectWriteTextSystem::new().unwrap();
+        text_system
+            .add_fonts(vec![std::fs::read(
+                "../../assets/fonts/plex-mono/ZedPlexMono-Regular.ttf",
+            )
+            .unwrap()
+            .into()])
+            .unwrap();
+        let font_id = text_system.font_id(&font("Zed Plex Mono")).unwrap();
+        let runs = [FontRun {
+            len: 3,
+            font_id,
+            features: FontFeatures::default(),
+        }];
+        let horizontal = text_system.layout_line_with_orientation(
+            "abc",
+            px(16.),
+            &runs,
+            TextOrientation::Horizontal,
+        );
+        let vertical = text_system.layout_line_with_orientation(
+            "abc",
+            px(16.),
+            &runs,
+            TextOrientation::Vertical,
+        );
+
+        let glyphs = &vertical.runs[0].glyphs;
+        assert_eq!(glyphs.len(), 3);
+        assert!(glyphs.iter().all(|glyph| glyph.position.x == px(0.)));
+        assert!(glyphs
+            .windows(2)
+            .all(|pair| pair[0].position.y < pair[1].position.y));
+        // stacked upright, the letters advance by the height of the font rather than by their
+        // widths
+        assert!(vertical.width > horizontal.width);
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    cell::Cell,
     thread::{current, ThreadId},
     time::Duration,
 };
@@ -11,6 +12,7 @@ use windows::{
     Foundation::TimeSpan,
     System::{
         DispatcherQueue, DispatcherQueueController, DispatcherQueueHandler,
+        DispatcherQueuePriority,
         Threading::{
             ThreadPool, ThreadPoolTimer, TimerElapsedHandler, WorkItemHandler, WorkItemOptions,
             WorkItemPriority,
@@ -22,7 +24,32 @@ use windows::{
     },
 };
 
-use crate::{PlatformDispatcher, TaskLabel};
+use crate::{HighResolutionTimer, PlatformDispatcher, TaskLabel, HIGH_RESOLUTION_TIMER_THRESHOLD};
+
+thread_local! {
+    static HANDLING_INPUT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the main thread as handling user input until it is dropped. Work spawned onto the
+/// main thread meanwhile runs before lower priority work such as frame callbacks, so typing
+/// stays responsive while the thread is busy painting.
+pub(crate) struct InputPriorityGuard {
+    was_handling_input: bool,
+}
+
+impl InputPriorityGuard {
+    pub(crate) fn new() -> Self {
+        Self {
+            was_handling_input: HANDLING_INPUT.replace(true),
+        }
+    }
+}
+
+impl Drop for InputPriorityGuard {
+    fn drop(&mut self) {
+        HANDLING_INPUT.set(self.was_handling_input);
+    }
+}
 
 pub(crate) struct WindowsDispatcher {
     controller: DispatcherQueueController,
@@ -75,8 +102,13 @@ impl WindowsDispatcher {
     fn dispatch_on_threadpool_after(&self, runnable: Runnable, duration: Duration) {
         let handler = {
             let mut task_wrapper = Some(runnable);
+            // thread pool timers follow the system timer resolution, so it's raised until the
+            // timer fires and dropped again once nothing needs it
+            let mut high_resolution =
+                (duration < HIGH_RESOLUTION_TIMER_THRESHOLD).then(HighResolutionTimer::request);
             TimerElapsedHandler::new(move |_| {
                 task_wrapper.take().unwrap().run();
+                high_resolution.take();
                 Ok(())
             })
         };
@@ -115,7 +147,14 @@ impl PlatformDispatcher for WindowsDispatcher {
                 Ok(())
             })
         };
-        self.main_queue.TryEnqueue(&handler).log_err();
+        let priority = if HANDLING_INPUT.get() {
+            DispatcherQueuePriority::High
+        } else {
+            DispatcherQueuePriority::Normal
+        };
+        self.main_queue
+            .TryEnqueueWithPriority(priority, &handler)
+            .log_err();
     }
 
     fn dispatch_after(&self, duration: Duration, runnable: Runnable) {
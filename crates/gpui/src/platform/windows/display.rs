@@ -17,7 +17,7 @@ use windows::{
 
 use crate::{logical_point, point, size, Bounds, DevicePixels, DisplayId, Pixels, PlatformDisplay};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) struct WindowsDisplay {
     pub handle: HMONITOR,
     pub display_id: DisplayId,
@@ -156,15 +156,17 @@ impl WindowsDisplay {
     }
 
     pub fn displays() -> Vec<Rc<dyn PlatformDisplay>> {
+        Self::all()
+            .into_iter()
+            .map(|display| Rc::new(display) as Rc<dyn PlatformDisplay>)
+            .collect()
+    }
+
+    pub(crate) fn all() -> Vec<Self> {
         available_monitors()
             .into_iter()
             .enumerate()
-            .map(|(id, handle)| {
-                Rc::new(WindowsDisplay::new_with_handle_and_id(
-                    handle,
-                    DisplayId(id as _),
-                )) as Rc<dyn PlatformDisplay>
-            })
+            .map(|(id, handle)| WindowsDisplay::new_with_handle_and_id(handle, DisplayId(id as _)))
             .collect()
     }
 
@@ -191,6 +193,10 @@ impl WindowsDisplay {
     pub fn physical_bounds(&self) -> Bounds<DevicePixels> {
         self.physical_bounds
     }
+
+    pub(crate) fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
 }
 
 impl PlatformDisplay for WindowsDisplay {
@@ -233,7 +239,7 @@ unsafe extern "system" fn monitor_enum_proc(
     BOOL(1)
 }
 
-fn get_monitor_info(hmonitor: HMONITOR) -> anyhow::Result<MONITORINFOEXW> {
+pub(crate) fn get_monitor_info(hmonitor: HMONITOR) -> anyhow::Result<MONITORINFOEXW> {
     let mut monitor_info: MONITORINFOEXW = unsafe { std::mem::zeroed() };
     monitor_info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
     let status = unsafe {
@@ -0,0 +1,232 @@
+use std::{mem::ManuallyDrop, os::windows::ffi::OsStrExt, path::PathBuf};
+
+use windows::{
+    core::implement,
+    Win32::{
+        Foundation::*,
+        System::{Com::*, Memory::*, Ole::*, SystemServices::*},
+        UI::Shell::*,
+    },
+};
+
+use crate::*;
+
+/// Drags `data` out of the window, returning once it has been dropped or the drag was
+/// cancelled. OLE runs a modal loop meanwhile, which keeps dispatching messages to the window.
+pub(crate) fn drag_out_of_window(data: ExternalDragData) -> ExternalDragOutcome {
+    let data_object: IDataObject = DragDataObject::new(data).into();
+    let drop_source: IDropSource = DragDropSource.into();
+    let mut effect = DROPEFFECT_NONE;
+    // moving is left out, so that dropping project files into Explorer never takes them away
+    let result = unsafe {
+        DoDragDrop(
+            &data_object,
+            &drop_source,
+            DROPEFFECT_COPY | DROPEFFECT_LINK,
+            &mut effect,
+        )
+    };
+    if result != DRAGDROP_S_DROP {
+        ExternalDragOutcome::Cancelled
+    } else if effect.0 & DROPEFFECT_COPY.0 != 0 {
+        ExternalDragOutcome::Copied
+    } else if effect.0 & DROPEFFECT_LINK.0 != 0 {
+        ExternalDragOutcome::Linked
+    } else {
+        ExternalDragOutcome::Cancelled
+    }
+}
+
+#[implement(IDataObject)]
+struct DragDataObject {
+    data: ExternalDragData,
+    formats: Vec<FORMATETC>,
+}
+
+impl DragDataObject {
+    fn new(data: ExternalDragData) -> Self {
+        let mut formats = Vec::new();
+        if !data.paths.is_empty() {
+            formats.push(hglobal_format(CF_HDROP));
+        }
+        if data.text.is_some() {
+            formats.push(hglobal_format(CF_UNICODETEXT));
+        }
+        Self { data, formats }
+    }
+
+    fn supports(&self, format: &FORMATETC) -> bool {
+        format.dwAspect == DVASPECT_CONTENT.0
+            && format.tymed & TYMED_HGLOBAL.0 as u32 != 0
+            && self
+                .formats
+                .iter()
+                .any(|supported| supported.cfFormat == format.cfFormat)
+    }
+}
+
+#[allow(non_snake_case)]
+impl IDataObject_Impl for DragDataObject {
+    fn GetData(&self, pformatetcin: *const FORMATETC) -> windows::core::Result<STGMEDIUM> {
+        let format = unsafe { pformatetcin.as_ref() }.ok_or(E_INVALIDARG)?;
+        if !self.supports(format) {
+            return Err(DV_E_FORMATETC.into());
+        }
+        let bytes = if format.cfFormat == CF_HDROP.0 {
+            hdrop_bytes(&self.data.paths)
+        } else {
+            let text = self.data.text.as_deref().unwrap_or_default();
+            text.encode_utf16()
+                .chain(Some(0))
+                .flat_map(u16::to_ne_bytes)
+                .collect()
+        };
+        Ok(STGMEDIUM {
+            tymed: TYMED_HGLOBAL.0 as u32,
+            u: STGMEDIUM_0 {
+                hGlobal: global_from_bytes(&bytes)?,
+            },
+            pUnkForRelease: ManuallyDrop::new(None),
+        })
+    }
+
+    fn GetDataHere(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _pmedium: *mut STGMEDIUM,
+    ) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn QueryGetData(&self, pformatetc: *const FORMATETC) -> windows::core::HRESULT {
+        match unsafe { pformatetc.as_ref() } {
+            Some(format) if self.supports(format) => S_OK,
+            Some(_) => DV_E_FORMATETC,
+            None => E_INVALIDARG,
+        }
+    }
+
+    fn GetCanonicalFormatEtc(
+        &self,
+        _pformatectin: *const FORMATETC,
+        pformatetcout: *mut FORMATETC,
+    ) -> windows::core::HRESULT {
+        if let Some(format) = unsafe { pformatetcout.as_mut() } {
+            format.ptd = std::ptr::null_mut();
+        }
+        E_NOTIMPL
+    }
+
+    // Explorer offers extra formats like drop descriptions, which aren't needed to drop files
+    fn SetData(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _pmedium: *const STGMEDIUM,
+        _frelease: BOOL,
+    ) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn EnumFormatEtc(&self, dwdirection: u32) -> windows::core::Result<IEnumFORMATETC> {
+        if dwdirection == DATADIR_GET.0 as u32 {
+            unsafe { SHCreateStdEnumFmtEtc(&self.formats) }
+        } else {
+            Err(E_NOTIMPL.into())
+        }
+    }
+
+    fn DAdvise(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _advf: u32,
+        _padvsink: Option<&IAdviseSink>,
+    ) -> windows::core::Result<u32> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn DUnadvise(&self, _dwconnection: u32) -> windows::core::Result<()> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn EnumDAdvise(&self) -> windows::core::Result<IEnumSTATDATA> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+}
+
+#[implement(IDropSource)]
+struct DragDropSource;
+
+#[allow(non_snake_case)]
+impl IDropSource_Impl for DragDropSource {
+    fn QueryContinueDrag(
+        &self,
+        fescapepressed: BOOL,
+        grfkeystate: MODIFIERKEYS_FLAGS,
+    ) -> windows::core::HRESULT {
+        if fescapepressed.as_bool() {
+            DRAGDROP_S_CANCEL
+        } else if grfkeystate.0 & MK_LBUTTON.0 == 0 {
+            DRAGDROP_S_DROP
+        } else {
+            S_OK
+        }
+    }
+
+    fn GiveFeedback(&self, _dweffect: DROPEFFECT) -> windows::core::HRESULT {
+        DRAGDROP_S_USEDEFAULTCURSORS
+    }
+}
+
+fn hglobal_format(format: CLIPBOARD_FORMAT) -> FORMATETC {
+    FORMATETC {
+        cfFormat: format.0,
+        ptd: std::ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT.0,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as u32,
+    }
+}
+
+// https://learn.microsoft.com/en-us/windows/win32/shell/clipboard#cf_hdrop
+fn hdrop_bytes(paths: &[PathBuf]) -> Vec<u8> {
+    let header = DROPFILES {
+        pFiles: std::mem::size_of::<DROPFILES>() as u32,
+        pt: POINT::default(),
+        fNC: FALSE,
+        fWide: TRUE,
+    };
+    let mut bytes = unsafe {
+        std::slice::from_raw_parts(
+            &header as *const DROPFILES as *const u8,
+            std::mem::size_of::<DROPFILES>(),
+        )
+    }
+    .to_vec();
+    // the file names are separated by a null and the list ends with another one
+    for path in paths {
+        bytes.extend(
+            path.as_os_str()
+                .encode_wide()
+                .chain(Some(0))
+                .flat_map(u16::to_ne_bytes),
+        );
+    }
+    bytes.extend(0u16.to_ne_bytes());
+    bytes
+}
+
+fn global_from_bytes(bytes: &[u8]) -> windows::core::Result<HGLOBAL> {
+    unsafe {
+        let global = GlobalAlloc(GMEM_MOVEABLE, bytes.len())?;
+        let data = GlobalLock(global);
+        if data.is_null() {
+            let error = windows::core::Error::from_win32();
+            GlobalFree(global).ok();
+            return Err(error);
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), data.cast::<u8>(), bytes.len());
+        // unlocking the last lock reports `NO_ERROR` as a failure
+        GlobalUnlock(global).ok();
+        Ok(global)
+    }
+}
@@ -1,24 +1,64 @@
-use std::rc::Rc;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
 
 use ::util::ResultExt;
 use anyhow::Context;
-use windows::Win32::{
-    Foundation::*,
-    Graphics::Gdi::*,
-    System::SystemServices::*,
-    UI::{
-        HiDpi::*,
-        Input::{Ime::*, KeyboardAndMouse::*},
-        WindowsAndMessaging::*,
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::{
+            Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy},
+            SystemInformation::GetTickCount,
+            SystemServices::*,
+        },
+        UI::{
+            HiDpi::*,
+            Input::{Ime::*, KeyboardAndMouse::*, Pointer::*, *},
+            Shell::THBN_CLICKED,
+            WindowsAndMessaging::*,
+        },
     },
 };
 
 use crate::*;
 
+thread_local! {
+    /// Whether AltGr is held down. It's reported by Windows as left control and right alt,
+    /// so the control key it sends is told apart from a real one by the message stream.
+    static ALT_GR_PRESSED: Cell<bool> = const { Cell::new(false) };
+
+    /// The quit callback of the platform. The process is terminated as soon as every window
+    /// has returned from `WM_ENDSESSION`, so it's run from the window procedure itself.
+    static SESSION_END_CALLBACK: RefCell<Option<QuitCallback>> = const { RefCell::new(None) };
+}
+
+pub(crate) type QuitCallback = Rc<RefCell<Option<Box<dyn FnMut()>>>>;
+
 pub(crate) const CURSOR_STYLE_CHANGED: u32 = WM_USER + 1;
 pub(crate) const CLOSE_ONE_WINDOW: u32 = WM_USER + 2;
+pub(crate) const APP_ACTIVE_CHANGED: u32 = WM_USER + 3;
+pub(crate) const ACCENT_COLOR_CHANGED: u32 = WM_USER + 4;
+pub(crate) const DEFAULT_HANDLERS_CHANGED: u32 = WM_USER + 5;
+pub(crate) const TOUCH_KEYBOARD_CHANGED: u32 = WM_USER + 6;
+pub(crate) const START_EXTERNAL_DRAG: u32 = WM_USER + 7;
+pub(crate) const DISPLAYS_CHANGED: u32 = WM_USER + 8;
+pub(crate) const SYSTEM_SUSPEND_CHANGED: u32 = WM_USER + 9;
+pub(crate) const SESSION_LOCK_CHANGED: u32 = WM_USER + 10;
+pub(crate) const TRAY_ICON_EVENT: u32 = WM_USER + 11;
+pub(crate) const NOTIFICATION_ACTIVATED: u32 = WM_USER + 12;
+pub(crate) const NOTIFICATION_CLOSED: u32 = WM_USER + 13;
+pub(crate) const KEYBOARD_LAYOUT_CHANGED: u32 = WM_USER + 14;
+pub(crate) const DESKTOP_SWITCHED: u32 = WM_USER + 15;
 
 const SIZE_MOVE_LOOP_TIMER_ID: usize = 1;
+const WHEEL_SCROLL_TIMER_ID: usize = 2;
+/// Set in the high word of the `wparam` of `WM_POINTERUP` when the system canceled the pointer.
+const POINTER_MESSAGE_FLAG_CANCELED: u16 = 0x8000;
 
 pub(crate) fn handle_msg(
     handle: HWND,
@@ -27,8 +67,17 @@ pub(crate) fn handle_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> LRESULT {
+    let _input_priority = is_input_msg(msg).then(|| {
+        record_input_latency(&state_ptr);
+        InputPriorityGuard::new()
+    });
     let handled = match msg {
         WM_ACTIVATE => handle_activate_msg(handle, wparam, state_ptr),
+        WM_ACTIVATEAPP => handle_activate_app_msg(handle, wparam, state_ptr),
+        WM_SETFOCUS => handle_set_focus_msg(state_ptr),
+        WM_KILLFOCUS => handle_kill_focus_msg(handle, state_ptr),
+        WM_DWMCOLORIZATIONCOLORCHANGED => handle_dwm_colorization_color_changed_msg(),
+        WM_INPUTLANGCHANGE => handle_input_language_changed_msg(),
         WM_CREATE => handle_create_msg(handle, state_ptr),
         WM_MOVE => handle_move_msg(handle, lparam, state_ptr),
         WM_SIZE => handle_size_msg(lparam, state_ptr),
@@ -36,17 +85,27 @@ pub(crate) fn handle_msg(
         WM_EXITSIZEMOVE | WM_EXITMENULOOP => handle_size_move_loop_exit(handle),
         WM_TIMER => handle_timer_msg(handle, wparam, state_ptr),
         WM_NCCALCSIZE => handle_calc_client_size(handle, wparam, lparam, state_ptr),
+        WM_GETMINMAXINFO => handle_get_min_max_info_msg(handle, lparam, state_ptr),
         WM_DPICHANGED => handle_dpi_changed_msg(handle, wparam, lparam, state_ptr),
-        WM_DISPLAYCHANGE => handle_display_change_msg(handle, state_ptr),
+        WM_DISPLAYCHANGE => handle_display_change_msg(handle, wparam, state_ptr),
+        WM_DEVICECHANGE => handle_device_change_msg(wparam),
+        WM_POWERBROADCAST => handle_power_broadcast_msg(wparam),
+        WM_WTSSESSION_CHANGE => handle_session_change_msg(wparam),
         WM_NCHITTEST => handle_hit_test_msg(handle, msg, wparam, lparam, state_ptr),
         WM_PAINT => handle_paint_msg(handle, state_ptr),
         WM_CLOSE => handle_close_msg(state_ptr),
-        WM_DESTROY => handle_destroy_msg(handle, state_ptr),
-        WM_MOUSEMOVE => handle_mouse_move_msg(lparam, wparam, state_ptr),
-        WM_NCMOUSEMOVE => handle_nc_mouse_move_msg(handle, lparam, state_ptr),
+        WM_QUERYENDSESSION => handle_query_end_session_msg(handle),
+        WM_ENDSESSION => handle_end_session_msg(handle, wparam),
+        WM_DESTROY => handle_destroy_msg(state_ptr),
+        WM_MOUSEMOVE => handle_mouse_move_msg(handle, lparam, wparam, state_ptr),
+        WM_MOUSELEAVE => handle_mouse_leave_msg(handle, state_ptr),
+        WM_CAPTURECHANGED => handle_capture_changed_msg(handle, lparam, state_ptr),
+        WM_INPUT => handle_raw_input_msg(lparam, state_ptr),
+        WM_NCMOUSEMOVE => handle_nc_mouse_move_msg(handle, wparam, lparam, state_ptr),
         WM_NCLBUTTONDOWN => {
             handle_nc_mouse_down_msg(handle, MouseButton::Left, wparam, lparam, state_ptr)
         }
+        WM_NCLBUTTONDBLCLK => handle_nc_double_click_msg(handle, wparam, lparam, state_ptr),
         WM_NCRBUTTONDOWN => {
             handle_nc_mouse_down_msg(handle, MouseButton::Right, wparam, lparam, state_ptr)
         }
@@ -72,18 +131,29 @@ pub(crate) fn handle_msg(
         WM_RBUTTONUP => handle_mouse_up_msg(handle, MouseButton::Right, lparam, state_ptr),
         WM_MBUTTONUP => handle_mouse_up_msg(handle, MouseButton::Middle, lparam, state_ptr),
         WM_XBUTTONUP => handle_xbutton_msg(handle, wparam, lparam, handle_mouse_up_msg, state_ptr),
+        WM_POINTERDOWN => handle_pointer_down_msg(handle, wparam, lparam, state_ptr),
+        WM_POINTERUPDATE => handle_pointer_update_msg(handle, wparam, lparam, state_ptr),
+        WM_POINTERUP => handle_pointer_up_msg(handle, wparam, lparam, state_ptr),
+        WM_POINTERCAPTURECHANGED => handle_pointer_capture_changed_msg(wparam, state_ptr),
         WM_MOUSEWHEEL => handle_mouse_wheel_msg(handle, wparam, lparam, state_ptr),
         WM_MOUSEHWHEEL => handle_mouse_horizontal_wheel_msg(handle, wparam, lparam, state_ptr),
         WM_SYSKEYDOWN => handle_syskeydown_msg(wparam, lparam, state_ptr),
         WM_SYSKEYUP => handle_syskeyup_msg(wparam, state_ptr),
         WM_KEYDOWN => handle_keydown_msg(wparam, lparam, state_ptr),
-        WM_KEYUP => handle_keyup_msg(wparam, state_ptr),
+        WM_KEYUP => handle_keyup_msg(wparam, lparam, state_ptr),
         WM_CHAR => handle_char_msg(wparam, lparam, state_ptr),
+        WM_UNICHAR => handle_unichar_msg(wparam, lparam, state_ptr),
+        WM_DEADCHAR => handle_dead_char_msg(state_ptr),
         WM_IME_STARTCOMPOSITION => handle_ime_position(handle, state_ptr),
         WM_IME_COMPOSITION => handle_ime_composition(handle, lparam, state_ptr),
         WM_SETCURSOR => handle_set_cursor(lparam, state_ptr),
-        WM_SETTINGCHANGE => handle_system_settings_changed(state_ptr),
+        WM_SETTINGCHANGE => handle_system_settings_changed(handle, lparam, state_ptr),
+        WM_SYSCOMMAND => handle_system_command_msg(handle, wparam, lparam, state_ptr),
+        WM_COMMAND => handle_command_msg(wparam, state_ptr),
         CURSOR_STYLE_CHANGED => handle_cursor_changed(lparam, state_ptr),
+        TOUCH_KEYBOARD_CHANGED => handle_touch_keyboard_changed(handle, wparam, state_ptr),
+        START_EXTERNAL_DRAG => handle_start_external_drag(state_ptr),
+        _ if msg == taskbar_button_created_msg() => handle_taskbar_button_created(state_ptr),
         _ => None,
     };
     if let Some(n) = handled {
@@ -93,6 +163,26 @@ pub(crate) fn handle_msg(
     }
 }
 
+fn is_input_msg(msg: u32) -> bool {
+    matches!(
+        msg,
+        WM_KEYFIRST..=WM_KEYLAST
+            | WM_MOUSEFIRST..=WM_MOUSELAST
+            | WM_POINTERUPDATE..=WM_POINTERUP
+            | WM_IME_STARTCOMPOSITION..=WM_IME_KEYLAST
+            | WM_INPUT
+    )
+}
+
+// input messages are posted, so their time is when they were queued
+fn record_input_latency(state_ptr: &WindowsWindowStatePtr) {
+    let queued_at = unsafe { GetMessageTime() } as u32;
+    let latency = unsafe { GetTickCount() }.wrapping_sub(queued_at);
+    if let Ok(mut lock) = state_ptr.state.try_borrow_mut() {
+        lock.last_input_latency = Some(Duration::from_millis(latency as u64));
+    }
+}
+
 fn handle_move_msg(
     handle: HWND,
     lparam: LPARAM,
@@ -134,18 +224,21 @@ fn handle_move_msg(
 fn handle_size_msg(lparam: LPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
     let width = lparam.loword().max(1) as i32;
     let height = lparam.hiword().max(1) as i32;
+    notify_resize(size(DevicePixels(width), DevicePixels(height)), &state_ptr);
+    Some(0)
+}
+
+fn notify_resize(new_size: Size<DevicePixels>, state_ptr: &WindowsWindowStatePtr) {
     let mut lock = state_ptr.state.borrow_mut();
-    let new_size = size(DevicePixels(width), DevicePixels(height));
     let scale_factor = lock.scale_factor;
     lock.renderer.update_drawable_size(new_size);
-    let new_size = new_size.to_pixels(scale_factor);
-    lock.logical_size = new_size;
+    lock.logical_size = new_size.to_pixels(scale_factor);
+    let new_size = lock.content_size();
     if let Some(mut callback) = lock.callbacks.resize.take() {
         drop(lock);
         callback(new_size, scale_factor);
         state_ptr.state.borrow_mut().callbacks.resize = Some(callback);
     }
-    Some(0)
 }
 
 fn handle_size_move_loop(handle: HWND) -> Option<isize> {
@@ -175,6 +268,8 @@ fn handle_timer_msg(
 ) -> Option<isize> {
     if wparam.0 == SIZE_MOVE_LOOP_TIMER_ID {
         handle_paint_msg(handle, state_ptr)
+    } else if wparam.0 == WHEEL_SCROLL_TIMER_ID {
+        handle_wheel_scroll_end(handle, state_ptr)
     } else {
         None
     }
@@ -207,7 +302,13 @@ fn handle_close_msg(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
     }
 }
 
-fn handle_destroy_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+fn handle_destroy_msg(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    state_ptr.unlock_pointer();
+    // hands raw mouse input back to another window that wants it
+    state_ptr.state.borrow_mut().captured_button = None;
+    state_ptr.update_raw_mouse_input();
+    state_ptr.set_cursor_visible(true);
+    state_ptr.show_cursor_after_typing();
     let callback = {
         let mut lock = state_ptr.state.borrow_mut();
         lock.callbacks.close.take()
@@ -216,17 +317,58 @@ fn handle_destroy_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Opt
         callback();
     }
     unsafe {
-        PostMessageW(None, CLOSE_ONE_WINDOW, None, LPARAM(handle.0)).log_err();
+        // the handle is gone by the time the message is handled, the window is closed by its ID
+        let window_id = state_ptr.handle.window_id().as_u64();
+        PostMessageW(None, CLOSE_ONE_WINDOW, None, LPARAM(window_id as isize)).log_err();
+    }
+    Some(0)
+}
+
+pub(crate) fn set_session_end_callback(callback: QuitCallback) {
+    SESSION_END_CALLBACK
+        .with_borrow_mut(|session_end_callback| *session_end_callback = Some(callback));
+}
+
+// The session is never held up, but Windows shows the reason when saving takes long enough
+// for the user to notice.
+fn handle_query_end_session_msg(handle: HWND) -> Option<isize> {
+    unsafe { ShutdownBlockReasonCreate(handle, w!("Saving your work…")) }.log_err();
+    Some(1)
+}
+
+fn handle_end_session_msg(handle: HWND, wparam: WPARAM) -> Option<isize> {
+    // every top level window receives this message, the callback only runs for the first one
+    if wparam.0 != 0 {
+        let quit = SESSION_END_CALLBACK.with_borrow(|callback| callback.clone());
+        let callback = quit.and_then(|quit| quit.borrow_mut().take());
+        if let Some(mut callback) = callback {
+            log::info!("session is ending, quitting");
+            callback();
+        }
     }
+    unsafe { ShutdownBlockReasonDestroy(handle) }.log_err();
     Some(0)
 }
 
 fn handle_mouse_move_msg(
+    handle: HWND,
     lparam: LPARAM,
     wparam: WPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
+    state_ptr.show_cursor_after_typing();
     let mut lock = state_ptr.state.borrow_mut();
+    if !lock.mouse_tracked {
+        // `WM_MOUSELEAVE` is only sent once per request, so tracking is requested again on
+        // the first move after the cursor comes back
+        let mut track = TRACKMOUSEEVENT {
+            cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+            dwFlags: TME_LEAVE,
+            hwndTrack: handle,
+            dwHoverTime: 0,
+        };
+        lock.mouse_tracked = unsafe { TrackMouseEvent(&mut track) }.log_err().is_some();
+    }
     if let Some(mut callback) = lock.callbacks.input.take() {
         let scale_factor = lock.scale_factor;
         drop(lock);
@@ -260,6 +402,76 @@ fn handle_mouse_move_msg(
     Some(1)
 }
 
+fn handle_mouse_leave_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    let mut lock = state_ptr.state.borrow_mut();
+    lock.mouse_tracked = false;
+    let Some(mut callback) = lock.callbacks.input.take() else {
+        return Some(1);
+    };
+    let scale_factor = lock.scale_factor;
+    drop(lock);
+    let mut cursor_point = POINT::default();
+    unsafe {
+        GetCursorPos(&mut cursor_point).log_err();
+        ScreenToClient(handle, &mut cursor_point).ok().log_err();
+    }
+    let event = MouseExitEvent {
+        position: logical_point(cursor_point.x as f32, cursor_point.y as f32, scale_factor),
+        pressed_button: None,
+        modifiers: current_modifiers(),
+    };
+    callback(PlatformInput::MouseExited(event));
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+    Some(0)
+}
+
+fn handle_raw_input_msg(lparam: LPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    let lock = state_ptr.state.borrow();
+    if !lock.raw_mouse_input {
+        return None;
+    }
+    let scale_factor = lock.scale_factor;
+    drop(lock);
+    let mut raw_input = RAWINPUT::default();
+    let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+    let result = unsafe {
+        GetRawInputData(
+            HRAWINPUT(lparam.0),
+            RID_INPUT,
+            Some(&mut raw_input as *mut _ as _),
+            &mut size,
+            std::mem::size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    if result == u32::MAX || raw_input.header.dwType != RIM_TYPEMOUSE.0 {
+        return None;
+    }
+    let mouse = unsafe { raw_input.data.mouse };
+    // tablets and remote desktop sessions report absolute positions, which have no deltas
+    if mouse.usFlags.0 & MOUSE_MOVE_ABSOLUTE.0 != 0 || (mouse.lLastX == 0 && mouse.lLastY == 0) {
+        return None;
+    }
+    let mut func = state_ptr.state.borrow_mut().callbacks.input.take()?;
+    let pressed_button = if is_virtual_key_pressed(VK_LBUTTON) {
+        Some(MouseButton::Left)
+    } else if is_virtual_key_pressed(VK_RBUTTON) {
+        Some(MouseButton::Right)
+    } else if is_virtual_key_pressed(VK_MBUTTON) {
+        Some(MouseButton::Middle)
+    } else {
+        None
+    };
+    let event = MouseDeltaEvent {
+        delta: logical_point(mouse.lLastX as f32, mouse.lLastY as f32, scale_factor),
+        pressed_button,
+        modifiers: current_modifiers(),
+    };
+    func(PlatformInput::MouseDelta(event));
+    state_ptr.state.borrow_mut().callbacks.input = Some(func);
+    // `DefWindowProcW` has to clean up after the input
+    None
+}
+
 fn handle_syskeydown_msg(
     wparam: WPARAM,
     lparam: LPARAM,
@@ -304,7 +516,27 @@ fn handle_keydown_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    let Some(keystroke_or_modifier) = parse_keydown_msg_keystroke(wparam) else {
+    if wparam.loword() == VK_ESCAPE.0 && state_ptr.state.borrow().pointer_locked {
+        state_ptr.unlock_pointer();
+        return Some(0);
+    }
+    if is_alt_gr_control(wparam, lparam) {
+        ALT_GR_PRESSED.set(true);
+        return Some(1);
+    }
+    let vk_code = wparam.loword();
+    if !is_modifier(VIRTUAL_KEY(vk_code)) {
+        // the characters composed with a dead key are sent after the next key is pressed,
+        // which may take shift, but not another modifier, to type
+        let mut lock = state_ptr.state.borrow_mut();
+        lock.composing_dead_key = std::mem::take(&mut lock.pending_dead_key);
+    }
+    // the accent is reported along with the character it ends up on, and binding it
+    // to an action would keep it from being typed at all
+    if is_dead_key(vk_code) {
+        return Some(1);
+    }
+    let Some(keystroke_or_modifier) = parse_keydown_msg_keystroke(wparam, lparam) else {
         return Some(1);
     };
     let mut lock = state_ptr.state.borrow_mut();
@@ -313,28 +545,78 @@ fn handle_keydown_msg(
     };
     drop(lock);
 
-    let event = match keystroke_or_modifier {
-        KeystrokeOrModifier::Keystroke(keystroke) => PlatformInput::KeyDown(KeyDownEvent {
-            keystroke,
-            is_held: lparam.0 & (0x1 << 30) > 0,
-        }),
+    let default_prevented = match keystroke_or_modifier {
+        KeystrokeOrModifier::Keystroke(keystroke) => {
+            let mut default_prevented = false;
+            for repeat in 0..key_repeat_count(lparam) {
+                let is_held = repeat > 0 || lparam.0 & (0x1 << 30) > 0;
+                let event = KeyDownEvent {
+                    keystroke: keystroke.clone(),
+                    is_held,
+                };
+                let result = func(PlatformInput::KeyDown(event));
+                default_prevented = result.default_prevented;
+                if keystroke.key.starts_with("numpad")
+                    && (result.default_prevented || !result.propagate)
+                {
+                    // the character the key types was already queued by `TranslateMessage`
+                    let mut msg = MSG::default();
+                    unsafe { PeekMessageW(&mut msg, None, WM_CHAR, WM_CHAR, PM_REMOVE) };
+                }
+            }
+            default_prevented
+        }
         KeystrokeOrModifier::Modifier(modifiers) => {
-            PlatformInput::ModifiersChanged(ModifiersChangedEvent { modifiers })
+            let event = PlatformInput::ModifiersChanged(ModifiersChangedEvent {
+                modifiers,
+                capslock: current_capslock(),
+            });
+            func(event).default_prevented
         }
     };
 
-    let result = if func(event).default_prevented {
-        Some(0)
-    } else {
-        Some(1)
-    };
+    let result = if default_prevented { Some(0) } else { Some(1) };
     state_ptr.state.borrow_mut().callbacks.input = Some(func);
 
     result
 }
 
-fn handle_keyup_msg(wparam: WPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
-    let Some(keystroke_or_modifier) = parse_keydown_msg_keystroke(wparam) else {
+/// When the app falls behind, the system folds the auto-repeats of a held key into a single
+/// message and counts them in its low word, so each of them is reported on its own.
+fn key_repeat_count(lparam: LPARAM) -> usize {
+    ((lparam.0 & 0xFFFF) as usize).max(1)
+}
+
+/// AltGr sends a left control key down with the same timestamp as the right alt key down
+/// that comes right after it.
+fn is_alt_gr_control(wparam: WPARAM, lparam: LPARAM) -> bool {
+    if VIRTUAL_KEY(wparam.loword()) != VK_CONTROL || is_extended_key(lparam) {
+        return false;
+    }
+    let mut next = MSG::default();
+    let has_next =
+        unsafe { PeekMessageW(&mut next, None, WM_KEYDOWN, WM_SYSKEYDOWN, PM_NOREMOVE) }.as_bool();
+    has_next
+        && matches!(next.message, WM_KEYDOWN | WM_SYSKEYDOWN)
+        && VIRTUAL_KEY(next.wParam.loword()) == VK_MENU
+        && is_extended_key(next.lParam)
+        && next.time == unsafe { GetMessageTime() } as u32
+}
+
+#[inline]
+fn is_extended_key(lparam: LPARAM) -> bool {
+    lparam.0 & (0x1 << 24) != 0
+}
+
+fn handle_keyup_msg(
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    if VIRTUAL_KEY(wparam.loword()) == VK_MENU && is_extended_key(lparam) {
+        ALT_GR_PRESSED.set(false);
+    }
+    let Some(keystroke_or_modifier) = parse_keydown_msg_keystroke(wparam, lparam) else {
         return Some(1);
     };
     let mut lock = state_ptr.state.borrow_mut();
@@ -346,7 +628,10 @@ fn handle_keyup_msg(wparam: WPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Opt
     let event = match keystroke_or_modifier {
         KeystrokeOrModifier::Keystroke(keystroke) => PlatformInput::KeyUp(KeyUpEvent { keystroke }),
         KeystrokeOrModifier::Modifier(modifiers) => {
-            PlatformInput::ModifiersChanged(ModifiersChangedEvent { modifiers })
+            PlatformInput::ModifiersChanged(ModifiersChangedEvent {
+                modifiers,
+                capslock: current_capslock(),
+            })
         }
     };
 
@@ -365,19 +650,109 @@ fn handle_char_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    let Some(keystroke) = parse_char_msg_keystroke(wparam) else {
+    let code_unit = wparam.0 as u16;
+    let decoded = decode_utf16_char(
+        &mut state_ptr.state.borrow_mut().pending_high_surrogate,
+        code_unit,
+    );
+    match decoded {
+        Some(character) => handle_char(character, lparam, &state_ptr),
+        // the low surrogate follows in another message
+        None if is_high_surrogate(code_unit) => Some(0),
+        None => Some(1),
+    }
+}
+
+/// Tools like AutoHotkey send characters outside of the basic multilingual plane as
+/// `WM_UNICHAR`, once they've been told it's understood by the window.
+fn handle_unichar_msg(
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    if wparam.0 as u32 == UNICODE_NOCHAR {
+        return Some(1);
+    }
+    if let Some(character) = char::from_u32(wparam.0 as u32) {
+        handle_char(character, lparam, &state_ptr);
+    }
+    Some(0)
+}
+
+/// Characters outside of the basic multilingual plane, like emoji, arrive as two `WM_CHAR`
+/// messages carrying the halves of a surrogate pair, so the first half is held on to until
+/// the second one arrives.
+fn decode_utf16_char(pending_high_surrogate: &mut Option<u16>, code_unit: u16) -> Option<char> {
+    if is_high_surrogate(code_unit) {
+        *pending_high_surrogate = Some(code_unit);
+        return None;
+    }
+    let high_surrogate = pending_high_surrogate.take();
+    if (0xDC00..=0xDFFF).contains(&code_unit) {
+        char::decode_utf16([high_surrogate?, code_unit])
+            .next()?
+            .ok()
+    } else {
+        char::from_u32(code_unit.into())
+    }
+}
+
+fn is_high_surrogate(code_unit: u16) -> bool {
+    (0xD800..=0xDBFF).contains(&code_unit)
+}
+
+fn handle_dead_char_msg(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    state_ptr.state.borrow_mut().pending_dead_key = true;
+    Some(0)
+}
+
+fn handle_char(
+    character: char,
+    lparam: LPARAM,
+    state_ptr: &Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    if !character.is_control() {
+        state_ptr.hide_cursor_while_typing();
+        if state_ptr.state.borrow().composing_dead_key {
+            return insert_composed_char(character, state_ptr);
+        }
+    }
+    let Some(keystroke) = parse_char_msg_keystroke(character) else {
         return Some(1);
     };
+    let mut result = Some(1);
+    for repeat in 0..key_repeat_count(lparam) {
+        let event = KeyDownEvent {
+            keystroke: keystroke.clone(),
+            is_held: repeat > 0 || lparam.0 & (0x1 << 30) > 0,
+        };
+        result = dispatch_char_event(event, state_ptr);
+    }
+    result
+}
+
+/// Characters composed with a dead key, like `ê` typed as `^` followed by `e`, are typed as
+/// they are rather than matched against bindings. When the accent can't go on the character,
+/// both of them arrive here one after the other.
+fn insert_composed_char(character: char, state_ptr: &Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    let Some(mut input_handler) = state_ptr.state.borrow_mut().input_handler.take() else {
+        return Some(1);
+    };
+    input_handler.replace_text_in_range(None, &character.to_string());
+    state_ptr.state.borrow_mut().input_handler = Some(input_handler);
+    Some(0)
+}
+
+fn dispatch_char_event(
+    event: KeyDownEvent,
+    state_ptr: &Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
     let mut lock = state_ptr.state.borrow_mut();
     let Some(mut func) = lock.callbacks.input.take() else {
         return Some(1);
     };
     drop(lock);
-    let ime_key = keystroke.ime_key.clone();
-    let event = KeyDownEvent {
-        keystroke,
-        is_held: lparam.0 & (0x1 << 30) > 0,
-    };
+    let ime_key = event.keystroke.ime_key.clone();
 
     let dispatch_event_result = func(PlatformInput::KeyDown(event));
     let mut lock = state_ptr.state.borrow_mut();
@@ -405,6 +780,8 @@ fn handle_mouse_down_msg(
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
     unsafe { SetCapture(handle) };
+    state_ptr.state.borrow_mut().captured_button = Some(button);
+    state_ptr.update_raw_mouse_input();
     let mut lock = state_ptr.state.borrow_mut();
     if let Some(mut callback) = lock.callbacks.input.take() {
         let x = lparam.signed_loword() as f32;
@@ -434,12 +811,24 @@ fn handle_mouse_down_msg(
     }
 }
 
+fn toggle_maximized(handle: HWND, state_ptr: &WindowsWindowStatePtr) {
+    let command = if state_ptr.state.borrow().is_maximized() {
+        SW_NORMAL
+    } else {
+        SW_MAXIMIZE
+    };
+    unsafe { ShowWindowAsync(handle, command).ok().log_err() };
+}
+
 fn handle_mouse_up_msg(
     _handle: HWND,
     button: MouseButton,
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
+    // cleared first, as releasing the capture sends `WM_CAPTURECHANGED` right away
+    state_ptr.state.borrow_mut().captured_button = None;
+    state_ptr.update_raw_mouse_input();
     unsafe { ReleaseCapture().log_err() };
     let mut lock = state_ptr.state.borrow_mut();
     if let Some(mut callback) = lock.callbacks.input.take() {
@@ -468,6 +857,45 @@ fn handle_mouse_up_msg(
     }
 }
 
+/// When the capture is taken away mid-drag, e.g. by a system popup, the button release is
+/// sent elsewhere, so it's reported here instead to keep the drag from getting stuck.
+fn handle_capture_changed_msg(
+    handle: HWND,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    if lparam.0 == handle.0 {
+        return Some(0);
+    }
+    let mut lock = state_ptr.state.borrow_mut();
+    let Some(button) = lock.captured_button.take() else {
+        return Some(0);
+    };
+    drop(lock);
+    state_ptr.update_raw_mouse_input();
+    let mut lock = state_ptr.state.borrow_mut();
+    let Some(mut callback) = lock.callbacks.input.take() else {
+        return Some(0);
+    };
+    let click_count = lock.click_state.current_count;
+    let scale_factor = lock.scale_factor;
+    drop(lock);
+    let mut cursor_point = POINT::default();
+    unsafe {
+        GetCursorPos(&mut cursor_point).log_err();
+        ScreenToClient(handle, &mut cursor_point).ok().log_err();
+    }
+    let event = MouseUpEvent {
+        button,
+        position: logical_point(cursor_point.x as f32, cursor_point.y as f32, scale_factor),
+        modifiers: current_modifiers(),
+        click_count,
+    };
+    callback(PlatformInput::MouseUp(event));
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+    Some(0)
+}
+
 fn handle_xbutton_msg(
     handle: HWND,
     wparam: WPARAM,
@@ -483,83 +911,434 @@ fn handle_xbutton_msg(
     handler(handle, MouseButton::Navigate(nav_dir), lparam, state_ptr)
 }
 
+// Mouse pointers are left to `DefWindowProcW`, which turns them into regular mouse messages,
+// and so are pens unless their `PenEvent` is prevented.
+fn handle_pointer_down_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let (pointer_id, pointer_type) = pointer_id_and_type(wparam)?;
+    if pointer_type == PT_PEN {
+        return handle_pen_msg(handle, pointer_id, PenPhase::Down, lparam, state_ptr);
+    }
+    if pointer_type != PT_TOUCH {
+        return None;
+    }
+    // touching the screen stops content that is still scrolling from a touchpad's inertia
+    handle_wheel_scroll_end(handle, state_ptr.clone());
+    let mut lock = state_ptr.state.borrow_mut();
+    let position = pointer_position(handle, lparam, lock.scale_factor);
+    let actions = lock.touch_state.down(pointer_id, position);
+    drop(lock);
+    for action in actions {
+        dispatch_touch_action(action, &state_ptr);
+    }
+    Some(0)
+}
+
+fn handle_pointer_update_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let (pointer_id, pointer_type) = pointer_id_and_type(wparam)?;
+    if pointer_type == PT_PEN {
+        return handle_pen_msg(handle, pointer_id, PenPhase::Move, lparam, state_ptr);
+    }
+    if pointer_type != PT_TOUCH {
+        return None;
+    }
+    let mut lock = state_ptr.state.borrow_mut();
+    let position = pointer_position(handle, lparam, lock.scale_factor);
+    let actions = lock.touch_state.update(pointer_id, position);
+    drop(lock);
+    for action in actions {
+        dispatch_touch_action(action, &state_ptr);
+    }
+    Some(0)
+}
+
+fn handle_pointer_up_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let (pointer_id, pointer_type) = pointer_id_and_type(wparam)?;
+    if pointer_type == PT_PEN {
+        return handle_pen_msg(handle, pointer_id, PenPhase::Up, lparam, state_ptr);
+    }
+    if pointer_type != PT_TOUCH {
+        return None;
+    }
+    let mut lock = state_ptr.state.borrow_mut();
+    let actions = if wparam.hiword() & POINTER_MESSAGE_FLAG_CANCELED != 0 {
+        lock.touch_state.cancel(pointer_id)
+    } else {
+        lock.touch_state.up(pointer_id)
+    };
+    drop(lock);
+    for action in actions {
+        dispatch_touch_action(action, &state_ptr);
+    }
+    Some(0)
+}
+
+// another window, or the system, took over the pointer, which gets no further messages
+fn handle_pointer_capture_changed_msg(
+    wparam: WPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let pointer_id = wparam.loword() as u32;
+    let actions = state_ptr.state.borrow_mut().touch_state.cancel(pointer_id);
+    for action in actions {
+        dispatch_touch_action(action, &state_ptr);
+    }
+    Some(0)
+}
+
+fn handle_pen_msg(
+    handle: HWND,
+    pointer_id: u32,
+    phase: PenPhase,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let mut info = POINTER_PEN_INFO::default();
+    unsafe { GetPointerPenInfo(pointer_id, &mut info) }.log_err()?;
+    let mut lock = state_ptr.state.borrow_mut();
+    let position = pointer_position(handle, lparam, lock.scale_factor);
+    let mut callback = lock.callbacks.input.take()?;
+    drop(lock);
+
+    let has_value = |mask| info.penMask & mask == mask;
+    let has_flag = |flag| info.penFlags & flag == flag;
+    let in_contact =
+        info.pointerInfo.pointerFlags & POINTER_FLAG_INCONTACT == POINTER_FLAG_INCONTACT;
+    let pressure = if has_value(PEN_MASK_PRESSURE) {
+        // pressure is reported from 0 to 1024
+        info.pressure as f32 / 1024.
+    } else if in_contact {
+        1.
+    } else {
+        0.
+    };
+    let tilt = point(
+        if has_value(PEN_MASK_TILT_X) {
+            info.tiltX as f32
+        } else {
+            0.
+        },
+        if has_value(PEN_MASK_TILT_Y) {
+            info.tiltY as f32
+        } else {
+            0.
+        },
+    );
+    let event = PenEvent {
+        phase,
+        position,
+        pressure,
+        tilt,
+        barrel_button: has_flag(PEN_FLAG_BARREL),
+        eraser: has_flag(PEN_FLAG_INVERTED) || has_flag(PEN_FLAG_ERASER),
+        modifiers: current_modifiers(),
+    };
+    let result = callback(PlatformInput::Pen(event));
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+    result.default_prevented.then_some(0)
+}
+
+fn pointer_id_and_type(wparam: WPARAM) -> Option<(u32, POINTER_INPUT_TYPE)> {
+    let pointer_id = wparam.loword() as u32;
+    let mut pointer_type = POINTER_INPUT_TYPE::default();
+    unsafe { GetPointerType(pointer_id, &mut pointer_type) }.log_err()?;
+    Some((pointer_id, pointer_type))
+}
+
+fn pointer_position(handle: HWND, lparam: LPARAM, scale_factor: f32) -> Point<Pixels> {
+    // pointer messages report screen coordinates
+    let mut position = POINT {
+        x: lparam.signed_loword() as i32,
+        y: lparam.signed_hiword() as i32,
+    };
+    unsafe { ScreenToClient(handle, &mut position) }
+        .ok()
+        .log_err();
+    logical_point(position.x as f32, position.y as f32, scale_factor)
+}
+
+fn dispatch_touch_action(action: TouchAction, state_ptr: &Rc<WindowsWindowStatePtr>) {
+    let mut lock = state_ptr.state.borrow_mut();
+    let Some(mut callback) = lock.callbacks.input.take() else {
+        return;
+    };
+    let modifiers = current_modifiers();
+    let events = match action {
+        TouchAction::Tap(position) => {
+            let scale_factor = lock.scale_factor;
+            let physical_point = point(
+                DevicePixels((position.x.0 * scale_factor) as i32),
+                DevicePixels((position.y.0 * scale_factor) as i32),
+            );
+            let click_count = lock.click_state.update(MouseButton::Left, physical_point);
+            vec![
+                PlatformInput::MouseMove(MouseMoveEvent {
+                    position,
+                    pressed_button: None,
+                    modifiers,
+                }),
+                PlatformInput::MouseDown(MouseDownEvent {
+                    button: MouseButton::Left,
+                    position,
+                    modifiers,
+                    click_count,
+                    first_mouse: false,
+                }),
+                PlatformInput::MouseUp(MouseUpEvent {
+                    button: MouseButton::Left,
+                    position,
+                    modifiers,
+                    click_count,
+                }),
+            ]
+        }
+        TouchAction::Scroll {
+            position,
+            delta,
+            phase,
+        } => {
+            let scroll = |delta, touch_phase| {
+                PlatformInput::ScrollWheel(ScrollWheelEvent {
+                    position,
+                    delta: ScrollDelta::Pixels(delta),
+                    modifiers,
+                    touch_phase,
+                })
+            };
+            match phase {
+                // the event starting a gesture doesn't scroll, as with wheels
+                TouchPhase::Started if delta != Point::default() => vec![
+                    scroll(Point::default(), TouchPhase::Started),
+                    scroll(delta, TouchPhase::Moved),
+                ],
+                phase => vec![scroll(delta, phase)],
+            }
+        }
+        TouchAction::Pinch {
+            position,
+            delta,
+            phase,
+        } => vec![PlatformInput::Pinch(PinchEvent {
+            position,
+            delta,
+            modifiers,
+            phase,
+        })],
+    };
+    drop(lock);
+    for event in events {
+        callback(event);
+    }
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+}
+
 fn handle_mouse_wheel_msg(
     handle: HWND,
     wparam: WPARAM,
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
+    let wheel_scroll_lines = state_ptr
+        .state
+        .borrow()
+        .system_settings
+        .mouse_wheel_settings
+        .wheel_scroll_lines;
+    let wheel_delta = wparam.signed_hiword();
+    let wheel_distance = (wheel_delta as f32 / WHEEL_DELTA as f32) * wheel_scroll_lines as f32;
+    let control = wparam.loword() as u32 & MK_CONTROL.0 != 0;
+    handle_wheel(
+        handle,
+        wheel_delta,
+        control,
+        point(0.0, wheel_distance),
+        lparam,
+        state_ptr,
+    )
+}
+
+fn handle_mouse_horizontal_wheel_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let wheel_scroll_chars = state_ptr
+        .state
+        .borrow()
+        .system_settings
+        .mouse_wheel_settings
+        .wheel_scroll_chars;
+    let wheel_delta = wparam.signed_hiword();
+    let wheel_distance = (-wheel_delta as f32 / WHEEL_DELTA as f32) * wheel_scroll_chars as f32;
+    handle_wheel(
+        handle,
+        wheel_delta,
+        false,
+        point(wheel_distance, 0.0),
+        lparam,
+        state_ptr,
+    )
+}
+
+fn handle_wheel(
+    handle: HWND,
+    wheel_delta: i16,
+    control: bool,
+    lines: Point<f32>,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let mut lock = state_ptr.state.borrow_mut();
+    let Some(mut callback) = lock.callbacks.input.take() else {
+        return Some(1);
+    };
+    let scale_factor = lock.scale_factor;
+    let mut cursor_point = POINT {
+        x: lparam.signed_loword().into(),
+        y: lparam.signed_hiword().into(),
+    };
+    unsafe { ScreenToClient(handle, &mut cursor_point).ok().log_err() };
+    let position = logical_point(cursor_point.x as f32, cursor_point.y as f32, scale_factor);
+    let (gesture, started) = lock.wheel_scroll.scroll(wheel_delta, control, position);
+    drop(lock);
+    // restarting the timer pushes back the end of the gesture
+    let ret = unsafe {
+        SetTimer(
+            handle,
+            WHEEL_SCROLL_TIMER_ID,
+            WHEEL_SCROLL_TIMEOUT.as_millis() as u32,
+            None,
+        )
+    };
+    if ret == 0 {
+        log::error!(
+            "unable to create timer: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    if gesture.pinch {
+        let delta = wheel_delta as f32 / WHEEL_DELTA as f32 * PINCH_MAGNIFICATION_PER_WHEEL_DELTA;
+        return dispatch_wheel_pinch(position, delta, started, callback, &state_ptr);
+    }
+    let modifiers = current_modifiers();
+    if started {
+        // like on macOS, the event starting a gesture doesn't scroll, so handlers can reset
+        // their state before the first delta arrives
+        callback(PlatformInput::ScrollWheel(ScrollWheelEvent {
+            position,
+            delta: no_scroll_delta(gesture.precise),
+            modifiers,
+            touch_phase: TouchPhase::Started,
+        }));
+    }
+    let delta = if gesture.precise {
+        ScrollDelta::Pixels(point(
+            px(lines.x * PRECISION_SCROLL_PIXELS_PER_LINE),
+            px(lines.y * PRECISION_SCROLL_PIXELS_PER_LINE),
+        ))
+    } else {
+        ScrollDelta::Lines(lines)
+    };
+    let event = ScrollWheelEvent {
+        position,
+        delta,
+        modifiers,
+        touch_phase: TouchPhase::Moved,
+    };
+    let result = if callback(PlatformInput::ScrollWheel(event)).default_prevented {
+        Some(0)
+    } else {
+        Some(1)
+    };
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+
+    result
+}
+
+fn handle_wheel_scroll_end(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    unsafe { KillTimer(handle, WHEEL_SCROLL_TIMER_ID).log_err() };
     let mut lock = state_ptr.state.borrow_mut();
-    if let Some(mut callback) = lock.callbacks.input.take() {
-        let scale_factor = lock.scale_factor;
-        let wheel_scroll_lines = lock.system_settings.mouse_wheel_settings.wheel_scroll_lines;
-        drop(lock);
-        let wheel_distance =
-            (wparam.signed_hiword() as f32 / WHEEL_DELTA as f32) * wheel_scroll_lines as f32;
-        let mut cursor_point = POINT {
-            x: lparam.signed_loword().into(),
-            y: lparam.signed_hiword().into(),
-        };
-        unsafe { ScreenToClient(handle, &mut cursor_point).ok().log_err() };
-        let event = ScrollWheelEvent {
-            position: logical_point(cursor_point.x as f32, cursor_point.y as f32, scale_factor),
-            delta: ScrollDelta::Lines(Point {
-                x: 0.0,
-                y: wheel_distance,
-            }),
+    let gesture = lock.wheel_scroll.end()?;
+    let mut callback = lock.callbacks.input.take()?;
+    drop(lock);
+    let event = if gesture.pinch {
+        PlatformInput::Pinch(PinchEvent {
+            position: gesture.position,
+            delta: 0.,
+            modifiers: pinch_modifiers(),
+            phase: TouchPhase::Ended,
+        })
+    } else {
+        PlatformInput::ScrollWheel(ScrollWheelEvent {
+            position: gesture.position,
+            delta: no_scroll_delta(gesture.precise),
             modifiers: current_modifiers(),
-            touch_phase: TouchPhase::Moved,
-        };
-        let result = if callback(PlatformInput::ScrollWheel(event)).default_prevented {
-            Some(0)
-        } else {
-            Some(1)
-        };
-        state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+            touch_phase: TouchPhase::Ended,
+        })
+    };
+    callback(event);
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+    Some(0)
+}
 
-        result
+fn dispatch_wheel_pinch(
+    position: Point<Pixels>,
+    delta: f32,
+    started: bool,
+    mut callback: Box<dyn FnMut(PlatformInput) -> DispatchEventResult>,
+    state_ptr: &Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let modifiers = pinch_modifiers();
+    if started {
+        callback(PlatformInput::Pinch(PinchEvent {
+            position,
+            delta: 0.,
+            modifiers,
+            phase: TouchPhase::Started,
+        }));
+    }
+    let result = callback(PlatformInput::Pinch(PinchEvent {
+        position,
+        delta,
+        modifiers,
+        phase: TouchPhase::Moved,
+    }));
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+    if result.default_prevented {
+        Some(0)
     } else {
         Some(1)
     }
 }
 
-fn handle_mouse_horizontal_wheel_msg(
-    handle: HWND,
-    wparam: WPARAM,
-    lparam: LPARAM,
-    state_ptr: Rc<WindowsWindowStatePtr>,
-) -> Option<isize> {
-    let mut lock = state_ptr.state.borrow_mut();
-    if let Some(mut callback) = lock.callbacks.input.take() {
-        let scale_factor = lock.scale_factor;
-        let wheel_scroll_chars = lock.system_settings.mouse_wheel_settings.wheel_scroll_chars;
-        drop(lock);
-        let wheel_distance =
-            (-wparam.signed_hiword() as f32 / WHEEL_DELTA as f32) * wheel_scroll_chars as f32;
-        let mut cursor_point = POINT {
-            x: lparam.signed_loword().into(),
-            y: lparam.signed_hiword().into(),
-        };
-        unsafe { ScreenToClient(handle, &mut cursor_point).ok().log_err() };
-        let event = ScrollWheelEvent {
-            position: logical_point(cursor_point.x as f32, cursor_point.y as f32, scale_factor),
-            delta: ScrollDelta::Lines(Point {
-                x: wheel_distance,
-                y: 0.0,
-            }),
-            modifiers: current_modifiers(),
-            touch_phase: TouchPhase::Moved,
-        };
-        let result = if callback(PlatformInput::ScrollWheel(event)).default_prevented {
-            Some(0)
-        } else {
-            Some(1)
-        };
-        state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+// Windows holds control down for the duration of a touchpad pinch, which the user didn't press.
+fn pinch_modifiers() -> Modifiers {
+    Modifiers {
+        control: false,
+        ..current_modifiers()
+    }
+}
 
-        result
+fn no_scroll_delta(precise: bool) -> ScrollDelta {
+    if precise {
+        ScrollDelta::Pixels(Point::default())
     } else {
-        Some(1)
+        ScrollDelta::Lines(Point::default())
     }
 }
 
@@ -600,6 +1379,7 @@ fn handle_ime_composition(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
+    state_ptr.hide_cursor_while_typing();
     let mut ime_input = None;
     if lparam.0 as u32 & GCS_COMPSTR.0 > 0 {
         let (comp_string, string_len) = parse_ime_compostion_string(handle)?;
@@ -641,23 +1421,24 @@ fn handle_calc_client_size(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    if !state_ptr.hide_title_bar || state_ptr.state.borrow().is_fullscreen() || wparam.0 == 0 {
+    if !state_ptr.hide_title_bar.get() || state_ptr.state.borrow().is_fullscreen() || wparam.0 == 0
+    {
         return None;
     }
 
-    let dpi = unsafe { GetDpiForWindow(handle) };
-
-    let frame_x = unsafe { GetSystemMetricsForDpi(SM_CXFRAME, dpi) };
-    let frame_y = unsafe { GetSystemMetricsForDpi(SM_CYFRAME, dpi) };
-    let padding = unsafe { GetSystemMetricsForDpi(SM_CXPADDEDBORDER, dpi) };
+    let insets = FrameInsets::for_dpi(unsafe { GetDpiForWindow(handle) });
 
     // wparam is TRUE so lparam points to an NCCALCSIZE_PARAMS structure
-    let mut params = lparam.0 as *mut NCCALCSIZE_PARAMS;
-    let mut requested_client_rect = unsafe { &mut ((*params).rgrc) };
-
-    requested_client_rect[0].right -= frame_x + padding;
-    requested_client_rect[0].left += frame_x + padding;
-    requested_client_rect[0].bottom -= frame_y + padding;
+    let params = lparam.0 as *mut NCCALCSIZE_PARAMS;
+    let requested_client_rect = unsafe { &mut ((*params).rgrc) };
+    requested_client_rect[0] = insets.client_rect(requested_client_rect[0]);
+
+    if state_ptr.state.borrow().is_maximized() {
+        let monitor = unsafe { MonitorFromWindow(handle, MONITOR_DEFAULTTONEAREST) };
+        if let Some(taskbar) = taskbar_position(monitor) {
+            taskbar.inset_for_auto_hide(&mut requested_client_rect[0]);
+        }
+    }
 
     Some(0)
 }
@@ -668,7 +1449,12 @@ fn handle_activate_msg(
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
     let activated = wparam.loword() > 0;
-    if state_ptr.hide_title_bar {
+    if !activated {
+        state_ptr.unlock_pointer();
+        // the focus may have been in a child window, which doesn't send us WM_KILLFOCUS
+        reset_input_state(handle, &state_ptr);
+    }
+    if state_ptr.hide_title_bar.get() {
         if let Some(titlebar_rect) = state_ptr.state.borrow().get_titlebar_rect().log_err() {
             unsafe {
                 InvalidateRect(handle, Some(&titlebar_rect), FALSE)
@@ -677,6 +1463,26 @@ fn handle_activate_msg(
             };
         }
     }
+    None
+}
+
+fn handle_set_focus_msg(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    notify_active_status_change(&state_ptr, true);
+    None
+}
+
+fn handle_kill_focus_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    reset_input_state(handle, &state_ptr);
+    // the cursor would stay hidden over other windows until it's moved over this one again
+    state_ptr.show_cursor_after_typing();
+    notify_active_status_change(&state_ptr, false);
+    None
+}
+
+/// The window is reported as active while it has the keyboard focus rather than while it's
+/// the active window, which it stays while the focus is in one of its child windows, so the
+/// caret stops blinking wherever keys stop arriving.
+fn notify_active_status_change(state_ptr: &Rc<WindowsWindowStatePtr>, active: bool) {
     let this = state_ptr.clone();
     state_ptr
         .executor
@@ -684,12 +1490,140 @@ fn handle_activate_msg(
             let mut lock = this.state.borrow_mut();
             if let Some(mut cb) = lock.callbacks.active_status_change.take() {
                 drop(lock);
-                cb(activated);
+                cb(active);
                 this.state.borrow_mut().callbacks.active_status_change = Some(cb);
             }
         })
         .detach();
+}
+
+/// Keys and mouse buttons released while another window has the focus are never reported to
+/// this one, so the modifiers are reported again from the state of the keyboard itself and
+/// the mouse capture is given up.
+fn reset_input_state(handle: HWND, state_ptr: &Rc<WindowsWindowStatePtr>) {
+    if unsafe { GetCapture() } == handle {
+        unsafe { ReleaseCapture().log_err() };
+    }
+    ALT_GR_PRESSED.set(ALT_GR_PRESSED.get() && unsafe { GetAsyncKeyState(VK_RMENU.0 as i32) } < 0);
+    let mut lock = state_ptr.state.borrow_mut();
+    let Some(mut callback) = lock.callbacks.input.take() else {
+        return;
+    };
+    drop(lock);
+    callback(PlatformInput::ModifiersChanged(ModifiersChangedEvent {
+        modifiers: async_modifiers(),
+        capslock: current_capslock(),
+    }));
+    state_ptr.state.borrow_mut().callbacks.input = Some(callback);
+}
+
+fn handle_dwm_colorization_color_changed_msg() -> Option<isize> {
+    // every top level window receives this message, the platform only reports changes
+    unsafe {
+        PostMessageW(None, ACCENT_COLOR_CHANGED, None, None).log_err();
+    }
+    None
+}
+
+fn handle_input_language_changed_msg() -> Option<isize> {
+    // the layout is shared by the windows of the thread, so the platform reports it once
+    unsafe {
+        PostMessageW(None, KEYBOARD_LAYOUT_CHANGED, None, None).log_err();
+    }
+    // passed on to the child windows
+    None
+}
+
+// The drag is started from a posted message rather than from the app's mouse handlers, since
+// it runs a modal loop that dispatches window messages until the drop is done.
+fn handle_start_external_drag(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    let (data, on_complete) = state_ptr.state.borrow_mut().pending_external_drag.take()?;
+    on_complete(drag_out_of_window(data));
+    Some(0)
+}
+
+fn handle_taskbar_button_created(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    state_ptr.state.borrow_mut().taskbar_button.created();
+    Some(0)
+}
+
+fn handle_command_msg(wparam: WPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    if (wparam.0 >> 16) as u32 & 0xFFFF != THBN_CLICKED {
+        return None;
+    }
+    let button_id = wparam.0 as u32 & 0xFFFF;
+    let mut lock = state_ptr.state.borrow_mut();
+    let action = lock.taskbar_button.thumbnail_button_action(button_id)?;
+    // thumbnail buttons dispatch their actions like the items of the window menu
+    let mut callback = lock.callbacks.window_menu_action.take()?;
+    drop(lock);
+    callback(action.as_ref());
+    state_ptr.state.borrow_mut().callbacks.window_menu_action = Some(callback);
+    Some(0)
+}
+
+// Keeps the caret visible by shrinking the content area when the touch keyboard covers it.
+fn handle_touch_keyboard_changed(
+    handle: HWND,
+    wparam: WPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let mut lock = state_ptr.state.borrow_mut();
+    let occluded_bounds = lock
+        .touch_keyboard
+        .occluded_bounds()
+        .filter(|_| wparam.0 != 0);
+    let mut inset = px(0.);
+    if let Some(occluded_bounds) = occluded_bounds {
+        let mut client_origin = POINT::default();
+        unsafe { ClientToScreen(handle, &mut client_origin) }
+            .ok()
+            .log_err();
+        // the occluded bounds are in device independent pixels, like our logical pixels
+        let keyboard_top =
+            occluded_bounds.origin.y - px(client_origin.y as f32 / lock.scale_factor);
+        if keyboard_top < lock.logical_size.height {
+            let mut input_handler = lock.input_handler.take();
+            drop(lock);
+            let caret_bottom = input_handler.as_mut().and_then(|input_handler| {
+                let caret_range = input_handler.selected_text_range()?;
+                let caret_bounds = input_handler.bounds_for_range(caret_range)?;
+                Some(caret_bounds.bottom())
+            });
+            lock = state_ptr.state.borrow_mut();
+            lock.input_handler = input_handler;
+            if caret_bottom.is_some_and(|caret_bottom| caret_bottom > keyboard_top) {
+                inset = lock.logical_size.height - keyboard_top.max(px(0.));
+            }
+        }
+    }
+    if lock.touch_keyboard_inset == inset {
+        return Some(0);
+    }
+    lock.touch_keyboard_inset = inset;
+    let content_size = lock.content_size();
+    let scale_factor = lock.scale_factor;
+    if let Some(mut callback) = lock.callbacks.resize.take() {
+        drop(lock);
+        callback(content_size, scale_factor);
+        state_ptr.state.borrow_mut().callbacks.resize = Some(callback);
+    }
+    Some(0)
+}
 
+fn handle_activate_app_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    // every top level window receives this message, the platform only reports changes
+    unsafe {
+        PostMessageW(None, APP_ACTIVE_CHANGED, wparam, None).log_err();
+    }
+    let level = state_ptr.state.borrow().level;
+    if level == WindowLevel::Floating {
+        set_topmost(handle, level, wparam.0 != 0);
+    }
     None
 }
 
@@ -700,7 +1634,7 @@ fn handle_create_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Opti
     let width = size_rect.right - size_rect.left;
     let height = size_rect.bottom - size_rect.top;
 
-    if state_ptr.hide_title_bar {
+    if state_ptr.hide_title_bar.get() {
         unsafe {
             SetWindowPos(
                 handle,
@@ -718,6 +1652,58 @@ fn handle_create_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Opti
     Some(0)
 }
 
+/// The constraints are set on the content of the window, so the frame around it, whether
+/// it's drawn by the system or the application, is added to them.
+fn handle_get_min_max_info_msg(
+    handle: HWND,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let lock = state_ptr.state.borrow();
+    if lock.min_size.is_none() && lock.max_size.is_none() {
+        return None;
+    }
+    let min_size = lock
+        .min_size
+        .map(|size| size.to_device_pixels(lock.scale_factor));
+    let max_size = lock
+        .max_size
+        .map(|size| size.to_device_pixels(lock.scale_factor));
+    drop(lock);
+    let mut window_rect = RECT::default();
+    let mut client_rect = RECT::default();
+    unsafe {
+        GetWindowRect(handle, &mut window_rect).log_err();
+        GetClientRect(handle, &mut client_rect).log_err();
+    }
+    let frame = size(
+        DevicePixels((window_rect.right - window_rect.left) - client_rect.right),
+        DevicePixels((window_rect.bottom - window_rect.top) - client_rect.bottom),
+    );
+    let info = unsafe { &mut *(lparam.0 as *mut MINMAXINFO) };
+    apply_track_size_constraints(info, min_size, max_size, frame);
+    Some(0)
+}
+
+fn apply_track_size_constraints(
+    info: &mut MINMAXINFO,
+    min_size: Option<Size<DevicePixels>>,
+    max_size: Option<Size<DevicePixels>>,
+    frame: Size<DevicePixels>,
+) {
+    if let Some(min_size) = min_size {
+        info.ptMinTrackSize.x = info.ptMinTrackSize.x.max(min_size.width.0 + frame.width.0);
+        info.ptMinTrackSize.y = info
+            .ptMinTrackSize
+            .y
+            .max(min_size.height.0 + frame.height.0);
+    }
+    if let Some(max_size) = max_size {
+        info.ptMaxTrackSize.x = max_size.width.0 + frame.width.0;
+        info.ptMaxTrackSize.y = max_size.height.0 + frame.height.0;
+    }
+}
+
 fn handle_dpi_changed_msg(
     handle: HWND,
     wparam: WPARAM,
@@ -730,6 +1716,8 @@ fn handle_dpi_changed_msg(
     let rect = unsafe { &*(lparam.0 as *const RECT) };
     let width = rect.right - rect.left;
     let height = rect.bottom - rect.top;
+    let mut client_rect = RECT::default();
+    unsafe { GetClientRect(handle, &mut client_rect) }.log_err();
     // this will emit `WM_SIZE` and `WM_MOVE` right here
     // even before this function returns
     // the new size is handled in `WM_SIZE`
@@ -746,6 +1734,17 @@ fn handle_dpi_changed_msg(
         .context("unable to set window position after dpi has changed")
         .log_err();
     }
+    // No `WM_SIZE` is sent when the suggested rect keeps the size of the client area, which
+    // still has to be drawn again at the new scale.
+    let mut new_client_rect = RECT::default();
+    unsafe { GetClientRect(handle, &mut new_client_rect) }.log_err();
+    if new_client_rect == client_rect {
+        let client_size = size(
+            DevicePixels(client_rect.right - client_rect.left),
+            DevicePixels(client_rect.bottom - client_rect.top),
+        );
+        notify_resize(client_size, &state_ptr);
+    }
 
     Some(0)
 }
@@ -759,7 +1758,18 @@ fn handle_dpi_changed_msg(
 ///
 /// For example, in the case of condition 2, where the monitor on which the window is
 /// located has actually changed nothing, it will still receive this event.
-fn handle_display_change_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+fn handle_display_change_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    unsafe { PostMessageW(None, DISPLAYS_CHANGED, None, None) }.log_err();
+    // the color depth can change in place, e.g. when a remote session is reconnected
+    state_ptr
+        .state
+        .borrow_mut()
+        .renderer
+        .update_color_depth(wparam.0 as u32);
     // NOTE:
     // Even the `lParam` holds the resolution of the screen, we just ignore it.
     // Because WM_DPICHANGED, WM_MOVE, WM_SIEZ will come first, window reposition and resize
@@ -787,6 +1797,45 @@ fn handle_display_change_msg(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>)
     Some(0)
 }
 
+// Monitors that are plugged in before their mode is set, or unplugged while the desktop
+// doesn't extend to them, only show up as device node changes.
+fn handle_device_change_msg(wparam: WPARAM) -> Option<isize> {
+    if wparam.0 as u32 == DBT_DEVNODES_CHANGED {
+        unsafe { PostMessageW(None, DISPLAYS_CHANGED, None, None) }.log_err();
+    }
+    None
+}
+
+fn handle_power_broadcast_msg(wparam: WPARAM) -> Option<isize> {
+    // every top level window receives this message, the platform only reports changes
+    let suspended = match wparam.0 as u32 {
+        PBT_APMSUSPEND => true,
+        PBT_APMRESUMEAUTOMATIC => false,
+        _ => return None,
+    };
+    unsafe {
+        PostMessageW(
+            None,
+            SYSTEM_SUSPEND_CHANGED,
+            WPARAM(suspended as usize),
+            None,
+        )
+    }
+    .log_err();
+    None
+}
+
+fn handle_session_change_msg(wparam: WPARAM) -> Option<isize> {
+    // every window is registered for session notifications, the platform only reports changes
+    let locked = match wparam.0 as u32 {
+        WTS_SESSION_LOCK => true,
+        WTS_SESSION_UNLOCK => false,
+        _ => return None,
+    };
+    unsafe { PostMessageW(None, SESSION_LOCK_CHANGED, WPARAM(locked as usize), None) }.log_err();
+    None
+}
+
 fn handle_hit_test_msg(
     handle: HWND,
     msg: u32,
@@ -794,24 +1843,13 @@ fn handle_hit_test_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    if !state_ptr.hide_title_bar {
+    if !state_ptr.hide_title_bar.get() {
         return None;
     }
 
     // default handler for resize areas
     let hit = unsafe { DefWindowProcW(handle, msg, wparam, lparam) };
-    if matches!(
-        hit.0 as u32,
-        HTNOWHERE
-            | HTRIGHT
-            | HTLEFT
-            | HTTOPLEFT
-            | HTTOP
-            | HTTOPRIGHT
-            | HTBOTTOMRIGHT
-            | HTBOTTOM
-            | HTBOTTOMLEFT
-    ) {
+    if is_resize_border_hit(hit.0 as u32) {
         return Some(hit.0);
     }
 
@@ -819,45 +1857,24 @@ fn handle_hit_test_msg(
         return Some(HTCLIENT as _);
     }
 
-    let dpi = unsafe { GetDpiForWindow(handle) };
-    let frame_y = unsafe { GetSystemMetricsForDpi(SM_CYFRAME, dpi) };
-    let padding = unsafe { GetSystemMetricsForDpi(SM_CXPADDEDBORDER, dpi) };
-
+    let insets = FrameInsets::for_dpi(unsafe { GetDpiForWindow(handle) });
     let mut cursor_point = POINT {
         x: lparam.signed_loword().into(),
         y: lparam.signed_hiword().into(),
     };
     unsafe { ScreenToClient(handle, &mut cursor_point).ok().log_err() };
-    if cursor_point.y > 0 && cursor_point.y < frame_y + padding {
-        return Some(HTTOP as _);
-    }
-
-    let titlebar_rect = state_ptr.state.borrow().get_titlebar_rect();
-    if let Ok(titlebar_rect) = titlebar_rect {
-        if cursor_point.y < titlebar_rect.bottom {
-            let caption_btn_width = (state_ptr.state.borrow().caption_button_width().0
-                * state_ptr.state.borrow().scale_factor) as i32;
-            if cursor_point.x >= titlebar_rect.right - caption_btn_width {
-                return Some(HTCLOSE as _);
-            } else if cursor_point.x >= titlebar_rect.right - caption_btn_width * 2 {
-                return Some(HTMAXBUTTON as _);
-            } else if cursor_point.x >= titlebar_rect.right - caption_btn_width * 3 {
-                return Some(HTMINBUTTON as _);
-            }
 
-            return Some(HTCAPTION as _);
-        }
-    }
-
-    Some(HTCLIENT as _)
+    let titlebar = state_ptr.state.borrow().titlebar_layout();
+    Some(insets.hit_test_client(cursor_point, titlebar.as_ref()) as _)
 }
 
 fn handle_nc_mouse_move_msg(
     handle: HWND,
+    wparam: WPARAM,
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    if !state_ptr.hide_title_bar {
+    if !state_ptr.hide_title_bar.get() {
         return None;
     }
 
@@ -882,6 +1899,11 @@ fn handle_nc_mouse_move_msg(
         };
         state_ptr.state.borrow_mut().callbacks.input = Some(callback);
 
+        // the shell only opens the snap layouts flyout on Windows 11 once the default window
+        // proc has seen the cursor hovering over the maximize button
+        if wparam.0 as u32 == HTMAXBUTTON {
+            return None;
+        }
         result
     } else {
         None
@@ -895,7 +1917,7 @@ fn handle_nc_mouse_down_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    if !state_ptr.hide_title_bar {
+    if !state_ptr.hide_title_bar.get() {
         return None;
     }
 
@@ -945,6 +1967,28 @@ fn handle_nc_mouse_down_msg(
     }
 }
 
+// Non-client double clicks are sent even without `CS_DBLCLKS`, in place of the second press.
+// On the caption buttons that press has to be recorded like any other, or the default window
+// proc would draw and track its own buttons under ours.
+fn handle_nc_double_click_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let hit = wparam.0 as u32;
+    if !matches!(hit, HTCAPTION | HTMINBUTTON | HTMAXBUTTON | HTCLOSE) {
+        return None;
+    }
+    let result =
+        handle_nc_mouse_down_msg(handle, MouseButton::Left, wparam, lparam, state_ptr.clone());
+    if hit == HTCAPTION && result.is_none() && state_ptr.hide_title_bar.get() {
+        toggle_maximized(handle, &state_ptr);
+        return Some(0);
+    }
+    result
+}
+
 fn handle_nc_mouse_up_msg(
     handle: HWND,
     button: MouseButton,
@@ -952,7 +1996,7 @@ fn handle_nc_mouse_up_msg(
     lparam: LPARAM,
     state_ptr: Rc<WindowsWindowStatePtr>,
 ) -> Option<isize> {
-    if !state_ptr.hide_title_bar {
+    if !state_ptr.hide_title_bar.get() {
         return None;
     }
 
@@ -984,6 +2028,15 @@ fn handle_nc_mouse_up_msg(
         drop(lock);
     }
 
+    if button == MouseButton::Right && wparam.0 as u32 == HTCAPTION {
+        let position = POINT {
+            x: lparam.signed_loword().into(),
+            y: lparam.signed_hiword().into(),
+        };
+        show_system_menu(handle, position);
+        return Some(0);
+    }
+
     let last_pressed = state_ptr.state.borrow_mut().nc_button_pressed.take();
     if button == MouseButton::Left && last_pressed.is_some() {
         let last_button = last_pressed.unwrap();
@@ -997,11 +2050,7 @@ fn handle_nc_mouse_up_msg(
             }
             HTMAXBUTTON => {
                 if last_button == HTMAXBUTTON {
-                    if state_ptr.state.borrow().is_maximized() {
-                        unsafe { ShowWindowAsync(handle, SW_NORMAL).ok().log_err() };
-                    } else {
-                        unsafe { ShowWindowAsync(handle, SW_MAXIMIZE).ok().log_err() };
-                    }
+                    toggle_maximized(handle, &state_ptr);
                     handled = true;
                 }
             }
@@ -1030,20 +2079,88 @@ fn handle_cursor_changed(lparam: LPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -
 }
 
 fn handle_set_cursor(lparam: LPARAM, state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+    if state_ptr.state.borrow().pointer_locked {
+        unsafe { SetCursor(None) };
+        return Some(1);
+    }
     if matches!(
         lparam.loword() as u32,
         HTLEFT | HTRIGHT | HTTOP | HTTOPLEFT | HTTOPRIGHT | HTBOTTOM | HTBOTTOMLEFT | HTBOTTOMRIGHT
     ) {
         return None;
     }
-    unsafe { SetCursor(state_ptr.state.borrow().current_cursor) };
+    let lock = state_ptr.state.borrow();
+    unsafe { SetCursor(scaled_cursor(lock.current_cursor, lock.scale_factor)) };
     Some(1)
 }
 
-fn handle_system_settings_changed(state_ptr: Rc<WindowsWindowStatePtr>) -> Option<isize> {
+fn handle_system_settings_changed(
+    handle: HWND,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
     let mut lock = state_ptr.state.borrow_mut();
     // mouse wheel
     lock.system_settings.mouse_wheel_settings.update();
+    lock.system_settings.update_hide_cursor_while_typing();
+    drop(lock);
+    // switching between light and dark mode is announced as a change of this area
+    let area = PCWSTR(lparam.0 as *const u16);
+    if !area.is_null() && unsafe { area.to_string() }.ok().as_deref() == Some("ImmersiveColorSet") {
+        handle_appearance_changed(handle, state_ptr);
+    }
+    Some(0)
+}
+
+fn handle_appearance_changed(handle: HWND, state_ptr: Rc<WindowsWindowStatePtr>) {
+    let Some(appearance) = system_appearance().log_err() else {
+        return;
+    };
+    let mut lock = state_ptr.state.borrow_mut();
+    if lock.appearance == appearance {
+        return;
+    }
+    lock.appearance = appearance;
+    if lock.frame_appearance.is_none() {
+        set_frame_appearance(handle, appearance);
+    }
+    if let Some(mut callback) = lock.callbacks.appearance_changed.take() {
+        drop(lock);
+        callback();
+        state_ptr.state.borrow_mut().callbacks.appearance_changed = Some(callback);
+    }
+}
+
+fn handle_system_command_msg(
+    handle: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    state_ptr: Rc<WindowsWindowStatePtr>,
+) -> Option<isize> {
+    let command = (wparam.0 & 0xFFF0) as u32;
+    // Alt+Space, which would otherwise open the menu where the native caption used to be
+    if command == SC_KEYMENU && lparam.0 == ' ' as isize && state_ptr.hide_title_bar.get() {
+        let mut origin = POINT::default();
+        unsafe { ClientToScreen(handle, &mut origin) }
+            .ok()
+            .log_err();
+        show_system_menu(handle, origin);
+        return Some(0);
+    }
+    if !is_window_menu_command(command) {
+        return None;
+    }
+    let mut lock = state_ptr.state.borrow_mut();
+    let action = lock
+        .window_menu
+        .iter()
+        .find(|entry| entry.command_id == command)?
+        .action
+        .boxed_clone();
+    let mut callback = lock.callbacks.window_menu_action.take()?;
+    drop(lock);
+    callback(action.as_ref());
+    state_ptr.state.borrow_mut().callbacks.window_menu_action = Some(callback);
     Some(0)
 }
 
@@ -1088,14 +2205,31 @@ enum KeystrokeOrModifier {
     Modifier(Modifiers),
 }
 
-fn parse_keydown_msg_keystroke(wparam: WPARAM) -> Option<KeystrokeOrModifier> {
+fn parse_keydown_msg_keystroke(wparam: WPARAM, lparam: LPARAM) -> Option<KeystrokeOrModifier> {
     let vk_code = wparam.loword();
 
     let modifiers = current_modifiers();
 
     let key = match VIRTUAL_KEY(vk_code) {
         VK_BACK => "backspace",
+        // the enter key of the numpad shares its virtual key with the main one
+        VK_RETURN if is_extended_key(lparam) => "numpadenter",
         VK_RETURN => "enter",
+        VK_NUMPAD0 => "numpad0",
+        VK_NUMPAD1 => "numpad1",
+        VK_NUMPAD2 => "numpad2",
+        VK_NUMPAD3 => "numpad3",
+        VK_NUMPAD4 => "numpad4",
+        VK_NUMPAD5 => "numpad5",
+        VK_NUMPAD6 => "numpad6",
+        VK_NUMPAD7 => "numpad7",
+        VK_NUMPAD8 => "numpad8",
+        VK_NUMPAD9 => "numpad9",
+        VK_DECIMAL => "numpaddecimal",
+        VK_ADD => "numpadadd",
+        VK_SUBTRACT => "numpadsubtract",
+        VK_MULTIPLY => "numpadmultiply",
+        VK_DIVIDE => "numpaddivide",
         VK_TAB => "tab",
         VK_UP => "up",
         VK_DOWN => "down",
@@ -1140,8 +2274,7 @@ fn parse_keydown_msg_keystroke(wparam: WPARAM) -> Option<KeystrokeOrModifier> {
     }))
 }
 
-fn parse_char_msg_keystroke(wparam: WPARAM) -> Option<Keystroke> {
-    let first_char = char::from_u32((wparam.0 as u16).into())?;
+fn parse_char_msg_keystroke(first_char: char) -> Option<Keystroke> {
     if first_char.is_control() {
         None
     } else {
@@ -1254,17 +2387,125 @@ fn is_virtual_key_pressed(vkey: VIRTUAL_KEY) -> bool {
 fn is_modifier(virtual_key: VIRTUAL_KEY) -> bool {
     matches!(
         virtual_key,
-        VK_CONTROL | VK_MENU | VK_SHIFT | VK_LWIN | VK_RWIN
+        VK_CONTROL | VK_MENU | VK_SHIFT | VK_LWIN | VK_RWIN | VK_CAPITAL
     )
 }
 
 #[inline]
 fn current_modifiers() -> Modifiers {
+    modifiers_from_key_state(is_virtual_key_pressed, ALT_GR_PRESSED.get())
+}
+
+/// Whether capslock is toggled on, which is kept in the lowest bit of the key state.
+pub(crate) fn current_capslock() -> Capslock {
+    Capslock {
+        on: unsafe { GetKeyState(VK_CAPITAL.0 as i32) } & 1 != 0,
+    }
+}
+
+/// The modifiers held down right now, rather than when the message being handled was sent.
+fn async_modifiers() -> Modifiers {
+    modifiers_from_key_state(
+        |vkey| unsafe { GetAsyncKeyState(vkey.0 as i32) } < 0,
+        ALT_GR_PRESSED.get(),
+    )
+}
+
+/// While AltGr is held down, only the keys it doesn't pretend to press count as control and
+/// alt, so that typing `[` or `€` on a European layout doesn't match `ctrl-alt` bindings.
+fn modifiers_from_key_state(is_pressed: impl Fn(VIRTUAL_KEY) -> bool, alt_gr: bool) -> Modifiers {
     Modifiers {
-        control: is_virtual_key_pressed(VK_CONTROL),
-        alt: is_virtual_key_pressed(VK_MENU),
-        shift: is_virtual_key_pressed(VK_SHIFT),
-        platform: is_virtual_key_pressed(VK_LWIN) || is_virtual_key_pressed(VK_RWIN),
+        control: if alt_gr {
+            is_pressed(VK_RCONTROL)
+        } else {
+            is_pressed(VK_CONTROL)
+        },
+        alt: if alt_gr {
+            is_pressed(VK_LMENU)
+        } else {
+            is_pressed(VK_MENU)
+        },
+        shift: is_pressed(VK_SHIFT),
+        platform: is_pressed(VK_LWIN) || is_pressed(VK_RWIN),
         function: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modifiers_from_key_state() {
+        let modifiers = modifiers_from_key_state(|vkey| vkey == VK_MENU || vkey == VK_RWIN, false);
+        assert_eq!(
+            modifiers,
+            Modifiers {
+                alt: true,
+                platform: true,
+                ..Default::default()
+            }
+        );
+
+        let modifiers = modifiers_from_key_state(|_| false, false);
+        assert_eq!(modifiers, Modifiers::default());
+
+        let alt_gr =
+            |vkey: VIRTUAL_KEY| matches!(vkey, VK_CONTROL | VK_LCONTROL | VK_MENU | VK_RMENU);
+        assert_eq!(modifiers_from_key_state(alt_gr, true), Modifiers::default());
+        assert_eq!(
+            modifiers_from_key_state(alt_gr, false),
+            Modifiers {
+                control: true,
+                alt: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_utf16_char() {
+        let mut pending = None;
+        assert_eq!(decode_utf16_char(&mut pending, 'a' as u16), Some('a'));
+
+        let mut units = [0u16; 2];
+        '😀'.encode_utf16(&mut units);
+        assert_eq!(decode_utf16_char(&mut pending, units[0]), None);
+        assert_eq!(decode_utf16_char(&mut pending, units[1]), Some('😀'));
+        assert_eq!(pending, None);
+
+        // a lone low surrogate is dropped, as is a high one that isn't followed by one
+        assert_eq!(decode_utf16_char(&mut pending, units[1]), None);
+        assert_eq!(decode_utf16_char(&mut pending, units[0]), None);
+        assert_eq!(decode_utf16_char(&mut pending, 'b' as u16), Some('b'));
+        assert_eq!(pending, None);
+    }
+
+    #[test]
+    fn test_apply_track_size_constraints() {
+        let frame = size(DevicePixels(16), DevicePixels(39));
+        let mut info = MINMAXINFO {
+            ptMinTrackSize: POINT { x: 136, y: 39 },
+            ptMaxTrackSize: POINT { x: 3000, y: 2000 },
+            ..Default::default()
+        };
+        apply_track_size_constraints(
+            &mut info,
+            Some(size(DevicePixels(400), DevicePixels(300))),
+            None,
+            frame,
+        );
+        assert_eq!((info.ptMinTrackSize.x, info.ptMinTrackSize.y), (416, 339));
+        assert_eq!((info.ptMaxTrackSize.x, info.ptMaxTrackSize.y), (3000, 2000));
+
+        // the system minimum is kept when it's larger than the one asked for
+        apply_track_size_constraints(
+            &mut info,
+            Some(size(DevicePixels(50), DevicePixels(50))),
+            Some(size(DevicePixels(800), DevicePixels(600))),
+            frame,
+        );
+        assert_eq!((info.ptMinTrackSize.x, info.ptMinTrackSize.y), (416, 339));
+        assert_eq!((info.ptMaxTrackSize.x, info.ptMaxTrackSize.y), (816, 639));
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use ::util::ResultExt;
+use anyhow::{Context, Result};
+use futures::channel::oneshot;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        System::Com::*,
+        UI::{
+            Input::KeyboardAndMouse::GetActiveWindow,
+            Shell::{Common::COMDLG_FILTERSPEC, *},
+        },
+    },
+};
+
+use crate::*;
+
+/// Shows a dialog for picking existing files, or folders when `options` allows directories,
+/// as the dialog can't pick both.
+pub(crate) fn prompt_for_paths_dialog(
+    options: PathPromptOptions,
+    filters: Vec<PathPromptFilter>,
+) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
+    spawn_dialog(move |owner| unsafe {
+        let dialog: IFileOpenDialog =
+            CoCreateInstance(&FileOpenDialog, None, CLSCTX_INPROC_SERVER)?;
+        let mut dialog_options =
+            dialog.GetOptions()? | FOS_FORCEFILESYSTEM | FOS_PATHMUSTEXIST | FOS_FILEMUSTEXIST;
+        if options.multiple {
+            dialog_options |= FOS_ALLOWMULTISELECT;
+        }
+        if options.directories {
+            dialog_options |= FOS_PICKFOLDERS;
+            // the dialog is titled "Open" otherwise, like when picking files
+            dialog.SetTitle(w!("Select a folder"))?;
+        } else {
+            set_file_types(&dialog, &filters)?;
+        }
+        dialog.SetOptions(dialog_options)?;
+        if !show_dialog(&dialog, owner)? {
+            return Ok(None);
+        }
+        let results = dialog.GetResults()?;
+        let mut paths = Vec::new();
+        for index in 0..results.GetCount()? {
+            paths.push(shell_item_path(&results.GetItemAt(index)?)?);
+        }
+        Ok((!paths.is_empty()).then_some(paths))
+    })
+}
+
+/// Shows a dialog for picking where to save a new file, starting in `directory` with
+/// `suggested_name` filled in. The first filter decides the extension added to names typed
+/// without one.
+pub(crate) fn prompt_for_new_path_dialog(
+    directory: PathBuf,
+    suggested_name: Option<String>,
+    filters: Vec<PathPromptFilter>,
+) -> oneshot::Receiver<Option<PathBuf>> {
+    spawn_dialog(move |owner| unsafe {
+        let dialog: IFileSaveDialog =
+            CoCreateInstance(&FileSaveDialog, None, CLSCTX_INPROC_SERVER)?;
+        dialog.SetOptions(dialog.GetOptions()? | FOS_FORCEFILESYSTEM | FOS_OVERWRITEPROMPT)?;
+        if let Some(folder) = folder_item(&directory) {
+            dialog.SetFolder(&folder).log_err();
+        }
+        if let Some(name) = suggested_name {
+            dialog.SetFileName(&HSTRING::from(name))?;
+        }
+        set_file_types(&dialog, &filters)?;
+        if let Some(extension) = filters.first().and_then(|filter| filter.extensions.first()) {
+            dialog.SetDefaultExtension(&HSTRING::from(extension.as_ref()))?;
+        }
+        if !show_dialog(&dialog, owner)? {
+            return Ok(None);
+        }
+        Ok(Some(shell_item_path(&dialog.GetResult()?)?))
+    })
+}
+
+/// Runs a dialog on a thread of its own. Dialogs run a modal loop until they're closed, which
+/// would hold up the tasks of the main thread if it was run there. The dialog is owned by the
+/// active window, which it's shown over and which can't be used until it's closed.
+fn spawn_dialog<T: Send + 'static>(
+    show: impl FnOnce(HWND) -> Result<Option<T>> + Send + 'static,
+) -> oneshot::Receiver<Option<T>> {
+    // the active window is the one of the calling thread
+    let owner = unsafe { GetActiveWindow() };
+    let (tx, rx) = oneshot::channel();
+    std::thread::Builder::new()
+        .name("FileDialog".to_string())
+        .spawn(move || {
+            let result =
+                unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE) }
+                    .ok()
+                    .context("unable to initialize COM for a file dialog")
+                    .and_then(|_| {
+                        let result = show(owner);
+                        unsafe { CoUninitialize() };
+                        result
+                    });
+            tx.send(result.log_err().flatten()).ok();
+        })
+        .log_err();
+    rx
+}
+
+/// Returns whether the user picked something, rather than canceling the dialog.
+unsafe fn show_dialog(dialog: &IFileDialog, owner: HWND) -> Result<bool> {
+    match dialog.Show(owner) {
+        Ok(()) => Ok(true),
+        Err(error) if error.code() == ERROR_CANCELLED.to_hresult() => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+unsafe fn set_file_types(dialog: &IFileDialog, filters: &[PathPromptFilter]) -> Result<()> {
+    if filters.is_empty() {
+        return Ok(());
+    }
+    // the specs point into these strings, which have to outlive the call
+    let strings = filters
+        .iter()
+        .map(|filter| {
+            (
+                HSTRING::from(filter.name.as_ref()),
+                HSTRING::from(filter_spec(filter)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let specs = strings
+        .iter()
+        .map(|(name, spec)| COMDLG_FILTERSPEC {
+            pszName: PCWSTR(name.as_ptr()),
+            pszSpec: PCWSTR(spec.as_ptr()),
+        })
+        .collect::<Vec<_>>();
+    dialog.SetFileTypes(&specs)?;
+    Ok(())
+}
+
+/// The patterns of the files a filter lists, like `*.rs;*.toml`.
+fn filter_spec(filter: &PathPromptFilter) -> String {
+    if filter.extensions.is_empty() {
+        return "*.*".to_string();
+    }
+    filter
+        .extensions
+        .iter()
+        .map(|extension| format!("*.{}", extension))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn folder_item(directory: &Path) -> Option<IShellItem> {
+    // the shell doesn't parse relative paths, or the `\\?\` ones `canonicalize` returns
+    if !directory.is_absolute() || !directory.is_dir() {
+        return None;
+    }
+    unsafe { SHCreateItemFromParsingName(&HSTRING::from(directory), None) }.log_err()
+}
+
+unsafe fn shell_item_path(item: &IShellItem) -> Result<PathBuf> {
+    let name = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+    let path = name.to_string();
+    CoTaskMemFree(Some(name.0 as *const _));
+    Ok(PathBuf::from(path?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filter_spec;
+    use crate::PathPromptFilter;
+
+    #[test]
+    fn test_filter_spec() {
+        let filter = PathPromptFilter {
+            name: "Rust".into(),
+            extensions: vec!["rs".into()],
+        };
+        assert_eq!(filter_spec(&filter), "*.rs");
+
+        let filter = PathPromptFilter {
+            name: "Images".into(),
+            extensions: vec!["png".into(), "jpg".into(), "svg".into()],
+        };
+        assert_eq!(filter_spec(&filter), "*.png;*.jpg;*.svg");
+
+        // a filter without extensions lists every file
+        let filter = PathPromptFilter {
+            name: "All Files".into(),
+            extensions: Vec::new(),
+        };
+        assert_eq!(filter_spec(&filter), "*.*");
+    }
+}
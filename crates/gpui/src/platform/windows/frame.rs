@@ -0,0 +1,310 @@
+use windows::Win32::{
+    Foundation::*,
+    UI::{HiDpi::*, WindowsAndMessaging::*},
+};
+
+/// The resize borders left around the client area of a window whose client area is extended
+/// over the title bar, in physical pixels at the window's DPI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct FrameInsets {
+    pub(crate) resize_x: i32,
+    pub(crate) resize_y: i32,
+}
+
+impl FrameInsets {
+    pub(crate) fn for_dpi(dpi: u32) -> Self {
+        let frame_x = unsafe { GetSystemMetricsForDpi(SM_CXFRAME, dpi) };
+        let frame_y = unsafe { GetSystemMetricsForDpi(SM_CYFRAME, dpi) };
+        let padding = unsafe { GetSystemMetricsForDpi(SM_CXPADDEDBORDER, dpi) };
+        Self {
+            resize_x: frame_x + padding,
+            resize_y: frame_y + padding,
+        }
+    }
+
+    /// Turns the window rect proposed by `WM_NCCALCSIZE` into the client rect. The client area
+    /// covers the top border too, which is hit-tested by [`Self::hit_test_client`] instead.
+    pub(crate) fn client_rect(&self, window_rect: RECT) -> RECT {
+        RECT {
+            left: window_rect.left + self.resize_x,
+            top: window_rect.top,
+            right: window_rect.right - self.resize_x,
+            bottom: window_rect.bottom - self.resize_y,
+        }
+    }
+
+    /// Hit-tests a point in client coordinates, for points the default window procedure
+    /// reported as part of the client area.
+    pub(crate) fn hit_test_client(&self, point: POINT, titlebar: Option<&TitlebarLayout>) -> u32 {
+        if point.y > 0 && point.y < self.resize_y {
+            return HTTOP;
+        }
+        titlebar
+            .and_then(|titlebar| titlebar.hit_test(point))
+            .unwrap_or(HTCLIENT)
+    }
+}
+
+/// Whether a hit code from the default window procedure is one of the resize borders, which
+/// it still handles correctly for a client area extended over the title bar.
+pub(crate) fn is_resize_border_hit(hit: u32) -> bool {
+    matches!(
+        hit,
+        HTNOWHERE
+            | HTRIGHT
+            | HTLEFT
+            | HTTOPLEFT
+            | HTTOP
+            | HTTOPRIGHT
+            | HTBOTTOMRIGHT
+            | HTBOTTOM
+            | HTBOTTOMLEFT
+    )
+}
+
+/// The title bar drawn by the app, with its caption buttons in the top right corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TitlebarLayout {
+    /// The title bar, in client coordinates.
+    pub(crate) rect: RECT,
+    /// The width of each caption button, in physical pixels.
+    pub(crate) caption_button_width: i32,
+}
+
+impl TitlebarLayout {
+    fn hit_test(&self, point: POINT) -> Option<u32> {
+        if point.y >= self.rect.bottom {
+            return None;
+        }
+        let button_width = self.caption_button_width;
+        Some(if point.x >= self.rect.right - button_width {
+            HTCLOSE
+        } else if point.x >= self.rect.right - button_width * 2 {
+            HTMAXBUTTON
+        } else if point.x >= self.rect.right - button_width * 3 {
+            HTMINBUTTON
+        } else {
+            HTCAPTION
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use windows::Win32::Graphics::Gdi::*;
+
+    use super::*;
+    use crate::{
+        point, px, size, Bounds, Empty, ForegroundExecutor, PlatformWindow, TitlebarOptions,
+        WindowBackgroundAppearance, WindowHandle, WindowId, WindowKind, WindowParams,
+        WindowsDispatcher, WindowsWindow,
+    };
+
+    const DPIS: [u32; 5] = [96, 120, 144, 168, 192];
+
+    fn titlebar_for_dpi(dpi: u32, client_width: i32) -> TitlebarLayout {
+        let scale = |logical: i32| logical * dpi as i32 / 96;
+        TitlebarLayout {
+            rect: RECT {
+                left: 0,
+                top: 0,
+                right: client_width,
+                bottom: scale(32),
+            },
+            caption_button_width: scale(36),
+        }
+    }
+
+    #[test]
+    fn test_client_rect_keeps_top_border() {
+        let window_rect = RECT {
+            left: 100,
+            top: 50,
+            right: 900,
+            bottom: 650,
+        };
+        let mut previous_insets = FrameInsets::for_dpi(DPIS[0]);
+        for dpi in DPIS {
+            let insets = FrameInsets::for_dpi(dpi);
+            assert!(insets.resize_x >= previous_insets.resize_x, "dpi {dpi}");
+            assert!(insets.resize_y >= previous_insets.resize_y, "dpi {dpi}");
+            previous_insets = insets;
+
+            let client_rect = insets.client_rect(window_rect);
+            assert_eq!(client_rect.top, window_rect.top, "dpi {dpi}");
+            assert_eq!(client_rect.left - window_rect.left, insets.resize_x);
+            assert_eq!(window_rect.right - client_rect.right, insets.resize_x);
+            assert_eq!(window_rect.bottom - client_rect.bottom, insets.resize_y);
+        }
+    }
+
+    #[test]
+    fn test_hit_test_caption_buttons() {
+        for dpi in DPIS {
+            let insets = FrameInsets::for_dpi(dpi);
+            let titlebar = titlebar_for_dpi(dpi, 800);
+            let button_width = titlebar.caption_button_width;
+            let caption_y = (insets.resize_y + titlebar.rect.bottom) / 2;
+            let hit_test = |x, y| insets.hit_test_client(POINT { x, y }, Some(&titlebar));
+
+            assert_eq!(hit_test(400, 1), HTTOP, "dpi {dpi}");
+            assert_eq!(hit_test(400, insets.resize_y), HTCAPTION, "dpi {dpi}");
+            assert_eq!(hit_test(799, caption_y), HTCLOSE, "dpi {dpi}");
+            assert_eq!(hit_test(800 - button_width, caption_y), HTCLOSE);
+            assert_eq!(hit_test(799 - button_width, caption_y), HTMAXBUTTON);
+            assert_eq!(hit_test(800 - button_width * 2, caption_y), HTMAXBUTTON);
+            assert_eq!(hit_test(799 - button_width * 2, caption_y), HTMINBUTTON);
+            assert_eq!(hit_test(800 - button_width * 3, caption_y), HTMINBUTTON);
+            assert_eq!(hit_test(799 - button_width * 3, caption_y), HTCAPTION);
+            assert_eq!(hit_test(799, titlebar.rect.bottom), HTCLIENT, "dpi {dpi}");
+            assert_eq!(
+                insets.hit_test_client(
+                    POINT {
+                        x: 799,
+                        y: caption_y
+                    },
+                    None
+                ),
+                HTCLIENT
+            );
+        }
+    }
+
+    // Drives a window with the real window procedure through DPI changes and maximizing, so
+    // the `WM_NCCALCSIZE` and `WM_NCHITTEST` handlers are covered along with the system's
+    // handling of the rects and hit codes they return.
+    #[test]
+    fn test_frame_of_window() {
+        let executor = ForegroundExecutor::new(Arc::new(WindowsDispatcher::new()));
+        let params = WindowParams {
+            bounds: Bounds::new(point(px(100.), px(100.)), size(px(800.), px(600.))),
+            titlebar: Some(TitlebarOptions {
+                appears_transparent: true,
+                ..Default::default()
+            }),
+            kind: WindowKind::Normal,
+            is_movable: true,
+            focus: false,
+            show: false,
+            display_id: None,
+            window_background: WindowBackgroundAppearance::Opaque,
+            window_min_size: None,
+        };
+        let window = WindowsWindow::new(
+            WindowHandle::<Empty>::new(WindowId::from(u64::MAX)).into(),
+            params,
+            HICON::default(),
+            executor,
+            HCURSOR::default(),
+        );
+        let hwnd = window.get_raw_handle();
+        let _ = unsafe { ShowWindow(hwnd, SW_HIDE) };
+
+        for dpi in DPIS {
+            // the window takes the scale of the title bar from the message, while the system
+            // keeps laying out its borders for the DPI of the monitor it's on
+            let mut window_rect = RECT::default();
+            unsafe { GetWindowRect(hwnd, &mut window_rect) }.unwrap();
+            let suggested_rect = RECT {
+                right: window_rect.left + 800 * dpi as i32 / 96,
+                bottom: window_rect.top + 600 * dpi as i32 / 96,
+                ..window_rect
+            };
+            unsafe {
+                SendMessageW(
+                    hwnd,
+                    WM_DPICHANGED,
+                    WPARAM(((dpi << 16) | dpi) as usize),
+                    LPARAM(&suggested_rect as *const RECT as isize),
+                )
+            };
+            assert_frame(hwnd, dpi);
+        }
+
+        // maximizing moves the borders outside of the monitor, restoring has to bring them back
+        let dpi = unsafe { GetDpiForWindow(hwnd) };
+        let _ = unsafe { ShowWindow(hwnd, SW_MAXIMIZE) };
+        let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+        let mut monitor_info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        assert!(unsafe { GetMonitorInfoW(monitor, &mut monitor_info) }.as_bool());
+        let client_rect = screen_client_rect(hwnd);
+        assert!(client_rect.left >= monitor_info.rcMonitor.left);
+        assert!(client_rect.right <= monitor_info.rcMonitor.right);
+        assert!(client_rect.bottom <= monitor_info.rcWork.bottom);
+        // the caption buttons stay in the corner of the screen, where the pointer stops
+        let caption_y = client_rect.top + 32 * dpi as i32 / 96 / 2;
+        assert_eq!(hit_test(hwnd, client_rect.right - 1, caption_y), HTCLOSE);
+        let center_x = (client_rect.left + client_rect.right) / 2;
+        assert_eq!(hit_test(hwnd, center_x, caption_y), HTCAPTION);
+
+        let _ = unsafe { ShowWindow(hwnd, SW_RESTORE) };
+        let _ = unsafe { ShowWindow(hwnd, SW_HIDE) };
+        assert_frame(hwnd, DPIS[DPIS.len() - 1]);
+
+        unsafe { DestroyWindow(hwnd) }.unwrap();
+        drop(window);
+    }
+
+    /// Checks the frame of a window whose title bar is laid out for `dpi`.
+    fn assert_frame(hwnd: HWND, dpi: u32) {
+        let insets = FrameInsets::for_dpi(unsafe { GetDpiForWindow(hwnd) });
+        let mut window_rect = RECT::default();
+        unsafe { GetWindowRect(hwnd, &mut window_rect) }.unwrap();
+        let client_rect = screen_client_rect(hwnd);
+        assert_eq!(client_rect.top, window_rect.top, "dpi {dpi}");
+        assert_eq!(client_rect.left - window_rect.left, insets.resize_x);
+        assert_eq!(window_rect.right - client_rect.right, insets.resize_x);
+        assert_eq!(window_rect.bottom - client_rect.bottom, insets.resize_y);
+
+        let titlebar = titlebar_for_dpi(dpi, client_rect.right - client_rect.left);
+        let button_width = titlebar.caption_button_width;
+        let center_x = (window_rect.left + window_rect.right) / 2;
+        let center_y = (window_rect.top + window_rect.bottom) / 2;
+        let caption_y = client_rect.top + (insets.resize_y + titlebar.rect.bottom) / 2;
+        let hit_test = |x, y| hit_test(hwnd, x, y);
+        assert_eq!(
+            hit_test(window_rect.left + 1, center_y),
+            HTLEFT,
+            "dpi {dpi}"
+        );
+        assert_eq!(hit_test(window_rect.right - 2, center_y), HTRIGHT);
+        assert_eq!(hit_test(center_x, window_rect.bottom - 2), HTBOTTOM);
+        assert_eq!(hit_test(center_x, window_rect.top + 1), HTTOP, "dpi {dpi}");
+        assert_eq!(hit_test(client_rect.right - 1, caption_y), HTCLOSE);
+        assert_eq!(
+            hit_test(client_rect.right - button_width - 1, caption_y),
+            HTMAXBUTTON,
+            "dpi {dpi}"
+        );
+        assert_eq!(
+            hit_test(client_rect.right - button_width * 2 - 1, caption_y),
+            HTMINBUTTON,
+            "dpi {dpi}"
+        );
+        assert_eq!(hit_test(center_x, caption_y), HTCAPTION, "dpi {dpi}");
+        assert_eq!(hit_test(center_x, center_y), HTCLIENT, "dpi {dpi}");
+    }
+
+    fn hit_test(hwnd: HWND, x: i32, y: i32) -> u32 {
+        let lparam = LPARAM(((y as u16 as isize) << 16) | x as u16 as isize);
+        unsafe { SendMessageW(hwnd, WM_NCHITTEST, WPARAM(0), lparam) }.0 as u32
+    }
+
+    fn screen_client_rect(hwnd: HWND) -> RECT {
+        let mut client_rect = RECT::default();
+        unsafe { GetClientRect(hwnd, &mut client_rect) }.unwrap();
+        let mut origin = POINT::default();
+        unsafe { ClientToScreen(hwnd, &mut origin) }.ok().unwrap();
+        RECT {
+            left: origin.x,
+            top: origin.y,
+            right: origin.x + client_rect.right,
+            bottom: origin.y + client_rect.bottom,
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use windows::{
+    core::*,
+    Win32::{
+        System::Com::*,
+        UI::Shell::{Common::*, PropertiesSystem::*, *},
+    },
+};
+
+use crate::*;
+
+// https://learn.microsoft.com/en-us/windows/win32/properties/props-system-title
+const PKEY_TITLE: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0xf29f85e0_4ff9_1068_ab91_08002b27b3d9),
+    pid: 2,
+};
+
+/// Replaces the jump list of the app's taskbar button. Each entry is a shortcut that launches
+/// the app again with the entry's arguments.
+pub(crate) fn update_jump_list(jump_list: &JumpList) -> Result<()> {
+    let app_path = std::env::current_exe().context("unable to locate the app")?;
+    unsafe {
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut max_slots = 0;
+        let removed: IObjectArray = list.BeginList(&mut max_slots)?;
+        // adding back an entry the user removed from the list fails the whole category
+        let removed_arguments = removed_arguments(&removed)?;
+        let recent = jump_list
+            .recent
+            .iter()
+            .map(|item| (item, command_line(&item.arguments)))
+            .filter(|(_, arguments)| !removed_arguments.contains(arguments))
+            .take(max_slots as usize)
+            .collect::<Vec<_>>();
+        if !recent.is_empty() {
+            let links = object_collection()?;
+            for (item, arguments) in recent {
+                links.AddObject(&create_link(&app_path, item, &arguments)?)?;
+            }
+            list.AppendCategory(w!("Recent"), &links.cast::<IObjectArray>()?)?;
+        }
+        if !jump_list.tasks.is_empty() {
+            let links = object_collection()?;
+            for item in &jump_list.tasks {
+                links.AddObject(&create_link(
+                    &app_path,
+                    item,
+                    &command_line(&item.arguments),
+                )?)?;
+            }
+            list.AddUserTasks(&links.cast::<IObjectArray>()?)?;
+        }
+        list.CommitList()?;
+    }
+    Ok(())
+}
+
+unsafe fn object_collection() -> Result<IObjectCollection> {
+    Ok(CoCreateInstance(
+        &EnumerableObjectCollection,
+        None,
+        CLSCTX_INPROC_SERVER,
+    )?)
+}
+
+unsafe fn create_link(
+    app_path: &Path,
+    item: &JumpListItem,
+    arguments: &str,
+) -> Result<IShellLinkW> {
+    let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+    let app_path = HSTRING::from(app_path);
+    link.SetPath(&app_path)?;
+    link.SetArguments(&HSTRING::from(arguments))?;
+    link.SetIconLocation(&app_path, 0)?;
+    if let Some(description) = &item.description {
+        link.SetDescription(&HSTRING::from(description.as_ref()))?;
+    }
+    // the shell shows the title property of a link, rather than a file name it doesn't have
+    let properties: IPropertyStore = link.cast()?;
+    properties.SetValue(&PKEY_TITLE, &PROPVARIANT::from(item.title.as_ref()))?;
+    properties.Commit()?;
+    Ok(link)
+}
+
+unsafe fn removed_arguments(removed: &IObjectArray) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    for index in 0..removed.GetCount()? {
+        let Ok(link) = removed.GetAt::<IShellLinkW>(index) else {
+            continue;
+        };
+        let mut buffer = [0u16; 1024];
+        link.GetArguments(&mut buffer)?;
+        let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+        arguments.push(String::from_utf16_lossy(&buffer[..len]));
+    }
+    Ok(arguments)
+}
+
+fn command_line(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| quote_argument(argument))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// https://learn.microsoft.com/en-us/cpp/c-language/parsing-c-command-line-arguments
+fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '"']) {
+        return argument.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // backslashes only escape something when they come before a quote
+        let escapes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.extend(std::iter::repeat('\\').take(escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.extend(std::iter::repeat('\\').take(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_line, quote_argument};
+
+    #[test]
+    fn test_quote_argument() {
+        assert_eq!(quote_argument(r"C:\projects\zed"), r"C:\projects\zed");
+        assert_eq!(quote_argument(""), r#""""#);
+        assert_eq!(
+            quote_argument(r"C:\My Projects\zed"),
+            r#""C:\My Projects\zed""#
+        );
+        assert_eq!(quote_argument(r#"say "hi""#), r#""say \"hi\"""#);
+        // backslashes are doubled before a quote, including the closing one
+        assert_eq!(quote_argument(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_argument(r"C:\My Projects\"), r#""C:\My Projects\\""#);
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(
+            command_line(&[
+                r"C:\My Projects\zed".to_string(),
+                r"C:\tmp".to_string(),
+                String::new(),
+            ]),
+            r#""C:\My Projects\zed" C:\tmp """#
+        );
+    }
+}
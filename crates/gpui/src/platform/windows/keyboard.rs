@@ -0,0 +1,69 @@
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    MapVirtualKeyW, VkKeyScanW, MAPVK_VK_TO_CHAR, MAPVK_VSC_TO_VK,
+};
+
+use crate::{KeyboardMapper, Modifiers, PhysicalKey};
+
+/// Resolves physical keys through the keyboard layout of the current thread.
+pub(crate) struct WindowsKeyboardMapper;
+
+impl KeyboardMapper for WindowsKeyboardMapper {
+    fn character_for_physical_key(&self, key: PhysicalKey) -> Option<String> {
+        let scan_code = scan_code_for_physical_key(key);
+        let vk_code = unsafe { MapVirtualKeyW(scan_code, MAPVK_VSC_TO_VK) };
+        if vk_code == 0 {
+            return None;
+        }
+        // the high bit is set for dead keys, the character is still in the low word
+        let character = unsafe { MapVirtualKeyW(vk_code, MAPVK_VK_TO_CHAR) } & 0xFFFF;
+        if character == 0 {
+            return None;
+        }
+        char::from_u32(character).map(|character| character.to_lowercase().to_string())
+    }
+
+    fn modifiers_for_character(&self, character: &str) -> Option<Modifiers> {
+        let mut utf16 = character.encode_utf16();
+        let (Some(code_unit), None) = (utf16.next(), utf16.next()) else {
+            // characters outside the BMP can't be looked up, assume they can be typed
+            return Some(Modifiers::default());
+        };
+        let scan = unsafe { VkKeyScanW(code_unit) };
+        if scan == -1 {
+            return None;
+        }
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-vkkeyscanw
+        let shift_state = (scan as u16 >> 8) as u8;
+        Some(Modifiers {
+            shift: shift_state & 1 != 0,
+            control: shift_state & 2 != 0,
+            alt: shift_state & 4 != 0,
+            ..Default::default()
+        })
+    }
+}
+
+/// Whether the key only marks an accent to put on the next character in the current layout,
+/// like `^` on a French keyboard, instead of typing a character itself.
+pub(crate) fn is_dead_key(vk_code: u16) -> bool {
+    unsafe { MapVirtualKeyW(vk_code as u32, MAPVK_VK_TO_CHAR) }
+    &0x8000_0000 != 0
+}
+
+// https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input#scan-codes
+fn scan_code_for_physical_key(key: PhysicalKey) -> u32 {
+    match key {
+        PhysicalKey::Backquote => 0x29,
+        PhysicalKey::Minus => 0x0C,
+        PhysicalKey::Equal => 0x0D,
+        PhysicalKey::BracketLeft => 0x1A,
+        PhysicalKey::BracketRight => 0x1B,
+        PhysicalKey::Backslash => 0x2B,
+        PhysicalKey::Semicolon => 0x27,
+        PhysicalKey::Quote => 0x28,
+        PhysicalKey::Comma => 0x33,
+        PhysicalKey::Period => 0x34,
+        PhysicalKey::Slash => 0x35,
+        PhysicalKey::IntlBackslash => 0x56,
+    }
+}
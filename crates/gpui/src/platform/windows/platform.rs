@@ -2,9 +2,8 @@
 #![allow(unused_variables)]
 
 use std::{
-    cell::{Cell, RefCell},
-    ffi::{c_void, OsString},
-    os::windows::ffi::{OsStrExt, OsStringExt},
+    cell::RefCell,
+    ffi::c_void,
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
@@ -13,7 +12,8 @@ use std::{
 use ::util::ResultExt;
 use anyhow::{anyhow, Context, Result};
 use clipboard_win::{get_clipboard_string, set_clipboard_string};
-use futures::channel::oneshot::{self, Receiver};
+use collections::HashMap;
+use futures::channel::oneshot::Receiver;
 use itertools::Itertools;
 use parking_lot::RwLock;
 use smallvec::SmallVec;
@@ -24,11 +24,18 @@ use windows::{
         Foundation::*,
         Graphics::Gdi::*,
         Security::Credentials::*,
-        System::{Com::*, LibraryLoader::*, Ole::*, SystemInformation::*, Threading::*, Time::*},
-        UI::{Input::KeyboardAndMouse::*, Shell::*, WindowsAndMessaging::*},
+        System::{
+            Com::*, LibraryLoader::*, Ole::*, StationsAndDesktops::*, SystemInformation::*,
+            Threading::*, Time::*,
+        },
+        UI::{
+            Accessibility::*, HiDpi::*, Input::KeyboardAndMouse::*, Shell::*,
+            WindowsAndMessaging::*,
+        },
     },
     UI::{
         Color,
+        Notifications::ToastNotification,
         ViewManagement::{UIColorType, UISettings},
     },
 };
@@ -37,9 +44,13 @@ use crate::*;
 
 pub(crate) struct WindowsPlatform {
     state: RefCell<WindowsPlatformState>,
-    raw_window_handles: RwLock<SmallVec<[HWND; 4]>>,
+    // Windows are tracked by their ID, the window handle is only looked up when a message is
+    // sent to the window.
+    raw_window_handles: RwLock<SmallVec<[(WindowId, HWND); 4]>>,
     // The below members will never change throughout the entire lifecycle of the app.
     icon: HICON,
+    sandbox: SandboxCapabilities,
+    main_thread_id: u32,
     background_executor: BackgroundExecutor,
     foreground_executor: ForegroundExecutor,
     text_system: Arc<dyn PlatformTextSystem>,
@@ -49,26 +60,72 @@ pub(crate) struct WindowsPlatformState {
     callbacks: PlatformCallbacks,
     // NOTE: standard cursor handles don't need to close.
     pub(crate) current_cursor: HCURSOR,
+    secure_desktop_active: bool,
+    app_active: bool,
+    accent_color: Option<SystemAccentColor>,
+    default_handler_ids: DefaultHandlerIds,
+    displays: Vec<WindowsDisplay>,
+    suspended: bool,
+    session_locked: bool,
+    tray_icon: Option<TrayIcon>,
+    app_user_model_id: Option<String>,
+    // toasts only report clicks while they're alive, so they're kept until clicked or replaced
+    notifications: HashMap<SharedString, ToastNotification>,
 }
 
 #[derive(Default)]
 struct PlatformCallbacks {
     open_urls: Option<Box<dyn FnMut(Vec<String>)>>,
-    quit: Option<Box<dyn FnMut()>>,
+    // shared with the window procedure, which runs it when the session ends
+    quit: QuitCallback,
     reopen: Option<Box<dyn FnMut()>>,
     app_menu_action: Option<Box<dyn FnMut(&dyn Action)>>,
     will_open_app_menu: Option<Box<dyn FnMut()>>,
     validate_app_menu_command: Option<Box<dyn FnMut(&dyn Action) -> bool>>,
+    secure_desktop_change: Option<Box<dyn FnMut(bool)>>,
+    session_lock_change: Option<Box<dyn FnMut(bool)>>,
+    app_active_status_change: Option<Box<dyn FnMut(bool)>>,
+    accent_color_change: Option<Box<dyn FnMut()>>,
+    default_handlers_change: Option<Box<dyn FnMut()>>,
+    displays_change: Option<Box<dyn FnMut()>>,
+    keyboard_layout_change: Option<Box<dyn FnMut()>>,
+    tray_icon_click: Option<Box<dyn FnMut()>>,
+    notification_activated: Option<Box<dyn FnMut(SharedString, Option<usize>)>>,
 }
 
 impl WindowsPlatformState {
-    fn new() -> Self {
+    fn new(sandbox: &SandboxCapabilities) -> Self {
         let callbacks = PlatformCallbacks::default();
         let current_cursor = load_cursor(CursorStyle::Arrow);
+        let secure_desktop_active = false;
+        // the first window that is activated reports the app as active
+        let app_active = false;
+        let accent_color = system_accent_color().log_err();
+        let default_handler_ids = if sandbox.user_registry {
+            DefaultHandlerIds::current()
+        } else {
+            DefaultHandlerIds::default()
+        };
+        let displays = WindowsDisplay::all();
+        let suspended = false;
+        let session_locked = false;
+        let tray_icon = None;
+        let app_user_model_id = None;
+        let notifications = HashMap::default();
 
         Self {
             callbacks,
             current_cursor,
+            secure_desktop_active,
+            app_active,
+            accent_color,
+            default_handler_ids,
+            displays,
+            suspended,
+            session_locked,
+            tray_icon,
+            app_user_model_id,
+            notifications,
         }
     }
 }
@@ -89,13 +146,17 @@ impl WindowsPlatform {
             Arc::new(CosmicTextSystem::new()) as Arc<dyn PlatformTextSystem>
         };
         let icon = load_icon().unwrap_or_default();
-        let state = RefCell::new(WindowsPlatformState::new());
+        let sandbox = SandboxCapabilities::probe();
+        let state = RefCell::new(WindowsPlatformState::new(&sandbox));
         let raw_window_handles = RwLock::new(SmallVec::new());
+        let main_thread_id = unsafe { GetCurrentThreadId() };
 
         Self {
             state,
             raw_window_handles,
             icon,
+            sandbox,
+            main_thread_id,
             background_executor,
             foreground_executor,
             text_system,
@@ -103,7 +164,7 @@ impl WindowsPlatform {
     }
 
     fn redraw_all(&self) {
-        for handle in self.raw_window_handles.read().iter() {
+        for (_, handle) in self.raw_window_handles.read().iter() {
             unsafe {
                 RedrawWindow(
                     *handle,
@@ -121,8 +182,8 @@ impl WindowsPlatform {
         self.raw_window_handles
             .read()
             .iter()
-            .find(|entry| *entry == &hwnd)
-            .and_then(|hwnd| try_get_window_inner(*hwnd))
+            .find(|(_, handle)| *handle == hwnd)
+            .and_then(|(_, hwnd)| try_get_window_inner(*hwnd))
     }
 
     #[inline]
@@ -130,16 +191,217 @@ impl WindowsPlatform {
         self.raw_window_handles
             .read()
             .iter()
-            .for_each(|handle| unsafe {
+            .for_each(|(_, handle)| unsafe {
                 PostMessageW(*handle, message, wparam, lparam).log_err();
             });
     }
 
-    fn close_one_window(&self, target_window: HWND) -> bool {
+    fn update_secure_desktop_state(&self) {
+        let active = !is_input_desktop_accessible();
+        let mut lock = self.state.borrow_mut();
+        if lock.secure_desktop_active == active {
+            return;
+        }
+        lock.secure_desktop_active = active;
+        log::info!(
+            "secure desktop {}",
+            if active { "activated" } else { "deactivated" }
+        );
+        if let Some(mut callback) = lock.callbacks.secure_desktop_change.take() {
+            drop(lock);
+            callback(active);
+            self.state.borrow_mut().callbacks.secure_desktop_change = Some(callback);
+        }
+    }
+
+    fn update_session_lock_state(&self, locked: bool) {
+        let mut lock = self.state.borrow_mut();
+        if lock.session_locked == locked {
+            return;
+        }
+        lock.session_locked = locked;
+        log::info!("session {}", if locked { "locked" } else { "unlocked" });
+        if let Some(mut callback) = lock.callbacks.session_lock_change.take() {
+            drop(lock);
+            callback(locked);
+            self.state.borrow_mut().callbacks.session_lock_change = Some(callback);
+        }
+    }
+
+    fn update_app_active_state(&self, active: bool) {
+        let mut lock = self.state.borrow_mut();
+        if lock.app_active == active {
+            return;
+        }
+        lock.app_active = active;
+        if let Some(mut callback) = lock.callbacks.app_active_status_change.take() {
+            drop(lock);
+            callback(active);
+            self.state.borrow_mut().callbacks.app_active_status_change = Some(callback);
+        }
+    }
+
+    fn update_accent_color(&self) {
+        let accent_color = system_accent_color().log_err();
+        let mut lock = self.state.borrow_mut();
+        if lock.accent_color == accent_color {
+            return;
+        }
+        lock.accent_color = accent_color;
+        if let Some(mut callback) = lock.callbacks.accent_color_change.take() {
+            drop(lock);
+            callback();
+            self.state.borrow_mut().callbacks.accent_color_change = Some(callback);
+        }
+    }
+
+    fn update_default_handlers(&self) {
+        let default_handler_ids = DefaultHandlerIds::current();
+        let mut lock = self.state.borrow_mut();
+        if lock.default_handler_ids == default_handler_ids {
+            return;
+        }
+        lock.default_handler_ids = default_handler_ids;
+        if let Some(mut callback) = lock.callbacks.default_handlers_change.take() {
+            drop(lock);
+            callback();
+            self.state.borrow_mut().callbacks.default_handlers_change = Some(callback);
+        }
+    }
+
+    fn update_displays(&self) {
+        // every window is told about the change, and device changes aren't only about displays
+        let displays = WindowsDisplay::all();
+        let mut lock = self.state.borrow_mut();
+        if lock.displays == displays {
+            return;
+        }
+        log::info!("displays changed, {} connected", displays.len());
+        lock.displays = displays;
+        if let Some(mut callback) = lock.callbacks.displays_change.take() {
+            drop(lock);
+            callback();
+            self.state.borrow_mut().callbacks.displays_change = Some(callback);
+        }
+    }
+
+    fn update_keyboard_layout(&self) {
+        let mut lock = self.state.borrow_mut();
+        if let Some(mut callback) = lock.callbacks.keyboard_layout_change.take() {
+            drop(lock);
+            callback();
+            self.state.borrow_mut().callbacks.keyboard_layout_change = Some(callback);
+        }
+    }
+
+    // Nothing is drawn while the system sleeps, as the compositor stops and presenting frames
+    // would only fail. The swapchains may not survive it, so they are configured again on resume.
+    fn update_suspended_state(&self, suspended: bool, vsync_enabled: HANDLE) {
+        let mut lock = self.state.borrow_mut();
+        if lock.suspended == suspended {
+            return;
+        }
+        lock.suspended = suspended;
+        drop(lock);
+        log::info!("system {}", if suspended { "suspended" } else { "resumed" });
+        if suspended {
+            unsafe { ResetEvent(vsync_enabled) }.log_err();
+            return;
+        }
+        for (_, handle) in self.raw_window_handles.read().iter() {
+            if let Some(state_ptr) = try_get_window_inner(*handle) {
+                state_ptr.state.borrow_mut().renderer.reset_surface();
+            }
+        }
+        unsafe { SetEvent(vsync_enabled) }.log_err();
+    }
+
+    fn handle_tray_icon_event(&self, event: u32, position: LPARAM) {
+        match event {
+            TRAY_ICON_RECREATED => {
+                if let Some(tray_icon) = &self.state.borrow().tray_icon {
+                    tray_icon.add().log_err();
+                }
+            }
+            NIN_SELECT | NIN_KEYSELECT => {
+                let mut lock = self.state.borrow_mut();
+                if let Some(mut callback) = lock.callbacks.tray_icon_click.take() {
+                    drop(lock);
+                    callback();
+                    self.state.borrow_mut().callbacks.tray_icon_click = Some(callback);
+                }
+            }
+            WM_CONTEXTMENU => {
+                let position = POINT {
+                    x: position.0 as i16 as i32,
+                    y: (position.0 >> 16) as i16 as i32,
+                };
+                self.open_tray_menu(position);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_notification_closed(&self, closed: NotificationClosed) {
+        let mut lock = self.state.borrow_mut();
+        // a toast that was replaced under the same id is kept
+        if lock
+            .notifications
+            .get(&closed.id)
+            .is_some_and(|toast| toast.as_raw() as usize == closed.toast)
+        {
+            lock.notifications.remove(&closed.id);
+        }
+    }
+
+    fn handle_notification_activated(&self, activation: NotificationActivation) {
+        let mut lock = self.state.borrow_mut();
+        lock.notifications.remove(&activation.id);
+        if let Some(mut callback) = lock.callbacks.notification_activated.take() {
+            drop(lock);
+            callback(activation.id, activation.action);
+            self.state.borrow_mut().callbacks.notification_activated = Some(callback);
+        }
+    }
+
+    // the menu runs a modal loop, which can get back to the platform, so nothing is borrowed
+    // while it's shown
+    fn open_tray_menu(&self, position: POINT) {
+        let Some((hwnd, menu, actions)) =
+            self.state.borrow().tray_icon.as_ref().map(TrayIcon::menu)
+        else {
+            return;
+        };
+        let will_open = self.state.borrow_mut().callbacks.will_open_app_menu.take();
+        if let Some(mut callback) = will_open {
+            callback();
+            self.state.borrow_mut().callbacks.will_open_app_menu = Some(callback);
+        }
+        let mut validate = self
+            .state
+            .borrow_mut()
+            .callbacks
+            .validate_app_menu_command
+            .take();
+        let picked = show_tray_menu(hwnd, menu, &actions, position, |action| {
+            validate.as_mut().map_or(true, |validate| validate(action))
+        });
+        self.state.borrow_mut().callbacks.validate_app_menu_command = validate;
+        let Some(action) = picked.and_then(|index| actions.get(index)) else {
+            return;
+        };
+        let callback = self.state.borrow_mut().callbacks.app_menu_action.take();
+        if let Some(mut callback) = callback {
+            callback(action.as_ref());
+            self.state.borrow_mut().callbacks.app_menu_action = Some(callback);
+        }
+    }
+
+    fn close_one_window(&self, target_window: WindowId) -> bool {
         let mut lock = self.raw_window_handles.write();
         let index = lock
             .iter()
-            .position(|handle| *handle == target_window)
+            .position(|(window_id, _)| *window_id == target_window)
             .unwrap();
         lock.remove(index);
 
@@ -162,8 +424,41 @@ impl Platform for WindowsPlatform {
 
     fn run(&self, on_finish_launching: Box<dyn 'static + FnOnce()>) {
         on_finish_launching();
+        set_session_end_callback(self.state.borrow().callbacks.quit.clone());
         let vsync_event = unsafe { Owned::new(CreateEventW(None, false, false, None).unwrap()) };
-        begin_vsync(*vsync_event);
+        let vsync_enabled = unsafe { Owned::new(CreateEventW(None, true, true, None).unwrap()) };
+        begin_vsync(*vsync_event, *vsync_enabled);
+        let watcher_stop_event =
+            unsafe { Owned::new(CreateEventW(None, true, false, None).unwrap()) };
+        let default_handlers_watcher = self
+            .sandbox
+            .user_registry
+            .then(|| watch_default_handlers(unsafe { GetCurrentThreadId() }, *watcher_stop_event));
+        // switches are reported to this thread while it retrieves its messages
+        let desktop_switch_hook = self
+            .sandbox
+            .input_desktop
+            .then(|| unsafe {
+                SetWinEventHook(
+                    EVENT_SYSTEM_DESKTOPSWITCH,
+                    EVENT_SYSTEM_DESKTOPSWITCH,
+                    None,
+                    Some(desktop_switch_proc),
+                    0,
+                    0,
+                    WINEVENT_OUTOFCONTEXT,
+                )
+            })
+            .filter(|hook| {
+                if hook.is_invalid() {
+                    log::error!("unable to watch for secure desktop switches");
+                }
+                !hook.is_invalid()
+            });
+        if desktop_switch_hook.is_some() {
+            // the app may have been launched from the secure desktop, like by a UAC prompt
+            self.update_secure_desktop_state();
+        }
         'a: loop {
             let wait_result = unsafe {
                 MsgWaitForMultipleObjects(Some(&[*vsync_event]), false, INFINITE, QS_ALLINPUT)
@@ -172,7 +467,14 @@ impl Platform for WindowsPlatform {
             match wait_result {
                 // compositor clock ticked so we should draw a frame
                 WAIT_EVENT(0) => {
-                    self.redraw_all();
+                    // nothing we draw is visible while the secure desktop is shown,
+                    // so rendering is paused until the user switches back.
+                    if !self.state.borrow().secure_desktop_active {
+                        // input that arrived in the meantime is handled before painting,
+                        // so typing keeps up while frames are expensive to draw
+                        dispatch_pending_input();
+                        self.redraw_all();
+                    }
                 }
                 // Windows thread messages are posted
                 WAIT_EVENT(1) => {
@@ -182,16 +484,52 @@ impl Platform for WindowsPlatform {
                             match msg.message {
                                 WM_QUIT => break 'a,
                                 CLOSE_ONE_WINDOW => {
-                                    if self.close_one_window(HWND(msg.lParam.0)) {
+                                    let window_id = WindowId::from(msg.lParam.0 as u64);
+                                    // the app keeps running in the tray once its windows are closed
+                                    if self.close_one_window(window_id)
+                                        && self.state.borrow().tray_icon.is_none()
+                                    {
                                         break 'a;
                                     }
                                 }
-                                _ => {
-                                    // todo(windows)
-                                    // crate `windows 0.56` reports true as Err
-                                    TranslateMessage(&msg).as_bool();
-                                    DispatchMessageW(&msg);
+                                APP_ACTIVE_CHANGED => {
+                                    self.update_app_active_state(msg.wParam.0 != 0);
+                                }
+                                ACCENT_COLOR_CHANGED => {
+                                    self.update_accent_color();
+                                }
+                                DEFAULT_HANDLERS_CHANGED => {
+                                    self.update_default_handlers();
+                                }
+                                DISPLAYS_CHANGED => {
+                                    self.update_displays();
+                                }
+                                KEYBOARD_LAYOUT_CHANGED => {
+                                    self.update_keyboard_layout();
+                                }
+                                SESSION_LOCK_CHANGED => {
+                                    self.update_session_lock_state(msg.wParam.0 != 0);
+                                }
+                                TRAY_ICON_EVENT => {
+                                    self.handle_tray_icon_event(msg.wParam.0 as u32, msg.lParam);
+                                }
+                                NOTIFICATION_ACTIVATED => {
+                                    let activation =
+                                        Box::from_raw(msg.lParam.0 as *mut NotificationActivation);
+                                    self.handle_notification_activated(*activation);
+                                }
+                                NOTIFICATION_CLOSED => {
+                                    let closed =
+                                        Box::from_raw(msg.lParam.0 as *mut NotificationClosed);
+                                    self.handle_notification_closed(*closed);
+                                }
+                                SYSTEM_SUSPEND_CHANGED => {
+                                    self.update_suspended_state(msg.wParam.0 != 0, *vsync_enabled);
                                 }
+                                DESKTOP_SWITCHED => {
+                                    self.update_secure_desktop_state();
+                                }
+                                _ => dispatch_message(&msg),
                             }
                         }
                     }
@@ -203,7 +541,20 @@ impl Platform for WindowsPlatform {
             }
         }
 
-        if let Some(ref mut callback) = self.state.borrow_mut().callbacks.quit {
+        if let Some(desktop_switch_hook) = desktop_switch_hook {
+            unsafe { UnhookWinEvent(desktop_switch_hook) }
+                .ok()
+                .log_err();
+        }
+        if let Some(watcher) = default_handlers_watcher {
+            unsafe { SetEvent(*watcher_stop_event) }.log_err();
+            watcher.join().ok();
+        }
+
+        let quit = self.state.borrow().callbacks.quit.clone();
+        // taken, as it has already run if the session ended
+        let callback = quit.borrow_mut().take();
+        if let Some(mut callback) = callback {
             callback();
         }
     }
@@ -247,12 +598,22 @@ impl Platform for WindowsPlatform {
         }
     }
 
-    // todo(windows)
-    fn activate(&self, ignoring_other_apps: bool) {}
+    /// Shows the windows hidden with [`Self::hide`] again.
+    fn activate(&self, _ignoring_other_apps: bool) {
+        for (_, handle) in self.raw_window_handles.read().iter() {
+            unsafe {
+                if !IsWindowVisible(*handle).as_bool() {
+                    let _ = ShowWindow(*handle, SW_SHOW);
+                }
+            }
+        }
+    }
 
-    // todo(windows)
+    /// Hides every window along with its taskbar button, as apps in the notification area do.
     fn hide(&self) {
-        unimplemented!()
+        for (_, handle) in self.raw_window_handles.read().iter() {
+            let _ = unsafe { ShowWindow(*handle, SW_HIDE) };
+        }
     }
 
     // todo(windows)
@@ -273,6 +634,10 @@ impl Platform for WindowsPlatform {
         WindowsDisplay::primary_monitor().map(|display| Rc::new(display) as Rc<dyn PlatformDisplay>)
     }
 
+    fn on_displays_change(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().callbacks.displays_change = Some(callback);
+    }
+
     fn active_window(&self) -> Option<AnyWindowHandle> {
         let active_window_hwnd = unsafe { GetActiveWindow() };
         self.try_get_windows_inner_from_hwnd(active_window_hwnd)
@@ -293,8 +658,10 @@ impl Platform for WindowsPlatform {
             lock.current_cursor,
         );
         drop(lock);
-        let handle = window.get_raw_handle();
-        self.raw_window_handles.write().push(handle);
+        let raw_handle = window.get_raw_handle();
+        self.raw_window_handles
+            .write()
+            .push((handle.window_id(), raw_handle));
 
         Ok(Box::new(window))
     }
@@ -319,102 +686,25 @@ impl Platform for WindowsPlatform {
         self.state.borrow_mut().callbacks.open_urls = Some(callback);
     }
 
-    fn prompt_for_paths(&self, options: PathPromptOptions) -> Receiver<Option<Vec<PathBuf>>> {
-        let (tx, rx) = oneshot::channel();
-
-        self.foreground_executor()
-            .spawn(async move {
-                let tx = Cell::new(Some(tx));
-
-                // create file open dialog
-                let folder_dialog: IFileOpenDialog = unsafe {
-                    CoCreateInstance::<std::option::Option<&IUnknown>, IFileOpenDialog>(
-                        &FileOpenDialog,
-                        None,
-                        CLSCTX_ALL,
-                    )
-                    .unwrap()
-                };
-
-                // dialog options
-                let mut dialog_options: FILEOPENDIALOGOPTIONS = FOS_FILEMUSTEXIST;
-                if options.multiple {
-                    dialog_options |= FOS_ALLOWMULTISELECT;
-                }
-                if options.directories {
-                    dialog_options |= FOS_PICKFOLDERS;
-                }
-
-                unsafe {
-                    folder_dialog.SetOptions(dialog_options).unwrap();
-                    folder_dialog
-                        .SetTitle(&HSTRING::from(OsString::from("Select a folder")))
-                        .unwrap();
-                }
-
-                let hr = unsafe { folder_dialog.Show(None) };
-
-                if hr.is_err() {
-                    if hr.unwrap_err().code() == HRESULT(0x800704C7u32 as i32) {
-                        // user canceled error
-                        if let Some(tx) = tx.take() {
-                            tx.send(None).unwrap();
-                        }
-                        return;
-                    }
-                }
-
-                let mut results = unsafe { folder_dialog.GetResults().unwrap() };
-
-                let mut paths: Vec<PathBuf> = Vec::new();
-                for i in 0..unsafe { results.GetCount().unwrap() } {
-                    let mut item: IShellItem = unsafe { results.GetItemAt(i).unwrap() };
-                    let mut path: PWSTR =
-                        unsafe { item.GetDisplayName(SIGDN_FILESYSPATH).unwrap() };
-                    let mut path_os_string = OsString::from_wide(unsafe { path.as_wide() });
-
-                    paths.push(PathBuf::from(path_os_string));
-                }
-
-                if let Some(tx) = tx.take() {
-                    if paths.len() == 0 {
-                        tx.send(None).unwrap();
-                    } else {
-                        tx.send(Some(paths)).unwrap();
-                    }
-                }
-            })
-            .detach();
-
-        rx
+    fn prompt_for_paths(
+        &self,
+        options: PathPromptOptions,
+        filters: Vec<PathPromptFilter>,
+    ) -> Receiver<Option<Vec<PathBuf>>> {
+        prompt_for_paths_dialog(options, filters)
     }
 
-    fn prompt_for_new_path(&self, directory: &Path) -> Receiver<Option<PathBuf>> {
-        let directory = directory.to_owned();
-        let (tx, rx) = oneshot::channel();
-        self.foreground_executor()
-            .spawn(async move {
-                unsafe {
-                    let Ok(dialog) = show_savefile_dialog(directory) else {
-                        let _ = tx.send(None);
-                        return;
-                    };
-                    let Ok(_) = dialog.Show(None) else {
-                        let _ = tx.send(None); // user cancel
-                        return;
-                    };
-                    if let Ok(shell_item) = dialog.GetResult() {
-                        if let Ok(file) = shell_item.GetDisplayName(SIGDN_FILESYSPATH) {
-                            let _ = tx.send(Some(PathBuf::from(file.to_string().unwrap())));
-                            return;
-                        }
-                    }
-                    let _ = tx.send(None);
-                }
-            })
-            .detach();
-
-        rx
+    fn prompt_for_new_path(
+        &self,
+        directory: &Path,
+        suggested_name: Option<&str>,
+        filters: Vec<PathPromptFilter>,
+    ) -> Receiver<Option<PathBuf>> {
+        prompt_for_new_path_dialog(
+            directory.to_owned(),
+            suggested_name.map(ToString::to_string),
+            filters,
+        )
     }
 
     fn reveal_path(&self, path: &Path) {
@@ -436,7 +726,7 @@ impl Platform for WindowsPlatform {
     }
 
     fn on_quit(&self, callback: Box<dyn FnMut()>) {
-        self.state.borrow_mut().callbacks.quit = Some(callback);
+        *self.state.borrow().callbacks.quit.borrow_mut() = Some(callback);
     }
 
     fn on_reopen(&self, callback: Box<dyn FnMut()>) {
@@ -496,10 +786,28 @@ impl Platform for WindowsPlatform {
         }
     }
 
+    fn register_custom_cursor(&self, images: Vec<CustomCursorImage>) -> Result<CustomCursorId> {
+        register_custom_cursor(images)
+    }
+
     fn should_auto_hide_scrollbars(&self) -> bool {
         should_auto_hide_scrollbars().log_err().unwrap_or(false)
     }
 
+    fn scrollbar_width(&self) -> Option<Pixels> {
+        // the metrics at the default DPI are already in logical pixels
+        let width = unsafe { GetSystemMetricsForDpi(SM_CXVSCROLL, USER_DEFAULT_SCREEN_DPI) };
+        (width > 0).then(|| px(width as f32))
+    }
+
+    fn keyboard_mapper(&self) -> Box<dyn KeyboardMapper> {
+        Box::new(WindowsKeyboardMapper)
+    }
+
+    fn on_keyboard_layout_change(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().callbacks.keyboard_layout_change = Some(callback);
+    }
+
     fn write_to_clipboard(&self, item: ClipboardItem) {
         if item.text.len() > 0 {
             set_clipboard_string(item.text()).unwrap();
@@ -585,6 +893,110 @@ impl Platform for WindowsPlatform {
     fn register_url_scheme(&self, _: &str) -> Task<anyhow::Result<()>> {
         Task::ready(Err(anyhow!("register_url_scheme unimplemented")))
     }
+
+    fn on_secure_desktop_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.state.borrow_mut().callbacks.secure_desktop_change = Some(callback);
+    }
+
+    fn is_secure_desktop_active(&self) -> bool {
+        self.state.borrow().secure_desktop_active
+    }
+
+    fn on_session_lock_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.state.borrow_mut().callbacks.session_lock_change = Some(callback);
+    }
+
+    fn is_session_locked(&self) -> bool {
+        self.state.borrow().session_locked
+    }
+
+    fn set_jump_list(&self, jump_list: JumpList) {
+        update_jump_list(&jump_list).log_err();
+    }
+
+    fn set_tray_icon(&self, tooltip: &str, menu: Vec<MenuItem>) {
+        let mut lock = self.state.borrow_mut();
+        if lock.tray_icon.is_none() {
+            lock.tray_icon = TrayIcon::new(self.icon).log_err();
+        }
+        if let Some(tray_icon) = lock.tray_icon.as_mut() {
+            tray_icon.update(tooltip, menu);
+        }
+    }
+
+    fn remove_tray_icon(&self) {
+        let Some(tray_icon) = self.state.borrow_mut().tray_icon.take() else {
+            return;
+        };
+        drop(tray_icon);
+        // the app was only kept running by the icon once its last window closed
+        if self.raw_window_handles.read().is_empty() {
+            self.quit();
+        }
+    }
+
+    fn on_tray_icon_click(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().callbacks.tray_icon_click = Some(callback);
+    }
+
+    fn set_app_user_model_id(&self, id: &str) {
+        set_process_app_user_model_id(id).log_err();
+        self.state.borrow_mut().app_user_model_id = Some(id.to_string());
+    }
+
+    fn show_notification(&self, notification: SystemNotification) -> Result<()> {
+        let mut lock = self.state.borrow_mut();
+        let app_user_model_id = lock
+            .app_user_model_id
+            .as_deref()
+            .context("notifications need an AppUserModelID")?;
+        let toast = show_toast(app_user_model_id, &notification, self.main_thread_id)?;
+        lock.notifications.insert(notification.id, toast);
+        Ok(())
+    }
+
+    fn on_notification_activated(&self, callback: Box<dyn FnMut(SharedString, Option<usize>)>) {
+        self.state.borrow_mut().callbacks.notification_activated = Some(callback);
+    }
+
+    fn on_app_active_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.state.borrow_mut().callbacks.app_active_status_change = Some(callback);
+    }
+
+    fn is_app_active(&self) -> bool {
+        self.state.borrow().app_active
+    }
+
+    fn accent_color(&self) -> Option<SystemAccentColor> {
+        self.state.borrow().accent_color
+    }
+
+    fn on_accent_color_change(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().callbacks.accent_color_change = Some(callback);
+    }
+
+    fn default_handler(&self, kind: DefaultHandlerKind) -> Option<DefaultHandler> {
+        if !self.sandbox.user_registry {
+            return None;
+        }
+        default_handler(kind)
+    }
+
+    fn on_default_handlers_change(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().callbacks.default_handlers_change = Some(callback);
+    }
+
+    fn degraded_features(&self) -> Vec<DegradedFeature> {
+        self.sandbox.degraded_features()
+    }
+
+    fn is_loopback_available(&self) -> bool {
+        self.sandbox.loopback
+    }
+
+    fn timer_resolution_statistics(&self) -> TimerResolutionStatistics {
+        timer_resolution_statistics()
+    }
 }
 
 impl Drop for WindowsPlatform {
@@ -630,32 +1042,29 @@ fn open_target_in_explorer(target: &str) {
     }
 }
 
-unsafe fn show_savefile_dialog(directory: PathBuf) -> Result<IFileSaveDialog> {
-    let dialog: IFileSaveDialog = CoCreateInstance(&FileSaveDialog, None, CLSCTX_ALL)?;
-    let bind_context = CreateBindCtx(0)?;
-    let Ok(full_path) = directory.canonicalize() else {
-        return Ok(dialog);
-    };
-    let dir_str = full_path.into_os_string();
-    if dir_str.is_empty() {
-        return Ok(dialog);
-    }
-    let dir_vec = dir_str.encode_wide().collect_vec();
-    let ret = SHCreateItemFromParsingName(PCWSTR::from_raw(dir_vec.as_ptr()), &bind_context)
-        .inspect_err(|e| log::error!("unable to create IShellItem: {}", e));
-    if ret.is_ok() {
-        let dir_shell_item: IShellItem = ret.unwrap();
-        let _ = dialog
-            .SetFolder(&dir_shell_item)
-            .inspect_err(|e| log::error!("unable to set folder for save file dialog: {}", e));
+fn dispatch_pending_input() {
+    let mut msg = MSG::default();
+    unsafe {
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE | PM_QS_INPUT).as_bool() {
+            dispatch_message(&msg);
+        }
     }
+}
 
-    Ok(dialog)
+fn dispatch_message(msg: &MSG) {
+    unsafe {
+        // todo(windows)
+        // crate `windows 0.56` reports true as Err
+        TranslateMessage(msg).as_bool();
+        DispatchMessageW(msg);
+    }
 }
 
-fn begin_vsync(vsync_evnet: HANDLE) {
+fn begin_vsync(vsync_evnet: HANDLE, vsync_enabled: HANDLE) {
     std::thread::spawn(move || unsafe {
         loop {
+            // `DwmFlush` returns right away while the compositor is stopped
+            WaitForSingleObject(vsync_enabled, INFINITE);
             windows::Win32::Graphics::Dwm::DwmFlush().log_err();
             SetEvent(vsync_evnet).log_err();
         }
@@ -680,7 +1089,7 @@ fn load_icon() -> Result<HICON> {
 
 // https://learn.microsoft.com/en-us/windows/apps/desktop/modernize/apply-windows-themes
 #[inline]
-fn system_appearance() -> Result<WindowAppearance> {
+pub(crate) fn system_appearance() -> Result<WindowAppearance> {
     let ui_settings = UISettings::new()?;
     let foreground_color = ui_settings.GetColorValue(UIColorType::Foreground)?;
     // If the foreground is light, then is_color_light will evaluate to true,
@@ -697,6 +1106,59 @@ fn is_color_light(color: &Color) -> bool {
     ((5 * color.G as u32) + (2 * color.R as u32) + color.B as u32) > (8 * 128)
 }
 
+// https://learn.microsoft.com/en-us/windows/apps/design/style/color#accent-color
+fn system_accent_color() -> Result<SystemAccentColor> {
+    let ui_settings = UISettings::new()?;
+    let color = |color_type| -> Result<Hsla> {
+        let color = ui_settings.GetColorValue(color_type)?;
+        Ok(Rgba {
+            r: color.R as f32 / 255.0,
+            g: color.G as f32 / 255.0,
+            b: color.B as f32 / 255.0,
+            a: color.A as f32 / 255.0,
+        }
+        .into())
+    };
+    Ok(SystemAccentColor {
+        accent: color(UIColorType::Accent)?,
+        light: [
+            color(UIColorType::AccentLight1)?,
+            color(UIColorType::AccentLight2)?,
+            color(UIColorType::AccentLight3)?,
+        ],
+        dark: [
+            color(UIColorType::AccentDark1)?,
+            color(UIColorType::AccentDark2)?,
+            color(UIColorType::AccentDark3)?,
+        ],
+    })
+}
+
+// The secure desktop (UAC prompts, the ctrl+alt+del screen and the lock screen) can't be
+// opened by regular processes, so failing to open the input desktop means it is active.
+pub(crate) fn is_input_desktop_accessible() -> bool {
+    match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) } {
+        Ok(desktop) => {
+            unsafe { CloseDesktop(desktop) }.log_err();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+unsafe extern "system" fn desktop_switch_proc(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _object_id: i32,
+    _child_id: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    // the hook has no way to reach the platform, which handles the switch with its messages
+    PostMessageW(None, DESKTOP_SWITCHED, None, None).log_err();
+}
+
 #[inline]
 fn should_auto_hide_scrollbars() -> Result<bool> {
     let ui_settings = UISettings::new()?;
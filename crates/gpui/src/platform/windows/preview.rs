@@ -0,0 +1,266 @@
+use std::{ffi::c_void, path::Path, sync::Arc};
+
+use ::util::ResultExt;
+use anyhow::{anyhow, Context, Result};
+use image::RgbaImage;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        Storage::FileSystem::FILE_ATTRIBUTE_NORMAL,
+        System::Com::*,
+        UI::{
+            Shell::PropertiesSystem::{IInitializeWithFile, IInitializeWithStream},
+            Shell::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+
+use crate::*;
+
+// https://learn.microsoft.com/en-us/windows/win32/shell/how-to-register-a-preview-handler
+const PREVIEW_HANDLER_SHELL_EXTENSION: PCWSTR = w!("{8895b1c6-b41f-4c1c-a562-0d564250836f}");
+
+/// Hosts the preview handler the shell has registered for a file type (PDF, Office
+/// documents, images, ...) inside a child window, for files GPUI can't render itself.
+///
+/// The preview is unloaded and its window destroyed when this is dropped.
+pub struct NativePreview {
+    host: HWND,
+    handler: IPreviewHandler,
+}
+
+impl NativePreview {
+    pub(crate) fn new(
+        parent: HWND,
+        path: &Path,
+        bounds: Bounds<Pixels>,
+        scale_factor: f32,
+    ) -> Result<Self> {
+        let clsid = preview_handler_clsid(path)?;
+        let handler: IPreviewHandler =
+            unsafe { CoCreateInstance(&clsid, None, CLSCTX_INPROC_SERVER | CLSCTX_LOCAL_SERVER) }
+                .context("unable to create preview handler")?;
+        initialize_handler(&handler, path)?;
+
+        let bounds = bounds.to_device_pixels(scale_factor);
+        let host = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                w!("STATIC"),
+                None,
+                WS_CHILD | WS_VISIBLE | WS_CLIPCHILDREN,
+                bounds.left().0,
+                bounds.top().0,
+                bounds.size.width.0,
+                bounds.size.height.0,
+                parent,
+                None,
+                None,
+                None,
+            )
+        };
+        if host.0 == 0 {
+            return Err(anyhow!(
+                "unable to create preview window: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let preview = Self { host, handler };
+        let rect = preview.client_rect();
+        unsafe {
+            preview.handler.SetWindow(host, &rect)?;
+            preview.handler.DoPreview()?;
+        }
+        Ok(preview)
+    }
+
+    /// Moves and resizes the preview. The bounds are relative to the window's content area.
+    pub fn set_bounds(&self, bounds: Bounds<Pixels>, scale_factor: f32) {
+        let bounds = bounds.to_device_pixels(scale_factor);
+        unsafe {
+            SetWindowPos(
+                self.host,
+                HWND::default(),
+                bounds.left().0,
+                bounds.top().0,
+                bounds.size.width.0,
+                bounds.size.height.0,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        }
+        .log_err();
+        let rect = self.client_rect();
+        unsafe { self.handler.SetRect(&rect) }.log_err();
+    }
+
+    /// Shows or hides the preview without unloading it.
+    pub fn set_visible(&self, visible: bool) {
+        let command = if visible { SW_SHOWNA } else { SW_HIDE };
+        unsafe { ShowWindow(self.host, command) };
+    }
+
+    /// Moves the keyboard focus into the preview.
+    pub fn focus(&self) {
+        unsafe { self.handler.SetFocus() }.log_err();
+    }
+
+    fn client_rect(&self) -> RECT {
+        let mut rect = RECT::default();
+        unsafe { GetClientRect(self.host, &mut rect) }.log_err();
+        rect
+    }
+}
+
+/// Hands the file to the handler as a stream, which is all that many handlers support, like
+/// the ones Windows ships for PDFs, Office documents and media, or as a path otherwise.
+fn initialize_handler(handler: &IPreviewHandler, path: &Path) -> Result<()> {
+    let path = HSTRING::from(path.as_os_str());
+    if let Ok(initializer) = handler.cast::<IInitializeWithStream>() {
+        let stream = unsafe {
+            SHCreateStreamOnFileEx(
+                &path,
+                (STGM_READ | STGM_SHARE_DENY_NONE).0,
+                FILE_ATTRIBUTE_NORMAL.0,
+                FALSE,
+                None,
+            )
+        }
+        .context("unable to open the file to preview")?;
+        unsafe { initializer.Initialize(&stream, STGM_READ.0) }?;
+        return Ok(());
+    }
+    let initializer: IInitializeWithFile = handler
+        .cast()
+        .context("preview handler can't be initialized with a stream or a file")?;
+    unsafe { initializer.Initialize(&path, STGM_READ.0) }?;
+    Ok(())
+}
+
+impl Drop for NativePreview {
+    fn drop(&mut self) {
+        unsafe {
+            self.handler.Unload().log_err();
+            DestroyWindow(self.host).log_err();
+        }
+    }
+}
+
+fn preview_handler_clsid(path: &Path) -> Result<GUID> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .ok_or_else(|| anyhow!("{} has no file extension", path.display()))?;
+    let extension = HSTRING::from(format!(".{}", extension));
+    let mut buffer = [0u16; 64];
+    let mut length = buffer.len() as u32;
+    unsafe {
+        AssocQueryStringW(
+            ASSOCF_INIT_DEFAULTTOSTAR | ASSOCF_NOTRUNCATE,
+            ASSOCSTR_SHELLEXTENSION,
+            &extension,
+            PREVIEW_HANDLER_SHELL_EXTENSION,
+            PWSTR::from_raw(buffer.as_mut_ptr()),
+            &mut length,
+        )
+    }
+    .ok()
+    .with_context(|| format!("no preview handler registered for {}", extension))?;
+    Ok(unsafe { CLSIDFromString(PCWSTR::from_raw(buffer.as_ptr())) }?)
+}
+
+/// Returns the icon the shell displays for files of the given path's type.
+pub(crate) fn file_type_icon(path: &Path) -> Option<Arc<ImageData>> {
+    shell_icon(path, SHGFI_USEFILEATTRIBUTES)
+}
+
+/// Returns the icon the shell displays for the given file, such as an executable's own icon.
+pub(crate) fn file_icon(path: &Path) -> Option<Arc<ImageData>> {
+    shell_icon(path, SHGFI_FLAGS(0))
+}
+
+fn shell_icon(path: &Path, flags: SHGFI_FLAGS) -> Option<Arc<ImageData>> {
+    let mut info = SHFILEINFOW::default();
+    let result = unsafe {
+        SHGetFileInfoW(
+            &HSTRING::from(path.as_os_str()),
+            FILE_ATTRIBUTE_NORMAL,
+            Some(&mut info as *mut _),
+            std::mem::size_of::<SHFILEINFOW>() as u32,
+            SHGFI_ICON | SHGFI_LARGEICON | flags,
+        )
+    };
+    if result == 0 || info.hIcon.is_invalid() {
+        return None;
+    }
+    let image = icon_to_image(info.hIcon).log_err();
+    unsafe { DestroyIcon(info.hIcon) }.log_err();
+    image.map(|image| Arc::new(ImageData::new(image)))
+}
+
+fn icon_to_image(icon: HICON) -> Result<RgbaImage> {
+    let mut icon_info = ICONINFO::default();
+    unsafe { GetIconInfo(icon, &mut icon_info) }?;
+    let result = color_bitmap_to_image(icon_info.hbmColor);
+    unsafe {
+        DeleteObject(icon_info.hbmColor);
+        DeleteObject(icon_info.hbmMask);
+    }
+    result
+}
+
+// the pixels are kept in BGRA order, as `ImageData` expects
+fn color_bitmap_to_image(bitmap: HBITMAP) -> Result<RgbaImage> {
+    let mut bitmap_info = BITMAP::default();
+    let size = unsafe {
+        GetObjectW(
+            bitmap,
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut bitmap_info as *mut _ as *mut c_void),
+        )
+    };
+    if size == 0 {
+        return Err(anyhow!("icon has no color bitmap"));
+    }
+    let width = bitmap_info.bmWidth;
+    let height = bitmap_info.bmHeight;
+    let mut header = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            // a negative height requests a top-down bitmap
+            biHeight: -height,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    let lines = unsafe {
+        let dc = GetDC(None);
+        let lines = GetDIBits(
+            dc,
+            bitmap,
+            0,
+            height as u32,
+            Some(pixels.as_mut_ptr() as *mut c_void),
+            &mut header,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(None, dc);
+        lines
+    };
+    if lines == 0 {
+        return Err(anyhow!("unable to read icon pixels"));
+    }
+    // icons without an alpha channel report every pixel as fully transparent
+    if pixels.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 0xFF);
+    }
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or_else(|| anyhow!("invalid icon size"))
+}
@@ -0,0 +1,179 @@
+use std::ffi::c_void;
+
+use ::util::ResultExt;
+use windows::Win32::{
+    Foundation::*,
+    NetworkManagement::WindowsFirewall::NetworkIsolationGetAppContainerConfig,
+    Security::*,
+    System::{
+        Memory::{GetProcessHeap, HeapFree, HEAP_FLAGS},
+        Registry::*,
+        Threading::*,
+    },
+};
+
+use crate::*;
+
+/// What the process is allowed to access, probed once at startup so that features needing
+/// more than a restricted sandbox grants are turned off instead of failing over and over.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SandboxCapabilities {
+    /// Whether the process runs inside an AppContainer.
+    pub(crate) app_container: bool,
+    /// Whether the user's registry hive can be read and watched.
+    pub(crate) user_registry: bool,
+    /// Whether the input desktop can be opened, which secure desktop detection relies on.
+    pub(crate) input_desktop: bool,
+    /// Whether loopback ports of other processes can be connected to, which the single
+    /// instance check relies on.
+    pub(crate) loopback: bool,
+}
+
+impl SandboxCapabilities {
+    pub(crate) fn probe() -> Self {
+        let app_container = is_app_container();
+        let capabilities = Self {
+            app_container,
+            user_registry: can_read_user_registry(),
+            // outside of a sandbox, failing to open the input desktop means the secure
+            // desktop is showing, not that it can't ever be opened
+            input_desktop: !app_container || is_input_desktop_accessible(),
+            loopback: !app_container || is_loopback_exempt(),
+        };
+        for feature in capabilities.degraded_features() {
+            log::warn!("{} is unavailable: {}", feature.feature, feature.reason);
+        }
+        capabilities
+    }
+
+    pub(crate) fn degraded_features(&self) -> Vec<DegradedFeature> {
+        let mut features = Vec::new();
+        if !self.user_registry {
+            features.push(DegradedFeature {
+                feature: "default browser and terminal lookup".into(),
+                reason: "the user's registry hive can't be read".into(),
+            });
+        }
+        if !self.input_desktop {
+            features.push(DegradedFeature {
+                feature: "secure desktop detection".into(),
+                reason: "the input desktop can't be opened from an AppContainer".into(),
+            });
+        }
+        if !self.loopback {
+            features.push(DegradedFeature {
+                feature: "single instance check".into(),
+                reason: "loopback connections are blocked for the AppContainer".into(),
+            });
+        }
+        features
+    }
+}
+
+fn is_app_container() -> bool {
+    let mut token = HANDLE::default();
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) }
+        .log_err()
+        .is_none()
+    {
+        return false;
+    }
+    let mut is_app_container = 0u32;
+    let mut size = 0u32;
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenIsAppContainer,
+            Some(&mut is_app_container as *mut u32 as _),
+            std::mem::size_of::<u32>() as u32,
+            &mut size,
+        )
+    };
+    unsafe { CloseHandle(token) }.log_err();
+    result.log_err().is_some() && is_app_container != 0
+}
+
+fn can_read_user_registry() -> bool {
+    let mut key = HKEY::default();
+    let result = unsafe {
+        RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            URL_ASSOCIATIONS_KEY,
+            0,
+            KEY_READ | KEY_NOTIFY,
+            &mut key,
+        )
+    };
+    if result.is_err() {
+        return false;
+    }
+    unsafe { RegCloseKey(key) }.ok().log_err();
+    true
+}
+
+/// AppContainers can't connect to loopback ports of other processes, unless they were exempted
+/// from loopback isolation, like with `CheckNetIsolation LoopbackExempt`.
+fn is_loopback_exempt() -> bool {
+    let Some(sid) = app_container_sid() else {
+        return false;
+    };
+    let mut count = 0u32;
+    let mut exempt_sids: *mut SID_AND_ATTRIBUTES = std::ptr::null_mut();
+    let result = unsafe { NetworkIsolationGetAppContainerConfig(&mut count, &mut exempt_sids) };
+    if result != ERROR_SUCCESS.0 {
+        log::error!("unable to read the loopback exemptions: {}", result);
+        return false;
+    }
+    if exempt_sids.is_null() {
+        return false;
+    }
+    let exempt_sids = unsafe { std::slice::from_raw_parts(exempt_sids, count as usize) };
+    let exempt = exempt_sids
+        .iter()
+        .any(|exempt_sid| sid_bytes(exempt_sid.Sid) == sid);
+    // the list and its SIDs are allocated on the process heap
+    if let Some(heap) = unsafe { GetProcessHeap() }.log_err() {
+        for exempt_sid in exempt_sids {
+            unsafe { HeapFree(heap, HEAP_FLAGS(0), Some(exempt_sid.Sid.0 as *const c_void)) }
+                .log_err();
+        }
+        unsafe {
+            HeapFree(
+                heap,
+                HEAP_FLAGS(0),
+                Some(exempt_sids.as_ptr() as *const c_void),
+            )
+        }
+        .log_err();
+    }
+    exempt
+}
+
+fn app_container_sid() -> Option<Vec<u8>> {
+    let mut token = HANDLE::default();
+    unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) }.log_err()?;
+    // the first call only reports the size of the information
+    let mut size = 0u32;
+    unsafe { GetTokenInformation(token, TokenAppContainerSid, None, 0, &mut size) }.ok();
+    // aligned for the pointer the information starts with
+    let mut buffer = vec![0usize; (size as usize).div_ceil(std::mem::size_of::<usize>())];
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenAppContainerSid,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            size,
+            &mut size,
+        )
+    };
+    unsafe { CloseHandle(token) }.log_err();
+    result.log_err()?;
+    let information = unsafe { &*(buffer.as_ptr() as *const TOKEN_APPCONTAINER_INFORMATION) };
+    let sid = information.TokenAppContainer;
+    (!sid.0.is_null()).then(|| sid_bytes(sid))
+}
+
+fn sid_bytes(sid: PSID) -> Vec<u8> {
+    let len = unsafe { GetLengthSid(sid) } as usize;
+    unsafe { std::slice::from_raw_parts(sid.0 as *const u8, len) }.to_vec()
+}
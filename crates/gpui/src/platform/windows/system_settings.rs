@@ -1,16 +1,30 @@
 use std::ffi::{c_uint, c_void};
 
 use util::ResultExt;
-use windows::Win32::UI::WindowsAndMessaging::{
-    SystemParametersInfoW, SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES,
-    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+use windows::Win32::{
+    Foundation::{BOOL, HWND, RECT},
+    Graphics::Gdi::HMONITOR,
+    UI::{
+        Shell::{
+            SHAppBarMessage, ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETAUTOHIDEBAREX,
+            APPBARDATA,
+        },
+        WindowsAndMessaging::{
+            GetWindowRect, SystemParametersInfoW, SPI_GETMOUSEVANISH, SPI_GETWHEELSCROLLCHARS,
+            SPI_GETWHEELSCROLLLINES, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+        },
+    },
 };
 
+use crate::{get_monitor_info, DisplayId, WindowsDisplay};
+
 /// Windows settings pulled from SystemParametersInfo
 /// https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-systemparametersinfow
 #[derive(Default, Debug, Clone, Copy)]
 pub(crate) struct WindowsSystemSettings {
     pub(crate) mouse_wheel_settings: MouseWheelSettings,
+    /// SEE: SPI_GETMOUSEVANISH
+    pub(crate) hide_cursor_while_typing: bool,
 }
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -30,6 +44,23 @@ impl WindowsSystemSettings {
 
     fn init(&mut self) {
         self.mouse_wheel_settings.update();
+        self.update_hide_cursor_while_typing();
+    }
+
+    pub(crate) fn update_hide_cursor_while_typing(&mut self) {
+        let mut value = BOOL::default();
+        let result = unsafe {
+            SystemParametersInfoW(
+                SPI_GETMOUSEVANISH,
+                0,
+                Some((&mut value) as *mut BOOL as *mut c_void),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS::default(),
+            )
+        };
+
+        if result.log_err().is_some() {
+            self.hide_cursor_while_typing = value.as_bool();
+        }
     }
 }
 
@@ -71,3 +102,166 @@ impl MouseWheelSettings {
         }
     }
 }
+
+/// The edge of a monitor a taskbar is docked to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskbarEdge {
+    /// The left edge of the monitor
+    Left,
+    /// The top edge of the monitor
+    Top,
+    /// The right edge of the monitor
+    Right,
+    /// The bottom edge of the monitor
+    Bottom,
+}
+
+/// Where the taskbar is placed on a monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskbarPosition {
+    /// The edge of the monitor the taskbar is docked to
+    pub edge: TaskbarEdge,
+    /// How far the taskbar reaches into the monitor, in device pixels. For an auto-hidden
+    /// taskbar this is its size at the moment, which is only a sliver while it's hidden.
+    pub thickness: i32,
+    /// Whether the taskbar hides itself until the pointer reaches its edge. An auto-hidden
+    /// taskbar doesn't take any space from the monitor's work area.
+    pub auto_hide: bool,
+}
+
+impl TaskbarPosition {
+    /// Returns the taskbar on the given display, if it has one.
+    pub fn for_display(display_id: DisplayId) -> Option<Self> {
+        taskbar_position(WindowsDisplay::new(display_id)?.handle)
+    }
+
+    /// Moves the edge of the given rect away from an auto-hidden taskbar. A window that
+    /// covers the whole monitor would otherwise keep the taskbar from ever being revealed.
+    pub(crate) fn inset_for_auto_hide(&self, rect: &mut RECT) {
+        if !self.auto_hide {
+            return;
+        }
+        match self.edge {
+            TaskbarEdge::Left => rect.left += 1,
+            TaskbarEdge::Top => rect.top += 1,
+            TaskbarEdge::Right => rect.right -= 1,
+            TaskbarEdge::Bottom => rect.bottom -= 1,
+        }
+    }
+}
+
+pub(crate) fn taskbar_position(monitor: HMONITOR) -> Option<TaskbarPosition> {
+    let monitor_info = get_monitor_info(monitor).log_err()?.monitorInfo;
+    if let Some((edge, thickness)) = docked_edge(&monitor_info.rcMonitor, &monitor_info.rcWork) {
+        return Some(TaskbarPosition {
+            edge,
+            thickness,
+            auto_hide: false,
+        });
+    }
+    for (edge, appbar_edge) in [
+        (TaskbarEdge::Bottom, ABE_BOTTOM),
+        (TaskbarEdge::Left, ABE_LEFT),
+        (TaskbarEdge::Top, ABE_TOP),
+        (TaskbarEdge::Right, ABE_RIGHT),
+    ] {
+        let mut data = APPBARDATA {
+            cbSize: std::mem::size_of::<APPBARDATA>() as u32,
+            uEdge: appbar_edge,
+            rc: monitor_info.rcMonitor,
+            ..Default::default()
+        };
+        let appbar = HWND(unsafe { SHAppBarMessage(ABM_GETAUTOHIDEBAREX, &mut data) } as isize);
+        if appbar.0 == 0 {
+            continue;
+        }
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(appbar, &mut rect) }.log_err();
+        let thickness = match edge {
+            TaskbarEdge::Left | TaskbarEdge::Right => rect.right - rect.left,
+            TaskbarEdge::Top | TaskbarEdge::Bottom => rect.bottom - rect.top,
+        };
+        return Some(TaskbarPosition {
+            edge,
+            thickness,
+            auto_hide: true,
+        });
+    }
+    None
+}
+
+/// Returns the edge of the monitor the work area has been shrunk from, and by how much.
+fn docked_edge(monitor: &RECT, work_area: &RECT) -> Option<(TaskbarEdge, i32)> {
+    if work_area.bottom < monitor.bottom {
+        Some((TaskbarEdge::Bottom, monitor.bottom - work_area.bottom))
+    } else if work_area.top > monitor.top {
+        Some((TaskbarEdge::Top, work_area.top - monitor.top))
+    } else if work_area.left > monitor.left {
+        Some((TaskbarEdge::Left, work_area.left - monitor.left))
+    } else if work_area.right < monitor.right {
+        Some((TaskbarEdge::Right, monitor.right - work_area.right))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::Foundation::RECT;
+
+    use super::{docked_edge, TaskbarEdge, TaskbarPosition};
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_docked_edge() {
+        let monitor = rect(0, 0, 1920, 1080);
+        assert_eq!(
+            docked_edge(&monitor, &rect(0, 0, 1920, 1032)),
+            Some((TaskbarEdge::Bottom, 48))
+        );
+        assert_eq!(
+            docked_edge(&monitor, &rect(0, 40, 1920, 1080)),
+            Some((TaskbarEdge::Top, 40))
+        );
+        assert_eq!(
+            docked_edge(&monitor, &rect(62, 0, 1920, 1080)),
+            Some((TaskbarEdge::Left, 62))
+        );
+        assert_eq!(docked_edge(&monitor, &monitor), None);
+
+        // a secondary monitor to the left of the primary one
+        let monitor = rect(-2560, 0, 0, 1440);
+        assert_eq!(
+            docked_edge(&monitor, &rect(-2560, 0, -62, 1440)),
+            Some((TaskbarEdge::Right, 62))
+        );
+    }
+
+    #[test]
+    fn test_inset_for_auto_hide() {
+        let mut client_rect = rect(0, 0, 1920, 1080);
+        TaskbarPosition {
+            edge: TaskbarEdge::Bottom,
+            thickness: 48,
+            auto_hide: false,
+        }
+        .inset_for_auto_hide(&mut client_rect);
+        assert_eq!(client_rect, rect(0, 0, 1920, 1080));
+
+        TaskbarPosition {
+            edge: TaskbarEdge::Left,
+            thickness: 2,
+            auto_hide: true,
+        }
+        .inset_for_auto_hide(&mut client_rect);
+        assert_eq!(client_rect, rect(1, 0, 1920, 1080));
+    }
+}
@@ -0,0 +1,334 @@
+use std::sync::OnceLock;
+
+use ::util::ResultExt;
+use anyhow::anyhow;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::Com::*,
+        UI::{HiDpi::*, Shell::*, WindowsAndMessaging::*},
+    },
+};
+
+use crate::*;
+
+/// The resolution of the progress reported to the taskbar, which only takes integers.
+const PROGRESS_TOTAL: u64 = 10_000;
+/// The fill of badges, as RGB.
+const BADGE_COLOR: [u8; 3] = [0xC4, 0x2B, 0x1C];
+/// Counts above this are shown as `99+`, as more digits don't fit in a small icon.
+const BADGE_MAX_COUNT: usize = 99;
+/// The taskbar has room for this many buttons below a thumbnail.
+const MAX_THUMBNAIL_BUTTONS: usize = 7;
+
+/// The message the taskbar sends a window once its button exists, and again whenever Explorer
+/// restarts and recreates every button.
+pub(crate) fn taskbar_button_created_msg() -> u32 {
+    static MSG: OnceLock<u32> = OnceLock::new();
+    *MSG.get_or_init(|| unsafe { RegisterWindowMessageW(w!("TaskbarButtonCreated")) })
+}
+
+/// The taskbar button of a window. It can only be changed once the taskbar has created it,
+/// so what's set before that, or before Explorer restarts, is kept and applied again then.
+pub(crate) struct TaskbarButton {
+    hwnd: HWND,
+    taskbar: Option<ITaskbarList3>,
+    progress: WindowProgress,
+    badge: Option<WindowBadge>,
+    thumbnail_buttons: Vec<ThumbnailButton>,
+    // the toolbar can only be added once per button, and its buttons are hidden rather than
+    // removed afterwards
+    thumbnail_toolbar_added: bool,
+    thumbnail_icons: Vec<HICON>,
+}
+
+impl TaskbarButton {
+    pub(crate) fn new(hwnd: HWND) -> Self {
+        // an elevated process doesn't receive messages from Explorer unless it allows them
+        for msg in [taskbar_button_created_msg(), WM_COMMAND] {
+            unsafe { ChangeWindowMessageFilterEx(hwnd, msg, MSGFLT_ALLOW, None) }.log_err();
+        }
+        Self {
+            hwnd,
+            taskbar: None,
+            progress: WindowProgress::None,
+            badge: None,
+            thumbnail_buttons: Vec::new(),
+            thumbnail_toolbar_added: false,
+            thumbnail_icons: Vec::new(),
+        }
+    }
+
+    pub(crate) fn created(&mut self) {
+        self.taskbar = create_taskbar_list().log_err();
+        self.thumbnail_toolbar_added = false;
+        self.apply_progress();
+        self.apply_badge();
+        self.apply_thumbnail_buttons();
+    }
+
+    pub(crate) fn set_progress(&mut self, progress: WindowProgress) {
+        if self.progress == progress {
+            return;
+        }
+        self.progress = progress;
+        self.apply_progress();
+    }
+
+    fn apply_progress(&self) {
+        let Some(taskbar) = &self.taskbar else {
+            return;
+        };
+        let (state, value) = match self.progress {
+            WindowProgress::None => (TBPF_NOPROGRESS, None),
+            WindowProgress::Indeterminate => (TBPF_INDETERMINATE, None),
+            WindowProgress::Normal(value) => (TBPF_NORMAL, Some(value)),
+            WindowProgress::Error(value) => (TBPF_ERROR, Some(value)),
+        };
+        unsafe {
+            // setting the value turns hidden or indeterminate progress into normal progress,
+            // so the state goes last
+            if let Some(value) = value {
+                let completed = (value.clamp(0., 1.) * PROGRESS_TOTAL as f32) as u64;
+                taskbar
+                    .SetProgressValue(self.hwnd, completed, PROGRESS_TOTAL)
+                    .log_err();
+            }
+            taskbar.SetProgressState(self.hwnd, state).log_err();
+        }
+    }
+
+    pub(crate) fn set_badge(&mut self, badge: Option<WindowBadge>) {
+        let badge = badge.filter(|badge| *badge != WindowBadge::Count(0));
+        if self.badge == badge {
+            return;
+        }
+        self.badge = badge;
+        self.apply_badge();
+    }
+
+    fn apply_badge(&self) {
+        let Some(taskbar) = &self.taskbar else {
+            return;
+        };
+        let Some(badge) = self.badge else {
+            unsafe { taskbar.SetOverlayIcon(self.hwnd, HICON::default(), PCWSTR::null()) }
+                .log_err();
+            return;
+        };
+        let size = unsafe { GetSystemMetricsForDpi(SM_CXSMICON, GetDpiForWindow(self.hwnd)) };
+        let Some(icon) = create_badge_icon(badge, size).log_err() else {
+            return;
+        };
+        // screen readers announce the description along with the button
+        let description = match badge {
+            WindowBadge::Count(count) => HSTRING::from(count.to_string()),
+            WindowBadge::Dot => HSTRING::new(),
+        };
+        unsafe {
+            taskbar
+                .SetOverlayIcon(self.hwnd, icon, &description)
+                .log_err();
+            // the taskbar keeps its own copy of the icon
+            DestroyIcon(icon).log_err();
+        }
+    }
+
+    pub(crate) fn set_thumbnail_buttons(&mut self, mut buttons: Vec<ThumbnailButton>) {
+        buttons.truncate(MAX_THUMBNAIL_BUTTONS);
+        self.thumbnail_buttons = buttons;
+        self.apply_thumbnail_buttons();
+    }
+
+    /// Returns the action of the button with the id sent along `THBN_CLICKED`.
+    pub(crate) fn thumbnail_button_action(&self, id: u32) -> Option<Box<dyn Action>> {
+        self.thumbnail_buttons
+            .get(id as usize)
+            .map(|button| button.action.boxed_clone())
+    }
+
+    fn apply_thumbnail_buttons(&mut self) {
+        let Some(taskbar) = &self.taskbar else {
+            return;
+        };
+        if !self.thumbnail_toolbar_added && self.thumbnail_buttons.is_empty() {
+            return;
+        }
+        let icons = self
+            .thumbnail_buttons
+            .iter()
+            .map(|button| {
+                create_thumbnail_icon(&button.icon)
+                    .log_err()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        // every slot is added up front, since buttons can't be added to the toolbar later
+        let buttons = (0..MAX_THUMBNAIL_BUTTONS)
+            .map(|index| {
+                let mut thumb_button = THUMBBUTTON {
+                    dwMask: THB_ICON | THB_TOOLTIP | THB_FLAGS,
+                    iId: index as u32,
+                    dwFlags: THBF_HIDDEN,
+                    ..Default::default()
+                };
+                if let Some(button) = self.thumbnail_buttons.get(index) {
+                    thumb_button.hIcon = icons[index];
+                    thumb_button.dwFlags = if button.enabled {
+                        THBF_ENABLED
+                    } else {
+                        THBF_DISABLED
+                    };
+                    // the tooltip is cut to fit, leaving room for the terminating null
+                    let tooltip_len = thumb_button.szTip.len() - 1;
+                    for (dest, src) in thumb_button
+                        .szTip
+                        .iter_mut()
+                        .zip(button.tooltip.encode_utf16().take(tooltip_len))
+                    {
+                        *dest = src;
+                    }
+                }
+                thumb_button
+            })
+            .collect::<Vec<_>>();
+        let result = if self.thumbnail_toolbar_added {
+            unsafe { taskbar.ThumbBarUpdateButtons(self.hwnd, &buttons) }
+        } else {
+            unsafe { taskbar.ThumbBarAddButtons(self.hwnd, &buttons) }
+        };
+        if result.log_err().is_some() {
+            self.thumbnail_toolbar_added = true;
+        }
+        // the taskbar draws the icons as long as they're shown, so the previous ones are only
+        // destroyed once they've been replaced
+        for icon in std::mem::replace(&mut self.thumbnail_icons, icons) {
+            if !icon.is_invalid() {
+                unsafe { DestroyIcon(icon) }.log_err();
+            }
+        }
+    }
+}
+
+impl Drop for TaskbarButton {
+    fn drop(&mut self) {
+        for icon in self.thumbnail_icons.drain(..) {
+            if !icon.is_invalid() {
+                unsafe { DestroyIcon(icon) }.log_err();
+            }
+        }
+    }
+}
+
+fn create_taskbar_list() -> Result<ITaskbarList3> {
+    let taskbar: ITaskbarList3 =
+        unsafe { CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER) }?;
+    unsafe { taskbar.HrInit() }?;
+    Ok(taskbar)
+}
+
+/// Turns an image, whose pixels are in BGRA order, into an icon.
+fn create_thumbnail_icon(image: &ImageData) -> anyhow::Result<HICON> {
+    let size = image.size();
+    create_icon_from_bgra(size.width.0, size.height.0, image.as_bytes(), None)
+        .map_err(|error| anyhow!("unable to create a thumbnail button icon: {}", error))
+}
+
+/// Draws a badge as a filled circle with its count in it, as an icon `size` pixels wide.
+fn create_badge_icon(badge: WindowBadge, size: i32) -> anyhow::Result<HICON> {
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size,
+            // a negative height requests a top-down bitmap
+            biHeight: -size,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    unsafe {
+        let dc = CreateCompatibleDC(None);
+        let mut bits = std::ptr::null_mut();
+        let color = CreateDIBSection(dc, &info, DIB_RGB_COLORS, &mut bits, None, 0);
+        let color = match color {
+            Ok(color) if !bits.is_null() => color,
+            _ => {
+                DeleteDC(dc);
+                return Err(anyhow!("unable to create a {}px badge bitmap", size));
+            }
+        };
+        let previous_bitmap = SelectObject(dc, color);
+        // GDI can't draw with alpha, so the text is drawn white on black and its brightness
+        // is used as its coverage afterwards
+        if let WindowBadge::Count(count) = badge {
+            draw_badge_text(dc, count, size);
+        }
+        GdiFlush();
+        SelectObject(dc, previous_bitmap);
+        DeleteDC(dc);
+
+        let pixels = std::slice::from_raw_parts_mut(bits as *mut u8, (size * size * 4) as usize);
+        let radius = size as f32 / 2.;
+        for (index, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let x = (index as i32 % size) as f32 + 0.5 - radius;
+            let y = (index as i32 / size) as f32 + 0.5 - radius;
+            let circle_coverage = (radius - (x * x + y * y).sqrt()).clamp(0., 1.);
+            let text_coverage = pixel[1] as f32 / 255.;
+            let [r, g, b] = BADGE_COLOR.map(|channel| {
+                (channel as f32 + (255. - channel as f32) * text_coverage).round() as u8
+            });
+            pixel.copy_from_slice(&[b, g, r, (circle_coverage * 255.).round() as u8]);
+        }
+
+        let icon = create_icon_from_bgra(size, size, pixels, None);
+        DeleteObject(color);
+        icon.map_err(|error| anyhow!("unable to create a badge icon: {}", error))
+    }
+}
+
+unsafe fn draw_badge_text(dc: HDC, count: usize, size: i32) {
+    let text = if count > BADGE_MAX_COUNT {
+        format!("{}+", BADGE_MAX_COUNT)
+    } else {
+        count.to_string()
+    };
+    let mut font_info = LOGFONTW {
+        // a negative height is the height of the characters, without the line spacing, which
+        // shrinks as digits are added to keep them within the circle
+        lfHeight: -(size * (14 - 2 * text.len() as i32) / 16),
+        lfWeight: FW_BOLD.0 as i32,
+        lfQuality: ANTIALIASED_QUALITY,
+        ..Default::default()
+    };
+    for (dest, src) in font_info
+        .lfFaceName
+        .iter_mut()
+        .zip("Segoe UI".encode_utf16())
+    {
+        *dest = src;
+    }
+    let font = CreateFontIndirectW(&font_info);
+    let previous_font = SelectObject(dc, font);
+    SetTextColor(dc, COLORREF(0x00FF_FFFF));
+    SetBkMode(dc, TRANSPARENT);
+    let mut text = text.encode_utf16().collect::<Vec<_>>();
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: size,
+        bottom: size,
+    };
+    DrawTextW(
+        dc,
+        &mut text,
+        &mut rect,
+        DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX,
+    );
+    SelectObject(dc, previous_font);
+    DeleteObject(font);
+}
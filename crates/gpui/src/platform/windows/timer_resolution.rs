@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use windows::Win32::Media::{timeBeginPeriod, timeEndPeriod, TIMERR_NOERROR};
+
+use crate::*;
+
+/// Timers shorter than this fire late by a large part of their delay at the default 15.6ms
+/// resolution, which makes animations driven by them stutter.
+pub(crate) const HIGH_RESOLUTION_TIMER_THRESHOLD: Duration = Duration::from_millis(50);
+
+const HIGH_RESOLUTION_PERIOD_MS: u32 = 1;
+
+static STATE: Mutex<TimerResolutionState> = Mutex::new(TimerResolutionState {
+    requests: 0,
+    raised: false,
+    active_since: None,
+    activations: 0,
+    active_duration: Duration::ZERO,
+});
+
+struct TimerResolutionState {
+    requests: usize,
+    raised: bool,
+    active_since: Option<Instant>,
+    activations: u64,
+    active_duration: Duration,
+}
+
+/// Keeps the system timer resolution raised to 1ms while alive. The resolution is shared by
+/// the whole system and costs battery, so it's only requested while a short timer is pending.
+pub(crate) struct HighResolutionTimer(());
+
+impl HighResolutionTimer {
+    pub(crate) fn request() -> Self {
+        let mut state = STATE.lock();
+        state.requests += 1;
+        if state.requests == 1 {
+            state.raised = unsafe { timeBeginPeriod(HIGH_RESOLUTION_PERIOD_MS) } == TIMERR_NOERROR;
+            if state.raised {
+                state.active_since = Some(Instant::now());
+                state.activations += 1;
+            } else {
+                log::error!("unable to raise the timer resolution");
+            }
+        }
+        Self(())
+    }
+}
+
+impl Drop for HighResolutionTimer {
+    fn drop(&mut self) {
+        let mut state = STATE.lock();
+        state.requests -= 1;
+        if state.requests == 0 && state.raised {
+            unsafe { timeEndPeriod(HIGH_RESOLUTION_PERIOD_MS) };
+            state.raised = false;
+            if let Some(active_since) = state.active_since.take() {
+                state.active_duration += active_since.elapsed();
+            }
+        }
+    }
+}
+
+pub(crate) fn timer_resolution_statistics() -> TimerResolutionStatistics {
+    let state = STATE.lock();
+    TimerResolutionStatistics {
+        activations: state.activations,
+        active_duration: state.active_duration
+            + state
+                .active_since
+                .map_or(Duration::ZERO, |active_since| active_since.elapsed()),
+        active: state.raised,
+    }
+}
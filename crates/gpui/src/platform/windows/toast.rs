@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Result};
+use windows::{
+    core::*,
+    Data::Xml::Dom::XmlDocument,
+    Foundation::TypedEventHandler,
+    Win32::{
+        Foundation::*,
+        UI::{Shell::*, WindowsAndMessaging::*},
+    },
+    UI::Notifications::{
+        ToastActivatedEventArgs, ToastDismissedEventArgs, ToastFailedEventArgs, ToastNotification,
+        ToastNotificationManager,
+    },
+};
+
+use crate::*;
+
+/// Toasts show at most this many buttons.
+const MAX_NOTIFICATION_ACTIONS: usize = 5;
+
+/// What the user clicked in a toast, sent to the main thread as the `lparam` of a
+/// [`NOTIFICATION_ACTIVATED`] message.
+pub(crate) struct NotificationActivation {
+    pub(crate) id: SharedString,
+    /// The index of the button, or `None` when the toast itself was clicked.
+    pub(crate) action: Option<usize>,
+}
+
+/// A toast that was dismissed or failed to show, sent to the main thread as the `lparam` of a
+/// [`NOTIFICATION_CLOSED`] message.
+pub(crate) struct NotificationClosed {
+    pub(crate) id: SharedString,
+    /// Tells the toast apart from one that replaced it under the same id.
+    pub(crate) toast: usize,
+}
+
+/// Makes the process use the given AppUserModelID, which groups its windows on the taskbar and
+/// is the identity toasts are shown under.
+pub(crate) fn set_process_app_user_model_id(id: &str) -> Result<()> {
+    unsafe { SetCurrentProcessExplicitAppUserModelID(&HSTRING::from(id)) }?;
+    Ok(())
+}
+
+/// Shows a toast under the given AppUserModelID. Windows only shows toasts of unpackaged apps
+/// whose ID was given to a Start menu shortcut, which is up to the installer.
+///
+/// Clicks are posted to `main_thread_id`, and so is the toast being dismissed or failing, after
+/// which it's dropped. Clicks are only reported while the returned toast is alive, which is why
+/// toasts left in the Action Center once they time out don't report them.
+pub(crate) fn show_toast(
+    app_user_model_id: &str,
+    notification: &SystemNotification,
+    main_thread_id: u32,
+) -> Result<ToastNotification> {
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(toast_xml(notification)))?;
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    // a toast replaces the one with the same tag
+    toast.SetTag(&HSTRING::from(notification.id.as_ref()))?;
+    let id = notification.id.clone();
+    toast.Activated(&TypedEventHandler::new(
+        move |_, args: &Option<IInspectable>| {
+            // buttons have their index as arguments, and the toast itself has none
+            let action = args
+                .as_ref()
+                .and_then(|args| args.cast::<ToastActivatedEventArgs>().ok())
+                .and_then(|args| args.Arguments().ok())
+                .and_then(|arguments| arguments.to_string().parse().ok());
+            post_to_main_thread(
+                main_thread_id,
+                NOTIFICATION_ACTIVATED,
+                NotificationActivation {
+                    id: id.clone(),
+                    action,
+                },
+            )
+        },
+    ))?;
+    let post_closed = {
+        let id = notification.id.clone();
+        let toast = toast.as_raw() as usize;
+        move || {
+            let closed = NotificationClosed {
+                id: id.clone(),
+                toast,
+            };
+            post_to_main_thread(main_thread_id, NOTIFICATION_CLOSED, closed)
+        }
+    };
+    toast.Dismissed(&TypedEventHandler::new({
+        let post_closed = post_closed.clone();
+        move |_, _: &Option<ToastDismissedEventArgs>| post_closed()
+    }))?;
+    toast.Failed(&TypedEventHandler::new(
+        move |_, _: &Option<ToastFailedEventArgs>| post_closed(),
+    ))?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_user_model_id))?
+        .Show(&toast)
+        .map_err(|error| anyhow!("unable to show a notification: {}", error))?;
+    Ok(toast)
+}
+
+/// Hands the event over to the main thread, which takes ownership of it.
+fn post_to_main_thread<T>(
+    main_thread_id: u32,
+    message: u32,
+    event: T,
+) -> windows::core::Result<()> {
+    let event = Box::into_raw(Box::new(event));
+    let posted =
+        unsafe { PostThreadMessageW(main_thread_id, message, WPARAM(0), LPARAM(event as isize)) };
+    if posted.is_err() {
+        drop(unsafe { Box::from_raw(event) });
+    }
+    posted
+}
+
+// https://learn.microsoft.com/en-us/windows/apps/design/shell/tiles-and-notifications/adaptive-interactive-toasts
+fn toast_xml(notification: &SystemNotification) -> String {
+    let mut xml = String::from(r#"<toast><visual><binding template="ToastGeneric">"#);
+    for text in [&notification.title, &notification.body] {
+        if !text.is_empty() {
+            xml.push_str(&format!("<text>{}</text>", escape_xml(text)));
+        }
+    }
+    xml.push_str("</binding></visual>");
+    if !notification.actions.is_empty() {
+        xml.push_str("<actions>");
+        for (index, label) in notification
+            .actions
+            .iter()
+            .take(MAX_NOTIFICATION_ACTIONS)
+            .enumerate()
+        {
+            xml.push_str(&format!(
+                r#"<action content="{}" arguments="{}"/>"#,
+                escape_xml(label),
+                index
+            ));
+        }
+        xml.push_str("</actions>");
+    }
+    xml.push_str("</toast>");
+    xml
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_xml, toast_xml};
+    use crate::SystemNotification;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("plain text"), "plain text");
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_toast_xml() {
+        let notification = SystemNotification {
+            id: "call".into(),
+            title: "Incoming call".into(),
+            body: String::new().into(),
+            actions: vec!["Accept".into(), "Decline & <mute>".into()],
+        };
+        assert_eq!(
+            toast_xml(&notification),
+            concat!(
+                r#"<toast><visual><binding template="ToastGeneric">"#,
+                "<text>Incoming call</text>",
+                "</binding></visual><actions>",
+                r#"<action content="Accept" arguments="0"/>"#,
+                r#"<action content="Decline &amp; &lt;mute&gt;" arguments="1"/>"#,
+                "</actions></toast>",
+            )
+        );
+
+        // toasts show at most five buttons
+        let notification = SystemNotification {
+            actions: (0..7).map(|ix| ix.to_string().into()).collect(),
+            ..notification
+        };
+        assert_eq!(toast_xml(&notification).matches("<action ").count(), 5);
+    }
+}
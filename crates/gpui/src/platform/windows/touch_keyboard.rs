@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use ::util::ResultExt;
+use windows::{
+    core::*,
+    Foundation::TypedEventHandler,
+    Win32::{
+        Foundation::*,
+        System::WinRT::{IInputPaneInterop, IUIViewSettingsInterop},
+        UI::{Shell::*, WindowsAndMessaging::*},
+    },
+    UI::ViewManagement::{InputPane, UIViewSettings, UserInteractionMode},
+};
+
+use crate::*;
+
+/// Shows the touch keyboard while an editable element is focused and the device is being
+/// used as a tablet, the way native text boxes do.
+pub(crate) struct TouchKeyboard {
+    hwnd: HWND,
+    input_pane: Option<InputPane>,
+    text_input_focused: bool,
+}
+
+impl TouchKeyboard {
+    pub(crate) fn new(hwnd: HWND) -> Self {
+        let input_pane = input_pane_for_window(hwnd).log_err();
+        Self {
+            hwnd,
+            input_pane,
+            text_input_focused: false,
+        }
+    }
+
+    pub(crate) fn set_text_input_focused(&mut self, focused: bool) {
+        if self.text_input_focused == focused {
+            return;
+        }
+        self.text_input_focused = focused;
+        if focused {
+            if is_touch_interaction(self.hwnd) {
+                self.show();
+            }
+        } else if let Some(input_pane) = &self.input_pane {
+            input_pane.TryHide().log_err();
+        }
+    }
+
+    /// Returns the part of the screen the touch keyboard covers, in device independent pixels.
+    pub(crate) fn occluded_bounds(&self) -> Option<Bounds<Pixels>> {
+        let rect = self.input_pane.as_ref()?.OccludedRect().log_err()?;
+        (rect.Height > 0.).then(|| Bounds {
+            origin: point(px(rect.X), px(rect.Y)),
+            size: size(px(rect.Width), px(rect.Height)),
+        })
+    }
+
+    fn show(&self) {
+        let shown = self
+            .input_pane
+            .as_ref()
+            .and_then(|input_pane| input_pane.TryShow().log_err())
+            .unwrap_or(false);
+        if !shown {
+            launch_tab_tip();
+        }
+    }
+}
+
+fn input_pane_for_window(hwnd: HWND) -> Result<InputPane> {
+    let interop = factory::<InputPane, IInputPaneInterop>()?;
+    let input_pane: InputPane = unsafe { interop.GetForWindow(hwnd) }?;
+    input_pane.Showing(&TypedEventHandler::new(move |_, _| {
+        post_touch_keyboard_changed(hwnd, true)
+    }))?;
+    input_pane.Hiding(&TypedEventHandler::new(move |_, _| {
+        post_touch_keyboard_changed(hwnd, false)
+    }))?;
+    Ok(input_pane)
+}
+
+// the events are raised on the window's thread, but their handlers have to be `Send`
+fn post_touch_keyboard_changed(hwnd: HWND, showing: bool) -> Result<()> {
+    unsafe {
+        PostMessageW(
+            hwnd,
+            TOUCH_KEYBOARD_CHANGED,
+            WPARAM(showing as usize),
+            LPARAM(0),
+        )
+    }
+}
+
+// https://learn.microsoft.com/en-us/windows/apps/design/input/input-and-interactions#tablet-mode
+fn is_touch_interaction(hwnd: HWND) -> bool {
+    let mode = factory::<UIViewSettings, IUIViewSettingsInterop>()
+        .and_then(|interop| unsafe { interop.GetForWindow::<_, UIViewSettings>(hwnd) })
+        .and_then(|settings| settings.UserInteractionMode());
+    mode.log_err() == Some(UserInteractionMode::Touch)
+}
+
+// Versions of Windows without `InputPane.TryShow` only show the keyboard once TabTip is started.
+fn launch_tab_tip() {
+    let Some(common_files) =
+        std::env::var_os("CommonProgramW6432").or_else(|| std::env::var_os("CommonProgramFiles"))
+    else {
+        return;
+    };
+    let path = PathBuf::from(common_files).join("microsoft shared\\ink\\TabTip.exe");
+    let ret = unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            &HSTRING::from(path.as_os_str()),
+            None,
+            None,
+            SW_SHOWNORMAL,
+        )
+    };
+    if ret.0 <= 32 {
+        log::error!(
+            "Unable to launch the touch keyboard: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
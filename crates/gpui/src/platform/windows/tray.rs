@@ -0,0 +1,265 @@
+use std::sync::{Once, OnceLock};
+
+use ::util::ResultExt;
+use anyhow::{anyhow, Result};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        UI::{Shell::*, WindowsAndMessaging::*},
+    },
+};
+
+use crate::*;
+
+/// The message the shell sends the tray window about its icon.
+const TRAY_ICON_CALLBACK: u32 = WM_USER + 1;
+const TRAY_ICON_ID: u32 = 1;
+/// Posted as the event of [`TRAY_ICON_EVENT`] when Explorer restarts, which drops every icon
+/// from the notification area.
+pub(crate) const TRAY_ICON_RECREATED: u32 = 0;
+
+/// The icon of the app in the notification area of the taskbar. It's owned by a hidden window,
+/// which forwards clicks on it to the platform as [`TRAY_ICON_EVENT`] thread messages.
+pub(crate) struct TrayIcon {
+    hwnd: HWND,
+    icon: HICON,
+    tooltip: String,
+    menu: HMENU,
+    actions: Vec<Box<dyn Action>>,
+}
+
+impl TrayIcon {
+    pub(crate) fn new(icon: HICON) -> Result<Self> {
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                register_tray_wnd_class(),
+                None,
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                get_module_handle(),
+                None,
+            )
+        };
+        if hwnd.0 == 0 {
+            return Err(anyhow!(
+                "unable to create the tray icon window: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let this = Self {
+            hwnd,
+            icon,
+            tooltip: String::new(),
+            menu: HMENU::default(),
+            actions: Vec::new(),
+        };
+        this.add()?;
+        Ok(this)
+    }
+
+    /// Adds the icon to the notification area, again after Explorer restarted.
+    pub(crate) fn add(&self) -> Result<()> {
+        unsafe { Shell_NotifyIconW(NIM_ADD, &self.notify_icon_data()) }.ok()?;
+        let mut data = self.notify_icon_data();
+        data.Anonymous.uVersion = NOTIFYICON_VERSION_4;
+        unsafe { Shell_NotifyIconW(NIM_SETVERSION, &data) }.ok()?;
+        Ok(())
+    }
+
+    pub(crate) fn update(&mut self, tooltip: &str, menu: Vec<MenuItem>) {
+        if self.tooltip != tooltip {
+            self.tooltip = tooltip.to_string();
+            unsafe { Shell_NotifyIconW(NIM_MODIFY, &self.notify_icon_data()) }
+                .ok()
+                .log_err();
+        }
+        if !self.menu.is_invalid() {
+            unsafe { DestroyMenu(self.menu) }.log_err();
+        }
+        self.actions.clear();
+        self.menu = build_menu(menu, &mut self.actions)
+            .log_err()
+            .unwrap_or_default();
+    }
+
+    /// Returns what [`show_tray_menu`] needs, so that it can run without the icon borrowed.
+    pub(crate) fn menu(&self) -> (HWND, HMENU, Vec<Box<dyn Action>>) {
+        let actions = self
+            .actions
+            .iter()
+            .map(|action| action.boxed_clone())
+            .collect();
+        (self.hwnd, self.menu, actions)
+    }
+
+    fn notify_icon_data(&self) -> NOTIFYICONDATAW {
+        let mut data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: self.hwnd,
+            uID: TRAY_ICON_ID,
+            uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP | NIF_SHOWTIP,
+            uCallbackMessage: TRAY_ICON_CALLBACK,
+            hIcon: self.icon,
+            ..Default::default()
+        };
+        // the tooltip is cut to fit, leaving room for the terminating null
+        let tooltip_len = data.szTip.len() - 1;
+        for (dest, src) in data
+            .szTip
+            .iter_mut()
+            .zip(self.tooltip.encode_utf16().take(tooltip_len))
+        {
+            *dest = src;
+        }
+        data
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            Shell_NotifyIconW(NIM_DELETE, &self.notify_icon_data())
+                .ok()
+                .log_err();
+            if !self.menu.is_invalid() {
+                DestroyMenu(self.menu).log_err();
+            }
+            DestroyWindow(self.hwnd).log_err();
+        }
+    }
+}
+
+/// Shows the menu of the tray icon at the given point in screen coordinates, and returns the
+/// index of the action that was picked. Actions that fail `validate` are grayed out.
+pub(crate) fn show_tray_menu(
+    hwnd: HWND,
+    menu: HMENU,
+    actions: &[Box<dyn Action>],
+    position: POINT,
+    mut validate: impl FnMut(&dyn Action) -> bool,
+) -> Option<usize> {
+    for (index, action) in actions.iter().enumerate() {
+        let enabled = if validate(action.as_ref()) {
+            MF_ENABLED
+        } else {
+            MF_GRAYED
+        };
+        unsafe { EnableMenuItem(menu, menu_command_id(index), MF_BYCOMMAND | enabled) };
+    }
+    // the menu only closes when clicking elsewhere if its owner is in the foreground
+    unsafe { SetForegroundWindow(hwnd) };
+    let command = unsafe {
+        TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON | TPM_BOTTOMALIGN,
+            position.x,
+            position.y,
+            0,
+            hwnd,
+            None,
+        )
+    };
+    (command.0 > 0).then(|| command.0 as usize - 1)
+}
+
+fn build_menu(items: Vec<MenuItem>, actions: &mut Vec<Box<dyn Action>>) -> Result<HMENU> {
+    let menu = unsafe { CreatePopupMenu() }?;
+    for item in items {
+        match item {
+            MenuItem::Separator => {
+                unsafe { AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()) }.log_err();
+            }
+            MenuItem::Submenu(submenu) => {
+                let popup = build_menu(submenu.items, actions)?;
+                unsafe {
+                    AppendMenuW(
+                        menu,
+                        MF_POPUP,
+                        popup.0 as usize,
+                        &HSTRING::from(submenu.name),
+                    )
+                }
+                .log_err();
+            }
+            MenuItem::Action { name, action, .. } => {
+                let command_id = menu_command_id(actions.len());
+                actions.push(action);
+                unsafe { AppendMenuW(menu, MF_STRING, command_id as usize, &HSTRING::from(name)) }
+                    .log_err();
+            }
+        }
+    }
+    Ok(menu)
+}
+
+// `TrackPopupMenu` returns 0 when nothing is picked, so the ids start at 1
+fn menu_command_id(index: usize) -> u32 {
+    index as u32 + 1
+}
+
+fn taskbar_created_msg() -> u32 {
+    static MSG: OnceLock<u32> = OnceLock::new();
+    *MSG.get_or_init(|| unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) })
+}
+
+fn register_tray_wnd_class() -> PCWSTR {
+    const CLASS_NAME: PCWSTR = w!("Zed::TrayIcon");
+
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(tray_wnd_proc),
+            lpszClassName: CLASS_NAME,
+            hInstance: get_module_handle().into(),
+            ..Default::default()
+        };
+        unsafe { RegisterClassW(&wc) };
+    });
+
+    CLASS_NAME
+}
+
+unsafe extern "system" fn tray_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == TRAY_ICON_CALLBACK {
+        // with version 4 of the callback, the event is in the low word of `lparam` and the
+        // position of the icon is in `wparam`
+        let event = lparam.0 as u32 & 0xFFFF;
+        if matches!(event, NIN_SELECT | NIN_KEYSELECT | WM_CONTEXTMENU) {
+            unsafe {
+                PostMessageW(
+                    None,
+                    TRAY_ICON_EVENT,
+                    WPARAM(event as usize),
+                    LPARAM(wparam.0 as isize),
+                )
+            }
+            .log_err();
+        }
+        return LRESULT(0);
+    }
+    if msg == taskbar_created_msg() {
+        unsafe {
+            PostMessageW(
+                None,
+                TRAY_ICON_EVENT,
+                WPARAM(TRAY_ICON_RECREATED as usize),
+                LPARAM(0),
+            )
+        }
+        .log_err();
+        return LRESULT(0);
+    }
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
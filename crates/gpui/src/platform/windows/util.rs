@@ -1,7 +1,7 @@
-use std::sync::OnceLock;
+use std::{ffi::c_void, sync::OnceLock};
 
 use ::util::ResultExt;
-use windows::Win32::{Foundation::*, UI::WindowsAndMessaging::*};
+use windows::Win32::{Foundation::*, Graphics::Gdi::*, UI::WindowsAndMessaging::*};
 
 use crate::*;
 
@@ -86,6 +86,11 @@ pub(crate) fn load_cursor(style: CursorStyle) -> HCURSOR {
     static SIZEWE: OnceLock<HCURSOR> = OnceLock::new();
     static SIZENS: OnceLock<HCURSOR> = OnceLock::new();
     static NO: OnceLock<HCURSOR> = OnceLock::new();
+    if let CursorStyle::Custom(id) = style {
+        if let Some(cursor) = custom_cursor(id) {
+            return cursor;
+        }
+    }
     let (lock, name) = match style {
         CursorStyle::IBeam | CursorStyle::IBeamCursorForVerticalLayout => (&IBEAM, IDC_IBEAM),
         CursorStyle::Crosshair => (&CROSS, IDC_CROSS),
@@ -111,6 +116,34 @@ pub(crate) fn load_cursor(style: CursorStyle) -> HCURSOR {
     })
 }
 
+/// Creates an icon from pixels in BGRA order, or a cursor when it's given a hotspot.
+pub(crate) fn create_icon_from_bgra(
+    width: i32,
+    height: i32,
+    bgra: &[u8],
+    hotspot: Option<(u32, u32)>,
+) -> windows::core::Result<HICON> {
+    // the mask is ignored when the color bitmap has an alpha channel, but it's still required,
+    // with each of its rows padded to a whole number of 16-bit words
+    let mask = vec![0u8; ((width as usize + 15) / 16 * 2) * height as usize];
+    let (x_hotspot, y_hotspot) = hotspot.unwrap_or_default();
+    unsafe {
+        let color = CreateBitmap(width, height, 1, 32, Some(bgra.as_ptr() as *const c_void));
+        let mask = CreateBitmap(width, height, 1, 1, Some(mask.as_ptr() as *const c_void));
+        let info = ICONINFO {
+            fIcon: hotspot.is_none().into(),
+            xHotspot: x_hotspot,
+            yHotspot: y_hotspot,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&info);
+        DeleteObject(color);
+        DeleteObject(mask);
+        icon
+    }
+}
+
 #[inline]
 pub(crate) fn logical_point(x: f32, y: f32, scale_factor: f32) -> Point<Pixels> {
     Point {
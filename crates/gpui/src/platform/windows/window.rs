@@ -1,7 +1,7 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     num::NonZeroIsize,
     path::PathBuf,
     rc::{Rc, Weak},
@@ -13,16 +13,32 @@ use std::{
 use ::util::ResultExt;
 use anyhow::Context;
 use futures::channel::oneshot::{self, Receiver};
-use itertools::Itertools;
 use raw_window_handle as rwh;
 use smallvec::SmallVec;
 use windows::{
     core::*,
     Win32::{
         Foundation::*,
-        Graphics::Gdi::*,
-        System::{Com::*, LibraryLoader::*, Ole::*, SystemServices::*},
-        UI::{Controls::*, HiDpi::*, Input::KeyboardAndMouse::*, Shell::*, WindowsAndMessaging::*},
+        Graphics::{Dwm::*, Gdi::*},
+        System::{
+            Com::*,
+            LibraryLoader::*,
+            Memory::{GlobalLock, GlobalSize, GlobalUnlock},
+            Ole::*,
+            Performance::*,
+            RemoteDesktop::{
+                WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+                NOTIFY_FOR_THIS_SESSION,
+            },
+            SystemServices::*,
+        },
+        UI::{
+            Controls::*,
+            HiDpi::*,
+            Input::{KeyboardAndMouse::*, *},
+            Shell::*,
+            WindowsAndMessaging::*,
+        },
     },
 };
 
@@ -39,16 +55,43 @@ pub struct WindowsWindowState {
 
     pub callbacks: Callbacks,
     pub input_handler: Option<PlatformInputHandler>,
+    pub touch_keyboard: TouchKeyboard,
+    pub touch_keyboard_inset: Pixels,
+    pub taskbar_button: TaskbarButton,
 
     pub renderer: BladeRenderer,
+    present_count: u64,
+    last_present_qpc: i64,
+    dwm_frames_dropped_at_open: u64,
+    pub last_input_latency: Option<Duration>,
 
     pub click_state: ClickState,
+    pub touch_state: TouchState,
+    pub wheel_scroll: WheelScrollState,
     pub system_settings: WindowsSystemSettings,
     pub current_cursor: HCURSOR,
     pub nc_button_pressed: Option<u32>,
+    pub min_size: Option<Size<Pixels>>,
+    pub max_size: Option<Size<Pixels>>,
+    pub pointer_locked: bool,
+    pub raw_mouse_input: bool,
+    pub cursor_visibility: CursorVisibility,
+    pub mouse_tracked: bool,
+    pub captured_button: Option<MouseButton>,
+    pub pending_high_surrogate: Option<u16>,
+    pub pending_dead_key: bool,
+    pub composing_dead_key: bool,
+    pub window_menu: Vec<WindowMenuEntry>,
+    pub pending_external_drag: Option<(ExternalDragData, Box<dyn FnOnce(ExternalDragOutcome)>)>,
 
     pub display: WindowsDisplay,
+    pub appearance: WindowAppearance,
+    /// The appearance the app picked for the frame, which otherwise follows the system's.
+    pub frame_appearance: Option<WindowAppearance>,
+    pub level: WindowLevel,
     fullscreen: Option<StyleAndBounds>,
+    title: HSTRING,
+    edited: bool,
     hwnd: HWND,
 }
 
@@ -56,7 +99,7 @@ pub(crate) struct WindowsWindowStatePtr {
     hwnd: HWND,
     pub(crate) state: RefCell<WindowsWindowState>,
     pub(crate) handle: AnyWindowHandle,
-    pub(crate) hide_title_bar: bool,
+    pub(crate) hide_title_bar: Cell<bool>,
     pub(crate) executor: ForegroundExecutor,
 }
 
@@ -67,6 +110,7 @@ impl WindowsWindowState {
         cs: &CREATESTRUCTW,
         current_cursor: HCURSOR,
         display: WindowsDisplay,
+        min_size: Option<Size<Pixels>>,
     ) -> Self {
         let scale_factor = {
             let monitor_dpi = unsafe { GetDpiForWindow(hwnd) } as f32;
@@ -82,12 +126,39 @@ impl WindowsWindowState {
             size: logical_size,
         };
         let renderer = windows_renderer::windows_renderer(hwnd, transparent);
+        let present_count = 0;
+        let last_present_qpc = 0;
+        let dwm_frames_dropped_at_open = dwm_timing_info()
+            .map(|timing| timing.cFramesDropped)
+            .unwrap_or_default();
+        let last_input_latency = None;
         let callbacks = Callbacks::default();
         let input_handler = None;
+        let touch_keyboard = TouchKeyboard::new(hwnd);
+        let touch_keyboard_inset = px(0.);
+        let taskbar_button = TaskbarButton::new(hwnd);
         let click_state = ClickState::new();
+        let touch_state = TouchState::default();
+        let wheel_scroll = WheelScrollState::default();
         let system_settings = WindowsSystemSettings::new();
         let nc_button_pressed = None;
+        let max_size = None;
+        let pointer_locked = false;
+        let raw_mouse_input = false;
+        let cursor_visibility = CursorVisibility::default();
+        let mouse_tracked = false;
+        let captured_button = None;
+        let pending_high_surrogate = None;
+        let pending_dead_key = false;
+        let composing_dead_key = false;
+        let window_menu = Vec::new();
+        let pending_external_drag = None;
+        let appearance = system_appearance().log_err().unwrap_or_default();
+        let frame_appearance = None;
+        let level = WindowLevel::Normal;
         let fullscreen = None;
+        let title = unsafe { cs.lpszName.to_hstring() }.unwrap_or_default();
+        let edited = false;
 
         Self {
             origin,
@@ -96,13 +167,39 @@ impl WindowsWindowState {
             scale_factor,
             callbacks,
             input_handler,
+            touch_keyboard,
+            touch_keyboard_inset,
+            taskbar_button,
             renderer,
+            present_count,
+            last_present_qpc,
+            dwm_frames_dropped_at_open,
+            last_input_latency,
             click_state,
+            touch_state,
+            wheel_scroll,
             system_settings,
             current_cursor,
             nc_button_pressed,
+            min_size,
+            max_size,
+            pointer_locked,
+            raw_mouse_input,
+            cursor_visibility,
+            mouse_tracked,
+            captured_button,
+            pending_high_surrogate,
+            pending_dead_key,
+            composing_dead_key,
+            window_menu,
+            pending_external_drag,
             display,
+            appearance,
+            frame_appearance,
+            level,
             fullscreen,
+            title,
+            edited,
             hwnd,
         }
     }
@@ -158,8 +255,13 @@ impl WindowsWindowState {
     ///
     /// Currently, GPUI uses logical size of the app to handle mouse interactions (such as
     /// whether the mouse collides with other elements of GPUI).
-    fn content_size(&self) -> Size<Pixels> {
-        self.logical_size
+    ///
+    /// The part of the window covered by the touch keyboard is left out while it hides the caret.
+    pub(crate) fn content_size(&self) -> Size<Pixels> {
+        size(
+            self.logical_size.width,
+            self.logical_size.height - self.touch_keyboard_inset,
+        )
     }
 
     fn title_bar_padding(&self) -> Pixels {
@@ -195,6 +297,14 @@ impl WindowsWindowState {
         rect.bottom = rect.top + ((height.0 * self.scale_factor).round() as i32);
         Ok(rect)
     }
+
+    pub(crate) fn titlebar_layout(&self) -> Option<TitlebarLayout> {
+        let rect = self.get_titlebar_rect().ok()?;
+        Some(TitlebarLayout {
+            rect,
+            caption_button_width: (self.caption_button_width().0 * self.scale_factor) as i32,
+        })
+    }
 }
 
 impl WindowsWindowStatePtr {
@@ -205,16 +315,165 @@ impl WindowsWindowStatePtr {
             cs,
             context.current_cursor,
             context.display,
+            context.min_size,
         ));
 
         Rc::new(Self {
             state,
             hwnd,
             handle: context.handle,
-            hide_title_bar: context.hide_title_bar,
+            hide_title_bar: Cell::new(context.hide_title_bar),
             executor: context.executor.clone(),
         })
     }
+
+    pub(crate) fn lock_pointer(&self) {
+        if self.state.borrow().pointer_locked {
+            return;
+        }
+        // relative movement is only available through raw input, the cursor itself can't move
+        self.state.borrow_mut().pointer_locked = true;
+        if !self.update_raw_mouse_input() {
+            self.state.borrow_mut().pointer_locked = false;
+            return;
+        }
+        let mut position = POINT::default();
+        unsafe { GetCursorPos(&mut position) }.log_err();
+        let clip = RECT {
+            left: position.x,
+            top: position.y,
+            right: position.x + 1,
+            bottom: position.y + 1,
+        };
+        unsafe {
+            ClipCursor(Some(&clip as *const _)).log_err();
+            SetCursor(None);
+        }
+    }
+
+    /// Raw mouse input is received while the pointer is locked and while a button is held
+    /// down, which gives drags movement that is neither scaled by pointer acceleration nor
+    /// stopped at the edges of the screen. Returns whether the registration is up to date.
+    pub(crate) fn update_raw_mouse_input(&self) -> bool {
+        let mut lock = self.state.borrow_mut();
+        let wanted = lock.pointer_locked || lock.captured_button.is_some();
+        if lock.raw_mouse_input == wanted {
+            return true;
+        }
+        let updated = RAW_MOUSE_INPUT_WINDOWS.with_borrow_mut(|windows| {
+            let mut wanting = windows.clone();
+            wanting.retain(|hwnd| *hwnd != self.hwnd);
+            if wanted {
+                wanting.push(self.hwnd);
+            }
+            let updated = register_raw_mouse_input(wanting.last().copied())
+                .log_err()
+                .is_some();
+            if updated {
+                *windows = wanting;
+            }
+            updated
+        });
+        if updated {
+            lock.raw_mouse_input = wanted;
+        }
+        updated
+    }
+
+    /// Windows only checks the constraints from `WM_GETMINMAXINFO` when the window is being
+    /// resized, so it's resized to its current size to bring it within new ones right away.
+    /// That's deferred, as the resize reports back to the app that's setting the constraints.
+    fn apply_size_constraints(&self) {
+        let hwnd = self.hwnd;
+        self.executor
+            .spawn(async move {
+                let mut rect = RECT::default();
+                unsafe {
+                    GetWindowRect(hwnd, &mut rect).log_err();
+                    SetWindowPos(
+                        hwnd,
+                        None,
+                        0,
+                        0,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+                    )
+                    .log_err();
+                }
+            })
+            .detach();
+    }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        let change = self
+            .state
+            .borrow_mut()
+            .cursor_visibility
+            .set_hidden(!visible);
+        Self::show_cursor(change);
+    }
+
+    /// Hides the cursor until the mouse moves, when the user asked Windows to hide it while
+    /// typing.
+    pub(crate) fn hide_cursor_while_typing(&self) {
+        let mut lock = self.state.borrow_mut();
+        if !lock.system_settings.hide_cursor_while_typing {
+            return;
+        }
+        let change = lock.cursor_visibility.set_hidden_while_typing(true);
+        drop(lock);
+        Self::show_cursor(change);
+    }
+
+    pub(crate) fn show_cursor_after_typing(&self) {
+        let change = self
+            .state
+            .borrow_mut()
+            .cursor_visibility
+            .set_hidden_while_typing(false);
+        Self::show_cursor(change);
+    }
+
+    /// `ShowCursor` keeps a display count shared by all windows of the thread, so each window
+    /// only moves it once when hiding and once when showing the cursor again.
+    fn show_cursor(change: Option<bool>) {
+        if let Some(visible) = change {
+            unsafe { ShowCursor(visible) };
+        }
+    }
+
+    pub(crate) fn unlock_pointer(&self) {
+        let cursor = {
+            let mut lock = self.state.borrow_mut();
+            if !lock.pointer_locked {
+                return;
+            }
+            lock.pointer_locked = false;
+            scaled_cursor(lock.current_cursor, lock.scale_factor)
+        };
+        self.update_raw_mouse_input();
+        unsafe {
+            ClipCursor(None).log_err();
+            SetCursor(cursor);
+        }
+    }
+
+    /// Sets the window text to the title, followed by an asterisk while the window is edited,
+    /// unless the title bar is drawn by the application.
+    fn update_window_text(&self) {
+        let text = {
+            let state = self.state.borrow();
+            if state.edited && !self.hide_title_bar.get() {
+                HSTRING::from(format!("{} *", state.title))
+            } else {
+                state.title.clone()
+            }
+        };
+        unsafe { SetWindowTextW(self.hwnd, &text) }
+            .inspect_err(|e| log::error!("Set title failed: {e}"))
+            .ok();
+    }
 }
 
 #[derive(Default)]
@@ -227,6 +486,13 @@ pub(crate) struct Callbacks {
     pub(crate) should_close: Option<Box<dyn FnMut() -> bool>>,
     pub(crate) close: Option<Box<dyn FnOnce()>>,
     pub(crate) appearance_changed: Option<Box<dyn FnMut()>>,
+    pub(crate) window_menu_action: Option<Box<dyn FnMut(&dyn Action)>>,
+}
+
+/// A custom item that was appended to the system menu of a window.
+pub(crate) struct WindowMenuEntry {
+    pub(crate) command_id: u32,
+    pub(crate) action: Box<dyn Action>,
 }
 
 struct WindowCreateContext {
@@ -237,6 +503,7 @@ struct WindowCreateContext {
     transparent: bool,
     executor: ForegroundExecutor,
     current_cursor: HCURSOR,
+    min_size: Option<Size<Pixels>>,
 }
 
 impl WindowsWindow {
@@ -263,12 +530,26 @@ impl WindowsWindow {
         );
         let dwstyle = WS_THICKFRAME | WS_SYSMENU | WS_MAXIMIZEBOX | WS_MINIMIZEBOX;
         let hinstance = get_module_handle();
-        let display = if let Some(display_id) = params.display_id {
-            // if we obtain a display_id, then this ID must be valid.
-            WindowsDisplay::new(display_id).unwrap()
+        // the display may have been disconnected since its id was looked up
+        let display = params
+            .display_id
+            .and_then(WindowsDisplay::new)
+            .or_else(WindowsDisplay::primary_monitor)
+            .unwrap();
+        // the bounds may be not inside the display
+        let mut bounds = if display.check_given_bounds(params.bounds) {
+            params.bounds
         } else {
-            WindowsDisplay::primary_monitor().unwrap()
+            display.default_bounds()
         };
+        if let Some(min_size) = params.window_min_size {
+            bounds.size.width = bounds.size.width.max(min_size.width);
+            bounds.size.height = bounds.size.height.max(min_size.height);
+        }
+        // Scaled for the display the window lands on, and created right there, so that it
+        // starts out with that display's DPI instead of being resized by `WM_DPICHANGED` after
+        // being placed from the primary display.
+        let bounds = bounds.to_device_pixels(display.scale_factor());
         let mut context = WindowCreateContext {
             inner: None,
             handle,
@@ -277,6 +558,7 @@ impl WindowsWindow {
             transparent: params.window_background != WindowBackgroundAppearance::Opaque,
             executor,
             current_cursor,
+            min_size: params.window_min_size,
         };
         let lpparam = Some(&context as *const _ as *const _);
         let raw_hwnd = unsafe {
@@ -285,10 +567,10 @@ impl WindowsWindow {
                 classname,
                 &windowname,
                 dwstyle,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
+                bounds.left().0,
+                bounds.top().0,
+                bounds.size.width.0,
+                bounds.size.height.0,
                 None,
                 None,
                 hinstance,
@@ -296,7 +578,13 @@ impl WindowsWindow {
             )
         };
         let state_ptr = Rc::clone(context.inner.as_ref().unwrap());
+        // set before the window is shown, or its frame flashes light on a dark system
+        set_frame_appearance(raw_hwnd, state_ptr.state.borrow().appearance);
+        if params.window_background != WindowBackgroundAppearance::Opaque {
+            set_window_backdrop(raw_hwnd, params.window_background);
+        }
         register_drag_drop(state_ptr.clone());
+        unsafe { WTSRegisterSessionNotification(raw_hwnd, NOTIFY_FOR_THIS_SESSION) }.log_err();
         let wnd = Self(state_ptr);
 
         unsafe {
@@ -305,13 +593,6 @@ impl WindowsWindow {
                 ..Default::default()
             };
             GetWindowPlacement(raw_hwnd, &mut placement).log_err();
-            // the bounds may be not inside the display
-            let bounds = if display.check_given_bounds(params.bounds) {
-                params.bounds
-            } else {
-                display.default_bounds()
-            };
-            let bounds = bounds.to_device_pixels(wnd.0.state.borrow().scale_factor);
             placement.rcNormalPosition.left = bounds.left().0;
             placement.rcNormalPosition.right = bounds.right().0;
             placement.rcNormalPosition.top = bounds.top().0;
@@ -322,6 +603,34 @@ impl WindowsWindow {
 
         wnd
     }
+
+    /// Moves the renderer to a new GPU context once its device was lost, and has the whole
+    /// window drawn again, as the tiles of its atlas were lost too. That's deferred, as the
+    /// window reports being resized back to the app that's drawing it.
+    fn recover_renderer(&self) {
+        let state_ptr = self.0.clone();
+        self.0
+            .executor
+            .spawn(async move {
+                // frames drawn in the meantime asked to recover too
+                if !state_ptr.state.borrow().renderer.is_device_lost() {
+                    return;
+                }
+                let Some(gpu) = windows_renderer::windows_gpu(state_ptr.hwnd).log_err() else {
+                    return;
+                };
+                let mut lock = state_ptr.state.borrow_mut();
+                lock.renderer.recover(gpu);
+                let content_size = lock.content_size();
+                let scale_factor = lock.scale_factor;
+                if let Some(mut callback) = lock.callbacks.resize.take() {
+                    drop(lock);
+                    callback(content_size, scale_factor);
+                    state_ptr.state.borrow_mut().callbacks.resize = Some(callback);
+                }
+            })
+            .detach();
+    }
 }
 
 impl rwh::HasWindowHandle for WindowsWindow {
@@ -351,6 +660,7 @@ impl Drop for WindowsWindow {
                 let handle = this.hwnd;
                 unsafe {
                     RevokeDragDrop(handle).log_err();
+                    WTSUnRegisterSessionNotification(handle).log_err();
                     DestroyWindow(handle).log_err();
                 }
             })
@@ -383,9 +693,8 @@ impl PlatformWindow for WindowsWindow {
         self.0.state.borrow().scale_factor
     }
 
-    // todo(windows)
     fn appearance(&self) -> WindowAppearance {
-        WindowAppearance::Dark
+        self.0.state.borrow().appearance
     }
 
     fn display(&self) -> Option<Rc<dyn PlatformDisplay>> {
@@ -410,6 +719,10 @@ impl PlatformWindow for WindowsWindow {
         Modifiers::none()
     }
 
+    fn capslock(&self) -> Capslock {
+        current_capslock()
+    }
+
     fn set_input_handler(&mut self, input_handler: PlatformInputHandler) {
         self.0.state.borrow_mut().input_handler = Some(input_handler);
     }
@@ -418,6 +731,14 @@ impl PlatformWindow for WindowsWindow {
         self.0.state.borrow_mut().input_handler.take()
     }
 
+    fn set_text_input_focused(&self, focused: bool) {
+        self.0
+            .state
+            .borrow_mut()
+            .touch_keyboard
+            .set_text_input_focused(focused);
+    }
+
     fn prompt(
         &self,
         level: PromptLevel,
@@ -427,64 +748,16 @@ impl PlatformWindow for WindowsWindow {
     ) -> Option<Receiver<usize>> {
         let (done_tx, done_rx) = oneshot::channel();
         let msg = msg.to_string();
-        let detail_string = match detail {
-            Some(info) => Some(info.to_string()),
-            None => None,
-        };
-        let answers = answers.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let detail = detail.map(ToString::to_string);
+        let answers = answers.iter().map(ToString::to_string).collect::<Vec<_>>();
         let handle = self.0.hwnd;
+        // the dialog runs a modal loop, which would otherwise dispatch messages to the window
+        // while the caller still has it borrowed
         self.0
             .executor
             .spawn(async move {
-                unsafe {
-                    let mut config;
-                    config = std::mem::zeroed::<TASKDIALOGCONFIG>();
-                    config.cbSize = std::mem::size_of::<TASKDIALOGCONFIG>() as _;
-                    config.hwndParent = handle;
-                    let title;
-                    let main_icon;
-                    match level {
-                        crate::PromptLevel::Info => {
-                            title = windows::core::w!("Info");
-                            main_icon = TD_INFORMATION_ICON;
-                        }
-                        crate::PromptLevel::Warning => {
-                            title = windows::core::w!("Warning");
-                            main_icon = TD_WARNING_ICON;
-                        }
-                        crate::PromptLevel::Critical => {
-                            title = windows::core::w!("Critical");
-                            main_icon = TD_ERROR_ICON;
-                        }
-                    };
-                    config.pszWindowTitle = title;
-                    config.Anonymous1.pszMainIcon = main_icon;
-                    let instruction = msg.encode_utf16().chain(Some(0)).collect_vec();
-                    config.pszMainInstruction = PCWSTR::from_raw(instruction.as_ptr());
-                    let hints_encoded;
-                    if let Some(ref hints) = detail_string {
-                        hints_encoded = hints.encode_utf16().chain(Some(0)).collect_vec();
-                        config.pszContent = PCWSTR::from_raw(hints_encoded.as_ptr());
-                    };
-                    let mut buttons = Vec::new();
-                    let mut btn_encoded = Vec::new();
-                    for (index, btn_string) in answers.iter().enumerate() {
-                        let encoded = btn_string.encode_utf16().chain(Some(0)).collect_vec();
-                        buttons.push(TASKDIALOG_BUTTON {
-                            nButtonID: index as _,
-                            pszButtonText: PCWSTR::from_raw(encoded.as_ptr()),
-                        });
-                        btn_encoded.push(encoded);
-                    }
-                    config.cButtons = buttons.len() as _;
-                    config.pButtons = buttons.as_ptr();
-
-                    config.pfCallback = None;
-                    let mut res = std::mem::zeroed();
-                    let _ = TaskDialogIndirect(&config, Some(&mut res), None, None)
-                        .inspect_err(|e| log::error!("unable to create task dialog: {}", e));
-
-                    let _ = done_tx.send(res as usize);
+                if let Some(answer) = show_prompt_dialog(handle, level, &msg, detail, &answers) {
+                    done_tx.send(answer).ok();
                 }
             })
             .detach();
@@ -501,14 +774,158 @@ impl PlatformWindow for WindowsWindow {
         unsafe { SetForegroundWindow(hwnd).as_bool() };
     }
 
+    fn request_activation(&self) {
+        let hwnd = self.0.hwnd;
+        if unsafe { IsIconic(hwnd) }.as_bool() {
+            unsafe { ShowWindowAsync(hwnd, SW_RESTORE).ok().log_err() };
+        }
+        // the system only lets the foreground process, or one it allowed through
+        // `AllowSetForegroundWindow`, take the foreground. When we aren't one of those, the
+        // taskbar button flashes until the user switches to the window.
+        if !unsafe { SetForegroundWindow(hwnd) }.as_bool() {
+            flash_window(hwnd, FLASHW_TRAY | FLASHW_TIMERNOFG, 0);
+        }
+    }
+
+    fn set_progress(&self, progress: WindowProgress) {
+        self.0
+            .state
+            .borrow_mut()
+            .taskbar_button
+            .set_progress(progress);
+    }
+
+    fn set_badge(&self, badge: Option<WindowBadge>) {
+        self.0.state.borrow_mut().taskbar_button.set_badge(badge);
+    }
+
+    fn set_thumbnail_buttons(&self, buttons: Vec<ThumbnailButton>) {
+        self.0
+            .state
+            .borrow_mut()
+            .taskbar_button
+            .set_thumbnail_buttons(buttons);
+    }
+
+    fn window_placement(&self) -> Option<WindowPlacement> {
+        let hwnd = self.0.hwnd;
+        let mut placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        unsafe { GetWindowPlacement(hwnd, &mut placement) }.log_err()?;
+        let minimized = placement.showCmd == SW_SHOWMINIMIZED.0 as u32;
+        let maximized = if minimized {
+            (placement.flags & WPF_RESTORETOMAXIMIZED).0 != 0
+        } else {
+            placement.showCmd == SW_SHOWMAXIMIZED.0 as u32
+        };
+        // a minimized window is on the display it's restored to
+        let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+        let display_uuid = WindowsDisplay::all()
+            .into_iter()
+            .find(|display| display.handle == monitor)
+            .and_then(|display| display.uuid().ok());
+        // the normal position is in workspace coordinates, which don't count the taskbar when
+        // it's docked at the top or left of the primary display
+        let offset = workspace_offset();
+        let rect = placement.rcNormalPosition;
+        Some(WindowPlacement {
+            normal_bounds: Bounds {
+                origin: point(
+                    DevicePixels(rect.left + offset.x),
+                    DevicePixels(rect.top + offset.y),
+                ),
+                size: size(
+                    DevicePixels(rect.right - rect.left),
+                    DevicePixels(rect.bottom - rect.top),
+                ),
+            },
+            maximized,
+            minimized,
+            display_uuid,
+        })
+    }
+
+    fn set_window_placement(&self, placement: WindowPlacement) {
+        let offset = workspace_offset();
+        let bounds = placement.normal_bounds;
+        let mut rect = RECT {
+            left: bounds.left().0 + offset.x,
+            top: bounds.top().0 + offset.y,
+            right: bounds.right().0 + offset.x,
+            bottom: bounds.bottom().0 + offset.y,
+        };
+        let display = placement.display_uuid.and_then(|uuid| {
+            WindowsDisplay::all()
+                .into_iter()
+                .find(|display| display.uuid().ok() == Some(uuid))
+        });
+        let monitor = unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONULL) };
+        // the window is moved back to its display when the displays were rearranged, and onto
+        // the nearest one when it's off screen, which happens when its display was disconnected
+        let target_monitor = match display {
+            Some(display) if display.handle != monitor => Some(display.handle),
+            None if monitor.0 == 0 => {
+                Some(unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST) })
+            }
+            _ => None,
+        };
+        if let Some(info) = target_monitor.and_then(|monitor| get_monitor_info(monitor).log_err()) {
+            rect = center_rect(rect, info.monitorInfo.rcWork);
+        }
+        let show_cmd = if placement.minimized {
+            SW_SHOWMINIMIZED
+        } else if placement.maximized {
+            SW_SHOWMAXIMIZED
+        } else {
+            SW_SHOWNORMAL
+        };
+        let flags = if placement.minimized && placement.maximized {
+            WPF_RESTORETOMAXIMIZED
+        } else {
+            WINDOWPLACEMENT_FLAGS(0)
+        };
+        let window_placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            flags,
+            showCmd: show_cmd.0 as u32,
+            rcNormalPosition: RECT {
+                left: rect.left - offset.x,
+                top: rect.top - offset.y,
+                right: rect.right - offset.x,
+                bottom: rect.bottom - offset.y,
+            },
+            ..Default::default()
+        };
+        // deferred, as the window reports being resized back to the app that's placing it
+        let hwnd = self.0.hwnd;
+        self.0
+            .executor
+            .spawn(async move {
+                unsafe { SetWindowPlacement(hwnd, &window_placement) }.log_err();
+            })
+            .detach();
+    }
+
+    fn request_user_attention(&self, flash_count: Option<u32>) {
+        let hwnd = self.0.hwnd;
+        if unsafe { GetForegroundWindow() } == hwnd {
+            return;
+        }
+        match flash_count {
+            Some(count) => flash_window(hwnd, FLASHW_ALL, count),
+            None => flash_window(hwnd, FLASHW_ALL | FLASHW_TIMERNOFG, 0),
+        }
+    }
+
     fn is_active(&self) -> bool {
-        self.0.hwnd == unsafe { GetActiveWindow() }
+        self.0.hwnd == unsafe { GetFocus() }
     }
 
     fn set_title(&mut self, title: &str) {
-        unsafe { SetWindowTextW(self.0.hwnd, &HSTRING::from(title)) }
-            .inspect_err(|e| log::error!("Set title failed: {e}"))
-            .ok();
+        self.0.state.borrow_mut().title = HSTRING::from(title);
+        self.0.update_window_text();
     }
 
     fn set_app_id(&mut self, _app_id: &str) {}
@@ -519,14 +936,133 @@ impl PlatformWindow for WindowsWindow {
             .borrow_mut()
             .renderer
             .update_transparency(background_appearance != WindowBackgroundAppearance::Opaque);
+        set_window_backdrop(self.0.hwnd, background_appearance);
     }
 
-    // todo(windows)
-    fn set_edited(&mut self, _edited: bool) {}
+    // With the custom title bar, the indicator is rendered by the application instead.
+    fn set_edited(&mut self, edited: bool) {
+        self.0.state.borrow_mut().edited = edited;
+        self.0.update_window_text();
+    }
+
+    fn set_min_size(&self, size: Option<Size<Pixels>>) {
+        self.0.state.borrow_mut().min_size = size;
+        self.0.apply_size_constraints();
+    }
+
+    fn set_max_size(&self, size: Option<Size<Pixels>>) {
+        self.0.state.borrow_mut().max_size = size;
+        self.0.apply_size_constraints();
+    }
+
+    fn set_opacity(&self, opacity: f32) {
+        let hwnd = self.0.hwnd;
+        let ex_style = WINDOW_EX_STYLE(unsafe { get_window_long(hwnd, GWL_EXSTYLE) } as _);
+        if opacity >= 1. {
+            // layered windows are composed through an extra copy, so the style is only kept
+            // while the window is see-through
+            if ex_style.contains(WS_EX_LAYERED) {
+                unsafe {
+                    set_window_long(hwnd, GWL_EXSTYLE, (ex_style & !WS_EX_LAYERED).0 as isize)
+                };
+            }
+            return;
+        }
+        if !ex_style.contains(WS_EX_LAYERED) {
+            unsafe { set_window_long(hwnd, GWL_EXSTYLE, (ex_style | WS_EX_LAYERED).0 as isize) };
+        }
+        let alpha = (opacity.max(0.) * 255.).round() as u8;
+        unsafe { SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA) }.log_err();
+    }
+
+    fn set_level(&self, level: WindowLevel) {
+        self.0.state.borrow_mut().level = level;
+        // the app is active when one of its windows, which all belong to this thread, is
+        let app_active = unsafe { GetActiveWindow() }.0 != 0;
+        set_topmost(self.0.hwnd, level, app_active);
+    }
+
+    fn set_corner_preference(&self, preference: WindowCornerPreference) {
+        let preference = match preference {
+            WindowCornerPreference::Default => DWMWCP_DEFAULT,
+            WindowCornerPreference::Square => DWMWCP_DONOTROUND,
+            WindowCornerPreference::Round => DWMWCP_ROUND,
+            WindowCornerPreference::RoundSmall => DWMWCP_ROUNDSMALL,
+        };
+        // windows before Windows 11 don't know the attribute, and their corners are square anyway
+        unsafe {
+            DwmSetWindowAttribute(
+                self.0.hwnd,
+                DWMWA_WINDOW_CORNER_PREFERENCE,
+                &preference as *const DWM_WINDOW_CORNER_PREFERENCE as *const _,
+                std::mem::size_of::<DWM_WINDOW_CORNER_PREFERENCE>() as u32,
+            )
+        }
+        .ok();
+    }
+
+    fn set_titlebar_appears_transparent(&self, appears_transparent: bool) {
+        if self.0.hide_title_bar.replace(appears_transparent) == appears_transparent {
+            return;
+        }
+        self.0.update_window_text();
+        // the style of a fullscreen window is restored, with the new frame, when it leaves
+        // fullscreen
+        if self.0.state.borrow().is_fullscreen() {
+            return;
+        }
+        let hwnd = self.0.hwnd;
+        unsafe {
+            // the native title bar needs a caption, which the custom one keeps for snapping
+            let style = WINDOW_STYLE(get_window_long(hwnd, GWL_STYLE) as _);
+            if !style.contains(WS_CAPTION) {
+                set_window_long(hwnd, GWL_STYLE, (style | WS_CAPTION).0 as isize);
+            }
+            // recalculates the client area, keeping the window where it is
+            SetWindowPos(
+                hwnd,
+                HWND::default(),
+                0,
+                0,
+                0,
+                0,
+                SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+            .log_err();
+        }
+    }
+
+    fn titlebar_appears_transparent(&self) -> bool {
+        self.0.hide_title_bar.get()
+    }
+
+    fn set_frame_appearance(&self, appearance: Option<WindowAppearance>) {
+        let mut lock = self.0.state.borrow_mut();
+        lock.frame_appearance = appearance;
+        let appearance = appearance.unwrap_or(lock.appearance);
+        drop(lock);
+        set_frame_appearance(self.0.hwnd, appearance);
+    }
 
     // todo(windows)
     fn show_character_palette(&self) {}
 
+    fn lock_pointer(&self) {
+        self.0.lock_pointer();
+    }
+
+    fn unlock_pointer(&self) {
+        self.0.unlock_pointer();
+    }
+
+    fn is_pointer_locked(&self) -> bool {
+        self.0.state.borrow().pointer_locked
+    }
+
+    fn set_cursor_visible(&self, visible: bool) {
+        self.0.set_cursor_visible(visible);
+    }
+
     fn minimize(&self) {
         unsafe { ShowWindowAsync(self.0.hwnd, SW_MINIMIZE).ok().log_err() };
     }
@@ -635,18 +1171,165 @@ impl PlatformWindow for WindowsWindow {
     }
 
     fn draw(&self, scene: &Scene) {
-        self.0.state.borrow_mut().renderer.draw(scene)
+        let mut lock = self.0.state.borrow_mut();
+        lock.renderer.draw(scene);
+        if lock.renderer.is_device_lost() {
+            drop(lock);
+            self.recover_renderer();
+            return;
+        }
+        lock.present_count += 1;
+        unsafe { QueryPerformanceCounter(&mut lock.last_present_qpc).log_err() };
     }
 
     fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas> {
         self.0.state.borrow().renderer.sprite_atlas().clone()
     }
 
+    fn render_target_info(&self) -> Option<RenderTargetInfo> {
+        Some(self.0.state.borrow().renderer.render_target_info())
+    }
+
+    fn render_to_image(
+        &self,
+        scene: &Scene,
+        size: Size<DevicePixels>,
+    ) -> anyhow::Result<image::RgbaImage> {
+        self.0
+            .state
+            .borrow_mut()
+            .renderer
+            .render_to_image(scene, size)
+    }
+
+    fn start_external_drag(
+        &self,
+        data: ExternalDragData,
+        on_complete: Box<dyn FnOnce(ExternalDragOutcome)>,
+    ) -> anyhow::Result<()> {
+        let mut lock = self.0.state.borrow_mut();
+        if lock.pending_external_drag.is_some() {
+            return Err(anyhow::anyhow!(
+                "a drag out of the window is already starting"
+            ));
+        }
+        lock.pending_external_drag = Some((data, on_complete));
+        drop(lock);
+        let posted =
+            unsafe { PostMessageW(self.0.hwnd, START_EXTERNAL_DRAG, WPARAM(0), LPARAM(0)) };
+        if posted.is_err() {
+            self.0.state.borrow_mut().pending_external_drag = None;
+        }
+        posted.context("unable to start dragging out of the window")
+    }
+
+    // The renderer presents through Vulkan rather than a DXGI swap chain, so the statistics
+    // come from the timing information of the desktop compositor instead.
+    fn present_statistics(&self) -> Option<PresentStatistics> {
+        let lock = self.0.state.borrow();
+        let timing = dwm_timing_info()?;
+        let mut frequency = 0;
+        unsafe { QueryPerformanceFrequency(&mut frequency).log_err()? };
+        let present_latency = (lock.last_present_qpc > 0 && frequency > 0).then(|| {
+            let refresh_period = (timing.qpcRefreshPeriod as i64).max(1);
+            let ticks =
+                (timing.qpcVBlank as i64 - lock.last_present_qpc).rem_euclid(refresh_period);
+            Duration::from_secs_f64(ticks as f64 / frequency as f64)
+        });
+        Some(PresentStatistics {
+            present_count: lock.present_count,
+            dropped_frames: timing
+                .cFramesDropped
+                .saturating_sub(lock.dwm_frames_dropped_at_open),
+            present_latency,
+            input_latency: lock.last_input_latency,
+        })
+    }
+
     fn get_raw_handle(&self) -> HWND {
         self.0.hwnd
     }
 
-    fn show_window_menu(&self, _position: Point<Pixels>) {}
+    fn set_window_menu(&self, items: Vec<WindowMenuItem>) {
+        let hwnd = self.0.hwnd;
+        // reverting the menu first drops the items appended by a previous call
+        unsafe { GetSystemMenu(hwnd, TRUE) };
+        let menu = unsafe { GetSystemMenu(hwnd, FALSE) };
+        if menu.is_invalid() {
+            log::error!("unable to get the system menu of window {:?}", hwnd);
+            return;
+        }
+        let mut entries = Vec::new();
+        if !items.is_empty() {
+            unsafe { AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()) }.log_err();
+        }
+        for item in items {
+            match item {
+                WindowMenuItem::Separator => {
+                    unsafe { AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()) }.log_err();
+                }
+                WindowMenuItem::Action {
+                    name,
+                    action,
+                    checked,
+                } => {
+                    let Some(command_id) = window_menu_command_id(entries.len()) else {
+                        log::error!("too many items in the window menu, dropping {:?}", name);
+                        break;
+                    };
+                    let flags = if checked {
+                        MF_STRING | MF_CHECKED
+                    } else {
+                        MF_STRING | MF_UNCHECKED
+                    };
+                    unsafe {
+                        AppendMenuW(
+                            menu,
+                            flags,
+                            command_id as usize,
+                            &HSTRING::from(name.as_ref()),
+                        )
+                    }
+                    .log_err();
+                    entries.push(WindowMenuEntry { command_id, action });
+                }
+            }
+        }
+        self.0.state.borrow_mut().window_menu = entries;
+    }
+
+    fn set_window_menu_item_checked(&self, action: &dyn Action, checked: bool) {
+        let lock = self.0.state.borrow();
+        let Some(entry) = lock
+            .window_menu
+            .iter()
+            .find(|entry| entry.action.partial_eq(action))
+        else {
+            return;
+        };
+        let menu = unsafe { GetSystemMenu(self.0.hwnd, FALSE) };
+        if menu.is_invalid() {
+            return;
+        }
+        let check = if checked { MF_CHECKED } else { MF_UNCHECKED };
+        unsafe { CheckMenuItem(menu, entry.command_id, (MF_BYCOMMAND | check).0) };
+    }
+
+    fn on_window_menu_action(&self, callback: Box<dyn FnMut(&dyn Action)>) {
+        self.0.state.borrow_mut().callbacks.window_menu_action = Some(callback);
+    }
+
+    fn show_window_menu(&self, position: Point<Pixels>) {
+        let scale_factor = self.0.state.borrow().scale_factor;
+        let mut point = POINT {
+            x: (position.x.0 * scale_factor) as i32,
+            y: (position.y.0 * scale_factor) as i32,
+        };
+        unsafe { ClientToScreen(self.0.hwnd, &mut point) }
+            .ok()
+            .log_err();
+        show_system_menu(self.0.hwnd, point);
+    }
 
     fn start_system_move(&self) {}
 
@@ -656,16 +1339,40 @@ impl PlatformWindow for WindowsWindow {
 }
 
 #[implement(IDropTarget)]
-struct WindowsDragDropHandler(pub Rc<WindowsWindowStatePtr>);
+struct WindowsDragDropHandler {
+    state_ptr: Rc<WindowsWindowStatePtr>,
+    // the effect reported for the drag over the window, or `None` when it carries nothing the
+    // app is told about
+    drop_effect: Cell<Option<DROPEFFECT>>,
+}
+
+/// Text drags are reported to the app, but nothing takes the text yet, so the cursor
+/// doesn't promise a drop.
+const TEXT_DROP_EFFECT: DROPEFFECT = DROPEFFECT_NONE;
 
 impl WindowsDragDropHandler {
     fn handle_drag_drop(&self, input: PlatformInput) {
-        let mut lock = self.0.state.borrow_mut();
+        let mut lock = self.state_ptr.state.borrow_mut();
         if let Some(mut func) = lock.callbacks.input.take() {
             drop(lock);
             func(input);
-            self.0.state.borrow_mut().callbacks.input = Some(func);
+            self.state_ptr.state.borrow_mut().callbacks.input = Some(func);
+        }
+    }
+
+    fn client_position(&self, pt: &POINTL) -> Point<Pixels> {
+        let mut cursor_position = POINT { x: pt.x, y: pt.y };
+        unsafe {
+            ScreenToClient(self.state_ptr.hwnd, &mut cursor_position)
+                .ok()
+                .log_err();
         }
+        let scale_factor = self.state_ptr.state.borrow().scale_factor;
+        logical_point(
+            cursor_position.x as f32,
+            cursor_position.y as f32,
+            scale_factor,
+        )
     }
 }
 
@@ -678,63 +1385,34 @@ impl IDropTarget_Impl for WindowsDragDropHandler {
         pt: &POINTL,
         pdweffect: *mut DROPEFFECT,
     ) -> windows::core::Result<()> {
-        unsafe {
-            let Some(idata_obj) = pdataobj else {
-                log::info!("no dragging file or directory detected");
-                return Ok(());
-            };
-            let config = FORMATETC {
-                cfFormat: CF_HDROP.0,
-                ptd: std::ptr::null_mut() as _,
-                dwAspect: DVASPECT_CONTENT.0,
-                lindex: -1,
-                tymed: TYMED_HGLOBAL.0 as _,
-            };
-            if idata_obj.QueryGetData(&config as _) == S_OK {
-                *pdweffect = DROPEFFECT_LINK;
-                let Some(mut idata) = idata_obj.GetData(&config as _).log_err() else {
-                    return Ok(());
-                };
-                if idata.u.hGlobal.is_invalid() {
-                    return Ok(());
-                }
-                let hdrop = idata.u.hGlobal.0 as *mut HDROP;
-                let mut paths = SmallVec::<[PathBuf; 2]>::new();
-                let file_count = DragQueryFileW(*hdrop, DRAGDROP_GET_FILES_COUNT, None);
-                for file_index in 0..file_count {
-                    let filename_length = DragQueryFileW(*hdrop, file_index, None) as usize;
-                    let mut buffer = vec![0u16; filename_length + 1];
-                    let ret = DragQueryFileW(*hdrop, file_index, Some(buffer.as_mut_slice()));
-                    if ret == 0 {
-                        log::error!("unable to read file name");
-                        continue;
-                    }
-                    if let Some(file_name) =
-                        String::from_utf16(&buffer[0..filename_length]).log_err()
-                    {
-                        if let Some(path) = PathBuf::from_str(&file_name).log_err() {
-                            paths.push(path);
-                        }
-                    }
-                }
-                ReleaseStgMedium(&mut idata);
-                let mut cursor_position = POINT { x: pt.x, y: pt.y };
-                ScreenToClient(self.0.hwnd, &mut cursor_position)
-                    .ok()
-                    .log_err();
-                let scale_factor = self.0.state.borrow().scale_factor;
-                let input = PlatformInput::FileDrop(FileDropEvent::Entered {
-                    position: logical_point(
-                        cursor_position.x as f32,
-                        cursor_position.y as f32,
-                        scale_factor,
-                    ),
-                    paths: ExternalPaths(paths),
-                });
-                self.handle_drag_drop(input);
+        let position = self.client_position(pt);
+        // files take precedence, since file managers put the file names on the drag as text too
+        let entered = pdataobj.and_then(|data_object| {
+            if let Some(paths) = read_dropped_paths(data_object) {
+                Some((
+                    DROPEFFECT_LINK,
+                    FileDropEvent::Entered {
+                        position,
+                        paths: ExternalPaths(paths),
+                    },
+                ))
             } else {
-                *pdweffect = DROPEFFECT_NONE;
+                read_dropped_text(data_object).map(|text| {
+                    (
+                        TEXT_DROP_EFFECT,
+                        FileDropEvent::TextEntered {
+                            position,
+                            text: ExternalText(text.into()),
+                        },
+                    )
+                })
             }
+        });
+        let effect = entered.as_ref().map(|(effect, _)| *effect);
+        self.drop_effect.set(effect);
+        unsafe { *pdweffect = effect.unwrap_or(DROPEFFECT_NONE) };
+        if let Some((_, event)) = entered {
+            self.handle_drag_drop(PlatformInput::FileDrop(event));
         }
         Ok(())
     }
@@ -743,30 +1421,25 @@ impl IDropTarget_Impl for WindowsDragDropHandler {
         &self,
         _grfkeystate: MODIFIERKEYS_FLAGS,
         pt: &POINTL,
-        _pdweffect: *mut DROPEFFECT,
+        pdweffect: *mut DROPEFFECT,
     ) -> windows::core::Result<()> {
-        let mut cursor_position = POINT { x: pt.x, y: pt.y };
-        unsafe {
-            ScreenToClient(self.0.hwnd, &mut cursor_position)
-                .ok()
-                .log_err();
+        let effect = self.drop_effect.get();
+        unsafe { *pdweffect = effect.unwrap_or(DROPEFFECT_NONE) };
+        if effect.is_some() {
+            let input = PlatformInput::FileDrop(FileDropEvent::Pending {
+                position: self.client_position(pt),
+            });
+            self.handle_drag_drop(input);
         }
-        let scale_factor = self.0.state.borrow().scale_factor;
-        let input = PlatformInput::FileDrop(FileDropEvent::Pending {
-            position: logical_point(
-                cursor_position.x as f32,
-                cursor_position.y as f32,
-                scale_factor,
-            ),
-        });
-        self.handle_drag_drop(input);
 
         Ok(())
     }
 
     fn DragLeave(&self) -> windows::core::Result<()> {
-        let input = PlatformInput::FileDrop(FileDropEvent::Exited);
-        self.handle_drag_drop(input);
+        if self.drop_effect.take().is_some() {
+            let input = PlatformInput::FileDrop(FileDropEvent::Exited);
+            self.handle_drag_drop(input);
+        }
 
         Ok(())
     }
@@ -776,28 +1449,90 @@ impl IDropTarget_Impl for WindowsDragDropHandler {
         _pdataobj: Option<&IDataObject>,
         _grfkeystate: MODIFIERKEYS_FLAGS,
         pt: &POINTL,
-        _pdweffect: *mut DROPEFFECT,
+        pdweffect: *mut DROPEFFECT,
     ) -> windows::core::Result<()> {
-        let mut cursor_position = POINT { x: pt.x, y: pt.y };
-        unsafe {
-            ScreenToClient(self.0.hwnd, &mut cursor_position)
-                .ok()
-                .log_err();
+        let effect = self.drop_effect.take();
+        unsafe { *pdweffect = effect.unwrap_or(DROPEFFECT_NONE) };
+        match effect {
+            Some(effect) if effect != DROPEFFECT_NONE => {
+                let input = PlatformInput::FileDrop(FileDropEvent::Submit {
+                    position: self.client_position(pt),
+                });
+                self.handle_drag_drop(input);
+            }
+            // a drag the window refuses ends without a drop
+            Some(_) => self.handle_drag_drop(PlatformInput::FileDrop(FileDropEvent::Exited)),
+            None => {}
         }
-        let scale_factor = self.0.state.borrow().scale_factor;
-        let input = PlatformInput::FileDrop(FileDropEvent::Submit {
-            position: logical_point(
-                cursor_position.x as f32,
-                cursor_position.y as f32,
-                scale_factor,
-            ),
-        });
-        self.handle_drag_drop(input);
 
         Ok(())
     }
 }
 
+fn read_drop_data(data_object: &IDataObject, format: CLIPBOARD_FORMAT) -> Option<STGMEDIUM> {
+    let format = FORMATETC {
+        cfFormat: format.0,
+        ptd: std::ptr::null_mut() as _,
+        dwAspect: DVASPECT_CONTENT.0,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as _,
+    };
+    unsafe {
+        if data_object.QueryGetData(&format as _) != S_OK {
+            return None;
+        }
+        let mut medium = data_object.GetData(&format as _).log_err()?;
+        if medium.u.hGlobal.is_invalid() {
+            ReleaseStgMedium(&mut medium);
+            return None;
+        }
+        Some(medium)
+    }
+}
+
+fn read_dropped_paths(data_object: &IDataObject) -> Option<SmallVec<[PathBuf; 2]>> {
+    let mut medium = read_drop_data(data_object, CF_HDROP)?;
+    let mut paths = SmallVec::new();
+    unsafe {
+        let hdrop = medium.u.hGlobal.0 as *mut HDROP;
+        let file_count = DragQueryFileW(*hdrop, DRAGDROP_GET_FILES_COUNT, None);
+        for file_index in 0..file_count {
+            let filename_length = DragQueryFileW(*hdrop, file_index, None) as usize;
+            let mut buffer = vec![0u16; filename_length + 1];
+            let ret = DragQueryFileW(*hdrop, file_index, Some(buffer.as_mut_slice()));
+            if ret == 0 {
+                log::error!("unable to read file name");
+                continue;
+            }
+            if let Some(file_name) = String::from_utf16(&buffer[0..filename_length]).log_err() {
+                if let Some(path) = PathBuf::from_str(&file_name).log_err() {
+                    paths.push(path);
+                }
+            }
+        }
+        ReleaseStgMedium(&mut medium);
+    }
+    Some(paths)
+}
+
+fn read_dropped_text(data_object: &IDataObject) -> Option<String> {
+    let mut medium = read_drop_data(data_object, CF_UNICODETEXT)?;
+    let text = unsafe {
+        let hglobal = medium.u.hGlobal;
+        let data = GlobalLock(hglobal) as *const u16;
+        let text = (!data.is_null()).then(|| {
+            let buffer = std::slice::from_raw_parts(data, GlobalSize(hglobal) / 2);
+            let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+            String::from_utf16_lossy(&buffer[..len])
+        });
+        // unlocking the last lock reports `NO_ERROR` as a failure
+        GlobalUnlock(hglobal).ok();
+        ReleaseStgMedium(&mut medium);
+        text
+    };
+    text.filter(|text| !text.is_empty())
+}
+
 #[derive(Debug)]
 pub(crate) struct ClickState {
     button: MouseButton,
@@ -840,6 +1575,296 @@ impl ClickState {
     }
 }
 
+/// Turns the touch points reported by `WM_POINTER` messages into taps, scrolling and pinches.
+#[derive(Default)]
+pub(crate) struct TouchState {
+    points: SmallVec<[(u32, Point<Pixels>); 2]>,
+    gesture: Option<TouchGesture>,
+    /// The average distance of the touch points from their center, while two or more fingers
+    /// are down.
+    pinch_spread: Option<Pixels>,
+}
+
+#[derive(Clone, Copy)]
+enum TouchGesture {
+    /// A single finger that hasn't moved far enough to start scrolling.
+    Tap { origin: Point<Pixels> },
+    /// One or more fingers panning the content, tracked by the center of all touch points.
+    Scroll { center: Point<Pixels> },
+}
+
+pub(crate) enum TouchAction {
+    Tap(Point<Pixels>),
+    Scroll {
+        position: Point<Pixels>,
+        delta: Point<Pixels>,
+        phase: TouchPhase,
+    },
+    Pinch {
+        position: Point<Pixels>,
+        delta: f32,
+        phase: TouchPhase,
+    },
+}
+
+impl TouchState {
+    pub(crate) fn down(
+        &mut self,
+        pointer_id: u32,
+        position: Point<Pixels>,
+    ) -> SmallVec<[TouchAction; 2]> {
+        self.points.push((pointer_id, position));
+        let center = self.center();
+        let mut actions = SmallVec::new();
+        match self.gesture {
+            None => {
+                self.gesture = Some(TouchGesture::Tap { origin: position });
+            }
+            Some(TouchGesture::Tap { .. }) => {
+                self.gesture = Some(TouchGesture::Scroll { center });
+                actions.push(TouchAction::Scroll {
+                    position: center,
+                    delta: Point::default(),
+                    phase: TouchPhase::Started,
+                });
+            }
+            Some(TouchGesture::Scroll { .. }) => {
+                // the center jumps when a finger is added, which isn't a scroll
+                self.gesture = Some(TouchGesture::Scroll { center });
+            }
+        }
+        if self.points.len() >= 2 {
+            if self.pinch_spread.is_none() {
+                actions.push(TouchAction::Pinch {
+                    position: center,
+                    delta: 0.,
+                    phase: TouchPhase::Started,
+                });
+            }
+            // like the center, the spread jumps when a finger is added
+            self.pinch_spread = Some(self.spread());
+        }
+        actions
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        pointer_id: u32,
+        position: Point<Pixels>,
+    ) -> SmallVec<[TouchAction; 2]> {
+        let mut actions = SmallVec::new();
+        let Some(point) = self.points.iter_mut().find(|(id, _)| *id == pointer_id) else {
+            return actions;
+        };
+        point.1 = position;
+        let center = self.center();
+        match self.gesture {
+            None => {}
+            Some(TouchGesture::Tap { origin }) => {
+                let distance = position - origin;
+                if distance.x.abs() > TOUCH_SLOP || distance.y.abs() > TOUCH_SLOP {
+                    self.gesture = Some(TouchGesture::Scroll { center });
+                    actions.push(TouchAction::Scroll {
+                        position: center,
+                        delta: center - origin,
+                        phase: TouchPhase::Started,
+                    });
+                }
+            }
+            Some(TouchGesture::Scroll {
+                center: last_center,
+            }) => {
+                self.gesture = Some(TouchGesture::Scroll { center });
+                actions.push(TouchAction::Scroll {
+                    position: center,
+                    delta: center - last_center,
+                    phase: TouchPhase::Moved,
+                });
+            }
+        }
+        if let Some(last_spread) = self.pinch_spread {
+            let spread = self.spread();
+            self.pinch_spread = Some(spread);
+            if last_spread > px(0.) {
+                actions.push(TouchAction::Pinch {
+                    position: center,
+                    delta: spread / last_spread - 1.,
+                    phase: TouchPhase::Moved,
+                });
+            }
+        }
+        actions
+    }
+
+    pub(crate) fn up(&mut self, pointer_id: u32) -> SmallVec<[TouchAction; 2]> {
+        self.release(pointer_id, false)
+    }
+
+    /// Drops a touch point the system took over, such as for an edge swipe, or whose window
+    /// lost its capture. Gestures in progress end, but a tap isn't reported.
+    pub(crate) fn cancel(&mut self, pointer_id: u32) -> SmallVec<[TouchAction; 2]> {
+        self.release(pointer_id, true)
+    }
+
+    fn release(&mut self, pointer_id: u32, canceled: bool) -> SmallVec<[TouchAction; 2]> {
+        if !self.points.iter().any(|(id, _)| *id == pointer_id) {
+            return SmallVec::new();
+        }
+        self.points.retain(|(id, _)| *id != pointer_id);
+        let mut actions = SmallVec::new();
+        if self.pinch_spread.is_some() {
+            if self.points.len() < 2 {
+                self.pinch_spread = None;
+                actions.push(TouchAction::Pinch {
+                    position: self.center(),
+                    delta: 0.,
+                    phase: TouchPhase::Ended,
+                });
+            } else {
+                self.pinch_spread = Some(self.spread());
+            }
+        }
+        if !self.points.is_empty() {
+            if let Some(TouchGesture::Scroll { .. }) = self.gesture {
+                self.gesture = Some(TouchGesture::Scroll {
+                    center: self.center(),
+                });
+            }
+            return actions;
+        }
+        match self.gesture.take() {
+            None => {}
+            Some(TouchGesture::Tap { origin }) => {
+                if !canceled {
+                    actions.push(TouchAction::Tap(origin))
+                }
+            }
+            Some(TouchGesture::Scroll { center }) => actions.push(TouchAction::Scroll {
+                position: center,
+                delta: Point::default(),
+                phase: TouchPhase::Ended,
+            }),
+        }
+        actions
+    }
+
+    fn center(&self) -> Point<Pixels> {
+        let count = self.points.len().max(1) as f32;
+        let sum = self
+            .points
+            .iter()
+            .fold(Point::<Pixels>::default(), |sum, (_, position)| {
+                sum + *position
+            });
+        point(sum.x / count, sum.y / count)
+    }
+
+    fn spread(&self) -> Pixels {
+        let center = self.center();
+        let count = self.points.len().max(1) as f32;
+        let sum = self
+            .points
+            .iter()
+            .map(|(_, position)| {
+                let offset = *position - center;
+                (offset.x.0 * offset.x.0 + offset.y.0 * offset.y.0).sqrt()
+            })
+            .sum::<f32>();
+        px(sum / count)
+    }
+}
+
+/// Groups wheel messages into scroll gestures, since Windows doesn't report when one begins
+/// or ends the way macOS does. A gesture ends once no wheel message arrived for
+/// [`WHEEL_SCROLL_TIMEOUT`], which covers the inertia precision touchpads keep scrolling with.
+#[derive(Default)]
+pub(crate) struct WheelScrollState {
+    gesture: Option<WheelGesture>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct WheelGesture {
+    /// The position of the last wheel message of the gesture.
+    pub(crate) position: Point<Pixels>,
+    /// Whether the gesture comes from a precision touchpad or a high resolution wheel, which
+    /// report fractions of a notch that feel notchy when rounded to whole lines.
+    pub(crate) precise: bool,
+    /// Whether the gesture is a touchpad pinch, which Windows reports as scrolling with the
+    /// control key held.
+    pub(crate) pinch: bool,
+}
+
+impl WheelScrollState {
+    /// Adds a wheel message to the gesture in progress, returning the gesture and whether
+    /// the message started it.
+    pub(crate) fn scroll(
+        &mut self,
+        wheel_delta: i16,
+        control: bool,
+        position: Point<Pixels>,
+    ) -> (WheelGesture, bool) {
+        let fractional = wheel_delta as i32 % WHEEL_DELTA as i32 != 0;
+        let (gesture, started) = match self.gesture {
+            Some(gesture) => (
+                WheelGesture {
+                    position,
+                    precise: gesture.precise || fractional,
+                    ..gesture
+                },
+                false,
+            ),
+            // turning a mouse wheel with control held moves by whole notches, so only
+            // fractional deltas are pinches
+            None => (
+                WheelGesture {
+                    position,
+                    precise: fractional,
+                    pinch: control && fractional,
+                },
+                true,
+            ),
+        };
+        self.gesture = Some(gesture);
+        (gesture, started)
+    }
+
+    /// Ends the gesture in progress, if any.
+    pub(crate) fn end(&mut self) -> Option<WheelGesture> {
+        self.gesture.take()
+    }
+}
+
+/// Why a window hides the cursor, which it's hidden for as long as there's a reason to.
+#[derive(Default)]
+pub(crate) struct CursorVisibility {
+    /// Hidden by the app, like while presenting.
+    hidden: bool,
+    /// Hidden while typing, until the mouse moves.
+    hidden_while_typing: bool,
+}
+
+impl CursorVisibility {
+    /// Returns whether the cursor is to be shown, when that changes.
+    pub(crate) fn set_hidden(&mut self, hidden: bool) -> Option<bool> {
+        self.update(|this| this.hidden = hidden)
+    }
+
+    /// Returns whether the cursor is to be shown, when that changes.
+    pub(crate) fn set_hidden_while_typing(&mut self, hidden: bool) -> Option<bool> {
+        self.update(|this| this.hidden_while_typing = hidden)
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden || self.hidden_while_typing
+    }
+
+    fn update(&mut self, update: impl FnOnce(&mut Self)) -> Option<bool> {
+        let was_hidden = self.is_hidden();
+        update(self);
+        (self.is_hidden() != was_hidden).then_some(was_hidden)
+    }
+}
+
 struct StyleAndBounds {
     style: WINDOW_STYLE,
     x: i32,
@@ -915,7 +1940,7 @@ pub(crate) fn try_get_window_inner(hwnd: HWND) -> Option<Rc<WindowsWindowStatePt
     }
 }
 
-fn get_module_handle() -> HMODULE {
+pub(crate) fn get_module_handle() -> HMODULE {
     unsafe {
         let mut h_module = std::mem::zeroed();
         GetModuleHandleExW(
@@ -931,7 +1956,10 @@ fn get_module_handle() -> HMODULE {
 
 fn register_drag_drop(state_ptr: Rc<WindowsWindowStatePtr>) {
     let window_handle = state_ptr.hwnd;
-    let handler = WindowsDragDropHandler(state_ptr);
+    let handler = WindowsDragDropHandler {
+        state_ptr,
+        drop_effect: Cell::new(None),
+    };
     // The lifetime of `IDropTarget` is handled by Windows, it wont release untill
     // we call `RevokeDragDrop`.
     // So, it's safe to drop it here.
@@ -948,13 +1976,283 @@ const DRAGDROP_GET_FILES_COUNT: u32 = 0xFFFFFFFF;
 const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
 // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsystemmetrics
 const DOUBLE_CLICK_SPATIAL_TOLERANCE: i32 = 4;
+// how far a finger can move before a tap turns into scrolling
+const TOUCH_SLOP: Pixels = px(8.);
+// Touchpads keep sending wheel messages through the inertia phase, so a gap this long means
+// the fingers were lifted and the content came to rest.
+pub(crate) const WHEEL_SCROLL_TIMEOUT: Duration = Duration::from_millis(150);
+// https://source.chromium.org/chromium/chromium/src/+/main:ui/events/event_constants.h
+pub(crate) const PRECISION_SCROLL_PIXELS_PER_LINE: f32 = 100. / 3.;
+// how much a touchpad pinch as long as one wheel notch magnifies the content
+pub(crate) const PINCH_MAGNIFICATION_PER_WHEEL_DELTA: f32 = 0.1;
+// https://learn.microsoft.com/en-us/windows/win32/menurc/wm-syscommand
+// the four low-order bits of a system command are used internally by Windows,
+// and the predefined commands all start from 0xF000.
+const WINDOW_MENU_FIRST_COMMAND_ID: u32 = 0x1000;
+const WINDOW_MENU_LAST_COMMAND_ID: u32 = 0xEFF0;
+// The ids of prompt buttons start past the ones of the standard buttons, like `IDCANCEL`,
+// which the task dialog also reports when it's closed.
+const PROMPT_BUTTON_FIRST_ID: i32 = 100;
+// https://learn.microsoft.com/en-us/windows-hardware/drivers/hid/hid-usages#usage-page
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
+
+thread_local! {
+    /// The windows that want raw mouse input, most recent last. The registration is shared by
+    /// the whole process and delivers the input to a single window, so it goes to the last one,
+    /// and is only removed once none of them want it anymore.
+    static RAW_MOUSE_INPUT_WINDOWS: RefCell<Vec<HWND>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Sends raw mouse input to the given window, or stops receiving it.
+fn register_raw_mouse_input(target: Option<HWND>) -> windows::core::Result<()> {
+    let device = RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: HID_USAGE_GENERIC_MOUSE,
+        dwFlags: if target.is_some() {
+            RAWINPUTDEVICE_FLAGS::default()
+        } else {
+            RIDEV_REMOVE
+        },
+        hwndTarget: target.unwrap_or_default(),
+    };
+    unsafe { RegisterRawInputDevices(&[device], std::mem::size_of::<RAWINPUTDEVICE>() as u32) }
+}
+
+/// The id of the command of the item at `index`, or `None` when the ids run into the
+/// predefined commands.
+fn window_menu_command_id(index: usize) -> Option<u32> {
+    u32::try_from(index)
+        .ok()
+        .and_then(|index| index.checked_mul(1 << 4))
+        .and_then(|offset| WINDOW_MENU_FIRST_COMMAND_ID.checked_add(offset))
+        .filter(|command_id| is_window_menu_command(*command_id))
+}
+
+/// Draws the frame and the native title bar dark to match a dark appearance, as Windows
+/// keeps them light for every app that doesn't ask otherwise.
+pub(crate) fn set_frame_appearance(hwnd: HWND, appearance: WindowAppearance) {
+    let dark = BOOL::from(matches!(
+        appearance,
+        WindowAppearance::Dark | WindowAppearance::VibrantDark
+    ));
+    unsafe {
+        DwmSetWindowAttribute(
+            hwnd,
+            DWMWA_USE_IMMERSIVE_DARK_MODE,
+            &dark as *const BOOL as *const _,
+            std::mem::size_of::<BOOL>() as u32,
+        )
+    }
+    .log_err();
+}
+
+/// Floating windows are only kept above the windows of other apps while this one is active.
+pub(crate) fn set_topmost(hwnd: HWND, level: WindowLevel, app_active: bool) {
+    let topmost = match level {
+        WindowLevel::Normal => false,
+        WindowLevel::Floating => app_active,
+        WindowLevel::AlwaysOnTop => true,
+    };
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            if topmost {
+                HWND_TOPMOST
+            } else {
+                HWND_NOTOPMOST
+            },
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+    }
+    .log_err();
+}
+
+fn flash_window(hwnd: HWND, flags: FLASHWINFO_FLAGS, count: u32) {
+    let info = FLASHWINFO {
+        cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: flags,
+        uCount: count,
+        dwTimeout: 0,
+    };
+    unsafe { FlashWindowEx(&info) };
+}
+
+/// Returns the offset of screen coordinates from the workspace coordinates `WINDOWPLACEMENT` is in,
+/// which start at the work area of the primary display rather than at the display itself.
+fn workspace_offset() -> POINT {
+    let primary = unsafe { MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY) };
+    get_monitor_info(primary)
+        .log_err()
+        .map_or(POINT::default(), |info| POINT {
+            x: info.monitorInfo.rcWork.left - info.monitorInfo.rcMonitor.left,
+            y: info.monitorInfo.rcWork.top - info.monitorInfo.rcMonitor.top,
+        })
+}
+
+/// Centers a rectangle in an area, shrinking it to fit.
+fn center_rect(rect: RECT, area: RECT) -> RECT {
+    let width = (rect.right - rect.left).min(area.right - area.left);
+    let height = (rect.bottom - rect.top).min(area.bottom - area.top);
+    let left = area.left + (area.right - area.left - width) / 2;
+    let top = area.top + (area.bottom - area.top - height) / 2;
+    RECT {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    }
+}
+
+/// Shows a task dialog with a button for each answer, and returns the index of the one that was
+/// picked. Like on macOS, the first answer is the default one, and an answer labeled "Cancel"
+/// is also picked by closing the dialog or pressing escape.
+fn show_prompt_dialog(
+    hwnd: HWND,
+    level: PromptLevel,
+    msg: &str,
+    detail: Option<String>,
+    answers: &[String],
+) -> Option<usize> {
+    let cancel_index = answers.iter().position(|answer| answer == "Cancel");
+    let button_id = |index| prompt_button_id(index, cancel_index);
+    let (title, main_icon) = match level {
+        PromptLevel::Info => (w!("Info"), TD_INFORMATION_ICON),
+        PromptLevel::Warning => (w!("Warning"), TD_WARNING_ICON),
+        PromptLevel::Critical => (w!("Critical"), TD_ERROR_ICON),
+    };
+    let instruction = HSTRING::from(msg);
+    let content = detail.map(HSTRING::from);
+    // the buttons point into these strings, which have to outlive the dialog
+    let labels = answers
+        .iter()
+        .map(|answer| HSTRING::from(answer.as_str()))
+        .collect::<Vec<_>>();
+    let buttons = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| TASKDIALOG_BUTTON {
+            nButtonID: button_id(index),
+            pszButtonText: PCWSTR(label.as_ptr()),
+        })
+        .collect::<Vec<_>>();
+    let mut flags = TDF_POSITION_RELATIVE_TO_WINDOW;
+    if cancel_index.is_some() {
+        flags |= TDF_ALLOW_DIALOG_CANCELLATION;
+    }
+    let mut config = TASKDIALOGCONFIG {
+        cbSize: std::mem::size_of::<TASKDIALOGCONFIG>() as u32,
+        hwndParent: hwnd,
+        dwFlags: flags,
+        pszWindowTitle: title,
+        pszMainInstruction: PCWSTR(instruction.as_ptr()),
+        pszContent: content
+            .as_ref()
+            .map_or(PCWSTR::null(), |content| PCWSTR(content.as_ptr())),
+        cButtons: buttons.len() as u32,
+        pButtons: buttons.as_ptr(),
+        nDefaultButton: if answers.is_empty() { 0 } else { button_id(0) },
+        ..Default::default()
+    };
+    config.Anonymous1.pszMainIcon = main_icon;
+    let mut picked = 0;
+    unsafe { TaskDialogIndirect(&config, Some(&mut picked), None, None) }
+        .inspect_err(|error| log::error!("unable to show a prompt: {}", error))
+        .ok()?;
+    prompt_answer_index(picked, cancel_index, answers.len())
+}
+
+/// The id of the button for the answer at `index`. The "Cancel" answer gets the id the dialog
+/// also reports when it's closed.
+fn prompt_button_id(index: usize, cancel_index: Option<usize>) -> i32 {
+    if Some(index) == cancel_index {
+        IDCANCEL.0
+    } else {
+        PROMPT_BUTTON_FIRST_ID + index as i32
+    }
+}
+
+/// The index of the answer whose button has the id the dialog reported.
+fn prompt_answer_index(
+    button_id: i32,
+    cancel_index: Option<usize>,
+    answer_count: usize,
+) -> Option<usize> {
+    if button_id == IDCANCEL.0 {
+        return cancel_index;
+    }
+    usize::try_from(button_id - PROMPT_BUTTON_FIRST_ID)
+        .ok()
+        .filter(|index| *index < answer_count)
+}
+
+pub(crate) fn is_window_menu_command(command: u32) -> bool {
+    (WINDOW_MENU_FIRST_COMMAND_ID..=WINDOW_MENU_LAST_COMMAND_ID).contains(&command)
+}
+
+/// Opens the system menu at the given point in screen coordinates. Without a native title bar
+/// the default window proc doesn't keep the menu in sync with the window, so the items are
+/// enabled here to match whether it's maximized, and the picked command is posted back.
+pub(crate) fn show_system_menu(hwnd: HWND, position: POINT) {
+    let menu = unsafe { GetSystemMenu(hwnd, FALSE) };
+    if menu.is_invalid() {
+        log::error!("unable to get the system menu of window {:?}", hwnd);
+        return;
+    }
+    let maximized = unsafe { IsZoomed(hwnd) }.as_bool();
+    let enabled = |enabled: bool| if enabled { MF_ENABLED } else { MF_GRAYED };
+    unsafe {
+        EnableMenuItem(menu, SC_RESTORE, MF_BYCOMMAND | enabled(maximized));
+        EnableMenuItem(menu, SC_MOVE, MF_BYCOMMAND | enabled(!maximized));
+        EnableMenuItem(menu, SC_SIZE, MF_BYCOMMAND | enabled(!maximized));
+        EnableMenuItem(menu, SC_MINIMIZE, MF_BYCOMMAND | MF_ENABLED);
+        EnableMenuItem(menu, SC_MAXIMIZE, MF_BYCOMMAND | enabled(!maximized));
+        EnableMenuItem(menu, SC_CLOSE, MF_BYCOMMAND | MF_ENABLED);
+        SetMenuDefaultItem(menu, SC_CLOSE, 0).log_err();
+    }
+    let command = unsafe {
+        TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON,
+            position.x,
+            position.y,
+            0,
+            hwnd,
+            None,
+        )
+    };
+    if command.0 != 0 {
+        unsafe { PostMessageW(hwnd, WM_SYSCOMMAND, WPARAM(command.0 as usize), LPARAM(0)) }
+            .log_err();
+    }
+}
+
+// Since Windows 8.1 the compositor only reports timing for the whole desktop, so no window
+// handle is passed.
+fn dwm_timing_info() -> Option<DWM_TIMING_INFO> {
+    let mut timing = DWM_TIMING_INFO {
+        cbSize: std::mem::size_of::<DWM_TIMING_INFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { DwmGetCompositionTimingInfo(HWND::default(), &mut timing) }.ok()?;
+    Some(timing)
+}
 
 mod windows_renderer {
     use std::{num::NonZeroIsize, sync::Arc};
 
     use blade_graphics as gpu;
     use raw_window_handle as rwh;
-    use windows::Win32::{Foundation::HWND, UI::WindowsAndMessaging::GWLP_HINSTANCE};
+    use windows::Win32::{
+        Foundation::HWND,
+        Graphics::Gdi::{GetDC, GetDeviceCaps, ReleaseDC, BITSPIXEL, PLANES},
+        UI::WindowsAndMessaging::GWLP_HINSTANCE,
+    };
 
     use crate::{
         get_window_long,
@@ -962,26 +2260,42 @@ mod windows_renderer {
     };
 
     pub(super) fn windows_renderer(hwnd: HWND, transparent: bool) -> BladeRenderer {
-        let raw = RawWindow { hwnd: hwnd.0 };
-        let gpu: Arc<gpu::Context> = Arc::new(
-            unsafe {
-                gpu::Context::init_windowed(
-                    &raw,
-                    gpu::ContextDesc {
-                        validation: false,
-                        capture: false,
-                        overlay: false,
-                    },
-                )
-            }
-            .unwrap(),
-        );
+        let gpu = windows_gpu(hwnd).unwrap();
         let config = BladeSurfaceConfig {
             size: gpu::Extent::default(),
             transparent,
         };
 
-        BladeRenderer::new(gpu, config)
+        let mut renderer = BladeRenderer::new(gpu, config);
+        renderer.update_color_depth(display_color_depth(hwnd));
+        renderer
+    }
+
+    pub(super) fn windows_gpu(hwnd: HWND) -> anyhow::Result<Arc<gpu::Context>> {
+        let raw = RawWindow { hwnd: hwnd.0 };
+        let gpu = unsafe {
+            gpu::Context::init_windowed(
+                &raw,
+                gpu::ContextDesc {
+                    validation: false,
+                    capture: false,
+                    overlay: false,
+                },
+            )
+        }
+        .map_err(|error| anyhow::anyhow!("unable to initialize the GPU: {:?}", error))?;
+        Ok(Arc::new(gpu))
+    }
+
+    /// Returns the number of bits per pixel of the display the window is on. Remote and
+    /// virtualized sessions can run at 16 bits or with a 256 color palette.
+    fn display_color_depth(hwnd: HWND) -> u32 {
+        unsafe {
+            let dc = GetDC(hwnd);
+            let bits_per_pixel = GetDeviceCaps(dc, BITSPIXEL) * GetDeviceCaps(dc, PLANES);
+            ReleaseDC(hwnd, dc);
+            bits_per_pixel.max(0) as u32
+        }
     }
 
     struct RawWindow {
@@ -1010,9 +2324,13 @@ mod windows_renderer {
 
 #[cfg(test)]
 mod tests {
-    use super::ClickState;
-    use crate::{point, DevicePixels, MouseButton};
+    use super::{
+        center_rect, is_window_menu_command, prompt_answer_index, prompt_button_id,
+        window_menu_command_id, ClickState, CursorVisibility, TouchAction, TouchState,
+    };
+    use crate::{point, px, DevicePixels, MouseButton, TouchPhase};
     use std::time::Duration;
+    use windows::Win32::{Foundation::RECT, UI::WindowsAndMessaging::IDCANCEL};
 
     #[test]
     fn test_double_click_interval() {
@@ -1060,4 +2378,148 @@ mod tests {
             1
         );
     }
+
+    #[test]
+    fn test_center_rect() {
+        let area = RECT {
+            left: 1920,
+            top: 0,
+            right: 3840,
+            bottom: 1040,
+        };
+        let rect = RECT {
+            left: -500,
+            top: 200,
+            right: 300,
+            bottom: 800,
+        };
+        assert_eq!(
+            center_rect(rect, area),
+            RECT {
+                left: 2480,
+                top: 220,
+                right: 3280,
+                bottom: 820,
+            }
+        );
+
+        // a rectangle larger than the area is shrunk to fill it
+        let rect = RECT {
+            left: 0,
+            top: 0,
+            right: 2560,
+            bottom: 1440,
+        };
+        assert_eq!(center_rect(rect, area), area);
+    }
+
+    #[test]
+    fn test_prompt_button_ids() {
+        let answers = ["Save", "Don't Save", "Cancel"];
+        let cancel_index = answers.iter().position(|answer| *answer == "Cancel");
+        let ids = (0..answers.len())
+            .map(|index| prompt_button_id(index, cancel_index))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [100, 101, IDCANCEL.0]);
+        for (index, id) in ids.into_iter().enumerate() {
+            assert_eq!(
+                prompt_answer_index(id, cancel_index, answers.len()),
+                Some(index)
+            );
+        }
+
+        // closing the dialog picks "Cancel", and is only possible when there is one
+        assert_eq!(prompt_answer_index(IDCANCEL.0, Some(2), 3), Some(2));
+        assert_eq!(prompt_answer_index(IDCANCEL.0, None, 2), None);
+        // ids of no answer, like a failed dialog reporting zero, pick nothing
+        assert_eq!(prompt_answer_index(0, None, 2), None);
+        assert_eq!(prompt_answer_index(102, None, 2), None);
+    }
+
+    #[test]
+    fn test_window_menu_command_ids() {
+        assert_eq!(window_menu_command_id(0), Some(0x1000));
+        assert_eq!(window_menu_command_id(1), Some(0x1010));
+        assert_eq!(window_menu_command_id(3583), Some(0xEFF0));
+        // the ids past the range are the ones of the predefined commands, like `SC_SIZE`
+        assert_eq!(window_menu_command_id(3584), None);
+        assert_eq!(window_menu_command_id(usize::MAX), None);
+
+        assert!(!is_window_menu_command(0x0FF0));
+        assert!(is_window_menu_command(0x1000));
+        assert!(!is_window_menu_command(0xF000));
+    }
+
+    #[test]
+    fn test_cursor_visibility() {
+        let mut visibility = CursorVisibility::default();
+        assert_eq!(visibility.set_hidden_while_typing(true), Some(false));
+        // typing more doesn't hide it again, which would take two moves to show it
+        assert_eq!(visibility.set_hidden_while_typing(true), None);
+        assert_eq!(visibility.set_hidden_while_typing(false), Some(true));
+        assert_eq!(visibility.set_hidden_while_typing(false), None);
+
+        // moving the mouse doesn't show a cursor the app hid
+        assert_eq!(visibility.set_hidden(true), Some(false));
+        assert_eq!(visibility.set_hidden_while_typing(true), None);
+        assert_eq!(visibility.set_hidden_while_typing(false), None);
+        assert_eq!(visibility.set_hidden(false), Some(true));
+    }
+
+    #[test]
+    fn test_touch_tap_and_cancel() {
+        let mut state = TouchState::default();
+        assert!(state.down(1, point(px(10.), px(10.))).is_empty());
+        let origin = point(px(10.), px(10.));
+        let actions = state.up(1);
+        assert!(matches!(actions[..], [TouchAction::Tap(position)] if position == origin));
+
+        // a touch the system took over isn't a tap
+        state.down(1, point(px(10.), px(10.)));
+        assert!(state.cancel(1).is_empty());
+        assert!(state.up(1).is_empty());
+    }
+
+    #[test]
+    fn test_touch_cancel_ends_gestures() {
+        let mut state = TouchState::default();
+        state.down(1, point(px(0.), px(0.)));
+        let actions = state.down(2, point(px(100.), px(0.)));
+        assert!(matches!(
+            actions[..],
+            [
+                TouchAction::Scroll {
+                    phase: TouchPhase::Started,
+                    ..
+                },
+                TouchAction::Pinch {
+                    phase: TouchPhase::Started,
+                    ..
+                }
+            ]
+        ));
+
+        // losing one of two fingers ends the pinch, and the scroll goes on with the other
+        let actions = state.cancel(2);
+        assert!(matches!(
+            actions[..],
+            [TouchAction::Pinch {
+                phase: TouchPhase::Ended,
+                ..
+            }]
+        ));
+        let actions = state.cancel(1);
+        assert!(matches!(
+            actions[..],
+            [TouchAction::Scroll {
+                phase: TouchPhase::Ended,
+                ..
+            }]
+        ));
+
+        // pointers that aren't tracked, like ones whose capture changed after they were
+        // released, are ignored
+        assert!(state.cancel(3).is_empty());
+        assert!(state.update(1, point(px(50.), px(50.))).is_empty());
+    }
 }
@@ -6,9 +6,9 @@ use std::{
 
 use crate::{
     black, phi, point, quad, rems, AbsoluteLength, Bounds, ContentMask, Corners, CornersRefinement,
-    CursorStyle, DefiniteLength, Edges, EdgesRefinement, Font, FontFeatures, FontStyle, FontWeight,
-    Hsla, Length, Pixels, Point, PointRefinement, Rgba, SharedString, Size, SizeRefinement, Styled,
-    TextRun, WindowContext,
+    CursorStyle, DefiniteLength, Edges, EdgesRefinement, Font, FontFeatures, FontSimulations,
+    FontStretch, FontStyle, FontWeight, Hsla, Length, Pixels, Point, PointRefinement, Rgba,
+    SharedString, Size, SizeRefinement, Styled, TextRun, WindowContext,
 };
 use collections::HashSet;
 use refineable::Refineable;
@@ -189,6 +189,12 @@ pub struct TextStyle {
     /// The font weight, e.g. bold
     pub font_weight: FontWeight,
 
+    /// The font stretch, e.g. condensed
+    pub font_stretch: FontStretch,
+
+    /// Which faces may be synthesized for the font, e.g. a bold face
+    pub font_simulations: FontSimulations,
+
     /// The font style, e.g. italic
     pub font_style: FontStyle,
 
@@ -219,6 +225,8 @@ impl Default for TextStyle {
             font_size: rems(1.).into(),
             line_height: phi(),
             font_weight: FontWeight::default(),
+            font_stretch: FontStretch::default(),
+            font_simulations: FontSimulations::default(),
             font_style: FontStyle::default(),
             background_color: None,
             underline: None,
@@ -268,7 +276,9 @@ impl TextStyle {
             family: self.font_family.clone(),
             features: self.font_features.clone(),
             weight: self.font_weight,
+            stretch: self.font_stretch,
             style: self.font_style,
+            simulations: self.font_simulations,
         }
     }
 
@@ -285,7 +295,9 @@ impl TextStyle {
                 family: self.font_family.clone(),
                 features: Default::default(),
                 weight: self.font_weight,
+                stretch: self.font_stretch,
                 style: self.font_style,
+                simulations: self.font_simulations,
             },
             color: self.color,
             background_color: self.background_color,
@@ -799,14 +799,18 @@ pub trait Styled: Sized {
             family,
             features,
             weight,
+            stretch,
             style,
+            simulations,
         } = font;
 
         let text_style = self.text_style().get_or_insert_with(Default::default);
         text_style.font_family = Some(family);
         text_style.font_features = Some(features);
         text_style.font_weight = Some(weight);
+        text_style.font_stretch = Some(stretch);
         text_style.font_style = Some(style);
+        text_style.font_simulations = Some(simulations);
 
         self
     }
@@ -11,13 +11,14 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    px, Bounds, DevicePixels, Hsla, Pixels, PlatformTextSystem, Point, Result, SharedString, Size,
-    StrikethroughStyle, UnderlineStyle,
+    px, BackgroundExecutor, Bounds, DevicePixels, Hsla, IsZero, Pixels, PlatformTextSystem, Point,
+    Result, SharedString, Size, StrikethroughStyle, UnderlineStyle,
 };
 use anyhow::anyhow;
-use collections::{BTreeSet, FxHashMap};
+use collections::{BTreeSet, FxHashMap, FxHashSet};
 use core::fmt;
 use derive_more::Deref;
+use futures::channel::mpsc;
 use itertools::Itertools;
 use parking_lot::{Mutex, RwLock, RwLockUpgradableReadGuard};
 use smallvec::{smallvec, SmallVec};
@@ -26,9 +27,11 @@ use std::{
     cmp,
     fmt::{Debug, Display, Formatter},
     hash::{Hash, Hasher},
+    num::NonZeroU32,
     ops::{Deref, DerefMut, Range},
     sync::Arc,
 };
+use util::ResultExt;
 
 /// An opaque identifier for a specific font.
 #[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
@@ -39,17 +42,49 @@ pub struct FontId(pub usize);
 #[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
 pub struct FontFamilyId(pub usize);
 
+/// An error reported by the platform text system.
+///
+/// Fallible text system methods return these wrapped in an [`anyhow::Error`], so use
+/// `error.downcast_ref::<TextSystemError>()` to tell the failures apart.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TextSystemError {
+    /// The glyph has no outline or bitmap to rasterize.
+    #[error("glyph bounds are empty")]
+    MissingGlyph,
+    /// Neither the requested font nor any fallback could be loaded.
+    #[error("font {0} not found")]
+    FontNotFound(SharedString),
+    /// The graphics device used for rasterization was lost and has to be recreated.
+    #[error("the text rendering device was lost")]
+    DeviceLost,
+    /// The platform rejected the request, with the `HRESULT` it failed with on Windows.
+    #[error("invalid text system parameters (HRESULT {hresult:#010x})")]
+    InvalidParams {
+        /// The `HRESULT` returned by the platform, or zero on other platforms.
+        hresult: i32,
+    },
+}
+
 pub(crate) const SUBPIXEL_VARIANTS: u8 = 4;
+pub(crate) const OS2_TABLE_TAG: u32 = u32::from_be_bytes(*b"OS/2");
 
 /// The GPUI text rendering sub system.
 pub struct TextSystem {
     platform_text_system: Arc<dyn PlatformTextSystem>,
-    font_ids_by_font: RwLock<FxHashMap<Font, Result<FontId>>>,
+    font_ids_by_font: Arc<RwLock<FxHashMap<Font, Result<FontId>>>>,
     font_metrics: RwLock<FxHashMap<FontId, FontMetrics>>,
     raster_bounds: RwLock<FxHashMap<RenderGlyphParams, Bounds<DevicePixels>>>,
     wrapper_pool: Mutex<FxHashMap<FontIdWithSize, Vec<LineWrapper>>>,
     font_runs_pool: Mutex<Vec<Vec<FontRun>>>,
     fallback_font_stack: SmallVec<[Font; 2]>,
+    background_font_loader: RwLock<Option<BackgroundFontLoader>>,
+    loading_fonts: Arc<Mutex<FxHashSet<Font>>>,
+    font_palette: RwLock<FontPalette>,
+}
+
+struct BackgroundFontLoader {
+    executor: BackgroundExecutor,
+    font_loaded: mpsc::UnboundedSender<()>,
 }
 
 impl TextSystem {
@@ -58,9 +93,12 @@ impl TextSystem {
             platform_text_system,
             font_metrics: RwLock::default(),
             raster_bounds: RwLock::default(),
-            font_ids_by_font: RwLock::default(),
+            font_ids_by_font: Arc::default(),
             wrapper_pool: Mutex::default(),
             font_runs_pool: Mutex::default(),
+            background_font_loader: RwLock::default(),
+            loading_fonts: Arc::default(),
+            font_palette: RwLock::default(),
             fallback_font_stack: smallvec![
                 // TODO: This is currently Zed-specific.
                 // We should allow GPUI users to provide their own fallback font stack.
@@ -100,7 +138,10 @@ impl TextSystem {
         fn clone_font_id_result(font_id: &Result<FontId>) -> Result<FontId> {
             match font_id {
                 Ok(font_id) => Ok(*font_id),
-                Err(err) => Err(anyhow!("{}", err)),
+                Err(err) => match err.downcast_ref::<TextSystemError>() {
+                    Some(err) => Err(err.clone().into()),
+                    None => Err(anyhow!("{}", err)),
+                },
             }
         }
 
@@ -131,15 +172,65 @@ impl TextSystem {
             .next()
     }
 
+    /// Makes [`Self::resolve_font`] load fonts on a background thread the first time they are
+    /// used, instead of blocking until they are available. A message is sent on the returned
+    /// channel whenever a font finished loading, after which text using it should be laid out
+    /// again.
+    pub(crate) fn load_fonts_in_background(
+        &self,
+        executor: BackgroundExecutor,
+    ) -> mpsc::UnboundedReceiver<()> {
+        let (font_loaded, font_loaded_rx) = mpsc::unbounded();
+        *self.background_font_loader.write() = Some(BackgroundFontLoader {
+            executor,
+            font_loaded,
+        });
+        font_loaded_rx
+    }
+
+    /// Starts loading the font in the background if it wasn't loaded before and fonts are
+    /// loaded in the background. Returns whether the font is still being loaded.
+    fn load_font_in_background(&self, font: &Font) -> bool {
+        if self.font_ids_by_font.read().contains_key(font) {
+            return false;
+        }
+        let background_font_loader = self.background_font_loader.read();
+        let Some(loader) = background_font_loader.as_ref() else {
+            return false;
+        };
+        if self.loading_fonts.lock().insert(font.clone()) {
+            let platform_text_system = self.platform_text_system.clone();
+            let font_ids_by_font = self.font_ids_by_font.clone();
+            let loading_fonts = self.loading_fonts.clone();
+            let font_loaded = loader.font_loaded.clone();
+            let font = font.clone();
+            loader
+                .executor
+                .spawn(async move {
+                    let font_id = platform_text_system.font_id(&font);
+                    font_ids_by_font.write().insert(font.clone(), font_id);
+                    loading_fonts.lock().remove(&font);
+                    font_loaded.unbounded_send(()).ok();
+                })
+                .detach();
+        }
+        true
+    }
+
     /// Resolves the specified font, falling back to the default font stack if
     /// the font fails to load.
     ///
+    /// If fonts are loaded in the background, the fallback is also used while the font
+    /// is still being loaded, so its metrics act as a placeholder.
+    ///
     /// # Panics
     ///
     /// Panics if the font and none of the fallbacks can be resolved.
     pub fn resolve_font(&self, font: &Font) -> FontId {
-        if let Ok(font_id) = self.font_id(font) {
-            return font_id;
+        if !self.load_font_in_background(font) {
+            if let Ok(font_id) = self.font_id(font) {
+                return font_id;
+            }
         }
         for fallback in &self.fallback_font_stack {
             if let Ok(font_id) = self.font_id(fallback) {
@@ -195,6 +286,10 @@ impl TextSystem {
         Ok(result * font_size)
     }
 
+    pub(crate) fn glyph_for_char(&self, font_id: FontId, ch: char) -> Option<GlyphId> {
+        self.platform_text_system.glyph_for_char(font_id, ch)
+    }
+
     /// Get the number of font size units per 'em square',
     /// Per MDN: "an abstract square whose height is the intended distance between
     /// lines of type in the same type size"
@@ -202,6 +297,35 @@ impl TextSystem {
         self.read_metrics(font_id, |metrics| metrics.units_per_em)
     }
 
+    /// Sets the distance between tab stops, in multiples of the advance of a space in the
+    /// font of the line. Lines that are already laid out keep their tab stops until they
+    /// are laid out again.
+    pub fn set_tab_size(&self, tab_size: NonZeroU32) {
+        self.platform_text_system.set_tab_size(tab_size)
+    }
+
+    /// Sets how lines starting in the given font family are spaced, which decides their ascent
+    /// and descent. Lines of other families keep the spacing of their fonts.
+    pub fn set_line_spacing(&self, font_family: &str, line_spacing: LineSpacing) {
+        self.platform_text_system
+            .set_line_spacing(font_family, line_spacing)
+    }
+
+    /// Sets the palette color fonts, such as emoji fonts, are painted with from now on.
+    pub fn set_font_palette(&self, font_palette: FontPalette) {
+        *self.font_palette.write() = font_palette;
+    }
+
+    /// The palette color fonts are painted with.
+    pub fn font_palette(&self) -> FontPalette {
+        *self.font_palette.read()
+    }
+
+    /// Returns whether every glyph in the given font has the same advance width.
+    pub fn is_monospace(&self, font_id: FontId) -> bool {
+        self.platform_text_system.is_monospace(font_id)
+    }
+
     /// Get the height of a capital letter in the given font and size.
     pub fn cap_height(&self, font_id: FontId, font_size: Pixels) -> Pixels {
         self.read_metrics(font_id, |metrics| metrics.cap_height(font_size))
@@ -288,6 +412,26 @@ impl TextSystem {
         self.platform_text_system
             .rasterize_glyph(params, raster_bounds)
     }
+
+    /// Rasterizes the given glyphs in one batch, skipping the ones that don't cover any pixels.
+    pub(crate) fn rasterize_glyphs(
+        &self,
+        params: impl IntoIterator<Item = RenderGlyphParams>,
+    ) -> Vec<(RenderGlyphParams, Result<(Size<DevicePixels>, Vec<u8>)>)> {
+        let glyphs = params
+            .into_iter()
+            .filter_map(|params| {
+                let raster_bounds = self.raster_bounds(&params).log_err()?;
+                (!raster_bounds.is_zero()).then_some((params, raster_bounds))
+            })
+            .collect::<Vec<_>>();
+        let results = self.platform_text_system.rasterize_glyphs(&glyphs);
+        glyphs
+            .into_iter()
+            .map(|(params, _)| params)
+            .zip(results)
+            .collect()
+    }
 }
 
 /// The GPUI text layout subsystem.
@@ -299,9 +443,17 @@ pub struct WindowTextSystem {
 }
 
 impl WindowTextSystem {
-    pub(crate) fn new(text_system: Arc<TextSystem>) -> Self {
+    pub(crate) fn new(
+        text_system: Arc<TextSystem>,
+        executor: BackgroundExecutor,
+        on_line_shaped: mpsc::UnboundedSender<()>,
+    ) -> Self {
         Self {
-            line_layout_cache: LineLayoutCache::new(text_system.platform_text_system.clone()),
+            line_layout_cache: LineLayoutCache::with_background_shaping(
+                text_system.platform_text_system.clone(),
+                executor,
+                on_line_shaped,
+            ),
             text_system,
         }
     }
@@ -401,6 +553,7 @@ impl WindowTextSystem {
                     font_runs.push(FontRun {
                         len: run_len_within_line,
                         font_id: self.resolve_font(&run.font),
+                        features: run.font.features.clone(),
                     });
                 }
 
@@ -489,11 +642,74 @@ impl WindowTextSystem {
         font_size: Pixels,
         runs: &[TextRun],
     ) -> Result<Arc<LineLayout>> {
+        self.layout_line_with_orientation(text, font_size, runs, TextOrientation::Horizontal)
+    }
+
+    /// Layout the given line of text like [`Self::layout_line`], advancing the glyphs in
+    /// the given direction. Vertical layout is only supported on Windows, other platforms
+    /// lay the text out horizontally.
+    pub fn layout_line_with_orientation(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[TextRun],
+        orientation: TextOrientation,
+    ) -> Result<Arc<LineLayout>> {
+        let layout = self.with_font_runs(runs, |font_runs| {
+            self.line_layout_cache
+                .layout_line(text, font_size, font_runs, orientation)
+        });
+        Ok(layout)
+    }
+
+    /// Layout the given paragraph of text, breaking it into lines at newlines and wherever
+    /// a line would grow wider than `max_width`. The whole paragraph is shaped at once, so
+    /// this is cheaper than wrapping it by laying out each of its lines separately.
+    pub fn layout_paragraph(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[TextRun],
+        max_width: Pixels,
+    ) -> ParagraphLayout {
+        self.with_font_runs(runs, |font_runs| {
+            self.platform_text_system
+                .layout_paragraph(text, font_size, font_runs, max_width)
+        })
+    }
+
+    /// Returns the character boundary closest to the given x coordinate in the given line.
+    ///
+    /// Unlike [`LineLayout::closest_index_for_x`], this asks the platform to hit-test the text
+    /// where it can, so positions within ligatures and emoji sequences follow its clusters.
+    pub fn index_for_x(&self, text: &str, font_size: Pixels, runs: &[TextRun], x: Pixels) -> usize {
+        self.with_font_runs(runs, |font_runs| {
+            self.platform_text_system
+                .index_for_x(text, font_size, font_runs, x)
+        })
+    }
+
+    /// Returns the x coordinate of the caret at the given index in the given line, see
+    /// [`Self::index_for_x`].
+    pub fn x_for_index(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[TextRun],
+        index: usize,
+    ) -> Pixels {
+        self.with_font_runs(runs, |font_runs| {
+            self.platform_text_system
+                .x_for_index(text, font_size, font_runs, index)
+        })
+    }
+
+    fn with_font_runs<R>(&self, runs: &[TextRun], f: impl FnOnce(&[FontRun]) -> R) -> R {
         let mut font_runs = self.font_runs_pool.lock().pop().unwrap_or_default();
         for run in runs.iter() {
             let font_id = self.resolve_font(&run.font);
             if let Some(last_run) = font_runs.last_mut() {
-                if last_run.font_id == font_id {
+                if last_run.font_id == font_id && last_run.features == run.font.features {
                     last_run.len += run.len;
                     continue;
                 }
@@ -501,17 +717,16 @@ impl WindowTextSystem {
             font_runs.push(FontRun {
                 len: run.len,
                 font_id,
+                features: run.font.features.clone(),
             });
         }
 
-        let layout = self
-            .line_layout_cache
-            .layout_line(text, font_size, &font_runs);
+        let result = f(&font_runs);
 
         font_runs.clear();
         self.font_runs_pool.lock().push(font_runs);
 
-        Ok(layout)
+        result
     }
 }
 
@@ -608,6 +823,47 @@ pub enum FontStyle {
     Oblique,
 }
 
+/// How condensed or expanded the glyphs of a font are, relative to its normal width.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default)]
+pub enum FontStretch {
+    /// 50% of the normal width.
+    UltraCondensed,
+    /// 62.5% of the normal width.
+    ExtraCondensed,
+    /// 75% of the normal width.
+    Condensed,
+    /// 87.5% of the normal width.
+    SemiCondensed,
+    /// The normal width.
+    #[default]
+    Normal,
+    /// 112.5% of the normal width.
+    SemiExpanded,
+    /// 125% of the normal width.
+    Expanded,
+    /// 150% of the normal width.
+    ExtraExpanded,
+    /// 200% of the normal width.
+    UltraExpanded,
+}
+
+/// Which faces the platform may synthesize from another face of a font family, when the
+/// family has no face with the requested weight or style. Platforms that don't synthesize
+/// faces ignore this.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum FontSimulations {
+    /// Bold and oblique faces may both be synthesized.
+    #[default]
+    All,
+    /// Faces are never synthesized, the closest face of the family is used as is.
+    None,
+    /// Only bold faces may be synthesized.
+    SyntheticBold,
+    /// Only oblique faces may be synthesized.
+    SyntheticOblique,
+}
+
 impl Display for FontStyle {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         Debug::fmt(self, f)
@@ -644,6 +900,7 @@ pub(crate) struct RenderGlyphParams {
     pub(crate) subpixel_variant: Point<u8>,
     pub(crate) scale_factor: f32,
     pub(crate) is_emoji: bool,
+    pub(crate) palette: FontPalette,
 }
 
 impl Eq for RenderGlyphParams {}
@@ -655,9 +912,36 @@ impl Hash for RenderGlyphParams {
         self.font_size.0.to_bits().hash(state);
         self.subpixel_variant.hash(state);
         self.scale_factor.to_bits().hash(state);
+        self.palette.hash(state);
     }
 }
 
+/// The palette the glyphs of a color font are painted with. Color fonts can carry several
+/// palettes, such as variants meant for light and dark backgrounds.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontPalette {
+    /// Which of the font's palettes to use.
+    pub selection: FontPaletteSelection,
+    /// The color of the layers that are meant to be painted in the color of the text,
+    /// instead of a color from the palette.
+    pub foreground: Option<Hsla>,
+}
+
+/// Picks one of the palettes of a color font. Fonts that don't have the requested palette
+/// are painted with their first one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FontPaletteSelection {
+    /// The font's first palette.
+    #[default]
+    Default,
+    /// The palette at the given index.
+    Index(u16),
+    /// The first palette the font marks as usable on a light background.
+    LightBackground,
+    /// The first palette the font marks as usable on a dark background.
+    DarkBackground,
+}
+
 /// The parameters for rendering an emoji glyph.
 #[derive(Clone, Debug, PartialEq)]
 pub struct RenderEmojiParams {
@@ -692,8 +976,15 @@ pub struct Font {
     /// The font weight.
     pub weight: FontWeight,
 
+    /// How condensed or expanded the font is.
+    pub stretch: FontStretch,
+
     /// The font style.
     pub style: FontStyle,
+
+    /// Which faces may be synthesized when the family has no face with the requested weight
+    /// or style.
+    pub simulations: FontSimulations,
 }
 
 /// Get a [`Font`] for a given name.
@@ -702,7 +993,9 @@ pub fn font(family: impl Into<SharedString>) -> Font {
         family: family.into(),
         features: FontFeatures::default(),
         weight: FontWeight::default(),
+        stretch: FontStretch::default(),
         style: FontStyle::default(),
+        simulations: FontSimulations::default(),
     }
 }
 
@@ -749,6 +1042,18 @@ pub struct FontMetrics {
     /// The height of a lowercase x.
     pub(crate) x_height: f32,
 
+    /// The suggested height of superscript glyphs.
+    pub(crate) superscript_size: f32,
+
+    /// The suggested vertical offset of the superscript baseline, positive values are above the baseline.
+    pub(crate) superscript_offset: f32,
+
+    /// The suggested height of subscript glyphs.
+    pub(crate) subscript_size: f32,
+
+    /// The suggested vertical offset of the subscript baseline, positive values are above the baseline.
+    pub(crate) subscript_offset: f32,
+
     /// The outer limits of the area that the font covers.
     /// Corresponds to the xMin / xMax / yMin / yMax values in the OpenType `head` table
     pub(crate) bounding_box: Bounds<f32>,
@@ -790,8 +1095,97 @@ impl FontMetrics {
         Pixels((self.x_height / self.units_per_em as f32) * font_size.0)
     }
 
+    /// Returns the suggested height of superscript glyphs in pixels.
+    pub fn superscript_size(&self, font_size: Pixels) -> Pixels {
+        Pixels((self.superscript_size / self.units_per_em as f32) * font_size.0)
+    }
+
+    /// Returns the suggested vertical offset of the superscript baseline in pixels,
+    /// positive values are above the baseline.
+    pub fn superscript_offset(&self, font_size: Pixels) -> Pixels {
+        Pixels((self.superscript_offset / self.units_per_em as f32) * font_size.0)
+    }
+
+    /// Returns the suggested height of subscript glyphs in pixels.
+    pub fn subscript_size(&self, font_size: Pixels) -> Pixels {
+        Pixels((self.subscript_size / self.units_per_em as f32) * font_size.0)
+    }
+
+    /// Returns the suggested vertical offset of the subscript baseline in pixels,
+    /// positive values are above the baseline.
+    pub fn subscript_offset(&self, font_size: Pixels) -> Pixels {
+        Pixels((self.subscript_offset / self.units_per_em as f32) * font_size.0)
+    }
+
     /// Returns the outer limits of the area that the font covers in pixels.
     pub fn bounding_box(&self, font_size: Pixels) -> Bounds<Pixels> {
         (self.bounding_box / self.units_per_em as f32 * font_size.0).map(px)
     }
+
+    /// Fills in the superscript and subscript metrics from a font's raw OpenType `OS/2` table,
+    /// using common defaults when the font doesn't have one.
+    pub(crate) fn read_script_metrics(&mut self, os2_table: Option<&[u8]>) {
+        let read = |offset: usize| {
+            let bytes = os2_table?.get(offset..offset + 2)?;
+            Some(i16::from_be_bytes([bytes[0], bytes[1]]) as f32)
+        };
+        let units_per_em = self.units_per_em as f32;
+        self.subscript_size = read(12).unwrap_or(units_per_em * 0.6);
+        // the table measures the subscript offset downwards
+        self.subscript_offset = read(16).map_or(units_per_em * -0.075, |offset| -offset);
+        self.superscript_size = read(20).unwrap_or(units_per_em * 0.6);
+        self.superscript_offset = read(24).unwrap_or(units_per_em * 0.35);
+    }
+
+    /// Returns where this font suggests drawing underlines and strikethroughs, in pixels.
+    pub fn decoration_metrics(&self, font_size: Pixels) -> DecorationMetrics {
+        let thickness = self.underline_thickness(font_size);
+        DecorationMetrics {
+            underline_offset: -self.underline_position(font_size),
+            underline_thickness: thickness,
+            // These metrics don't include a strikethrough position, so center it on the x-height.
+            strikethrough_offset: -(self.x_height(font_size) + thickness) / 2.,
+            strikethrough_thickness: thickness,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TestAppContext, TestDispatcher};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_load_fonts_in_background() {
+        let dispatcher = TestDispatcher::new(StdRng::seed_from_u64(0));
+        let cx = TestAppContext::new(dispatcher, None);
+        let text_system = cx.text_system();
+        text_system
+            .add_fonts(
+                [
+                    "../../assets/fonts/plex-mono/ZedPlexMono-Regular.ttf",
+                    "../../assets/fonts/plex-sans/ZedPlexSans-Regular.ttf",
+                ]
+                .into_iter()
+                .map(|path| std::fs::read(path).unwrap().into())
+                .collect(),
+            )
+            .unwrap();
+        let mono = text_system.font_id(&font("Zed Plex Mono")).unwrap();
+        let mut font_loaded = text_system.load_fonts_in_background(cx.executor());
+
+        // fonts that were loaded before aren't loaded again
+        assert_eq!(text_system.resolve_font(&font("Zed Plex Mono")), mono);
+        // the first fallback stands in for the font while it's loading
+        assert_eq!(text_system.resolve_font(&font("Zed Plex Sans")), mono);
+        assert!(font_loaded.try_next().is_err());
+
+        cx.executor().run_until_parked();
+        assert_eq!(font_loaded.try_next().unwrap(), Some(()));
+        assert!(font_loaded.try_next().is_err());
+        let sans = text_system.resolve_font(&font("Zed Plex Sans"));
+        assert_ne!(sans, mono);
+        assert_eq!(text_system.font_id(&font("Zed Plex Sans")).unwrap(), sans);
+    }
 }
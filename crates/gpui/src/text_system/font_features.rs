@@ -72,8 +72,10 @@ impl<'de> serde::Deserialize<'de> for FontFeatures {
                                 }
                             }
                             FeatureValue::Number(value) => {
-                                if value.is_u64() {
-                                    feature_list.push((key, value.as_u64().unwrap() as u32));
+                                if let Some(value) =
+                                    value.as_u64().and_then(|value| u32::try_from(value).ok())
+                                {
+                                    feature_list.push((key, value));
                                 } else {
                                     log::error!(
                                         "Incorrect font feature value {} for feature tag {}",
@@ -1,6 +1,7 @@
 use crate::{
-    black, fill, point, px, size, Bounds, Hsla, LineLayout, Pixels, Point, Result, SharedString,
-    StrikethroughStyle, UnderlineStyle, WindowContext, WrapBoundary, WrappedLineLayout,
+    black, fill, point, px, size, Bounds, Hsla, LineLayout, Pixels, Point, Result, ShapedRun,
+    SharedString, StrikethroughStyle, UnderlineStyle, WindowContext, WrapBoundary,
+    WrappedLineLayout,
 };
 use derive_more::{Deref, DerefMut};
 use smallvec::SmallVec;
@@ -117,6 +118,10 @@ fn paint_line(
         let mut wraps = wrap_boundaries.iter().peekable();
         let mut run_end = 0;
         let mut color = black();
+        // the styles the decoration runs ask for, which are drawn with the metrics of the font
+        // of each shaped run they cover
+        let mut requested_underline: Option<UnderlineStyle> = None;
+        let mut requested_strikethrough: Option<StrikethroughStyle> = None;
         let mut current_underline: Option<(Point<Pixels>, UnderlineStyle)> = None;
         let mut current_strikethrough: Option<(Point<Pixels>, StrikethroughStyle)> = None;
         let mut current_background: Option<(Point<Pixels>, Hsla)> = None;
@@ -186,41 +191,40 @@ fn paint_line(
                             ));
                         }
 
-                        if let Some((_, underline_style)) = &mut current_underline {
-                            if style_run.underline.as_ref() != Some(underline_style) {
-                                finished_underline = current_underline.take();
-                            }
+                        let underline = style_run.underline.map(|underline| UnderlineStyle {
+                            color: Some(underline.color.unwrap_or(style_run.color)),
+                            ..underline
+                        });
+                        if underline != requested_underline {
+                            finished_underline = current_underline.take();
+                            requested_underline = underline;
                         }
-                        if let Some(run_underline) = style_run.underline.as_ref() {
-                            current_underline.get_or_insert((
-                                point(
-                                    glyph_origin.x,
-                                    glyph_origin.y + baseline_offset.y + (layout.descent * 0.618),
-                                ),
-                                UnderlineStyle {
-                                    color: Some(run_underline.color.unwrap_or(style_run.color)),
-                                    thickness: run_underline.thickness,
-                                    wavy: run_underline.wavy,
-                                },
-                            ));
+                        if let Some(underline) = requested_underline {
+                            current_underline.get_or_insert_with(|| {
+                                run_underline(run, layout, glyph_origin, baseline_offset, underline)
+                            });
                         }
-                        if let Some((_, strikethrough_style)) = &mut current_strikethrough {
-                            if style_run.strikethrough.as_ref() != Some(strikethrough_style) {
-                                finished_strikethrough = current_strikethrough.take();
-                            }
+                        let strikethrough =
+                            style_run
+                                .strikethrough
+                                .map(|strikethrough| StrikethroughStyle {
+                                    color: Some(strikethrough.color.unwrap_or(style_run.color)),
+                                    ..strikethrough
+                                });
+                        if strikethrough != requested_strikethrough {
+                            finished_strikethrough = current_strikethrough.take();
+                            requested_strikethrough = strikethrough;
                         }
-                        if let Some(run_strikethrough) = style_run.strikethrough.as_ref() {
-                            current_strikethrough.get_or_insert((
-                                point(
-                                    glyph_origin.x,
-                                    glyph_origin.y
-                                        + (((layout.ascent * 0.5) + baseline_offset.y) * 0.5),
-                                ),
-                                StrikethroughStyle {
-                                    color: Some(run_strikethrough.color.unwrap_or(style_run.color)),
-                                    thickness: run_strikethrough.thickness,
-                                },
-                            ));
+                        if let Some(strikethrough) = requested_strikethrough {
+                            current_strikethrough.get_or_insert_with(|| {
+                                run_strikethrough(
+                                    run,
+                                    layout,
+                                    glyph_origin,
+                                    baseline_offset,
+                                    strikethrough,
+                                )
+                            });
                         }
 
                         run_end += style_run.len as usize;
@@ -230,6 +234,41 @@ fn paint_line(
                         finished_background = current_background.take();
                         finished_underline = current_underline.take();
                         finished_strikethrough = current_strikethrough.take();
+                        requested_underline = None;
+                        requested_strikethrough = None;
+                    }
+                }
+
+                // a decoration spanning several shaped runs, like ones of fallback fonts, is
+                // split where the font changes, so each part sits where its own font puts it
+                if glyph_ix == 0 && finished_underline.is_none() {
+                    if let Some(underline) = requested_underline {
+                        let next =
+                            run_underline(run, layout, glyph_origin, baseline_offset, underline);
+                        if current_underline.as_ref().map_or(false, |(origin, style)| {
+                            origin.y != next.0.y || *style != next.1
+                        }) {
+                            finished_underline = current_underline.replace(next);
+                        }
+                    }
+                }
+                if glyph_ix == 0 && finished_strikethrough.is_none() {
+                    if let Some(strikethrough) = requested_strikethrough {
+                        let next = run_strikethrough(
+                            run,
+                            layout,
+                            glyph_origin,
+                            baseline_offset,
+                            strikethrough,
+                        );
+                        if current_strikethrough
+                            .as_ref()
+                            .map_or(false, |(origin, style)| {
+                                origin.y != next.0.y || *style != next.1
+                            })
+                        {
+                            finished_strikethrough = current_strikethrough.replace(next);
+                        }
                     }
                 }
 
@@ -322,3 +361,49 @@ fn paint_line(
         Ok(())
     })
 }
+
+/// Places an underline under a shaped run where its font suggests, falling back to a fraction
+/// of the line's descent. The font's thickness is used when it's thicker than the style's.
+fn run_underline(
+    run: &ShapedRun,
+    layout: &LineLayout,
+    glyph_origin: Point<Pixels>,
+    baseline_offset: Point<Pixels>,
+    style: UnderlineStyle,
+) -> (Point<Pixels>, UnderlineStyle) {
+    let (offset, thickness) = match run.decorations {
+        Some(decorations) => (
+            decorations.underline_offset,
+            style.thickness.max(decorations.underline_thickness),
+        ),
+        None => (layout.descent * 0.618, style.thickness),
+    };
+    (
+        point(glyph_origin.x, glyph_origin.y + baseline_offset.y + offset),
+        UnderlineStyle { thickness, ..style },
+    )
+}
+
+/// Places a strikethrough through a shaped run like [`run_underline`] places underlines.
+fn run_strikethrough(
+    run: &ShapedRun,
+    layout: &LineLayout,
+    glyph_origin: Point<Pixels>,
+    baseline_offset: Point<Pixels>,
+    style: StrikethroughStyle,
+) -> (Point<Pixels>, StrikethroughStyle) {
+    let (y, thickness) = match run.decorations {
+        Some(decorations) => (
+            baseline_offset.y + decorations.strikethrough_offset,
+            style.thickness.max(decorations.strikethrough_thickness),
+        ),
+        None => (
+            ((layout.ascent * 0.5) + baseline_offset.y) * 0.5,
+            style.thickness,
+        ),
+    };
+    (
+        point(glyph_origin.x, glyph_origin.y + y),
+        StrikethroughStyle { thickness, ..style },
+    )
+}
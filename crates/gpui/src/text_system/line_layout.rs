@@ -1,5 +1,9 @@
-use crate::{point, px, FontId, GlyphId, Pixels, PlatformTextSystem, Point, Size};
-use collections::FxHashMap;
+use crate::{
+    point, px, BackgroundExecutor, FontFeatures, FontId, GlyphId, Pixels, PlatformTextSystem,
+    Point, Size,
+};
+use collections::{FxHashMap, FxHashSet};
+use futures::channel::mpsc;
 use parking_lot::{Mutex, RwLock, RwLockUpgradableReadGuard};
 use smallvec::SmallVec;
 use std::{
@@ -16,6 +20,12 @@ pub struct LineLayout {
     pub font_size: Pixels,
     /// The width of the line
     pub width: Pixels,
+    /// The width of the whitespace at the start of the line, which is the whole width for
+    /// lines that only contain whitespace
+    pub leading_whitespace_width: Pixels,
+    /// The width of the whitespace at the end of the line, which is the whole width for
+    /// lines that only contain whitespace
+    pub trailing_whitespace_width: Pixels,
     /// The ascent of the line
     pub ascent: Pixels,
     /// The descent of the line
@@ -26,6 +36,71 @@ pub struct LineLayout {
     pub len: usize,
 }
 
+/// The direction in which the glyphs of a line advance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextOrientation {
+    /// Glyphs advance from left to right.
+    #[default]
+    Horizontal,
+    /// Glyphs advance from top to bottom, as in vertical CJK text. Every glyph is set upright:
+    /// ideographs use their vertical variants, and horizontal scripts are stacked letter by
+    /// letter rather than set sideways.
+    ///
+    /// The positions of the shaped glyphs are then offsets along the y axis, the `width`
+    /// of the [`LineLayout`] is the length of the column, and its `ascent` and `descent`
+    /// are measured horizontally.
+    Vertical,
+}
+
+/// How the platform text system spaces the lines that start in a font family, which decides
+/// the ascent and descent it reports for them. Only DirectWrite honors this, other platforms
+/// always use the metrics of the fonts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LineSpacing {
+    /// How the height of each line is determined
+    pub method: LineSpacingMethod,
+    /// Leave out the gap fonts ask for between lines, so the line fits its glyphs tightly.
+    /// Uniform line spacing ignores this.
+    pub trim_line_gap: bool,
+}
+
+/// How the height of a line is determined, see [`LineSpacing`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineSpacingMethod {
+    /// The height the fonts on the line ask for.
+    #[default]
+    Default,
+    /// The height the fonts on the line ask for, scaled by the given factor.
+    Proportional(f32),
+    /// The same height for every line, as a multiple of the font size. The glyphs of the
+    /// line's first font are centered in it, the same way editor rows center their text.
+    Uniform(f32),
+}
+
+// so that lines laid out with different spacing are cached apart
+impl Eq for LineSpacingMethod {}
+
+impl Hash for LineSpacingMethod {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            LineSpacingMethod::Default => {}
+            LineSpacingMethod::Proportional(factor) | LineSpacingMethod::Uniform(factor) => {
+                factor.to_bits().hash(state)
+            }
+        }
+    }
+}
+
+/// The part of a run covered by a selection, see [`LineLayout::selection_boxes`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectionBox {
+    /// The font of the run
+    pub font_id: FontId,
+    /// The horizontal extent of the box, relative to the start of the line
+    pub x: Range<Pixels>,
+}
+
 /// A run of text that has been shaped .
 #[derive(Debug)]
 pub struct ShapedRun {
@@ -33,6 +108,22 @@ pub struct ShapedRun {
     pub font_id: FontId,
     /// The glyphs that make up this run
     pub glyphs: SmallVec<[ShapedGlyph; 8]>,
+    /// Where the font of this run places underlines and strikethroughs, if the
+    /// text system reports it
+    pub decorations: Option<DecorationMetrics>,
+}
+
+/// The position and thickness of text decorations, as specified by a font.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecorationMetrics {
+    /// The offset of the top of the underline from the baseline, positive values are below it
+    pub underline_offset: Pixels,
+    /// The thickness of the underline
+    pub underline_thickness: Pixels,
+    /// The offset of the top of the strikethrough from the baseline, positive values are below it
+    pub strikethrough_offset: Pixels,
+    /// The thickness of the strikethrough
+    pub strikethrough_thickness: Pixels,
 }
 
 /// A single glyph, ready to paint.
@@ -103,6 +194,67 @@ impl LineLayout {
         self.width
     }
 
+    /// Returns the boxes to highlight for the given range of the line, one for each run the
+    /// range touches.
+    ///
+    /// A run's box covers the advances of its glyphs up to where the next run starts, and the
+    /// last run extends to the end of the line. The boxes of neighboring runs therefore share
+    /// their edges, even where italic overhang or a fallback font leaves a gap between glyphs.
+    pub fn selection_boxes(&self, range: Range<usize>) -> Vec<SelectionBox> {
+        let mut boxes = Vec::new();
+        for (ix, run) in self.runs.iter().enumerate() {
+            let Some(first_glyph) = run.glyphs.first() else {
+                continue;
+            };
+            let next_glyph = self.runs[ix + 1..]
+                .iter()
+                .find_map(|run| run.glyphs.first());
+            let run_end_x = next_glyph.map_or(self.width, |glyph| glyph.position.x);
+            let run_end = next_glyph.map_or(self.len, |glyph| glyph.index);
+
+            let start = range.start.max(first_glyph.index);
+            let end = range.end.min(run_end);
+            if start >= end {
+                continue;
+            }
+            let x_for_index = |index| {
+                run.glyphs
+                    .iter()
+                    .find(|glyph| glyph.index >= index)
+                    .map_or(run_end_x, |glyph| glyph.position.x)
+            };
+            boxes.push(SelectionBox {
+                font_id: run.font_id,
+                x: x_for_index(start)..x_for_index(end),
+            });
+        }
+        boxes
+    }
+
+    /// Measures the whitespace at the start and the end of the line from the positions of its
+    /// glyphs, for platforms that can't report it while laying out the line.
+    pub(crate) fn measure_whitespace(&mut self, text: &str) {
+        let is_whitespace = |glyph: &&ShapedGlyph| {
+            text[glyph.index..]
+                .chars()
+                .next()
+                .map_or(true, char::is_whitespace)
+        };
+        let glyphs = self.runs.iter().flat_map(|run| run.glyphs.iter());
+
+        self.leading_whitespace_width = glyphs
+            .clone()
+            .find(|glyph| !is_whitespace(glyph))
+            .map_or(self.width, |glyph| glyph.position.x);
+        // the trailing whitespace starts at its first glyph, after the last visible one
+        let trailing_whitespace_start = glyphs
+            .rev()
+            .take_while(is_whitespace)
+            .last()
+            .map_or(self.width, |glyph| glyph.position.x);
+        self.trailing_whitespace_width = self.width - trailing_whitespace_start;
+    }
+
     /// The corresponding Font at the given index
     pub fn font_id_for_index(&self, index: usize) -> Option<FontId> {
         for run in &self.runs {
@@ -179,6 +331,71 @@ impl LineLayout {
 
         boundaries
     }
+
+    /// Splits the line at the given wrap boundaries, returning each of the resulting lines
+    /// with the byte offset it starts at. The glyphs of every line are moved to start at the
+    /// origin, and their indices are made relative to the start of the line.
+    fn split_at_wrap_boundaries(
+        &self,
+        text: &str,
+        boundaries: &[WrapBoundary],
+    ) -> Vec<(usize, LineLayout)> {
+        let empty_line = || LineLayout {
+            font_size: self.font_size,
+            ascent: self.ascent,
+            descent: self.descent,
+            ..Default::default()
+        };
+        let finish_line = |line: &mut LineLayout, range: Range<usize>, width: Pixels| {
+            line.width = width;
+            line.len = range.len();
+            line.measure_whitespace(&text[range]);
+        };
+
+        let mut lines = Vec::with_capacity(boundaries.len() + 1);
+        let mut boundaries = boundaries.iter().peekable();
+        let mut line = empty_line();
+        let mut line_start = 0;
+        let mut line_x = px(0.);
+        for (run_ix, run) in self.runs.iter().enumerate() {
+            let mut glyphs = SmallVec::new();
+            for (glyph_ix, glyph) in run.glyphs.iter().enumerate() {
+                let boundary = WrapBoundary { run_ix, glyph_ix };
+                if boundaries.next_if(|next| **next == boundary).is_some() {
+                    if !glyphs.is_empty() {
+                        line.runs.push(ShapedRun {
+                            font_id: run.font_id,
+                            glyphs: std::mem::take(&mut glyphs),
+                            decorations: run.decorations,
+                        });
+                    }
+                    finish_line(
+                        &mut line,
+                        line_start..glyph.index,
+                        glyph.position.x - line_x,
+                    );
+                    lines.push((line_start, std::mem::replace(&mut line, empty_line())));
+                    line_start = glyph.index;
+                    line_x = glyph.position.x;
+                }
+                glyphs.push(ShapedGlyph {
+                    position: point(glyph.position.x - line_x, glyph.position.y),
+                    index: glyph.index - line_start,
+                    ..glyph.clone()
+                });
+            }
+            if !glyphs.is_empty() {
+                line.runs.push(ShapedRun {
+                    font_id: run.font_id,
+                    glyphs,
+                    decorations: run.decorations,
+                });
+            }
+        }
+        finish_line(&mut line, line_start..self.len, self.width - line_x);
+        lines.push((line_start, line));
+        lines
+    }
 }
 
 /// A line of text that has been wrapped to fit a given width
@@ -333,10 +550,173 @@ impl WrappedLineLayout {
     }
 }
 
+/// A paragraph of text that has been broken into lines to fit a given width
+#[derive(Default, Debug)]
+pub struct ParagraphLayout {
+    /// The layout of each line. The glyphs of every line start at the origin, and their
+    /// indices are relative to the start of the line.
+    pub lines: Vec<LineLayout>,
+    /// The byte offset in the paragraph's text at which each line starts
+    pub line_starts: Vec<usize>,
+}
+
+impl ParagraphLayout {
+    /// Breaks the text into lines at every newline, lays each of them out once with
+    /// `layout_line` and wraps it at the positions of its glyphs. This is used on platforms
+    /// that can't lay out paragraphs themselves.
+    pub(crate) fn wrap_lines(
+        text: &str,
+        runs: &[FontRun],
+        max_width: Pixels,
+        mut layout_line: impl FnMut(&str, &[FontRun]) -> LineLayout,
+    ) -> Self {
+        let mut paragraph = Self::default();
+        if runs.is_empty() {
+            return paragraph;
+        }
+        let mut line_start = 0;
+        for line_text in text.split('\n') {
+            let line_end = line_start + line_text.len();
+            let layout = layout_line(line_text, &runs_for_range(runs, line_start..line_end));
+            let boundaries = layout.compute_wrap_boundaries(line_text, max_width);
+            for (start, line) in layout.split_at_wrap_boundaries(line_text, &boundaries) {
+                paragraph.line_starts.push(line_start + start);
+                paragraph.lines.push(line);
+            }
+            line_start = line_end + 1;
+        }
+        paragraph
+    }
+
+    /// The width of the widest line
+    pub fn width(&self) -> Pixels {
+        self.lines
+            .iter()
+            .map(|line| line.width)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The size of the whole paragraph, for the given line_height.
+    pub fn size(&self, line_height: Pixels) -> Size<Pixels> {
+        Size {
+            width: self.width(),
+            height: line_height * self.lines.len(),
+        }
+    }
+
+    /// The range of the paragraph's text covered by the line at the given index, which
+    /// doesn't include the newline that ends it.
+    pub fn line_range(&self, line_ix: usize) -> Range<usize> {
+        let start = self.line_starts[line_ix];
+        start..start + self.lines[line_ix].len
+    }
+}
+
+/// Returns the parts of the runs that cover the given range of their text. An empty range
+/// keeps the font of the run it falls in, so empty lines still get its ascent and descent.
+fn runs_for_range(runs: &[FontRun], range: Range<usize>) -> Vec<FontRun> {
+    let mut result = Vec::new();
+    let mut enclosing_run = None;
+    let mut run_start = 0;
+    for run in runs {
+        let run_end = run_start + run.len;
+        let start = run_start.max(range.start);
+        let end = run_end.min(range.end);
+        if start < end {
+            result.push(FontRun {
+                len: end - start,
+                font_id: run.font_id,
+                features: run.features.clone(),
+            });
+        }
+        if run_start <= range.start {
+            enclosing_run = Some(run);
+        }
+        run_start = run_end;
+    }
+    if result.is_empty() {
+        if let Some(run) = enclosing_run {
+            result.push(FontRun {
+                len: 0,
+                font_id: run.font_id,
+                features: run.features.clone(),
+            });
+        }
+    }
+    result
+}
+
+/// Lines at least this many bytes long are shaped on the background executor when the
+/// cache has one, so that minified files don't block the UI thread.
+const BACKGROUND_SHAPING_MIN_LEN: usize = 8 * 1024;
+
+/// How much of a line being shaped in the background is shaped up front, so that its
+/// start can be shown in the meantime.
+const PLACEHOLDER_LEN: usize = 512;
+
 pub(crate) struct LineLayoutCache {
     previous_frame: Mutex<FrameCache>,
     current_frame: RwLock<FrameCache>,
     platform_text_system: Arc<dyn PlatformTextSystem>,
+    background_shaping: Option<BackgroundShaping>,
+}
+
+struct BackgroundShaping {
+    executor: BackgroundExecutor,
+    /// Lines that are being shaped, or have been shaped but not yet picked up by a frame.
+    lines: Arc<Mutex<FxHashMap<Arc<CacheKey>, Option<Arc<LineLayout>>>>>,
+    /// Lines that were asked for during the current frame.
+    polled: Mutex<FxHashSet<Arc<CacheKey>>>,
+    on_ready: mpsc::UnboundedSender<()>,
+}
+
+impl BackgroundShaping {
+    /// Returns the layout of the line if it has finished shaping, and starts shaping it
+    /// otherwise.
+    fn poll(
+        &self,
+        key: &Arc<CacheKey>,
+        platform_text_system: &Arc<dyn PlatformTextSystem>,
+    ) -> Option<Arc<LineLayout>> {
+        self.polled.lock().insert(key.clone());
+        let mut lines = self.lines.lock();
+        match lines.get(key) {
+            Some(Some(_)) => lines.remove(key).flatten(),
+            Some(None) => None,
+            None => {
+                lines.insert(key.clone(), None);
+                let lines = self.lines.clone();
+                let key = key.clone();
+                let platform_text_system = platform_text_system.clone();
+                let on_ready = self.on_ready.clone();
+                self.executor
+                    .spawn(async move {
+                        let layout = platform_text_system.layout_line_with_orientation(
+                            &key.text,
+                            key.font_size,
+                            &key.runs,
+                            key.orientation,
+                        );
+                        lines.lock().insert(key, Some(Arc::new(layout)));
+                        on_ready.unbounded_send(()).ok();
+                    })
+                    .detach();
+                None
+            }
+        }
+    }
+
+    /// Drops the lines that finished shaping but weren't asked for during the frame, like
+    /// those that were scrolled out of view before they were ready. Lines that are still
+    /// being shaped are kept until they finish.
+    fn finish_frame(&self) {
+        let mut polled = self.polled.lock();
+        self.lines
+            .lock()
+            .retain(|key, layout| layout.is_none() || polled.contains(key));
+        polled.clear();
+    }
 }
 
 #[derive(Default)]
@@ -359,6 +739,26 @@ impl LineLayoutCache {
             previous_frame: Mutex::default(),
             current_frame: RwLock::default(),
             platform_text_system,
+            background_shaping: None,
+        }
+    }
+
+    /// Like [`Self::new`], but very long lines are shaped on the given executor. Until
+    /// they are ready, a layout of their start is returned in their place and isn't
+    /// cached. A message is sent on `on_ready` each time a line finishes shaping.
+    pub fn with_background_shaping(
+        platform_text_system: Arc<dyn PlatformTextSystem>,
+        executor: BackgroundExecutor,
+        on_ready: mpsc::UnboundedSender<()>,
+    ) -> Self {
+        Self {
+            background_shaping: Some(BackgroundShaping {
+                executor,
+                lines: Arc::default(),
+                polled: Mutex::default(),
+                on_ready,
+            }),
+            ..Self::new(platform_text_system)
         }
     }
 
@@ -407,6 +807,9 @@ impl LineLayoutCache {
         curr_frame.wrapped_lines.clear();
         curr_frame.used_lines.clear();
         curr_frame.used_wrapped_lines.clear();
+        if let Some(background_shaping) = &self.background_shaping {
+            background_shaping.finish_frame();
+        }
     }
 
     pub fn layout_wrapped_line(
@@ -416,11 +819,14 @@ impl LineLayoutCache {
         runs: &[FontRun],
         wrap_width: Option<Pixels>,
     ) -> Arc<WrappedLineLayout> {
+        let line_spacing = self.line_spacing(runs);
         let key = &CacheKeyRef {
             text,
             font_size,
             runs,
             wrap_width,
+            orientation: TextOrientation::Horizontal,
+            line_spacing,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
@@ -439,7 +845,8 @@ impl LineLayoutCache {
         } else {
             drop(current_frame);
 
-            let unwrapped_layout = self.layout_line(text, font_size, runs);
+            let (unwrapped_layout, is_placeholder) =
+                self.layout_line_internal(text, font_size, runs, TextOrientation::Horizontal);
             let wrap_boundaries = if let Some(wrap_width) = wrap_width {
                 unwrapped_layout.compute_wrap_boundaries(text.as_ref(), wrap_width)
             } else {
@@ -450,11 +857,17 @@ impl LineLayoutCache {
                 wrap_boundaries,
                 wrap_width,
             });
+            if is_placeholder {
+                return layout;
+            }
+
             let key = Arc::new(CacheKey {
                 text: text.into(),
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width,
+                orientation: TextOrientation::Horizontal,
+                line_spacing,
             });
 
             let mut current_frame = self.current_frame.write();
@@ -467,44 +880,116 @@ impl LineLayoutCache {
         }
     }
 
-    pub fn layout_line(&self, text: &str, font_size: Pixels, runs: &[FontRun]) -> Arc<LineLayout> {
+    /// Returns how the line is spaced, which is decided by the font it starts with.
+    fn line_spacing(&self, runs: &[FontRun]) -> LineSpacing {
+        runs.first()
+            .map(|run| self.platform_text_system.line_spacing(run.font_id))
+            .unwrap_or_default()
+    }
+
+    pub fn layout_line(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        orientation: TextOrientation,
+    ) -> Arc<LineLayout> {
+        self.layout_line_internal(text, font_size, runs, orientation)
+            .0
+    }
+
+    /// Returns the layout of the line, and whether it is a placeholder for a line that
+    /// is still being shaped in the background.
+    fn layout_line_internal(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        orientation: TextOrientation,
+    ) -> (Arc<LineLayout>, bool) {
+        let line_spacing = self.line_spacing(runs);
         let key = &CacheKeyRef {
             text,
             font_size,
             runs,
             wrap_width: None,
+            orientation,
+            line_spacing,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
         if let Some(layout) = current_frame.lines.get(key) {
-            return layout.clone();
+            return (layout.clone(), false);
         }
 
         let mut current_frame = RwLockUpgradableReadGuard::upgrade(current_frame);
         if let Some((key, layout)) = self.previous_frame.lock().lines.remove_entry(key) {
             current_frame.lines.insert(key.clone(), layout.clone());
             current_frame.used_lines.push(key);
-            layout
+            (layout, false)
         } else {
-            let layout = Arc::new(self.platform_text_system.layout_line(text, font_size, runs));
             let key = Arc::new(CacheKey {
                 text: text.into(),
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width: None,
+                orientation,
+                line_spacing,
             });
+            let layout = match &self.background_shaping {
+                Some(background_shaping) if text.len() >= BACKGROUND_SHAPING_MIN_LEN => {
+                    match background_shaping.poll(&key, &self.platform_text_system) {
+                        Some(layout) => layout,
+                        None => {
+                            let placeholder =
+                                self.placeholder_layout(text, font_size, runs, orientation);
+                            return (Arc::new(placeholder), true);
+                        }
+                    }
+                }
+                _ => Arc::new(self.platform_text_system.layout_line_with_orientation(
+                    text,
+                    font_size,
+                    runs,
+                    orientation,
+                )),
+            };
             current_frame.lines.insert(key.clone(), layout.clone());
             current_frame.used_lines.push(key);
-            layout
+            (layout, false)
+        }
+    }
+
+    /// Lays out only the start of the line, to stand in for it while it is shaped in the
+    /// background. The layout only covers that start, so indices past it are placed at its end.
+    fn placeholder_layout(
+        &self,
+        text: &str,
+        font_size: Pixels,
+        runs: &[FontRun],
+        orientation: TextOrientation,
+    ) -> LineLayout {
+        let mut end = PLACEHOLDER_LEN.min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
         }
+        let runs = runs_for_range(runs, 0..end);
+        self.platform_text_system.layout_line_with_orientation(
+            &text[..end],
+            font_size,
+            &runs,
+            orientation,
+        )
     }
 }
 
 /// A run of text with a single font.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct FontRun {
     pub(crate) len: usize,
     pub(crate) font_id: FontId,
+    /// The OpenType features applied when shaping this run.
+    pub(crate) features: FontFeatures,
 }
 
 trait AsCacheKeyRef {
@@ -517,6 +1002,8 @@ struct CacheKey {
     font_size: Pixels,
     runs: SmallVec<[FontRun; 1]>,
     wrap_width: Option<Pixels>,
+    orientation: TextOrientation,
+    line_spacing: LineSpacing,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -525,6 +1012,8 @@ struct CacheKeyRef<'a> {
     font_size: Pixels,
     runs: &'a [FontRun],
     wrap_width: Option<Pixels>,
+    orientation: TextOrientation,
+    line_spacing: LineSpacing,
 }
 
 impl<'a> PartialEq for (dyn AsCacheKeyRef + 'a) {
@@ -548,6 +1037,8 @@ impl AsCacheKeyRef for CacheKey {
             font_size: self.font_size,
             runs: self.runs.as_slice(),
             wrap_width: self.wrap_width,
+            orientation: self.orientation,
+            line_spacing: self.line_spacing,
         }
     }
 }
@@ -575,3 +1066,184 @@ impl<'a> AsCacheKeyRef for CacheKeyRef<'a> {
         *self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font, TestAppContext, TestDispatcher};
+    use rand::prelude::*;
+    use smallvec::smallvec;
+
+    // every character is 10px wide
+    fn layout(text: &str) -> LineLayout {
+        let glyphs = text
+            .char_indices()
+            .enumerate()
+            .map(|(column, (index, _))| ShapedGlyph {
+                id: GlyphId(0),
+                position: point(px(column as f32 * 10.), px(0.)),
+                index,
+                is_emoji: false,
+            })
+            .collect();
+        let mut layout = LineLayout {
+            width: px(text.chars().count() as f32 * 10.),
+            runs: vec![ShapedRun {
+                font_id: FontId(0),
+                glyphs,
+                decorations: None,
+            }],
+            len: text.len(),
+            ..Default::default()
+        };
+        layout.measure_whitespace(text);
+        layout
+    }
+
+    #[test]
+    fn test_measure_whitespace() {
+        let line = layout("  fn main() {\t ");
+        assert_eq!(line.leading_whitespace_width, px(20.));
+        assert_eq!(line.trailing_whitespace_width, px(20.));
+
+        let line = layout("é—b");
+        assert_eq!(line.leading_whitespace_width, px(0.));
+        assert_eq!(line.trailing_whitespace_width, px(0.));
+
+        let line = layout("   ");
+        assert_eq!(line.leading_whitespace_width, px(30.));
+        assert_eq!(line.trailing_whitespace_width, px(30.));
+
+        let line = layout("");
+        assert_eq!(line.leading_whitespace_width, px(0.));
+        assert_eq!(line.trailing_whitespace_width, px(0.));
+    }
+
+    #[test]
+    fn test_selection_boxes() {
+        let glyph = |index, x| ShapedGlyph {
+            id: GlyphId(0),
+            position: point(px(x), px(0.)),
+            index,
+            is_emoji: false,
+        };
+        // the fallback font of the second run starts 2px after the first run's last advance
+        let line = LineLayout {
+            width: px(45.),
+            runs: vec![
+                ShapedRun {
+                    font_id: FontId(0),
+                    glyphs: smallvec![glyph(0, 0.), glyph(1, 10.)],
+                    decorations: None,
+                },
+                ShapedRun {
+                    font_id: FontId(1),
+                    glyphs: smallvec![glyph(2, 22.), glyph(3, 32.)],
+                    decorations: None,
+                },
+            ],
+            len: 4,
+            ..Default::default()
+        };
+
+        let boxes = line.selection_boxes(1..3);
+        assert_eq!(
+            boxes,
+            [
+                SelectionBox {
+                    font_id: FontId(0),
+                    x: px(10.)..px(22.),
+                },
+                SelectionBox {
+                    font_id: FontId(1),
+                    x: px(22.)..px(32.),
+                },
+            ]
+        );
+
+        let boxes = line.selection_boxes(3..4);
+        assert_eq!(boxes[0].x, px(32.)..px(45.));
+        assert!(line.selection_boxes(2..2).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_paragraph() {
+        let text = "one two three\n\nfour";
+        let runs = [FontRun {
+            len: text.len(),
+            font_id: FontId(0),
+            features: FontFeatures::default(),
+        }];
+        let paragraph =
+            ParagraphLayout::wrap_lines(text, &runs, px(85.), |line_text, line_runs| {
+                assert_eq!(line_runs.len(), 1);
+                assert_eq!(line_runs[0].len, line_text.len());
+                layout(line_text)
+            });
+
+        let lines = (0..paragraph.lines.len())
+            .map(|ix| &text[paragraph.line_range(ix)])
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["one two ", "three", "", "four"]);
+        assert_eq!(paragraph.line_starts, [0, 8, 14, 15]);
+
+        let second_line = &paragraph.lines[1];
+        assert_eq!(second_line.width, px(50.));
+        assert_eq!(second_line.runs[0].glyphs[0].position.x, px(0.));
+        assert_eq!(second_line.runs[0].glyphs[0].index, 0);
+        assert_eq!(paragraph.lines[0].trailing_whitespace_width, px(10.));
+        assert_eq!(paragraph.width(), px(80.));
+    }
+
+    #[test]
+    fn test_background_shaping() {
+        let dispatcher = TestDispatcher::new(StdRng::seed_from_u64(0));
+        let cx = TestAppContext::new(dispatcher, None);
+        cx.text_system()
+            .add_fonts(vec![std::fs::read(
+                "../../assets/fonts/plex-mono/ZedPlexMono-Regular.ttf",
+            )
+            .unwrap()
+            .into()])
+            .unwrap();
+        let font_id = cx.text_system().font_id(&font("Zed Plex Mono")).unwrap();
+        let (on_ready, mut ready) = mpsc::unbounded();
+        let cache = LineLayoutCache::with_background_shaping(
+            cx.text_system().platform_text_system.clone(),
+            cx.executor(),
+            on_ready,
+        );
+        let text = "a".repeat(BACKGROUND_SHAPING_MIN_LEN);
+        let runs = [FontRun {
+            len: text.len(),
+            font_id,
+            features: FontFeatures::default(),
+        }];
+        let layout_line = |text: &str| {
+            cache.layout_line_internal(text, px(16.), &runs, TextOrientation::Horizontal)
+        };
+
+        // the start of the line stands in for it until it's shaped
+        let (layout, is_placeholder) = layout_line(&text);
+        assert!(is_placeholder);
+        assert_eq!(layout.len, PLACEHOLDER_LEN);
+        assert!(ready.try_next().is_err());
+
+        cx.executor().run_until_parked();
+        assert_eq!(ready.try_next().unwrap(), Some(()));
+        let (layout, is_placeholder) = layout_line(&text);
+        assert!(!is_placeholder);
+        assert_eq!(layout.len, text.len());
+        cache.finish_frame();
+
+        // lines that are no longer asked for once they're shaped are dropped
+        let other_text = "b".repeat(BACKGROUND_SHAPING_MIN_LEN);
+        assert!(layout_line(&other_text).1);
+        cx.executor().run_until_parked();
+        cache.finish_frame();
+        let lines = &cache.background_shaping.as_ref().unwrap().lines;
+        assert_eq!(lines.lock().len(), 1);
+        cache.finish_frame();
+        assert!(lines.lock().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{px, FontId, FontRun, Pixels, PlatformTextSystem};
+use crate::{px, FontFeatures, FontId, FontRun, Pixels, PlatformTextSystem};
 use collections::HashMap;
 use std::{iter, sync::Arc};
 
@@ -119,6 +119,7 @@ impl LineWrapper {
                 &[FontRun {
                     len: buffer.len(),
                     font_id: self.font_id,
+                    features: FontFeatures::default(),
                 }],
             )
             .width
@@ -228,7 +229,11 @@ mod tests {
     #[crate::test]
     fn test_wrap_shaped_line(cx: &mut TestAppContext) {
         cx.update(|cx| {
-            let text_system = WindowTextSystem::new(cx.text_system().clone());
+            let text_system = WindowTextSystem::new(
+                cx.text_system().clone(),
+                cx.background_executor().clone(),
+                futures::channel::mpsc::unbounded().0,
+            );
 
             let normal = TextRun {
                 len: 0,
@@ -1,30 +1,31 @@
 use crate::{
     hash, point, prelude::*, px, size, transparent_black, Action, AnyDrag, AnyElement, AnyTooltip,
     AnyView, AppContext, Arena, Asset, AsyncWindowContext, AvailableSpace, Bounds, BoxShadow,
-    Context, Corners, CursorStyle, DevicePixels, DispatchActionListener, DispatchNodeId,
+    Capslock, Context, Corners, CursorStyle, DevicePixels, DispatchActionListener, DispatchNodeId,
     DispatchTree, DisplayId, Edges, Effect, Entity, EntityId, EventEmitter, FileDropEvent, Flatten,
-    FontId, Global, GlobalElementId, GlyphId, Hsla, ImageData, InputHandler, IsZero, KeyBinding,
-    KeyContext, KeyDownEvent, KeyEvent, KeyMatch, KeymatchResult, Keystroke, KeystrokeEvent,
-    LayoutId, LineLayoutIndex, Model, ModelContext, Modifiers, ModifiersChangedEvent,
-    MonochromeSprite, MouseButton, MouseEvent, MouseMoveEvent, MouseUpEvent, Path, Pixels,
-    PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler, PlatformWindow, Point,
-    PolychromeSprite, PromptLevel, Quad, Render, RenderGlyphParams, RenderImageParams,
-    RenderSvgParams, ScaledPixels, Scene, Shadow, SharedString, Size, StrikethroughStyle, Style,
-    SubscriberSet, Subscription, TaffyLayoutEngine, Task, TextStyle, TextStyleRefinement,
-    TransformationMatrix, Underline, UnderlineStyle, View, VisualContext, WeakView,
-    WindowAppearance, WindowBackgroundAppearance, WindowBounds, WindowOptions, WindowParams,
-    WindowTextSystem, SUBPIXEL_VARIANTS,
+    Font, FontId, FontPalette, Global, GlobalElementId, GlyphId, Hsla, ImageData, InputHandler,
+    IsZero, KeyBinding, KeyContext, KeyDownEvent, KeyEvent, KeyMatch, KeymatchResult, Keystroke,
+    KeystrokeDiagnostic, KeystrokeEvent, KeystrokeResolution, LayoutId, LineLayoutIndex, Model,
+    ModelContext, Modifiers, ModifiersChangedEvent, MonochromeSprite, MouseButton, MouseEvent,
+    MouseMoveEvent, MouseUpEvent, Path, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
+    PlatformInputHandler, PlatformWindow, Point, PolychromeSprite, PresentStatistics, PromptLevel,
+    Quad, Render, RenderGlyphParams, RenderImageParams, RenderSvgParams, RenderTargetInfo,
+    ScaledPixels, Scene, Shadow, SharedString, Size, StrikethroughStyle, Style, SubscriberSet,
+    Subscription, TaffyLayoutEngine, Task, TextStyle, TextStyleRefinement, TransformationMatrix,
+    Underline, UnderlineStyle, View, VisualContext, WeakView, WindowAppearance,
+    WindowBackgroundAppearance, WindowBounds, WindowOptions, WindowParams, WindowTextSystem,
+    SUBPIXEL_VARIANTS,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::{FxHashMap, FxHashSet};
 use derive_more::{Deref, DerefMut};
-use futures::channel::oneshot;
-use futures::{future::Shared, FutureExt};
+use futures::channel::{mpsc, oneshot};
+use futures::{future::Shared, FutureExt, StreamExt};
 #[cfg(target_os = "macos")]
 use media::core_video::CVImageBuffer;
 use parking_lot::RwLock;
 use refineable::Refineable;
-use slotmap::SlotMap;
+use slotmap::{KeyData, SlotMap};
 use smallvec::SmallVec;
 use std::{
     any::{Any, TypeId},
@@ -535,6 +536,7 @@ pub struct Window {
     mouse_position: Point<Pixels>,
     mouse_hit_test: HitTest,
     modifiers: Modifiers,
+    capslock: Capslock,
     scale_factor: f32,
     bounds_observers: SubscriberSet<(), AnyObserver>,
     appearance: WindowAppearance,
@@ -546,10 +548,12 @@ pub struct Window {
     pub(crate) refreshing: bool,
     pub(crate) draw_phase: DrawPhase,
     activation_observers: SubscriberSet<(), AnyObserver>,
+    edited: bool,
     pub(crate) focus: Option<FocusId>,
     focus_enabled: bool,
     pending_input: Option<PendingInput>,
     pending_modifier: ModifierState,
+    keystroke_diagnostic: Option<KeystrokeDiagnostic>,
     pending_input_observers: SubscriberSet<(), AnyObserver>,
     prompt: Option<RenderablePromptHandle>,
 }
@@ -662,10 +666,16 @@ impl Window {
         let sprite_atlas = platform_window.sprite_atlas();
         let mouse_position = platform_window.mouse_position();
         let modifiers = platform_window.modifiers();
+        let capslock = platform_window.capslock();
         let content_size = platform_window.content_size();
         let scale_factor = platform_window.scale_factor();
         let appearance = platform_window.appearance();
-        let text_system = Arc::new(WindowTextSystem::new(cx.text_system().clone()));
+        let (line_shaped_tx, mut line_shaped_rx) = mpsc::unbounded();
+        let text_system = Arc::new(WindowTextSystem::new(
+            cx.text_system().clone(),
+            cx.background_executor().clone(),
+            line_shaped_tx,
+        ));
         let dirty = Rc::new(Cell::new(true));
         let active = Rc::new(Cell::new(platform_window.is_active()));
         let needs_present = Rc::new(Cell::new(false));
@@ -680,6 +690,16 @@ impl Window {
             }
         }
 
+        // Long lines are shaped in the background and drawn with a placeholder until then
+        cx.spawn(|mut cx| async move {
+            while line_shaped_rx.next().await.is_some() {
+                if handle.update(&mut cx, |_, cx| cx.refresh()).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
         platform_window.on_close(Box::new({
             let mut cx = cx.to_async();
             move || {
@@ -761,6 +781,10 @@ impl Window {
                 handle
                     .update(&mut cx, |_, cx| {
                         cx.window.active.set(active);
+                        if !active {
+                            // the rest of a chord can't arrive while the window is inactive
+                            cx.cancel_pending_input();
+                        }
                         cx.window
                             .activation_observers
                             .clone()
@@ -781,6 +805,16 @@ impl Window {
             })
         });
 
+        #[cfg(target_os = "windows")]
+        platform_window.on_window_menu_action({
+            let mut cx = cx.to_async();
+            Box::new(move |action| {
+                handle
+                    .update(&mut cx, |_, cx| cx.dispatch_action(action.boxed_clone()))
+                    .log_err();
+            })
+        });
+
         if let Some(app_id) = app_id {
             platform_window.set_app_id(&app_id);
         }
@@ -816,6 +850,7 @@ impl Window {
             mouse_position,
             mouse_hit_test: HitTest::default(),
             modifiers,
+            capslock,
             scale_factor,
             bounds_observers: SubscriberSet::new(),
             appearance,
@@ -827,10 +862,12 @@ impl Window {
             refreshing: false,
             draw_phase: DrawPhase::None,
             activation_observers: SubscriberSet::new(),
+            edited: false,
             focus: None,
             focus_enabled: true,
             pending_input: None,
             pending_modifier: ModifierState::default(),
+            keystroke_diagnostic: None,
             pending_input_observers: SubscriberSet::new(),
             prompt: None,
         })
@@ -1214,6 +1251,31 @@ impl<'a> WindowContext<'a> {
         self.window.platform_window.show_window_menu(position)
     }
 
+    /// Hides the cursor and keeps it in place, so that mouse movement is reported as
+    /// [`crate::MouseDeltaEvent`]s instead, e.g. for dragging a value slider past the
+    /// edge of the screen (Windows).
+    ///
+    /// The pointer is unlocked again when the window loses focus or escape is pressed.
+    pub fn lock_pointer(&self) {
+        self.window.platform_window.lock_pointer()
+    }
+
+    /// Releases a pointer locked with [`Self::lock_pointer`].
+    pub fn unlock_pointer(&self) {
+        self.window.platform_window.unlock_pointer()
+    }
+
+    /// Returns whether the pointer is currently locked to the window.
+    pub fn is_pointer_locked(&self) -> bool {
+        self.window.platform_window.is_pointer_locked()
+    }
+
+    /// Hides the cursor while it's over the window, e.g. while typing or presenting, until
+    /// it's made visible again (Windows).
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.window.platform_window.set_cursor_visible(visible)
+    }
+
     /// Tells the compositor to take control of window movement (Wayland and X11)
     ///
     /// Events may not be received during a move operation.
@@ -1226,11 +1288,71 @@ impl<'a> WindowContext<'a> {
         self.window.platform_window.should_render_window_controls()
     }
 
+    /// Switches between a title bar drawn by the application, as with
+    /// [`TitlebarOptions::appears_transparent`](crate::TitlebarOptions::appears_transparent),
+    /// and the native one, keeping the window's content and placement. Only supported on
+    /// Windows.
+    pub fn set_titlebar_appears_transparent(&self, appears_transparent: bool) {
+        self.window
+            .platform_window
+            .set_titlebar_appears_transparent(appears_transparent);
+    }
+
+    /// Draws the frame and the native title bar of the window in the given appearance, like
+    /// the one of the app's theme, rather than the system's. `None` goes back to following the
+    /// system. Only supported on Windows.
+    pub fn set_frame_appearance(&self, appearance: Option<WindowAppearance>) {
+        self.window.platform_window.set_frame_appearance(appearance);
+    }
+
+    /// Returns whether the application draws the window's title bar, on platforms that can
+    /// switch it at runtime (Windows). Other platforms report `false`.
+    pub fn titlebar_appears_transparent(&self) -> bool {
+        self.window.platform_window.titlebar_appears_transparent()
+    }
+
     /// Updates the window's title at the platform level.
     pub fn set_window_title(&mut self, title: &str) {
         self.window.platform_window.set_title(title);
     }
 
+    /// Keeps the user from resizing the window's content below the given size (Windows).
+    pub fn set_window_min_size(&self, size: Option<Size<Pixels>>) {
+        self.window.platform_window.set_min_size(size);
+    }
+
+    /// Keeps the user from resizing the window's content beyond the given size (Windows).
+    pub fn set_window_max_size(&self, size: Option<Size<Pixels>>) {
+        self.window.platform_window.set_max_size(size);
+    }
+
+    /// Sets how opaque the whole window is, from fully transparent at 0 to opaque at 1.
+    pub fn set_window_opacity(&self, opacity: f32) {
+        self.window.platform_window.set_opacity(opacity);
+    }
+
+    /// Sets whether the window is kept above the windows of other apps.
+    pub fn set_window_level(&self, level: WindowLevel) {
+        self.window.platform_window.set_level(level);
+    }
+
+    /// Shows the progress of a long running operation on the window's taskbar button (Windows).
+    pub fn set_window_progress(&self, progress: WindowProgress) {
+        self.window.platform_window.set_progress(progress);
+    }
+
+    /// Shows a badge over the window's taskbar button, or clears it with `None`.
+    pub fn set_window_badge(&self, badge: Option<WindowBadge>) {
+        self.window.platform_window.set_badge(badge);
+    }
+
+    /// Sets how the corners of the window are rounded (Windows 11).
+    pub fn set_window_corner_preference(&self, preference: WindowCornerPreference) {
+        self.window
+            .platform_window
+            .set_corner_preference(preference);
+    }
+
     /// Sets the application identifier.
     pub fn set_app_id(&mut self, app_id: &str) {
         self.window.platform_window.set_app_id(app_id);
@@ -1245,7 +1367,17 @@ impl<'a> WindowContext<'a> {
 
     /// Mark the window as dirty at the platform level.
     pub fn set_window_edited(&mut self, edited: bool) {
-        self.window.platform_window.set_edited(edited);
+        if self.window.edited != edited {
+            self.window.edited = edited;
+            self.window.platform_window.set_edited(edited);
+            self.refresh();
+        }
+    }
+
+    /// Returns whether the window was last marked as dirty with [`Self::set_window_edited`],
+    /// for client-side decorations that render their own unsaved changes indicator.
+    pub fn is_window_edited(&self) -> bool {
+        self.window.edited
     }
 
     /// Determine the display on which the window is visible.
@@ -1261,6 +1393,90 @@ impl<'a> WindowContext<'a> {
         self.window.platform_window.show_character_palette();
     }
 
+    /// Describes the surface the window is rendered into, on platforms that can tell.
+    pub fn render_target_info(&self) -> Option<RenderTargetInfo> {
+        self.window.platform_window.render_target_info()
+    }
+
+    /// Reports how the window's frames have been presented, on platforms that can tell.
+    pub fn present_statistics(&self) -> Option<PresentStatistics> {
+        self.window.platform_window.present_statistics()
+    }
+
+    /// Hands the current drag over to the platform, so that `data` can be dropped into other
+    /// applications. The drag within the window is cancelled, and `on_complete` is called once
+    /// the data was dropped or the drag was cancelled.
+    pub fn start_external_drag(
+        &mut self,
+        data: ExternalDragData,
+        on_complete: impl FnOnce(ExternalDragOutcome, &mut WindowContext) + 'static,
+    ) -> Result<()> {
+        let mut cx = self.to_async();
+        self.window.platform_window.start_external_drag(
+            data,
+            Box::new(move |outcome| {
+                cx.update(|cx| on_complete(outcome, cx)).log_err();
+            }),
+        )?;
+        self.active_drag.take();
+        self.refresh();
+        Ok(())
+    }
+
+    /// Renders an element into an image instead of the window, e.g. to export part of the UI
+    /// as a screenshot. The element is laid out on its own in the given size and drawn at the
+    /// given scale factor, with none of the element state of the window's frames.
+    pub fn render_to_image(
+        &mut self,
+        element: impl IntoElement,
+        size: Size<Pixels>,
+        scale_factor: f32,
+    ) -> Result<ImageData> {
+        if self.window.draw_phase != DrawPhase::None {
+            return Err(anyhow!(
+                "can't render to an image while the window is drawn"
+            ));
+        }
+
+        let rendered_frame = mem::replace(
+            &mut self.window.rendered_frame,
+            Frame::new(DispatchTree::new(
+                self.app.keymap.clone(),
+                self.app.actions.clone(),
+            )),
+        );
+        let next_frame = mem::replace(
+            &mut self.window.next_frame,
+            Frame::new(DispatchTree::new(
+                self.app.keymap.clone(),
+                self.app.actions.clone(),
+            )),
+        );
+        let window_scale_factor = mem::replace(&mut self.window.scale_factor, scale_factor);
+        let viewport_size = mem::replace(&mut self.window.viewport_size, size);
+
+        let mut element = element.into_any_element();
+        self.window.draw_phase = DrawPhase::Prepaint;
+        element.prepaint_as_root(Point::default(), size.into(), self);
+        self.window.draw_phase = DrawPhase::Paint;
+        element.paint(self);
+        self.window.draw_phase = DrawPhase::None;
+        drop(element);
+        self.window.layout_engine.as_mut().unwrap().clear();
+
+        let mut frame = mem::replace(&mut self.window.next_frame, next_frame);
+        self.window.rendered_frame = rendered_frame;
+        self.window.scale_factor = window_scale_factor;
+        self.window.viewport_size = viewport_size;
+
+        frame.scene.finish();
+        let image = self
+            .window
+            .platform_window
+            .render_to_image(&frame.scene, size.to_device_pixels(scale_factor))?;
+        Ok(ImageData::new(image))
+    }
+
     /// The scale factor of the display associated with the window. For example, it could
     /// return 2.0 for a "retina" display, indicating that each logical pixel should actually
     /// be rendered as two pixels on screen.
@@ -1356,6 +1572,11 @@ impl<'a> WindowContext<'a> {
         self.window.modifiers
     }
 
+    /// The current state of the keyboard's capslock key (macOS and Windows)
+    pub fn capslock(&self) -> Capslock {
+        self.window.capslock
+    }
+
     fn complete_frame(&self) {
         self.window.platform_window.completed_frame();
     }
@@ -1389,11 +1610,18 @@ impl<'a> WindowContext<'a> {
         self.window.next_frame.window_active = self.window.active.get();
 
         // Register requested input handler with the platform window.
-        if let Some(input_handler) = self.window.next_frame.input_handlers.pop() {
-            self.window
-                .platform_window
-                .set_input_handler(input_handler.unwrap());
-        }
+        let text_input_focused =
+            if let Some(input_handler) = self.window.next_frame.input_handlers.pop() {
+                self.window
+                    .platform_window
+                    .set_input_handler(input_handler.unwrap());
+                true
+            } else {
+                false
+            };
+        self.window
+            .platform_window
+            .set_text_input_focused(text_input_focused);
 
         self.window.layout_engine.as_mut().unwrap().clear();
         self.text_system().finish_frame();
@@ -2405,6 +2633,7 @@ impl<'a> WindowContext<'a> {
             subpixel_variant,
             scale_factor,
             is_emoji: false,
+            palette: FontPalette::default(),
         };
 
         let raster_bounds = self.text_system().raster_bounds(&params)?;
@@ -2438,6 +2667,50 @@ impl<'a> WindowContext<'a> {
         Ok(())
     }
 
+    /// Rasterizes the glyphs of the given font for the characters of `text` into the window's
+    /// sprite atlas ahead of time, so the first frame that paints them doesn't have to rasterize
+    /// them one at a time. Each glyph is rasterized at every horizontal subpixel offset.
+    ///
+    /// The glyphs are rasterized in the background, so opening the window isn't held up, and
+    /// frames drawn in the meantime rasterize the ones they paint themselves.
+    pub fn prewarm_glyphs(&mut self, font: &Font, font_size: Pixels, text: &str) -> Task<()> {
+        let text_system = self.app.text_system().clone();
+        let sprite_atlas = self.window.sprite_atlas.clone();
+        let scale_factor = self.scale_factor();
+        let font = font.clone();
+        let text = text.to_string();
+        self.background_executor().spawn(async move {
+            let font_id = text_system.resolve_font(&font);
+            let glyph_ids = text
+                .chars()
+                .filter_map(|ch| text_system.glyph_for_char(font_id, ch))
+                .collect::<FxHashSet<_>>();
+            let params = glyph_ids.into_iter().flat_map(|glyph_id| {
+                (0..SUBPIXEL_VARIANTS).map(move |subpixel_x| RenderGlyphParams {
+                    font_id,
+                    glyph_id,
+                    font_size,
+                    subpixel_variant: point(subpixel_x, 0),
+                    scale_factor,
+                    is_emoji: false,
+                    palette: FontPalette::default(),
+                })
+            });
+
+            for (params, result) in text_system.rasterize_glyphs(params) {
+                let Some((size, bytes)) = result.log_err() else {
+                    continue;
+                };
+                let mut bytes = Some(bytes);
+                sprite_atlas
+                    .get_or_insert_with(&params.into(), &mut || {
+                        Ok(bytes.take().map(|bytes| (size, Cow::Owned(bytes))))
+                    })
+                    .log_err();
+            }
+        })
+    }
+
     /// Paints an emoji glyph into the scene for the next frame at the current z-index.
     ///
     /// The y component of the origin is the baseline of the glyph.
@@ -2469,6 +2742,7 @@ impl<'a> WindowContext<'a> {
             subpixel_variant: Default::default(),
             scale_factor,
             is_emoji: true,
+            palette: self.text_system().font_palette(),
         };
 
         let raster_bounds = self.text_system().raster_bounds(&params)?;
@@ -3032,12 +3306,17 @@ impl<'a> WindowContext<'a> {
                 self.window.modifiers = mouse_up.modifiers;
                 PlatformInput::MouseUp(mouse_up)
             }
+            PlatformInput::MouseDelta(mouse_delta) => {
+                self.window.modifiers = mouse_delta.modifiers;
+                PlatformInput::MouseDelta(mouse_delta)
+            }
             PlatformInput::MouseExited(mouse_exited) => {
                 self.window.modifiers = mouse_exited.modifiers;
                 PlatformInput::MouseExited(mouse_exited)
             }
             PlatformInput::ModifiersChanged(modifiers_changed) => {
                 self.window.modifiers = modifiers_changed.modifiers;
+                self.window.capslock = modifiers_changed.capslock;
                 PlatformInput::ModifiersChanged(modifiers_changed)
             }
             PlatformInput::ScrollWheel(scroll_wheel) => {
@@ -3045,23 +3324,24 @@ impl<'a> WindowContext<'a> {
                 self.window.modifiers = scroll_wheel.modifiers;
                 PlatformInput::ScrollWheel(scroll_wheel)
             }
+            PlatformInput::Pen(pen) => {
+                self.window.mouse_position = pen.position;
+                self.window.modifiers = pen.modifiers;
+                PlatformInput::Pen(pen)
+            }
+            PlatformInput::Pinch(pinch) => {
+                self.window.mouse_position = pinch.position;
+                self.window.modifiers = pinch.modifiers;
+                PlatformInput::Pinch(pinch)
+            }
             // Translate dragging and dropping of external files from the operating system
             // to internal drag and drop events.
             PlatformInput::FileDrop(file_drop) => match file_drop {
                 FileDropEvent::Entered { position, paths } => {
-                    self.window.mouse_position = position;
-                    if self.active_drag.is_none() {
-                        self.active_drag = Some(AnyDrag {
-                            value: Box::new(paths.clone()),
-                            view: self.new_view(|_| paths).into(),
-                            cursor_offset: position,
-                        });
-                    }
-                    PlatformInput::MouseMove(MouseMoveEvent {
-                        position,
-                        pressed_button: Some(MouseButton::Left),
-                        modifiers: Modifiers::default(),
-                    })
+                    self.start_external_drag(position, paths)
+                }
+                FileDropEvent::TextEntered { position, text } => {
+                    self.start_external_drag(position, text)
                 }
                 FileDropEvent::Pending { position } => {
                     self.window.mouse_position = position;
@@ -3101,6 +3381,26 @@ impl<'a> WindowContext<'a> {
         }
     }
 
+    fn start_external_drag<T: Render + Clone>(
+        &mut self,
+        position: Point<Pixels>,
+        value: T,
+    ) -> PlatformInput {
+        self.window.mouse_position = position;
+        if self.active_drag.is_none() {
+            self.active_drag = Some(AnyDrag {
+                value: Box::new(value.clone()),
+                view: self.new_view(|_| value).into(),
+                cursor_offset: position,
+            });
+        }
+        PlatformInput::MouseMove(MouseMoveEvent {
+            position,
+            pressed_button: Some(MouseButton::Left),
+            modifiers: Modifiers::default(),
+        })
+    }
+
     fn dispatch_mouse_event(&mut self, event: &dyn Any) {
         let hit_test = self.window.rendered_frame.hit_test(self.mouse_position());
         if hit_test != self.window.mouse_hit_test {
@@ -3236,6 +3536,24 @@ impl<'a> WindowContext<'a> {
             return;
         }
 
+        if self.keystroke_diagnostics.is_some() && event.is::<KeyDownEvent>() {
+            let dispatch_tree = &self.window.rendered_frame.dispatch_tree;
+            self.window.keystroke_diagnostic =
+                keystroke.clone().map(|keystroke| KeystrokeDiagnostic {
+                    keystroke,
+                    context_stack: dispatch_path
+                        .iter()
+                        .filter_map(|node_id| dispatch_tree.node(*node_id).context.clone())
+                        .collect(),
+                    bindings: bindings
+                        .iter()
+                        .map(|binding| binding.action.name().to_string().into())
+                        .collect(),
+                    resolution: KeystrokeResolution::Unhandled,
+                    notes: Vec::new(),
+                });
+        }
+
         if pending {
             let mut currently_pending = self.window.pending_input.take().unwrap_or_default();
             if currently_pending.focus.is_some() && currently_pending.focus != self.window.focus {
@@ -3266,6 +3584,7 @@ impl<'a> WindowContext<'a> {
             self.pending_input_changed();
 
             self.propagate_event = false;
+            self.finish_keystroke_diagnostic(KeystrokeResolution::Pending);
             return;
         } else if let Some(currently_pending) = self.window.pending_input.take() {
             self.pending_input_changed();
@@ -3285,6 +3604,9 @@ impl<'a> WindowContext<'a> {
         for binding in bindings {
             self.dispatch_action_on_node(node_id, binding.action.as_ref());
             if !self.propagate_event {
+                self.finish_keystroke_diagnostic(KeystrokeResolution::Action(
+                    binding.action.name().to_string().into(),
+                ));
                 self.dispatch_keystroke_observers(event, Some(binding.action));
                 return;
             }
@@ -3308,9 +3630,47 @@ impl<'a> WindowContext<'a> {
             return;
         }
 
+        self.finish_keystroke_diagnostic(KeystrokeResolution::Unhandled);
         self.dispatch_keystroke_observers(event, None);
     }
 
+    fn finish_keystroke_diagnostic(&mut self, resolution: KeystrokeResolution) {
+        if let Some(mut diagnostic) = self.window.keystroke_diagnostic.take() {
+            diagnostic.resolution = resolution;
+            self.app.record_keystroke_diagnostic(diagnostic);
+        }
+    }
+
+    fn keystroke_handled_by_listener(&mut self, node_id: DispatchNodeId) {
+        if self.window.keystroke_diagnostic.is_none() {
+            return;
+        }
+        let dispatch_tree = &self.window.rendered_frame.dispatch_tree;
+        let context = dispatch_tree
+            .dispatch_path(node_id)
+            .iter()
+            .rev()
+            .find_map(|node_id| dispatch_tree.node(*node_id).context.clone());
+        self.finish_keystroke_diagnostic(KeystrokeResolution::KeyListener(context));
+    }
+
+    /// Add a note to the diagnostic of the keystroke being dispatched, such as the
+    /// input a handler produced for it. The note is only built while keystroke
+    /// diagnostics are enabled, see [`AppContext::set_keystroke_diagnostics_enabled`].
+    pub fn annotate_keystroke(&mut self, note: impl FnOnce() -> String) {
+        if let Some(diagnostic) = self.window.keystroke_diagnostic.as_mut() {
+            diagnostic.notes.push(note());
+        }
+    }
+
+    /// Drops the keystrokes of the chord in progress without replaying them.
+    fn cancel_pending_input(&mut self) {
+        self.clear_pending_keystrokes();
+        if self.window.pending_input.take().is_some() {
+            self.pending_input_changed();
+        }
+    }
+
     fn pending_input_changed(&mut self) {
         self.window
             .pending_input_observers
@@ -3330,6 +3690,7 @@ impl<'a> WindowContext<'a> {
             for key_listener in node.key_listeners.clone() {
                 key_listener(event, DispatchPhase::Capture, self);
                 if !self.propagate_event {
+                    self.keystroke_handled_by_listener(*node_id);
                     return;
                 }
             }
@@ -3342,6 +3703,7 @@ impl<'a> WindowContext<'a> {
             for key_listener in node.key_listeners.clone() {
                 key_listener(event, DispatchPhase::Bubble, self);
                 if !self.propagate_event {
+                    self.keystroke_handled_by_listener(*node_id);
                     return;
                 }
             }
@@ -3553,6 +3915,22 @@ impl<'a> WindowContext<'a> {
         self.window.platform_window.activate();
     }
 
+    /// Bring the current window to the foreground if the platform allows it, or draw the
+    /// user's attention to it otherwise. Use this when activating in response to something
+    /// the user did outside of the app, like opening a file from another process.
+    pub fn request_window_activation(&self) {
+        self.window.platform_window.request_activation();
+    }
+
+    /// Draw the user's attention to the current window while it's in the background, by
+    /// flashing its taskbar button on Windows or bouncing the dock icon on macOS. `None` keeps
+    /// it going until the user switches to the window, otherwise it flashes `flash_count` times.
+    pub fn request_user_attention(&self, flash_count: Option<u32>) {
+        self.window
+            .platform_window
+            .request_user_attention(flash_count);
+    }
+
     /// Minimize the current window at the platform level.
     pub fn minimize_window(&self) {
         self.window.platform_window.minimize();
@@ -3727,6 +4105,49 @@ impl WindowContext<'_> {
     pub fn get_raw_handle(&self) -> windows::Win32::Foundation::HWND {
         self.window.platform_window.get_raw_handle()
     }
+
+    /// Replaces the custom items appended to the window's system menu. Selecting one of
+    /// these items dispatches its action to the focused element of this window.
+    pub fn set_window_menu(&self, items: Vec<crate::WindowMenuItem>) {
+        self.window.platform_window.set_window_menu(items)
+    }
+
+    /// Hosts the preview handler the shell has registered for the type of the file at the given
+    /// path, such as the PDF or Office previewers, at the given bounds of this window.
+    /// The preview stays open until the returned [`crate::NativePreview`] is dropped.
+    pub fn open_native_preview(
+        &self,
+        path: &std::path::Path,
+        bounds: Bounds<Pixels>,
+    ) -> Result<crate::NativePreview> {
+        crate::NativePreview::new(self.get_raw_handle(), path, bounds, self.scale_factor())
+    }
+
+    /// Replaces the buttons shown below the window's thumbnail when hovering its taskbar button,
+    /// such as media controls. The taskbar shows at most seven.
+    pub fn set_thumbnail_buttons(&self, buttons: Vec<crate::ThumbnailButton>) {
+        self.window.platform_window.set_thumbnail_buttons(buttons)
+    }
+
+    /// Returns where the window is and how it's shown, to be saved and later restored with
+    /// [`WindowContext::set_window_placement`].
+    pub fn window_placement(&self) -> Option<crate::WindowPlacement> {
+        self.window.platform_window.window_placement()
+    }
+
+    /// Moves the window to a placement returned by [`WindowContext::window_placement`]. When its
+    /// display is gone, or the bounds no longer land on it, the window is centered on the
+    /// nearest display that's still connected.
+    pub fn set_window_placement(&self, placement: crate::WindowPlacement) {
+        self.window.platform_window.set_window_placement(placement)
+    }
+
+    /// Updates the checkmark displayed next to the window menu item bound to the given action.
+    pub fn set_window_menu_item_checked(&self, action: &dyn Action, checked: bool) {
+        self.window
+            .platform_window
+            .set_window_menu_item_checked(action, checked)
+    }
 }
 
 impl Context for WindowContext<'_> {
@@ -4532,6 +4953,15 @@ impl WindowId {
     }
 }
 
+impl From<u64> for WindowId {
+    /// Converts a `u64` returned by [`WindowId::as_u64`] back to a window ID. Window IDs are
+    /// never reused while the application runs, so an ID that outlived its window refers to
+    /// no window rather than to another one.
+    fn from(value: u64) -> Self {
+        Self(KeyData::from_ffi(value))
+    }
+}
+
 /// A handle to a window with a specific root view type.
 /// Note that this does not keep the window alive on its own.
 #[derive(Deref, DerefMut)]
@@ -1,8 +1,8 @@
 use gpui::{
-    canvas, div, fill, img, opaque_grey, point, size, AnyElement, AppContext, Bounds, Context,
-    EventEmitter, FocusHandle, FocusableView, Img, InteractiveElement, IntoElement, Model,
-    ObjectFit, ParentElement, Render, Styled, Task, View, ViewContext, VisualContext, WeakView,
-    WindowContext,
+    canvas, div, fill, img, opaque_grey, point, relative, size, AnyElement, AppContext, Bounds,
+    Context, EventEmitter, FocusHandle, FocusableView, Img, InteractiveElement, IntoElement, Model,
+    ObjectFit, ParentElement, PinchEvent, Render, Styled, Task, View, ViewContext, VisualContext,
+    WeakView, WindowContext,
 };
 use persistence::IMAGE_VIEWER;
 use ui::prelude::*;
@@ -15,8 +15,15 @@ use workspace::{
     ItemId, Pane, Workspace, WorkspaceId,
 };
 
+#[cfg(target_os = "windows")]
+mod native_preview;
+
 const IMAGE_VIEWER_KIND: &str = "ImageView";
 
+/// Images are shown fitted to the pane at the smallest zoom, and pinching zooms into them.
+const MIN_ZOOM: f32 = 1.;
+const MAX_ZOOM: f32 = 8.;
+
 pub struct ImageItem {
     path: PathBuf,
     project_path: ProjectPath,
@@ -66,9 +73,29 @@ impl project::Item for ImageItem {
 
 pub struct ImageView {
     path: PathBuf,
+    /// The size of the image relative to the pane, set by pinching.
+    zoom: f32,
     focus_handle: FocusHandle,
 }
 
+impl ImageView {
+    fn new(path: PathBuf, cx: &mut ViewContext<Self>) -> Self {
+        Self {
+            path,
+            zoom: MIN_ZOOM,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn pinch(&mut self, event: &PinchEvent, cx: &mut ViewContext<Self>) {
+        let zoom = (self.zoom * (1. + event.delta)).clamp(MIN_ZOOM, MAX_ZOOM);
+        if zoom != self.zoom {
+            self.zoom = zoom;
+            cx.notify();
+        }
+    }
+}
+
 impl Item for ImageView {
     type Event = ();
 
@@ -126,10 +153,7 @@ impl Item for ImageView {
                 .get_image_path(item_id, workspace_id)?
                 .ok_or_else(|| anyhow::anyhow!("No image path found"))?;
 
-            cx.new_view(|cx| ImageView {
-                path: image_path,
-                focus_handle: cx.focus_handle(),
-            })
+            cx.new_view(|cx| ImageView::new(image_path, cx))
         })
     }
 
@@ -141,10 +165,7 @@ impl Item for ImageView {
     where
         Self: Sized,
     {
-        Some(cx.new_view(|cx| Self {
-            path: self.path.clone(),
-            focus_handle: cx.focus_handle(),
-        }))
+        Some(cx.new_view(|cx| Self::new(self.path.clone(), cx)))
     }
 }
 
@@ -200,9 +221,25 @@ impl Render for ImageView {
             .top_0()
             .left_0();
 
+        let image = img(self.path.clone());
+        let image = if self.zoom > MIN_ZOOM {
+            // zoomed images overflow the pane evenly on both sides, keeping their center in view
+            image
+                .object_fit(ObjectFit::Contain)
+                .flex_none()
+                .w(relative(self.zoom))
+                .h(relative(self.zoom))
+        } else {
+            image
+                .object_fit(ObjectFit::ScaleDown)
+                .max_w_full()
+                .max_h_full()
+        };
+
         div()
             .track_focus(&self.focus_handle)
             .size_full()
+            .on_pinch(cx.listener(Self::pinch))
             .child(checkered_background)
             .child(
                 div()
@@ -212,12 +249,8 @@ impl Render for ImageView {
                     .w_full()
                     // TODO: In browser based Tailwind & Flex this would be h-screen and we'd use w-full
                     .h_full()
-                    .child(
-                        img(self.path.clone())
-                            .object_fit(ObjectFit::ScaleDown)
-                            .max_w_full()
-                            .max_h_full(),
-                    ),
+                    .overflow_hidden()
+                    .child(image),
             )
     }
 }
@@ -233,16 +266,15 @@ impl ProjectItem for ImageView {
     where
         Self: Sized,
     {
-        Self {
-            path: item.read(cx).path.clone(),
-            focus_handle: cx.focus_handle(),
-        }
+        Self::new(item.read(cx).path.clone(), cx)
     }
 }
 
 pub fn init(cx: &mut AppContext) {
     workspace::register_project_item::<ImageView>(cx);
-    workspace::register_deserializable_item::<ImageView>(cx)
+    workspace::register_deserializable_item::<ImageView>(cx);
+    #[cfg(target_os = "windows")]
+    native_preview::init(cx);
 }
 
 mod persistence {
@@ -0,0 +1,201 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use gpui::{
+    canvas, div, AnyElement, AppContext, Bounds, Context, EventEmitter, FocusHandle, FocusableView,
+    InteractiveElement, IntoElement, Model, NativePreview, ParentElement, Render, Styled, Task,
+    View, ViewContext, VisualContext, WindowContext,
+};
+use project::{Project, ProjectEntryId, ProjectPath};
+use ui::prelude::*;
+use workspace::{
+    item::{Item, ProjectItem, TabContentParams},
+    WorkspaceId,
+};
+
+/// Documents the editor can't show, which Windows and Office ship preview handlers for.
+const PREVIEWED_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "vsdx", "odt", "ods", "odp", "msg",
+];
+
+pub struct NativePreviewItem {
+    path: PathBuf,
+    project_path: ProjectPath,
+}
+
+impl project::Item for NativePreviewItem {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<gpui::Result<Model<Self>>>> {
+        let path = path.clone();
+        let project = project.clone();
+
+        let ext = path
+            .path
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !PREVIEWED_EXTENSIONS.contains(&ext.as_str()) {
+            return None;
+        }
+        Some(cx.spawn(|mut cx| async move {
+            let abs_path = project
+                .read_with(&cx, |project, cx| project.absolute_path(&path, cx))?
+                .ok_or_else(|| anyhow::anyhow!("Failed to find the absolute path"))?;
+
+            cx.new_model(|_| NativePreviewItem {
+                path: abs_path,
+                project_path: path,
+            })
+        }))
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        None
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        Some(self.project_path.clone())
+    }
+}
+
+/// Shows a document with the preview handler the shell has registered for its type.
+///
+/// The preview is a native child window drawn over the pane, so it's hidden while its tab is
+/// in the background, and it covers popovers that overlap it.
+pub struct NativePreviewView {
+    path: PathBuf,
+    preview: Option<NativePreview>,
+    /// Set when the shell has no previewer for the file, or it failed to load.
+    error: Option<SharedString>,
+    focus_handle: FocusHandle,
+}
+
+impl NativePreviewView {
+    fn new(path: PathBuf, cx: &mut ViewContext<Self>) -> Self {
+        let focus_handle = cx.focus_handle();
+        cx.on_focus(&focus_handle, |this, _| {
+            if let Some(preview) = this.preview.as_ref() {
+                preview.focus();
+            }
+        })
+        .detach();
+        Self {
+            path,
+            preview: None,
+            error: None,
+            focus_handle,
+        }
+    }
+
+    /// Opens the preview the first time it's laid out, and keeps it over the view's bounds.
+    fn layout_preview(&mut self, bounds: Bounds<Pixels>, cx: &mut ViewContext<Self>) {
+        if let Some(preview) = self.preview.as_ref() {
+            preview.set_bounds(bounds, cx.scale_factor());
+            preview.set_visible(true);
+        } else if self.error.is_none() {
+            match cx.open_native_preview(&self.path, bounds) {
+                Ok(preview) => self.preview = Some(preview),
+                Err(error) => {
+                    self.error = Some(format!("No preview available: {error}").into());
+                    cx.notify();
+                }
+            }
+        }
+    }
+}
+
+impl Item for NativePreviewView {
+    type Event = ();
+
+    fn tab_content(&self, params: TabContentParams, _cx: &WindowContext) -> AnyElement {
+        let title = self
+            .path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        Label::new(title)
+            .single_line()
+            .color(if params.selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .italic(params.preview)
+            .into_any_element()
+    }
+
+    fn deactivated(&mut self, _: &mut ViewContext<Self>) {
+        if let Some(preview) = self.preview.as_ref() {
+            preview.set_visible(false);
+        }
+    }
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: Option<WorkspaceId>,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<View<Self>>
+    where
+        Self: Sized,
+    {
+        Some(cx.new_view(|cx| Self::new(self.path.clone(), cx)))
+    }
+}
+
+impl EventEmitter<()> for NativePreviewView {}
+impl FocusableView for NativePreviewView {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for NativePreviewView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content = if let Some(error) = self.error.clone() {
+            div()
+                .flex()
+                .justify_center()
+                .items_center()
+                .size_full()
+                .child(Label::new(error).color(Color::Muted))
+                .into_any_element()
+        } else {
+            let view = cx.view().clone();
+            canvas(
+                |_, _| (),
+                move |bounds, _, cx| {
+                    view.update(cx, |view, cx| view.layout_preview(bounds, cx));
+                },
+            )
+            .size_full()
+            .into_any_element()
+        };
+        div()
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .child(content)
+    }
+}
+
+impl ProjectItem for NativePreviewView {
+    type Item = NativePreviewItem;
+
+    fn for_project_item(
+        _project: Model<Project>,
+        item: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(item.read(cx).path.clone(), cx)
+    }
+}
+
+pub fn init(cx: &mut AppContext) {
+    workspace::register_project_item::<NativePreviewView>(cx);
+}
@@ -160,6 +160,8 @@ pub fn render_item<T>(
         font_features: settings.buffer_font.features.clone(),
         font_size: settings.buffer_font_size(cx).into(),
         font_weight: settings.buffer_font.weight,
+        font_stretch: settings.buffer_font.stretch,
+        font_simulations: settings.buffer_font.simulations,
         font_style: FontStyle::Normal,
         line_height: relative(1.),
         background_color: None,
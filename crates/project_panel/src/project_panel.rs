@@ -17,11 +17,11 @@ use collections::{hash_map, BTreeSet, HashMap};
 use git::repository::GitFileStatus;
 use gpui::{
     actions, anchored, deferred, div, impl_actions, px, uniform_list, Action, AnyElement,
-    AppContext, AssetSource, AsyncWindowContext, ClipboardItem, DismissEvent, Div, DragMoveEvent,
-    EventEmitter, ExternalPaths, FocusHandle, FocusableView, InteractiveElement, KeyContext,
-    ListSizingBehavior, Model, MouseButton, MouseDownEvent, ParentElement, Pixels, Point,
-    PromptLevel, Render, Stateful, Styled, Subscription, Task, UniformListScrollHandle, View,
-    ViewContext, VisualContext as _, WeakView, WindowContext,
+    AppContext, AssetSource, AsyncWindowContext, Bounds, ClipboardItem, DismissEvent, Div,
+    DragMoveEvent, EventEmitter, ExternalDragData, ExternalPaths, FocusHandle, FocusableView,
+    InteractiveElement, KeyContext, ListSizingBehavior, Model, MouseButton, MouseDownEvent,
+    ParentElement, Pixels, Point, PromptLevel, Render, Stateful, Styled, Subscription, Task,
+    UniformListScrollHandle, View, ViewContext, VisualContext as _, WeakView, WindowContext,
 };
 use menu::{Confirm, SelectFirst, SelectLast, SelectNext, SelectPrev};
 use project::{Entry, EntryKind, Fs, Project, ProjectEntryId, ProjectPath, Worktree, WorktreeId};
@@ -1693,6 +1693,36 @@ impl ProjectPanel {
         });
     }
 
+    fn drag_selection_out_of_window(
+        &mut self,
+        event: &DragMoveEvent<DraggedSelection>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // only Windows can hand drags over to other applications so far
+        let viewport = Bounds::new(Point::default(), cx.viewport_size());
+        if !cfg!(target_os = "windows") || viewport.contains(&event.event.position) {
+            return;
+        }
+        let project = self.project.read(cx);
+        if !project.is_local() {
+            return;
+        }
+        let paths = event
+            .drag(cx)
+            .items()
+            .filter_map(|selection| {
+                let worktree = project.worktree_for_id(selection.worktree_id, cx)?;
+                let worktree = worktree.read(cx);
+                let entry = worktree.entry_for_id(selection.entry_id)?;
+                worktree.absolutize(&entry.path).log_err()
+            })
+            .collect::<Vec<_>>();
+        if !paths.is_empty() {
+            cx.start_external_drag(ExternalDragData { paths, text: None }, |_, _| {})
+                .log_err();
+        }
+    }
+
     fn drop_external_files(
         &mut self,
         paths: &[PathBuf],
@@ -2434,6 +2464,7 @@ impl Render for ProjectPanel {
                         }
                     }),
                 )
+                .on_drag_move::<DraggedSelection>(cx.listener(Self::drag_selection_out_of_window))
                 .track_focus(&self.focus_handle)
                 .child(
                     uniform_list(cx.view().clone(), "entries", items_count, {
@@ -117,6 +117,8 @@ impl BufferSearchBar {
             font_features: settings.buffer_font.features.clone(),
             font_size: rems(0.875).into(),
             font_weight: settings.buffer_font.weight,
+            font_stretch: settings.buffer_font.stretch,
+            font_simulations: settings.buffer_font.simulations,
             font_style: FontStyle::Normal,
             line_height: relative(1.3),
             background_color: None,
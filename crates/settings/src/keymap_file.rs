@@ -1,7 +1,7 @@
-use crate::{settings_store::parse_json_with_comments, SettingsAssets};
+use crate::{settings_store::parse_json_with_comments, Settings, SettingsAssets, SettingsSources};
 use anyhow::{anyhow, Context, Result};
 use collections::BTreeMap;
-use gpui::{Action, AppContext, KeyBinding, SharedString};
+use gpui::{Action, AppContext, KeyBinding, KeyBindingFallback, SharedString};
 use schemars::{
     gen::{SchemaGenerator, SchemaSettings},
     schema::{InstanceType, Schema, SchemaObject, SingleOrVec, SubschemaValidation},
@@ -19,6 +19,9 @@ pub struct KeymapFile(Vec<KeymapBlock>);
 pub struct KeymapBlock {
     #[serde(default)]
     context: Option<String>,
+    /// Keystrokes, like `ctrl-shift-p`, and the actions they trigger. Keys whose character
+    /// depends on the keyboard layout can be bound by their position instead, like
+    /// `ctrl-code:BracketLeft` for the key that produces `[` on a US layout.
     bindings: BTreeMap<String, KeymapAction>,
 }
 
@@ -39,11 +42,35 @@ impl JsonSchema for KeymapAction {
 #[derive(Deserialize)]
 struct ActionWithData(Box<str>, Value);
 
+/// The `keymap_layout_fallback` setting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeymapLayoutFallback(pub KeyBindingFallback);
+
+impl Settings for KeymapLayoutFallback {
+    const KEY: Option<&'static str> = Some("keymap_layout_fallback");
+
+    type FileContent = Option<KeyBindingFallback>;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut AppContext) -> Result<Self> {
+        Ok(Self(sources.user.copied().flatten().unwrap_or(
+            sources.default.ok_or_else(Self::missing_default)?,
+        )))
+    }
+}
+
 impl KeymapFile {
     pub fn load_asset(asset_path: &str, cx: &mut AppContext) -> Result<()> {
+        Self::load_asset_with_fallback(asset_path, KeyBindingFallback::Keep, cx)
+    }
+
+    pub fn load_asset_with_fallback(
+        asset_path: &str,
+        fallback: KeyBindingFallback,
+        cx: &mut AppContext,
+    ) -> Result<()> {
         let content = asset_str::<SettingsAssets>(asset_path);
 
-        Self::parse(content.as_ref())?.add_to_cx(cx)
+        Self::parse(content.as_ref())?.add_to_cx_with_fallback(fallback, cx)
     }
 
     pub fn parse(content: &str) -> Result<Self> {
@@ -54,6 +81,17 @@ impl KeymapFile {
     }
 
     pub fn add_to_cx(self, cx: &mut AppContext) -> Result<()> {
+        self.add_to_cx_with_fallback(KeyBindingFallback::Keep, cx)
+    }
+
+    /// Add the bindings to the app, handling the ones that can't be typed on the current
+    /// keyboard layout according to `fallback`.
+    pub fn add_to_cx_with_fallback(
+        self,
+        fallback: KeyBindingFallback,
+        cx: &mut AppContext,
+    ) -> Result<()> {
+        let keyboard_mapper = cx.keyboard_mapper();
         for KeymapBlock { context, bindings } in self.0 {
             let bindings = bindings
                 .into_iter()
@@ -90,9 +128,19 @@ impl KeymapFile {
                         )
                     })
                     .log_err()
-                    .map(|action| KeyBinding::load(&keystroke, action, context.as_deref()))
+                    .map(|action| {
+                        KeyBinding::load_with_keyboard_mapper(
+                            &keystroke,
+                            action,
+                            context.as_deref(),
+                            keyboard_mapper.as_ref(),
+                        )
+                    })
                 })
-                .collect::<Result<Vec<_>>>()?;
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .filter_map(|binding| binding.with_fallback(fallback, keyboard_mapper.as_ref()))
+                .collect::<Vec<_>>();
 
             cx.bind_keys(bindings);
         }
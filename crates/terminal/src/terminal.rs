@@ -104,6 +104,8 @@ pub enum Event {
     SelectionsChanged,
     NewNavigationTarget(Option<MaybeNavigationTarget>),
     Open(MaybeNavigationTarget),
+    /// The task of the terminal stopped running, see [`Terminal::task`] for how it went.
+    TaskFinished,
 }
 
 #[derive(Clone, Debug)]
@@ -649,7 +651,16 @@ impl Terminal {
                     .map(|ci| ci.text().to_string())
                     .unwrap_or_else(|| "".to_string()),
             )),
-            AlacTermEvent::PtyWrite(out) => self.write_to_pty(out.clone()),
+            AlacTermEvent::PtyWrite(out) => {
+                let settings = TerminalSettings::get_global(cx);
+                let out = device_attributes_reply(
+                    out,
+                    settings.primary_device_attributes.as_deref(),
+                    settings.secondary_device_attributes.as_deref(),
+                )
+                .unwrap_or_else(|| out.clone());
+                self.write_to_pty(out)
+            }
             AlacTermEvent::TextAreaSizeRequest(format) => {
                 self.write_to_pty(format(self.last_content.size.into()))
             }
@@ -1056,14 +1067,12 @@ impl Terminal {
         self.write_bytes_to_pty(input);
     }
 
-    pub fn try_keystroke(&mut self, keystroke: &Keystroke, alt_is_meta: bool) -> bool {
-        let esc = to_esc_str(keystroke, &self.last_content.mode, alt_is_meta);
-        if let Some(esc) = esc {
-            self.input(esc);
-            true
-        } else {
-            false
-        }
+    /// Sends the escape sequence for the keystroke to the pty, returning it if the
+    /// keystroke has one in the current terminal mode.
+    pub fn try_keystroke(&mut self, keystroke: &Keystroke, alt_is_meta: bool) -> Option<String> {
+        let esc = to_esc_str(keystroke, &self.last_content.mode, alt_is_meta)?;
+        self.input(esc.clone());
+        Some(esc)
     }
 
     pub fn try_modifiers_change(&mut self, modifiers: &Modifiers) -> bool {
@@ -1534,6 +1543,7 @@ impl Terminal {
         // when Zed task finishes and no more output is made.
         // After the task summary is output once, no more text is appended to the terminal.
         unsafe { append_text_to_term(&mut self.term.lock(), &[&task_line, &command_line]) };
+        cx.emit(Event::TaskFinished);
     }
 }
 
@@ -1732,6 +1742,27 @@ pub fn rgba_color(r: u8, g: u8, b: u8) -> Hsla {
     .into()
 }
 
+/// Replaces the device attributes that alacritty reports for itself with the configured ones.
+fn device_attributes_reply(
+    out: &str,
+    primary_attributes: Option<&str>,
+    secondary_attributes: Option<&str>,
+) -> Option<String> {
+    // alacritty answers `CSI c` with "\x1b[?6c" and `CSI > c` with "\x1b[>0;{version};1c"
+    if !out.ends_with('c') {
+        return None;
+    }
+    if out.starts_with("\x1b[?") {
+        let attributes = primary_attributes?;
+        Some(format!("\x1b[?{attributes}c"))
+    } else if out.starts_with("\x1b[>") {
+        let attributes = secondary_attributes?;
+        Some(format!("\x1b[>{attributes}c"))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alacritty_terminal::{
@@ -1742,7 +1773,8 @@ mod tests {
     use rand::{distributions::Alphanumeric, rngs::ThreadRng, thread_rng, Rng};
 
     use crate::{
-        content_index_for_mouse, rgb_for_index, IndexedCell, TerminalContent, TerminalSize,
+        content_index_for_mouse, device_attributes_reply, rgb_for_index, IndexedCell,
+        TerminalContent, TerminalSize,
     };
 
     #[test]
@@ -1754,6 +1786,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_device_attributes_reply() {
+        let primary = Some("62;4;22");
+        let secondary = Some("41;100;0");
+        assert_eq!(
+            device_attributes_reply("\x1b[?6c", primary, secondary).as_deref(),
+            Some("\x1b[?62;4;22c")
+        );
+        assert_eq!(
+            device_attributes_reply("\x1b[>0;2401;1c", primary, secondary).as_deref(),
+            Some("\x1b[>41;100;0c")
+        );
+        assert_eq!(device_attributes_reply("\x1b[?6c", None, secondary), None);
+        // other reports are passed through
+        assert_eq!(
+            device_attributes_reply("\x1b[1;1R", primary, secondary),
+            None
+        );
+        assert_eq!(
+            device_attributes_reply("\x1b[?1;2$y", primary, secondary),
+            None
+        );
+    }
+
     #[test]
     fn test_mouse_to_cell_test() {
         let mut rng = thread_rng();
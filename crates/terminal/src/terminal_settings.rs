@@ -44,6 +44,8 @@ pub struct TerminalSettings {
     pub detect_venv: VenvSettings,
     pub max_scroll_history_lines: Option<usize>,
     pub toolbar: Toolbar,
+    pub primary_device_attributes: Option<String>,
+    pub secondary_device_attributes: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -171,6 +173,17 @@ pub struct TerminalSettingsContent {
     pub max_scroll_history_lines: Option<usize>,
     /// Toolbar related settings
     pub toolbar: Option<ToolbarContent>,
+    /// The parameters of the reply to primary device attribute requests (`CSI c`),
+    /// which programs use to find out which features the terminal supports,
+    /// e.g. "62;4;22" to announce a VT220 with sixel graphics and ANSI colors.
+    ///
+    /// Default: "6", the reply of the underlying terminal emulator
+    pub primary_device_attributes: Option<String>,
+    /// The parameters of the reply to secondary device attribute requests (`CSI > c`),
+    /// identifying the terminal type and its version.
+    ///
+    /// Default: the reply of the underlying terminal emulator
+    pub secondary_device_attributes: Option<String>,
 }
 
 impl settings::Settings for TerminalSettings {
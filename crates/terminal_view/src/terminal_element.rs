@@ -619,6 +619,8 @@ impl Element for TerminalElement {
                     font_family,
                     font_features,
                     font_weight,
+                    font_stretch: settings.buffer_font.stretch,
+                    font_simulations: settings.buffer_font.simulations,
                     font_size: font_size.into(),
                     font_style: FontStyle::Normal,
                     line_height: line_height.into(),
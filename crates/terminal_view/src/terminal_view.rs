@@ -622,10 +622,53 @@ fn subscribe_for_terminal_events(
             Event::BreadcrumbsChanged => cx.emit(ItemEvent::UpdateBreadcrumbs),
             Event::CloseTerminal => cx.emit(ItemEvent::CloseItem),
             Event::SelectionsChanged => cx.emit(SearchEvent::ActiveMatchChanged),
+            Event::TaskFinished => {
+                #[cfg(target_os = "windows")]
+                show_task_finished_toast(this, cx);
+            }
         });
     vec![terminal_subscription, terminal_events_subscription]
 }
 
+/// Tells about a finished task through the system while Zed is in the background, which is
+/// when long-running tasks tend to finish. Clicking it goes back to the task's terminal.
+#[cfg(target_os = "windows")]
+fn show_task_finished_toast(terminal_view: &TerminalView, cx: &mut ViewContext<TerminalView>) {
+    if cx.is_app_active() {
+        return;
+    }
+    let Some(task) = terminal_view.terminal().read(cx).task() else {
+        return;
+    };
+    let body = match task.status {
+        TaskStatus::Completed { success: true } => "Finished successfully",
+        TaskStatus::Completed { success: false } => "Failed",
+        TaskStatus::Unknown | TaskStatus::Running => "Finished",
+    };
+    let notification = gpui::SystemNotification {
+        id: format!("task-{}", task.id.0).into(),
+        title: task.full_label.clone().into(),
+        body: body.into(),
+        actions: Vec::new(),
+    };
+    let window = cx.window_handle();
+    let terminal_view = cx.view().downgrade();
+    workspace::notifications::system_notifications::show(
+        notification,
+        move |_, cx| {
+            cx.activate(true);
+            window
+                .update(cx, |_, cx| {
+                    cx.activate_window();
+                    terminal_view.update(cx, |_, cx| cx.focus_self()).ok();
+                })
+                .log_err();
+        },
+        cx,
+    )
+    .log_err();
+}
+
 fn possible_open_paths_metadata(
     fs: Arc<dyn Fs>,
     row: Option<u32>,
@@ -736,12 +779,15 @@ impl TerminalView {
         self.clear_bell(cx);
         self.pause_cursor_blinking(cx);
 
-        self.terminal.update(cx, |term, cx| {
+        let esc = self.terminal.update(cx, |term, cx| {
             term.try_keystroke(
                 &event.keystroke,
                 TerminalSettings::get_global(cx).option_as_meta,
             )
         });
+        if let Some(esc) = esc {
+            cx.annotate_keystroke(|| format!("terminal wrote {:?} to the pty", esc));
+        }
     }
 
     fn focus_in(&mut self, cx: &mut ViewContext<Self>) {
@@ -3,8 +3,8 @@ use crate::{Appearance, SyntaxTheme, Theme, ThemeRegistry, ThemeStyleContent};
 use anyhow::Result;
 use derive_more::{Deref, DerefMut};
 use gpui::{
-    px, AppContext, Font, FontFeatures, FontStyle, FontWeight, Global, Pixels, Subscription,
-    ViewContext, WindowContext,
+    px, AppContext, Font, FontFeatures, FontSimulations, FontStyle, FontWeight, Global, Hsla,
+    Pixels, Subscription, SystemAccentColor, ViewContext, WindowContext,
 };
 use refineable::Refineable;
 use schemars::{
@@ -90,6 +90,7 @@ pub struct ThemeSettings {
     pub theme_selection: Option<ThemeSelection>,
     pub active_theme: Arc<Theme>,
     pub theme_overrides: Option<ThemeStyleContent>,
+    pub use_system_accent_color: bool,
     pub ui_density: UiDensity,
 }
 
@@ -235,6 +236,9 @@ pub struct ThemeSettingsContent {
     /// The weight of the UI font in CSS units from 100 to 900.
     #[serde(default)]
     pub ui_font_weight: Option<f32>,
+    /// Which faces may be synthesized for the UI font when it has no bold or italic face.
+    #[serde(default)]
+    pub ui_font_simulations: Option<FontSimulations>,
     /// The name of a font to use for rendering in text buffers.
     #[serde(default)]
     pub buffer_font_family: Option<String>,
@@ -244,6 +248,9 @@ pub struct ThemeSettingsContent {
     /// The weight of the editor font in CSS units from 100 to 900.
     #[serde(default)]
     pub buffer_font_weight: Option<f32>,
+    /// Which faces may be synthesized for the editor font when it has no bold or italic face.
+    #[serde(default)]
+    pub buffer_font_simulations: Option<FontSimulations>,
     /// The buffer's line height.
     #[serde(default)]
     pub buffer_line_height: Option<BufferLineHeight>,
@@ -253,6 +260,10 @@ pub struct ThemeSettingsContent {
     /// The name of the Zed theme to use.
     #[serde(default)]
     pub theme: Option<ThemeSelection>,
+    /// Whether to derive the colors of selections and focus rings from the accent
+    /// color of the operating system, on platforms that have one.
+    #[serde(default)]
+    pub use_system_accent_color: Option<bool>,
 
     /// UNSTABLE: Expect many elements to be broken.
     ///
@@ -311,11 +322,33 @@ impl ThemeSettings {
             new_theme = Some(theme);
         }
 
+        self.apply_system_accent_color(cx.system_accent_color());
         self.apply_theme_overrides();
 
         new_theme
     }
 
+    /// Derives the selection and focus ring colors of the current theme from the given
+    /// system accent color, if [`Self::use_system_accent_color`] is enabled.
+    pub fn apply_system_accent_color(&mut self, accent_color: Option<SystemAccentColor>) {
+        let Some(accent_color) = accent_color.filter(|_| self.use_system_accent_color) else {
+            return;
+        };
+        let mut base_theme = (*self.active_theme).clone();
+
+        // The same shades the system uses for accented controls on light and dark backgrounds.
+        let color = match base_theme.appearance {
+            Appearance::Light => accent_color.dark[0],
+            Appearance::Dark => accent_color.light[1],
+        };
+        base_theme.styles.colors.border_focused = color;
+        if let Some(local_player) = base_theme.styles.player.0.first_mut() {
+            local_player.selection = Hsla { a: 0.25, ..color };
+        }
+
+        self.active_theme = Arc::new(base_theme);
+    }
+
     /// Applies the theme overrides, if there are any, to the current theme.
     pub fn apply_theme_overrides(&mut self) {
         if let Some(theme_overrides) = &self.theme_overrides {
@@ -388,6 +421,42 @@ pub fn reset_buffer_font_size(cx: &mut AppContext) {
     }
 }
 
+/// Returns the UI and buffer font sizes the user zoomed to, `None` for the ones that follow the settings.
+pub fn font_size_adjustments(cx: &AppContext) -> (Option<Pixels>, Option<Pixels>) {
+    (
+        cx.try_global::<AdjustedUiFontSize>()
+            .map(|adjusted_size| adjusted_size.0),
+        cx.try_global::<AdjustedBufferFontSize>()
+            .map(|adjusted_size| adjusted_size.0),
+    )
+}
+
+/// Restores font sizes previously returned by [`font_size_adjustments`].
+pub fn restore_font_size_adjustments(
+    cx: &mut AppContext,
+    ui_font_size: Option<Pixels>,
+    buffer_font_size: Option<Pixels>,
+) {
+    if font_size_adjustments(cx) == (ui_font_size, buffer_font_size) {
+        return;
+    }
+    match ui_font_size {
+        Some(size) => cx.set_global(AdjustedUiFontSize(size.max(MIN_FONT_SIZE))),
+        None if cx.has_global::<AdjustedUiFontSize>() => {
+            cx.remove_global::<AdjustedUiFontSize>();
+        }
+        None => {}
+    }
+    match buffer_font_size {
+        Some(size) => cx.set_global(AdjustedBufferFontSize(size.max(MIN_FONT_SIZE))),
+        None if cx.has_global::<AdjustedBufferFontSize>() => {
+            cx.remove_global::<AdjustedBufferFontSize>();
+        }
+        None => {}
+    }
+    cx.refresh();
+}
+
 pub fn setup_ui_font(cx: &mut WindowContext) -> gpui::Font {
     let (ui_font, ui_font_size) = {
         let theme_settings = ThemeSettings::get_global(cx);
@@ -440,13 +509,17 @@ impl settings::Settings for ThemeSettings {
                 family: defaults.ui_font_family.clone().unwrap().into(),
                 features: defaults.ui_font_features.clone().unwrap(),
                 weight: defaults.ui_font_weight.map(FontWeight).unwrap(),
+                stretch: Default::default(),
                 style: Default::default(),
+                simulations: defaults.ui_font_simulations.unwrap_or_default(),
             },
             buffer_font: Font {
                 family: defaults.buffer_font_family.clone().unwrap().into(),
                 features: defaults.buffer_font_features.clone().unwrap(),
                 weight: defaults.buffer_font_weight.map(FontWeight).unwrap(),
+                stretch: Default::default(),
                 style: FontStyle::default(),
+                simulations: defaults.buffer_font_simulations.unwrap_or_default(),
             },
             buffer_font_size: defaults.buffer_font_size.unwrap().into(),
             buffer_line_height: defaults.buffer_line_height.unwrap(),
@@ -456,6 +529,7 @@ impl settings::Settings for ThemeSettings {
                 .or(themes.get(&one_dark().name))
                 .unwrap(),
             theme_overrides: None,
+            use_system_accent_color: defaults.use_system_accent_color.unwrap_or(false),
             ui_density: defaults.ui_density.unwrap_or(UiDensity::Default),
         };
 
@@ -474,6 +548,9 @@ impl settings::Settings for ThemeSettings {
             if let Some(value) = value.buffer_font_weight {
                 this.buffer_font.weight = FontWeight(value);
             }
+            if let Some(value) = value.buffer_font_simulations {
+                this.buffer_font.simulations = value;
+            }
 
             if let Some(value) = value.ui_font_family.clone() {
                 this.ui_font.family = value.into();
@@ -484,6 +561,9 @@ impl settings::Settings for ThemeSettings {
             if let Some(value) = value.ui_font_weight {
                 this.ui_font.weight = FontWeight(value);
             }
+            if let Some(value) = value.ui_font_simulations {
+                this.ui_font.simulations = value;
+            }
 
             if let Some(value) = &value.theme {
                 this.theme_selection = Some(value.clone());
@@ -504,6 +584,16 @@ impl settings::Settings for ThemeSettings {
                 value.buffer_font_size.map(Into::into),
             );
             merge(&mut this.buffer_line_height, value.buffer_line_height);
+            merge(
+                &mut this.use_system_accent_color,
+                value.use_system_accent_color,
+            );
+        }
+
+        if this.use_system_accent_color {
+            // The theme overrides are applied again so they take precedence.
+            this.apply_system_accent_color(cx.system_accent_color());
+            this.apply_theme_overrides();
         }
 
         Ok(this)
@@ -79,6 +79,13 @@ pub fn init(themes_to_load: LoadThemes, cx: &mut AppContext) {
 
     ThemeSettings::register(cx);
 
+    cx.observe_system_accent_color(|cx| {
+        if ThemeSettings::get_global(cx).use_system_accent_color {
+            ThemeSettings::reload_current_theme(cx);
+        }
+    })
+    .detach();
+
     let mut prev_buffer_font_size = ThemeSettings::get_global(cx).buffer_font_size;
     cx.observe_global::<SettingsStore>(move |cx| {
         let buffer_font_size = ThemeSettings::get_global(cx).buffer_font_size;
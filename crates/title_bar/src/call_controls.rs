@@ -51,6 +51,12 @@ pub fn toggle_mute(_: &ToggleMute, cx: &mut AppContext) {
     }
 }
 
+pub fn leave_call(_: &LeaveCall, cx: &mut WindowContext) {
+    ActiveCall::global(cx)
+        .update(cx, |call, cx| call.hang_up(cx))
+        .detach_and_prompt_err("Failed to hang up", cx, |_, _| None);
+}
+
 pub fn toggle_deafen(_: &ToggleDeafen, cx: &mut AppContext) {
     if let Some(room) = ActiveCall::global(cx).read(cx).room().cloned() {
         room.update(cx, |room, cx| room.toggle_deafen(cx));
@@ -79,11 +79,14 @@ impl RenderOnce for WindowsWindowControls {
                 },
                 button_hover_color,
             ))
-            .child(WindowsCaptionButton::new(
-                "close",
-                WindowsCaptionButtonIcon::Close,
-                close_button_hover_color,
-            ))
+            .child(
+                WindowsCaptionButton::new(
+                    "close",
+                    WindowsCaptionButtonIcon::Close,
+                    close_button_hover_color,
+                )
+                .edited(cx.is_window_edited()),
+            )
     }
 }
 
@@ -100,6 +103,7 @@ struct WindowsCaptionButton {
     id: ElementId,
     icon: WindowsCaptionButtonIcon,
     hover_background_color: Rgba,
+    edited: bool,
 }
 
 impl WindowsCaptionButton {
@@ -112,12 +116,19 @@ impl WindowsCaptionButton {
             id: id.into(),
             icon,
             hover_background_color,
+            edited: false,
         }
     }
+
+    /// Shows an unsaved changes indicator on the button, like the dot in the close button on macOS.
+    pub fn edited(mut self, edited: bool) -> Self {
+        self.edited = edited;
+        self
+    }
 }
 
 impl RenderOnce for WindowsCaptionButton {
-    fn render(self, _cx: &mut WindowContext) -> impl IntoElement {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
         // todo(windows) report this width to the Windows platform API
         // NOTE: this is intentionally hard coded. An option to use the 'native' size
         //       could be added when the width is reported to the Windows platform API
@@ -126,6 +137,7 @@ impl RenderOnce for WindowsCaptionButton {
 
         h_flex()
             .id(self.id)
+            .relative()
             .justify_center()
             .content_center()
             .w(width)
@@ -144,5 +156,16 @@ impl RenderOnce for WindowsCaptionButton {
                 WindowsCaptionButtonIcon::Maximize => "\u{e922}",
                 WindowsCaptionButtonIcon::Close => "\u{e8bb}",
             })
+            .when(self.edited, |button| {
+                button.child(
+                    div()
+                        .absolute()
+                        .top(px(6.))
+                        .right(px(6.))
+                        .size(px(6.))
+                        .rounded_full()
+                        .bg(cx.theme().colors().text_accent),
+                )
+            })
     }
 }
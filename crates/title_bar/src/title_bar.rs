@@ -44,7 +44,13 @@ actions!(
 pub fn init(cx: &mut AppContext) {
     cx.observe_new_views(|workspace: &mut Workspace, cx| {
         let item = cx.new_view(|cx| TitleBar::new("title-bar", workspace, cx));
-        workspace.set_titlebar_item(item.into(), cx)
+        workspace.set_titlebar_item(item.into(), cx);
+        workspace.register_action(|_, action: &call_controls::ToggleMute, cx| {
+            call_controls::toggle_mute(action, cx)
+        });
+        workspace.register_action(|_, action: &call_controls::LeaveCall, cx| {
+            call_controls::leave_call(action, cx)
+        });
     })
     .detach();
 }
@@ -57,6 +63,10 @@ pub struct TitleBar {
     user_store: Model<UserStore>,
     client: Arc<Client>,
     workspace: WeakView<Workspace>,
+    /// Whether the microphone is muted, and can be used, in the call the buttons below the
+    /// window's thumbnail were last set for.
+    #[cfg(target_os = "windows")]
+    thumbnail_call_state: Option<(bool, bool)>,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -373,7 +383,9 @@ impl Render for TitleBar {
                         )
             )
             .when(
-                self.platform_style == PlatformStyle::Windows && !cx.is_fullscreen(),
+                self.platform_style == PlatformStyle::Windows
+                    && !cx.is_fullscreen()
+                    && cx.titlebar_appears_transparent(),
                 |title_bar| title_bar.child(platform_windows::WindowsWindowControls::new(height)),
             )
             .when(
@@ -417,6 +429,10 @@ impl TitleBar {
         subscriptions.push(cx.observe_window_activation(Self::window_activation_changed));
         subscriptions.push(cx.observe(&user_store, |_, _, cx| cx.notify()));
 
+        // the buttons of a call that's already going are shown once the title bar is created
+        #[cfg(target_os = "windows")]
+        cx.defer(|this, cx| this.update_thumbnail_buttons(cx));
+
         Self {
             platform_style: PlatformStyle::platform(),
             content: div().id(id.into()),
@@ -425,6 +441,8 @@ impl TitleBar {
             project,
             user_store,
             client,
+            #[cfg(target_os = "windows")]
+            thumbnail_call_state: None,
             _subscriptions: subscriptions,
         }
     }
@@ -810,9 +828,91 @@ impl TitleBar {
     }
 
     fn active_call_changed(&mut self, cx: &mut ViewContext<Self>) {
+        #[cfg(target_os = "windows")]
+        self.update_thumbnail_buttons(cx);
         cx.notify();
     }
 
+    /// Shows buttons for muting and leaving the call below the window's thumbnail in the
+    /// taskbar, for as long as there's a call.
+    #[cfg(target_os = "windows")]
+    fn update_thumbnail_buttons(&mut self, cx: &mut ViewContext<Self>) {
+        let call_state = ActiveCall::global(cx).read(cx).room().map(|room| {
+            let room = room.read(cx);
+            (room.is_muted(), room.can_use_microphone())
+        });
+        if call_state == self.thumbnail_call_state {
+            return;
+        }
+        self.thumbnail_call_state = call_state;
+        let Some((is_muted, can_use_microphone)) = call_state else {
+            cx.set_thumbnail_buttons(Vec::new());
+            return;
+        };
+        let buttons = [
+            (
+                if is_muted {
+                    IconName::MicMute
+                } else {
+                    IconName::Mic
+                },
+                if is_muted {
+                    "Unmute microphone"
+                } else {
+                    "Mute microphone"
+                },
+                call_controls::ToggleMute.boxed_clone(),
+                can_use_microphone,
+            ),
+            (
+                IconName::Exit,
+                "Leave call",
+                call_controls::LeaveCall.boxed_clone(),
+                true,
+            ),
+        ];
+        let buttons = buttons
+            .into_iter()
+            .filter_map(|(icon, tooltip, action, enabled)| {
+                Some(gpui::ThumbnailButton {
+                    icon: Arc::new(Self::render_thumbnail_icon(icon, cx)?),
+                    tooltip: tooltip.into(),
+                    action,
+                    enabled,
+                })
+            })
+            .collect();
+        cx.set_thumbnail_buttons(buttons);
+    }
+
+    /// Draws an icon of a thumbnail button, with its pixels in the BGRA order the taskbar
+    /// takes rather than the RGBA order they're rendered in.
+    #[cfg(target_os = "windows")]
+    fn render_thumbnail_icon(icon: IconName, cx: &mut WindowContext) -> Option<gpui::ImageData> {
+        let image = cx
+            .render_to_image(
+                div()
+                    .size_full()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .child(Icon::new(icon).size(IconSize::Medium)),
+                gpui::size(px(16.), px(16.)),
+                cx.scale_factor(),
+            )
+            .log_err()?;
+        let image_size = image.size();
+        let mut pixels = image::RgbaImage::from_raw(
+            image_size.width.0 as u32,
+            image_size.height.0 as u32,
+            image.as_bytes().to_vec(),
+        )?;
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        Some(gpui::ImageData::new(pixels))
+    }
+
     fn share_project(&mut self, _: &ShareProject, cx: &mut ViewContext<Self>) {
         let active_call = ActiveCall::global(cx);
         let project = self.project.clone();
@@ -574,3 +574,52 @@ where
         self.prompt_err(msg, cx, f).detach();
     }
 }
+
+/// Notifications shown through the system, which stay in its notification center while the
+/// app isn't in focus.
+#[cfg(target_os = "windows")]
+pub mod system_notifications {
+    use collections::HashMap;
+    use gpui::{AppContext, Global, SharedString, SystemNotification};
+
+    type ActivationHandler = Box<dyn FnOnce(Option<usize>, &mut AppContext)>;
+
+    #[derive(Default)]
+    struct ActivationHandlers(HashMap<SharedString, ActivationHandler>);
+
+    impl Global for ActivationHandlers {}
+
+    /// Shows a notification through the system, calling `on_activated` with the index of the
+    /// button the user clicked, or `None` when they clicked the notification itself. Showing
+    /// another notification with the same id replaces both the notification and its handler.
+    pub fn show(
+        notification: SystemNotification,
+        on_activated: impl FnOnce(Option<usize>, &mut AppContext) + 'static,
+        cx: &mut AppContext,
+    ) -> anyhow::Result<()> {
+        let id = notification.id.clone();
+        cx.show_notification(notification)?;
+        cx.default_global::<ActivationHandlers>()
+            .0
+            .insert(id, Box::new(on_activated));
+        Ok(())
+    }
+
+    /// Forgets the handler of a notification, for when what it was about is over.
+    pub fn forget(id: &str, cx: &mut AppContext) {
+        if cx.has_global::<ActivationHandlers>() {
+            cx.global_mut::<ActivationHandlers>().0.remove(id);
+        }
+    }
+
+    /// Routes a click on a notification to the handler it was shown with. Meant to be passed
+    /// to `App::on_notification_activated`.
+    pub fn activated(id: SharedString, action: Option<usize>, cx: &mut AppContext) {
+        if !cx.has_global::<ActivationHandlers>() {
+            return;
+        }
+        if let Some(handler) = cx.global_mut::<ActivationHandlers>().0.remove(&id) {
+            handler(action, cx);
+        }
+    }
+}
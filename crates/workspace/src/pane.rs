@@ -13,11 +13,11 @@ use collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use futures::{stream::FuturesUnordered, StreamExt};
 use gpui::{
     actions, anchored, deferred, impl_actions, prelude::*, Action, AnchorCorner, AnyElement,
-    AppContext, AsyncWindowContext, ClickEvent, DismissEvent, Div, DragMoveEvent, EntityId,
-    EventEmitter, ExternalPaths, FocusHandle, FocusOutEvent, FocusableView, KeyContext, Model,
-    MouseButton, MouseDownEvent, NavigationDirection, Pixels, Point, PromptLevel, Render,
-    ScrollHandle, Subscription, Task, View, ViewContext, VisualContext, WeakFocusHandle, WeakView,
-    WindowContext,
+    AppContext, AsyncWindowContext, Bounds, ClickEvent, DismissEvent, Div, DragMoveEvent, EntityId,
+    EventEmitter, ExternalDragData, ExternalPaths, FocusHandle, FocusOutEvent, FocusableView,
+    KeyContext, Model, MouseButton, MouseDownEvent, NavigationDirection, Pixels, Point,
+    PromptLevel, Render, ScrollHandle, Subscription, Task, View, ViewContext, VisualContext,
+    WeakFocusHandle, WeakView, WindowContext,
 };
 use itertools::Itertools;
 use parking_lot::Mutex;
@@ -1950,6 +1950,48 @@ impl Pane {
         }
     }
 
+    fn drag_tab_out_of_window(
+        &mut self,
+        event: &DragMoveEvent<DraggedTab>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // only Windows can hand drags over to other applications so far
+        let viewport = Bounds::new(Point::default(), cx.viewport_size());
+        if !cfg!(target_os = "windows") || viewport.contains(&event.event.position) {
+            return;
+        }
+        let Some(path) = event
+            .drag(cx)
+            .item
+            .project_path(cx)
+            .and_then(|project_path| {
+                let project = self.project.read(cx);
+                project
+                    .is_local()
+                    .then(|| project.absolute_path(&project_path, cx))
+                    .flatten()
+            })
+        else {
+            return;
+        };
+        let pane = cx.view().downgrade();
+        cx.start_external_drag(
+            ExternalDragData {
+                paths: vec![path],
+                text: None,
+            },
+            move |_, cx| {
+                // the drag left the window, so the split it was over is no longer shown
+                pane.update(cx, |pane, cx| {
+                    pane.drag_split_direction = None;
+                    cx.notify();
+                })
+                .ok();
+            },
+        )
+        .log_err();
+    }
+
     fn handle_tab_drop(
         &mut self,
         dragged_tab: &DraggedTab,
@@ -2223,6 +2265,7 @@ impl Render for Pane {
                     .relative()
                     .group("")
                     .on_drag_move::<DraggedTab>(cx.listener(Self::handle_drag_move))
+                    .on_drag_move::<DraggedTab>(cx.listener(Self::drag_tab_out_of_window))
                     .on_drag_move::<DraggedSelection>(cx.listener(Self::handle_drag_move))
                     .on_drag_move::<ExternalPaths>(cx.listener(Self::handle_drag_move))
                     .map(|div| {
@@ -344,6 +344,19 @@ define_connection! {
     sql!(
         ALTER TABLE workspaces ADD COLUMN local_paths_order BLOB;
     ),
+    // Add font size zoom, remembered per display the window was on
+    sql!(
+        CREATE TABLE display_zoom(
+            workspace_id INTEGER NOT NULL,
+            display BLOB NOT NULL,
+            ui_font_size REAL,
+            buffer_font_size REAL,
+            FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+            PRIMARY KEY(workspace_id, display)
+        ) STRICT;
+    ),
     ];
 }
 
@@ -972,6 +985,21 @@ impl WorkspaceDb {
         }
     }
 
+    query! {
+        pub(crate) async fn display_zoom(workspace_id: WorkspaceId, display: Uuid) -> Result<Option<(Option<f32>, Option<f32>)>> {
+            SELECT ui_font_size, buffer_font_size
+            FROM display_zoom
+            WHERE workspace_id = ? AND display = ?
+        }
+    }
+
+    query! {
+        pub(crate) async fn set_display_zoom(workspace_id: WorkspaceId, display: Uuid, ui_font_size: Option<f32>, buffer_font_size: Option<f32>) -> Result<()> {
+            INSERT OR REPLACE INTO display_zoom(workspace_id, display, ui_font_size, buffer_font_size)
+            VALUES (?1, ?2, ?3, ?4)
+        }
+    }
+
     query! {
         pub(crate) async fn set_centered_layout(workspace_id: WorkspaceId, centered_layout: bool) -> Result<()> {
             UPDATE workspaces
@@ -1192,6 +1220,51 @@ mod tests {
         assert_eq!(workspace, round_trip_workspace.unwrap());
     }
 
+    #[gpui::test]
+    async fn test_display_zoom() {
+        env_logger::try_init().ok();
+
+        let db = WorkspaceDb(open_test_db("test_display_zoom").await);
+
+        let workspace = SerializedWorkspace {
+            id: WorkspaceId(1),
+            location: LocalPaths::new(["/tmp"]).into(),
+            center_group: Default::default(),
+            window_bounds: Default::default(),
+            display: Default::default(),
+            docks: Default::default(),
+            centered_layout: false,
+        };
+        db.save_workspace(workspace).await;
+
+        let display_1 = Uuid::new_v4();
+        let display_2 = Uuid::new_v4();
+        db.set_display_zoom(WorkspaceId(1), display_1, Some(14.), None)
+            .await
+            .unwrap();
+        db.set_display_zoom(WorkspaceId(1), display_2, Some(20.), Some(18.))
+            .await
+            .unwrap();
+        db.set_display_zoom(WorkspaceId(1), display_1, Some(15.), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.display_zoom(WorkspaceId(1), display_1).await.unwrap(),
+            Some((Some(15.), None))
+        );
+        assert_eq!(
+            db.display_zoom(WorkspaceId(1), display_2).await.unwrap(),
+            Some((Some(20.), Some(18.)))
+        );
+        assert_eq!(
+            db.display_zoom(WorkspaceId(1), Uuid::new_v4())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
     #[gpui::test]
     async fn test_workspace_assignment() {
         env_logger::try_init().ok();
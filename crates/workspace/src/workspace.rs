@@ -297,11 +297,14 @@ pub fn init(app_state: Arc<AppState>, cx: &mut AppContext) {
     cx.on_action({
         let app_state = Arc::downgrade(&app_state);
         move |_: &Open, cx: &mut AppContext| {
-            let paths = cx.prompt_for_paths(PathPromptOptions {
-                files: true,
-                directories: true,
-                multiple: true,
-            });
+            let paths = cx.prompt_for_paths(
+                PathPromptOptions {
+                    files: true,
+                    directories: true,
+                    multiple: true,
+                },
+                Vec::new(),
+            );
 
             if let Some(app_state) = app_state.upgrade() {
                 cx.spawn(move |cx| async move {
@@ -609,6 +612,8 @@ pub struct Workspace {
     bounds: Bounds<Pixels>,
     centered_layout: bool,
     bounds_save_task_queued: Option<Task<()>>,
+    // The display the window was last seen on, its font size zoom is remembered separately
+    display: Option<Uuid>,
     on_prompt_for_new_path: Option<PromptForNewPath>,
     render_disconnected_overlay:
         Option<Box<dyn Fn(&mut Self, &mut ViewContext<Self>) -> AnyElement>>,
@@ -814,6 +819,10 @@ impl Workspace {
                                             display_uuid,
                                         ))
                                         .detach_and_log_err(cx);
+                                    if this.display != Some(display_uuid) {
+                                        this.display = Some(display_uuid);
+                                        Self::restore_display_zoom(database_id, display_uuid, cx);
+                                    }
                                 }
                             }
                         }
@@ -853,6 +862,11 @@ impl Workspace {
         cx.defer(|this, cx| {
             this.update_window_title(cx);
         });
+        let display = cx.display().and_then(|display| display.uuid().ok());
+        if let Some((workspace_id, display)) = workspace_id.zip(display) {
+            Self::restore_display_zoom(workspace_id, display, cx);
+        }
+
         Workspace {
             weak_self: weak_handle.clone(),
             zoomed: None,
@@ -889,6 +903,7 @@ impl Workspace {
             bounds: Default::default(),
             centered_layout: false,
             bounds_save_task_queued: None,
+            display,
             on_prompt_for_new_path: None,
             render_disconnected_overlay: None,
         }
@@ -1007,7 +1022,7 @@ impl Workspace {
                     .as_ref()
                     .map(|w| w.centered_layout)
                     .unwrap_or(false);
-                cx.open_window(options, {
+                let window = cx.open_window(options, {
                     let app_state = app_state.clone();
                     let project_handle = project_handle.clone();
                     move |cx| {
@@ -1018,7 +1033,16 @@ impl Workspace {
                             workspace
                         })
                     }
-                })?
+                })?;
+                #[cfg(target_os = "windows")]
+                if let Some((window_bounds, display)) = window_bounds.zip(display) {
+                    window
+                        .update(&mut cx, |_, cx| {
+                            restore_window_placement(window_bounds, display, cx)
+                        })
+                        .log_err();
+                }
+                window
             };
 
             notify_if_database_failed(window, &mut cx);
@@ -1292,7 +1316,7 @@ impl Workspace {
                 .unwrap_or_else(|| Path::new("").into());
 
             let (tx, rx) = oneshot::channel();
-            let abs_path = cx.prompt_for_new_path(&start_abs_path);
+            let abs_path = cx.prompt_for_new_path(&start_abs_path, None, Vec::new());
             cx.spawn(|this, mut cx| async move {
                 let abs_path = abs_path.await?;
                 let project_path = abs_path.and_then(|abs_path| {
@@ -1576,11 +1600,14 @@ impl Workspace {
         self.client()
             .telemetry()
             .report_app_event("open project".to_string());
-        let paths = cx.prompt_for_paths(PathPromptOptions {
-            files: true,
-            directories: true,
-            multiple: true,
-        });
+        let paths = cx.prompt_for_paths(
+            PathPromptOptions {
+                files: true,
+                directories: true,
+                multiple: true,
+            },
+            Vec::new(),
+        );
 
         cx.spawn(|this, mut cx| async move {
             let Some(paths) = paths.await.log_err().flatten() else {
@@ -1740,11 +1767,14 @@ impl Workspace {
             );
             return;
         }
-        let paths = cx.prompt_for_paths(PathPromptOptions {
-            files: false,
-            directories: true,
-            multiple: true,
-        });
+        let paths = cx.prompt_for_paths(
+            PathPromptOptions {
+                files: false,
+                directories: true,
+                multiple: true,
+            },
+            Vec::new(),
+        );
         cx.spawn(|this, mut cx| async move {
             if let Some(paths) = paths.await.log_err().flatten() {
                 let results = this
@@ -3990,6 +4020,56 @@ impl Workspace {
         cx.notify();
     }
 
+    /// Remembers the font size zoom for the display the window is on, so that it's restored
+    /// when the workspace is shown on that display again.
+    pub fn serialize_display_zoom(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(database_id) = self.database_id() else {
+            return;
+        };
+        let Some(display) = cx.display().and_then(|display| display.uuid().ok()) else {
+            return;
+        };
+        self.display = Some(display);
+        let (ui_font_size, buffer_font_size) = theme::font_size_adjustments(cx);
+        cx.background_executor()
+            .spawn(DB.set_display_zoom(
+                database_id,
+                display,
+                ui_font_size.map(|size| size.0),
+                buffer_font_size.map(|size| size.0),
+            ))
+            .detach_and_log_err(cx);
+    }
+
+    /// The font sizes are shared by every window, so the zoom of a display is only restored
+    /// while a single workspace window is open, rather than moving one window resizing the
+    /// text of the others.
+    fn restore_display_zoom(database_id: WorkspaceId, display: Uuid, cx: &mut ViewContext<Self>) {
+        cx.spawn(|_, mut cx| async move {
+            if let Some((ui_font_size, buffer_font_size)) =
+                DB.display_zoom(database_id, display).await?
+            {
+                cx.update(|cx| {
+                    let workspace_windows = cx
+                        .windows()
+                        .into_iter()
+                        .filter(|window| window.downcast::<Workspace>().is_some())
+                        .count();
+                    if workspace_windows > 1 {
+                        return;
+                    }
+                    theme::restore_font_size_adjustments(
+                        cx,
+                        ui_font_size.map(px),
+                        buffer_font_size.map(px),
+                    )
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
     fn adjust_padding(padding: Option<f32>) -> f32 {
         padding
             .unwrap_or(Self::DEFAULT_PADDING)
@@ -3997,6 +4077,25 @@ impl Workspace {
     }
 }
 
+/// Brings a window back to the display it was saved on, which Windows keeps track of across
+/// display changes, like the display being moved or disconnected since.
+#[cfg(target_os = "windows")]
+fn restore_window_placement(window_bounds: WindowBounds, display: Uuid, cx: &mut WindowContext) {
+    let maximized = match window_bounds {
+        WindowBounds::Windowed(_) => false,
+        WindowBounds::Maximized(_) => true,
+        WindowBounds::Fullscreen(_) => return,
+    };
+    cx.set_window_placement(gpui::WindowPlacement {
+        normal_bounds: window_bounds
+            .get_bounds()
+            .to_device_pixels(cx.scale_factor()),
+        maximized,
+        minimized: false,
+        display_uuid: Some(display),
+    });
+}
+
 fn window_bounds_env_override() -> Option<Bounds<Pixels>> {
     ZED_WINDOW_POSITION
         .zip(*ZED_WINDOW_SIZE)
@@ -319,7 +319,18 @@ fn main() {
     #[cfg(not(target_os = "linux"))]
     {
         use zed::only_instance::*;
-        if ensure_only_instance() != IsOnlyInstance::Yes {
+        let urls = Args::parse()
+            .paths_or_urls
+            .iter()
+            .filter_map(|arg| parse_path_arg(arg).ok())
+            .collect();
+        #[cfg(target_os = "windows")]
+        let loopback_available = app.is_loopback_available();
+        #[cfg(not(target_os = "windows"))]
+        let loopback_available = true;
+        if ensure_only_instance(open_listener.clone(), urls, loopback_available)
+            != IsOnlyInstance::Yes
+        {
             println!("zed is already running");
             return;
         }
@@ -364,6 +375,12 @@ fn main() {
     };
 
     app.on_open_urls(with_clone!(open_listener, move |urls| open_listener.open_urls(urls)));
+    #[cfg(target_os = "windows")]
+    app.on_tray_icon_click(zed::tray_icon::show_windows);
+    #[cfg(target_os = "windows")]
+    app.on_default_handlers_change(editor::refresh_default_browser);
+    #[cfg(target_os = "windows")]
+    app.on_notification_activated(workspace::notifications::system_notifications::activated);
     app.on_reopen(move |cx| {
         if let Some(app_state) = AppState::try_global(cx).and_then(|app_state| app_state.upgrade())
         {
@@ -380,7 +397,12 @@ fn main() {
     });
 
     app.run(move |cx| {
+        // DirectWrite can take long to find a font in the system collection
+        #[cfg(target_os = "windows")]
+        cx.enable_background_font_loading();
         release_channel::init(app_version, cx);
+        #[cfg(target_os = "windows")]
+        cx.set_app_user_model_id(release_channel::ReleaseChannel::global(cx).app_id());
         if let Some(build_sha) = option_env!("ZED_COMMIT_SHA") {
             AppCommitSha::set_global(AppCommitSha(build_sha.into()), cx);
         }
@@ -490,6 +512,15 @@ fn main() {
         cx.spawn(move |cx| async move {
             while let Some(urls) = open_rx.next().await {
                 cx.update(|cx| {
+                    // another instance launched with nothing to open asks for a new window
+                    if urls.is_empty() {
+                        workspace::open_new(app_state.clone(), cx, |workspace, cx| {
+                            Editor::new_file(workspace, &Default::default(), cx);
+                            cx.request_window_activation();
+                        })
+                        .detach();
+                        return;
+                    }
                     if let Some(request) = OpenRequest::parse(urls, cx).log_err() {
                         handle_open_request(request, app_state.clone(), cx);
                     }
@@ -518,13 +549,17 @@ fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut
     if !request.open_paths.is_empty() {
         let app_state = app_state.clone();
         task = Some(cx.spawn(|mut cx| async move {
-            let (_window, results) = open_paths_with_positions(
+            let (window, results) = open_paths_with_positions(
                 &request.open_paths,
                 app_state,
                 workspace::OpenOptions::default(),
                 &mut cx,
             )
             .await?;
+            // the request may come from another process, like a second instance
+            window
+                .update(&mut cx, |_, cx| cx.request_window_activation())
+                .log_err();
             for result in results.into_iter().flatten() {
                 if let Err(err) = result {
                     log::error!("Error opening path: {err}",);
@@ -881,12 +916,21 @@ struct Args {
 }
 
 fn parse_url_arg(arg: &str, cx: &AppContext) -> Result<String> {
+    parse_path_arg(arg).or_else(|error| {
+        if arg.starts_with("zed-cli://") || parse_zed_link(&arg, cx).is_some() {
+            Ok(arg.into())
+        } else {
+            Err(error)
+        }
+    })
+}
+
+/// Returns the `file://` URL of a path argument, which doesn't need the app to be running.
+fn parse_path_arg(arg: &str) -> Result<String> {
     match std::fs::canonicalize(Path::new(&arg)) {
         Ok(path) => Ok(format!("file://{}", path.to_string_lossy())),
         Err(error) => {
-            if arg.starts_with("file://") || arg.starts_with("zed-cli://") {
-                Ok(arg.into())
-            } else if let Some(_) = parse_zed_link(&arg, cx) {
+            if arg.starts_with("file://") {
                 Ok(arg.into())
             } else {
                 Err(anyhow!("error parsing path argument: {}", error))
@@ -1,10 +1,14 @@
 mod app_menus;
 pub mod inline_completion_registry;
+#[cfg(target_os = "windows")]
+mod jump_list;
 #[cfg(target_os = "linux")]
 pub(crate) mod linux_prompts;
 #[cfg(not(target_os = "linux"))]
 pub(crate) mod only_instance;
 mod open_listener;
+#[cfg(target_os = "windows")]
+pub(crate) mod tray_icon;
 
 pub use app_menus::*;
 use breadcrumbs::Breadcrumbs;
@@ -12,14 +16,17 @@ use client::ZED_URL_SCHEME;
 use collections::VecDeque;
 use editor::{scroll::Autoscroll, Editor, MultiBuffer};
 use gpui::{
-    actions, point, px, AppContext, AsyncAppContext, Context, FocusableView, MenuItem, PromptLevel,
-    ReadGlobal, TitlebarOptions, View, ViewContext, VisualContext, WindowKind, WindowOptions,
+    actions, point, px, AppContext, AsyncAppContext, Context, FocusableView, FontPalette,
+    FontPaletteSelection, KeyBindingResolution, Keystroke, KeystrokeResolution, LineSpacing,
+    LineSpacingMethod, MenuItem, PromptLevel, ReadGlobal, TitlebarOptions, View, ViewContext,
+    VisualContext, WindowKind, WindowOptions,
 };
 pub use open_listener::*;
 
 use anyhow::Context as _;
 use assets::Assets;
 use futures::{channel::mpsc, select_biased, StreamExt};
+use language::language_settings::language_settings;
 use outline_panel::OutlinePanel;
 use project::TaskSourceKind;
 use project_panel::ProjectPanel;
@@ -28,12 +35,12 @@ use release_channel::{AppCommitSha, ReleaseChannel};
 use rope::Rope;
 use search::project_search::ProjectSearchBar;
 use settings::{
-    initial_local_settings_content, initial_tasks_content, watch_config_file, KeymapFile, Settings,
-    SettingsStore, DEFAULT_KEYMAP_PATH,
+    initial_local_settings_content, initial_tasks_content, watch_config_file, KeymapFile,
+    KeymapLayoutFallback, Settings, SettingsStore, DEFAULT_KEYMAP_PATH,
 };
 use std::{borrow::Cow, ops::Deref, path::Path, sync::Arc};
 use task::static_source::{StaticSource, TrackedFile};
-use theme::ActiveTheme;
+use theme::{ActiveTheme, ThemeSettings};
 use workspace::notifications::NotificationId;
 
 use paths::{local_settings_file_relative_path, local_tasks_file_relative_path};
@@ -58,12 +65,14 @@ actions!(
         Minimize,
         OpenDefaultKeymap,
         OpenDefaultSettings,
+        OpenKeystrokeDiagnostics,
         OpenLocalSettings,
         OpenLocalTasks,
         OpenTasks,
         ResetDatabase,
         ShowAll,
         ToggleFullScreen,
+        ToggleKeystrokeDiagnostics,
         Zoom,
         TestPanic,
     ]
@@ -77,10 +86,64 @@ pub fn init(cx: &mut AppContext) {
     #[cfg(target_os = "macos")]
     cx.on_action(|_: &ShowAll, cx| cx.unhide_other_apps());
     cx.on_action(quit);
+    cx.on_action(|_: &ToggleKeystrokeDiagnostics, cx| {
+        cx.set_keystroke_diagnostics_enabled(!cx.keystroke_diagnostics_enabled())
+    });
 
     if ReleaseChannel::global(cx) == ReleaseChannel::Dev {
         cx.on_action(test_panic);
     }
+
+    #[cfg(target_os = "windows")]
+    jump_list::init(cx);
+    #[cfg(target_os = "windows")]
+    tray_icon::init(cx);
+
+    // Hard tabs that reach text layout (rather than being expanded by the editor)
+    // line up with the default tab size.
+    sync_tab_size(cx);
+    cx.observe_global::<SettingsStore>(sync_tab_size).detach();
+    sync_line_spacing(cx);
+    cx.observe_global::<SettingsStore>(sync_line_spacing)
+        .detach();
+    sync_font_palette(cx);
+    cx.observe_global::<SettingsStore>(sync_font_palette)
+        .detach();
+}
+
+fn sync_tab_size(cx: &mut AppContext) {
+    let tab_size = language_settings(None, None, cx).tab_size;
+    cx.text_system().set_tab_size(tab_size);
+}
+
+// Lay buffer lines out as tall as editor rows, so the ascent and descent the text system
+// reports agree with where the editor places the baseline.
+fn sync_line_spacing(cx: &mut AppContext) {
+    let settings = ThemeSettings::get_global(cx);
+    let line_height = settings.line_height();
+    let buffer_font_family = settings.buffer_font.family.clone();
+    cx.text_system().set_line_spacing(
+        buffer_font_family.as_ref(),
+        LineSpacing {
+            method: LineSpacingMethod::Uniform(line_height),
+            trim_line_gap: false,
+        },
+    );
+}
+
+// Paint color fonts with the palette meant for the background of the active theme.
+fn sync_font_palette(cx: &mut AppContext) {
+    let theme = cx.theme();
+    let selection = if theme.appearance().is_light() {
+        FontPaletteSelection::LightBackground
+    } else {
+        FontPaletteSelection::DarkBackground
+    };
+    let foreground = theme.colors().text;
+    cx.text_system().set_font_palette(FontPalette {
+        selection,
+        foreground: Some(foreground),
+    });
 }
 
 pub fn build_window_options(display_uuid: Option<Uuid>, cx: &mut AppContext) -> WindowOptions {
@@ -112,6 +175,22 @@ pub fn build_window_options(display_uuid: Option<Uuid>, cx: &mut AppContext) ->
     }
 }
 
+/// Printable ASCII, which covers letters, digits and common punctuation.
+const PREWARMED_CHARACTERS: &str = concat!(
+    "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`",
+    "abcdefghijklmnopqrstuvwxyz{|}~",
+);
+
+/// Draws the window frame in the theme's appearance, which can differ from the system's.
+#[cfg(target_os = "windows")]
+fn sync_frame_appearance(cx: &mut gpui::WindowContext) {
+    let appearance = match cx.theme().appearance() {
+        theme::Appearance::Light => gpui::WindowAppearance::Light,
+        theme::Appearance::Dark => gpui::WindowAppearance::Dark,
+    };
+    cx.set_frame_appearance(Some(appearance));
+}
+
 pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
     cx.observe_new_views(move |workspace: &mut Workspace, cx| {
         let workspace_handle = cx.view().clone();
@@ -154,8 +233,23 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
             status_bar.add_right_item(cursor_position, cx);
         });
 
+        // Rasterize the characters that fill most of a code buffer up front, so the first
+        // editor that opens doesn't pay for it glyph by glyph.
+        let theme_settings = ThemeSettings::get_global(cx);
+        let buffer_font = theme_settings.buffer_font.clone();
+        let buffer_font_size = theme_settings.buffer_font_size(cx);
+        cx.prewarm_glyphs(&buffer_font, buffer_font_size, PREWARMED_CHARACTERS)
+            .detach();
+
         auto_update::notify_of_any_new_update(cx);
 
+        #[cfg(target_os = "windows")]
+        {
+            sync_frame_appearance(cx);
+            cx.observe_global::<SettingsStore>(|_, cx| sync_frame_appearance(cx))
+                .detach();
+        }
+
         let handle = cx.view().downgrade();
         cx.on_window_should_close(move |cx| {
             handle
@@ -249,32 +343,41 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 OpenListener::global(cx).open_urls(vec![action.url.clone()])
             })
             .register_action(|_, action: &OpenBrowser, cx| cx.open_url(&action.url))
-            .register_action(move |_, _: &zed_actions::IncreaseBufferFontSize, cx| {
-                theme::adjust_buffer_font_size(cx, |size| *size += px(1.0))
+            .register_action(move |workspace, _: &zed_actions::IncreaseBufferFontSize, cx| {
+                theme::adjust_buffer_font_size(cx, |size| *size += px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::DecreaseBufferFontSize, cx| {
-                theme::adjust_buffer_font_size(cx, |size| *size -= px(1.0))
+            .register_action(move |workspace, _: &zed_actions::DecreaseBufferFontSize, cx| {
+                theme::adjust_buffer_font_size(cx, |size| *size -= px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::ResetBufferFontSize, cx| {
-                theme::reset_buffer_font_size(cx)
+            .register_action(move |workspace, _: &zed_actions::ResetBufferFontSize, cx| {
+                theme::reset_buffer_font_size(cx);
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::IncreaseUiFontSize, cx| {
-                theme::adjust_ui_font_size(cx, |size| *size += px(1.0))
+            .register_action(move |workspace, _: &zed_actions::IncreaseUiFontSize, cx| {
+                theme::adjust_ui_font_size(cx, |size| *size += px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::DecreaseUiFontSize, cx| {
-                theme::adjust_ui_font_size(cx, |size| *size -= px(1.0))
+            .register_action(move |workspace, _: &zed_actions::DecreaseUiFontSize, cx| {
+                theme::adjust_ui_font_size(cx, |size| *size -= px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::ResetUiFontSize, cx| {
-                theme::reset_ui_font_size(cx)
+            .register_action(move |workspace, _: &zed_actions::ResetUiFontSize, cx| {
+                theme::reset_ui_font_size(cx);
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::IncreaseBufferFontSize, cx| {
-                theme::adjust_buffer_font_size(cx, |size| *size += px(1.0))
+            .register_action(move |workspace, _: &zed_actions::IncreaseBufferFontSize, cx| {
+                theme::adjust_buffer_font_size(cx, |size| *size += px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::DecreaseBufferFontSize, cx| {
-                theme::adjust_buffer_font_size(cx, |size| *size -= px(1.0))
+            .register_action(move |workspace, _: &zed_actions::DecreaseBufferFontSize, cx| {
+                theme::adjust_buffer_font_size(cx, |size| *size -= px(1.0));
+                workspace.serialize_display_zoom(cx);
             })
-            .register_action(move |_, _: &zed_actions::ResetBufferFontSize, cx| {
-                theme::reset_buffer_font_size(cx)
+            .register_action(move |workspace, _: &zed_actions::ResetBufferFontSize, cx| {
+                theme::reset_buffer_font_size(cx);
+                workspace.serialize_display_zoom(cx);
             })
             .register_action(|_, _: &install_cli::Install, cx| {
                 cx.spawn(|workspace, mut cx| async move {
@@ -340,6 +443,16 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
             .register_action(|workspace, _: &OpenLog, cx| {
                 open_log_file(workspace, cx);
             })
+            .register_action(|workspace, _: &OpenKeystrokeDiagnostics, cx| {
+                let text = keystroke_diagnostics_text(cx);
+                open_bundled_file(
+                    workspace,
+                    text.into(),
+                    "Keystroke Diagnostics",
+                    "Plain Text",
+                    cx,
+                );
+            })
             .register_action(|workspace, _: &zed_actions::OpenLicenses, cx| {
                 open_bundled_file(
                     workspace,
@@ -671,17 +784,32 @@ pub fn handle_keymap_file_changes(
 ) {
     BaseKeymap::register(cx);
     VimModeSetting::register(cx);
+    KeymapLayoutFallback::register(cx);
 
     let (base_keymap_tx, mut base_keymap_rx) = mpsc::unbounded();
+    // bindings to physical keys are resolved against the layout when they're loaded
+    cx.observe_keyboard_layout({
+        let base_keymap_tx = base_keymap_tx.clone();
+        move |_| {
+            base_keymap_tx.unbounded_send(()).ok();
+        }
+    })
+    .detach();
     let mut old_base_keymap = *BaseKeymap::get_global(cx);
     let mut old_vim_enabled = VimModeSetting::get_global(cx).0;
+    let mut old_layout_fallback = *KeymapLayoutFallback::get_global(cx);
     cx.observe_global::<SettingsStore>(move |cx| {
         let new_base_keymap = *BaseKeymap::get_global(cx);
         let new_vim_enabled = VimModeSetting::get_global(cx).0;
+        let new_layout_fallback = *KeymapLayoutFallback::get_global(cx);
 
-        if new_base_keymap != old_base_keymap || new_vim_enabled != old_vim_enabled {
+        if new_base_keymap != old_base_keymap
+            || new_vim_enabled != old_vim_enabled
+            || new_layout_fallback != old_layout_fallback
+        {
             old_base_keymap = new_base_keymap;
             old_vim_enabled = new_vim_enabled;
+            old_layout_fallback = new_layout_fallback;
             base_keymap_tx.unbounded_send(()).unwrap();
         }
     })
@@ -713,7 +841,11 @@ pub fn handle_keymap_file_changes(
 fn reload_keymaps(cx: &mut AppContext, keymap_content: &KeymapFile) {
     cx.clear_key_bindings();
     load_default_keymap(cx);
-    keymap_content.clone().add_to_cx(cx).log_err();
+    let fallback = KeymapLayoutFallback::get_global(cx).0;
+    keymap_content
+        .clone()
+        .add_to_cx_with_fallback(fallback, cx)
+        .log_err();
     cx.set_menus(app_menus());
     cx.set_dock_menu(vec![MenuItem::action("New Window", workspace::NewWindow)])
 }
@@ -724,13 +856,14 @@ pub fn load_default_keymap(cx: &mut AppContext) {
         return;
     }
 
-    KeymapFile::load_asset(DEFAULT_KEYMAP_PATH, cx).unwrap();
+    let fallback = KeymapLayoutFallback::get_global(cx).0;
+    KeymapFile::load_asset_with_fallback(DEFAULT_KEYMAP_PATH, fallback, cx).unwrap();
     if VimModeSetting::get_global(cx).0 {
-        KeymapFile::load_asset("keymaps/vim.json", cx).unwrap();
+        KeymapFile::load_asset_with_fallback("keymaps/vim.json", fallback, cx).unwrap();
     }
 
     if let Some(asset_path) = base_keymap.asset_path() {
-        KeymapFile::load_asset(asset_path, cx).unwrap();
+        KeymapFile::load_asset_with_fallback(asset_path, fallback, cx).unwrap();
     }
 }
 
@@ -885,6 +1018,111 @@ fn open_telemetry_log_file(workspace: &mut Workspace, cx: &mut ViewContext<Works
     }).detach();
 }
 
+fn keystroke_diagnostics_text(cx: &AppContext) -> String {
+    use std::fmt::Write as _;
+
+    if !cx.keystroke_diagnostics_enabled() {
+        let mut text = concat!(
+            "Keystroke diagnostics are disabled.\n",
+            "Run `zed: toggle keystroke diagnostics` to record how keystrokes are dispatched.\n",
+        )
+        .to_string();
+        write_unresolved_key_bindings(&mut text, cx);
+        return text;
+    }
+
+    let mut text = String::new();
+    for diagnostic in cx.keystroke_diagnostics() {
+        writeln!(text, "{}", diagnostic.keystroke).ok();
+        let context_stack = diagnostic
+            .context_stack
+            .iter()
+            .map(|context| format!("{:?}", context))
+            .collect::<Vec<_>>();
+        writeln!(text, "  context: {}", context_stack.join(" > ")).ok();
+        if !diagnostic.bindings.is_empty() {
+            let bindings = diagnostic
+                .bindings
+                .iter()
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>();
+            writeln!(text, "  bindings: {}", bindings.join(", ")).ok();
+        }
+        let resolution = match diagnostic.resolution {
+            KeystrokeResolution::Pending => "pending multi-stroke binding".to_string(),
+            KeystrokeResolution::Action(name) => format!("action {}", name),
+            KeystrokeResolution::KeyListener(Some(context)) => {
+                format!("key listener in {:?}", context)
+            }
+            KeystrokeResolution::KeyListener(None) => "key listener".to_string(),
+            KeystrokeResolution::Unhandled => "nothing, left to text input".to_string(),
+        };
+        writeln!(text, "  handled by: {}", resolution).ok();
+        for note in diagnostic.notes {
+            writeln!(text, "  note: {}", note).ok();
+        }
+    }
+    if text.is_empty() {
+        text.push_str("No keystrokes have been recorded yet.\n");
+    }
+    write_unresolved_key_bindings(&mut text, cx);
+    text
+}
+
+fn write_unresolved_key_bindings(text: &mut String, cx: &AppContext) {
+    use std::fmt::Write as _;
+
+    let unresolved_bindings = cx.unresolved_key_bindings();
+    if !unresolved_bindings.is_empty() {
+        writeln!(text, "\nBindings affected by the current keyboard layout:").ok();
+    }
+    for (binding, resolution) in unresolved_bindings {
+        let keystrokes = binding
+            .keystrokes()
+            .iter()
+            .map(|keystroke| keystroke.to_string())
+            .collect::<Vec<_>>();
+        writeln!(
+            text,
+            "{} ({})",
+            keystrokes.join(" "),
+            binding.action().name()
+        )
+        .ok();
+        match resolution {
+            KeyBindingResolution::Resolvable => {}
+            KeyBindingResolution::RequiresModifier {
+                keystroke,
+                modifiers,
+            } => {
+                let keys = Keystroke {
+                    modifiers,
+                    key: keystroke.key.clone(),
+                    ime_key: None,
+                };
+                writeln!(text, "  {} is typed as {}", keystroke.key, keys).ok();
+            }
+            KeyBindingResolution::Unavailable {
+                keystroke,
+                suggestion: Some(suggestion),
+            } => {
+                writeln!(
+                    text,
+                    "  {} is not on this layout, suggested: {}",
+                    keystroke, suggestion
+                )
+                .ok();
+            }
+            KeyBindingResolution::Unavailable {
+                keystroke,
+                suggestion: None,
+            } => {
+                writeln!(text, "  {} is not on this layout", keystroke).ok();
+            }
+        }
+    }
+}
+
 fn open_bundled_file(
     workspace: &mut Workspace,
     text: Cow<'static, str>,
@@ -958,7 +1196,7 @@ mod tests {
     use serde_json::json;
     use settings::{handle_settings_file_changes, watch_config_file, SettingsStore};
     use std::path::{Path, PathBuf};
-    use theme::{ThemeRegistry, ThemeSettings};
+    use theme::ThemeRegistry;
     use workspace::{
         item::{Item, ItemHandle},
         open_new, open_paths, pane, NewFile, OpenVisible, SaveIntent, SplitDirection,
@@ -0,0 +1,68 @@
+use gpui::{AppContext, JumpList, JumpListItem};
+use workspace::{SerializedWorkspaceLocation, Workspace, WORKSPACE_DB};
+
+/// Keeps the jump list of the taskbar button listing the projects opened recently, so they
+/// can be reopened directly from it. Its entries launch Zed with the project's paths, which a
+/// second instance forwards to the running one.
+pub fn init(cx: &mut AppContext) {
+    update_jump_list(cx);
+    cx.observe_new_views(|_: &mut Workspace, cx| {
+        update_jump_list(cx);
+        // a workspace is only saved to the database after it opens
+        cx.on_release(|_, _, cx| update_jump_list(cx)).detach();
+    })
+    .detach();
+}
+
+fn update_jump_list(cx: &mut AppContext) {
+    cx.spawn(|cx| async move {
+        let workspaces = WORKSPACE_DB.recent_workspaces_on_disk().await?;
+        let recent = workspaces
+            .into_iter()
+            .filter_map(|(_, location)| match location {
+                SerializedWorkspaceLocation::Local(paths, order) => Some(
+                    order
+                        .order()
+                        .iter()
+                        .filter_map(|i| paths.paths().get(*i).cloned())
+                        .collect::<Vec<_>>(),
+                ),
+                SerializedWorkspaceLocation::DevServer(_) => None,
+            })
+            .map(|paths| JumpListItem {
+                title: paths
+                    .iter()
+                    .map(|path| {
+                        path.file_name()
+                            .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    .into(),
+                description: Some(
+                    paths
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                        .into(),
+                ),
+                arguments: paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+            })
+            .collect();
+        cx.update(|cx| {
+            cx.set_jump_list(JumpList {
+                tasks: vec![JumpListItem {
+                    title: "New Window".into(),
+                    description: None,
+                    arguments: Vec::new(),
+                }],
+                recent,
+            })
+        })
+    })
+    .detach_and_log_err(cx);
+}
@@ -1,16 +1,25 @@
 use std::{
     io::{Read, Write},
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
     thread,
     time::Duration,
 };
 
 use release_channel::ReleaseChannel;
 
+use crate::OpenListener;
+
 const LOCALHOST: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
 const CONNECT_TIMEOUT: Duration = Duration::from_millis(10);
 const RECEIVE_TIMEOUT: Duration = Duration::from_millis(35);
 const SEND_TIMEOUT: Duration = Duration::from_millis(20);
+/// How long the running instance waits for an open request once it sent the handshake, which
+/// leaves time for a second instance that was slowed down by a busy system.
+const OPEN_REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+/// Open requests start with this line and end with `OPEN_REQUEST_END`, so that a connection
+/// closed after reading the handshake isn't taken for a request to open nothing.
+const OPEN_REQUEST_START: &str = "open";
+const OPEN_REQUEST_END: &str = "end";
 
 fn address() -> SocketAddr {
     let port = match *release_channel::RELEASE_CHANNEL {
@@ -32,18 +41,43 @@ fn instance_handshake() -> &'static str {
     }
 }
 
+/// Windows only lets the foreground process bring a window to the front, which this process
+/// is when the user just launched it, so it hands that right over to the running instance.
+fn allow_running_instance_to_activate() {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows::Win32::UI::WindowsAndMessaging::{AllowSetForegroundWindow, ASFW_ANY};
+        AllowSetForegroundWindow(ASFW_ANY).ok();
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IsOnlyInstance {
     Yes,
     No,
 }
 
-pub fn ensure_only_instance() -> IsOnlyInstance {
+/// Makes sure only one instance of Zed runs. On Windows, where files aren't handed to the running
+/// instance by the system, the `file://` URLs this one was launched with are sent to it in an
+/// open request, and it opens them. A request without URLs asks it for a new window.
+///
+/// The instances talk over a loopback port, so without loopback access, like in a sandbox,
+/// every instance runs on its own.
+pub fn ensure_only_instance(
+    opener: OpenListener,
+    urls: Vec<String>,
+    loopback_available: bool,
+) -> IsOnlyInstance {
     if *db::ZED_STATELESS || *release_channel::RELEASE_CHANNEL == ReleaseChannel::Dev {
         return IsOnlyInstance::Yes;
     }
+    if !loopback_available {
+        log::warn!("Loopback connections are unavailable, skipping the single instance check");
+        return IsOnlyInstance::Yes;
+    }
 
-    if check_got_handshake() {
+    if check_got_handshake(&urls) {
+        allow_running_instance_to_activate();
         return IsOnlyInstance::No;
     }
 
@@ -52,7 +86,8 @@ pub fn ensure_only_instance() -> IsOnlyInstance {
 
         Err(err) => {
             log::warn!("Error binding to single instance port: {err}");
-            if check_got_handshake() {
+            if check_got_handshake(&urls) {
+                allow_running_instance_to_activate();
                 return IsOnlyInstance::No;
             }
 
@@ -74,13 +109,26 @@ pub fn ensure_only_instance() -> IsOnlyInstance {
             _ = stream.set_nodelay(true);
             _ = stream.set_read_timeout(Some(SEND_TIMEOUT));
             _ = stream.write_all(instance_handshake().as_bytes());
+
+            if cfg!(target_os = "windows") {
+                // connections that only check for the handshake time out without closing, and
+                // a request is complete once it ends, whether or not the stream was closed
+                let mut request = String::new();
+                _ = stream.set_read_timeout(Some(OPEN_REQUEST_TIMEOUT));
+                _ = stream.read_to_string(&mut request);
+                if let Some(urls) = parse_open_request(&request) {
+                    opener.open_urls(urls);
+                } else if !request.is_empty() {
+                    log::warn!("Ignoring malformed open request");
+                }
+            }
         }
     });
 
     IsOnlyInstance::Yes
 }
 
-fn check_got_handshake() -> bool {
+fn check_got_handshake(urls: &[String]) -> bool {
     match TcpStream::connect_timeout(&address(), CONNECT_TIMEOUT) {
         Ok(mut stream) => {
             let mut buf = vec![0u8; instance_handshake().len()];
@@ -93,6 +141,9 @@ fn check_got_handshake() -> bool {
 
             if buf == instance_handshake().as_bytes() {
                 log::info!("Got instance handshake");
+                if cfg!(target_os = "windows") {
+                    send_open_request(&mut stream, urls);
+                }
                 return true;
             }
 
@@ -103,3 +154,67 @@ fn check_got_handshake() -> bool {
         Err(_) => false,
     }
 }
+
+fn open_request(urls: &[String]) -> String {
+    let mut request = format!("{OPEN_REQUEST_START}\n");
+    for url in urls {
+        request.push_str(url);
+        request.push('\n');
+    }
+    request.push_str(OPEN_REQUEST_END);
+    request.push('\n');
+    request
+}
+
+/// Returns the URLs of a complete open request. Any process can connect to the port, so
+/// requests are only allowed to open files.
+fn parse_open_request(request: &str) -> Option<Vec<String>> {
+    let mut lines = request.lines();
+    if lines.next()? != OPEN_REQUEST_START {
+        return None;
+    }
+    let mut urls = Vec::new();
+    for line in lines.by_ref() {
+        if line == OPEN_REQUEST_END {
+            return lines.next().is_none().then_some(urls);
+        }
+        if !line.starts_with("file://") {
+            return None;
+        }
+        urls.push(line.to_owned());
+    }
+    None
+}
+
+fn send_open_request(stream: &mut TcpStream, urls: &[String]) {
+    _ = stream.set_write_timeout(Some(SEND_TIMEOUT));
+    if let Err(err) = stream
+        .write_all(open_request(urls).as_bytes())
+        .and_then(|_| stream.shutdown(Shutdown::Write))
+    {
+        log::warn!("Failed to send open request to the running instance: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open_request, parse_open_request};
+
+    #[test]
+    fn test_open_request() {
+        let urls = vec![
+            "file:///C:/Users/me/a%20file.rs".to_string(),
+            "file:///C:/Users/me/project".to_string(),
+        ];
+        assert_eq!(parse_open_request(&open_request(&urls)), Some(urls));
+        // a new window is only opened when asked for
+        assert_eq!(parse_open_request(&open_request(&[])), Some(Vec::new()));
+        assert_eq!(parse_open_request(""), None);
+
+        // requests cut short, or with more than files, are ignored
+        assert_eq!(parse_open_request("open\nfile:///C:/Users/me/a.rs\n"), None);
+        assert_eq!(parse_open_request("open\nzed://extension/x\nend\n"), None);
+        assert_eq!(parse_open_request("open\nend\nfile:///C:/a.rs\n"), None);
+        assert_eq!(parse_open_request("file:///C:/a.rs\nend\n"), None);
+    }
+}
@@ -0,0 +1,74 @@
+use editor::Editor;
+use gpui::{actions, AppContext, MenuItem};
+use release_channel::ReleaseChannel;
+use settings::{Settings, SettingsStore};
+use util::ResultExt;
+use workspace::{open_new, AppState, NewWindow, WorkspaceSettings};
+use zed_actions::Quit;
+
+use crate::zed::Hide;
+
+actions!(tray_icon, [ShowWindows]);
+
+/// Shows an icon for Zed in the notification area while `show_tray_icon` is on. Zed then keeps
+/// running there after its last window is closed, and clicking the icon brings a window back.
+pub fn init(cx: &mut AppContext) {
+    cx.on_action(|_: &ShowWindows, cx| show_windows(cx));
+    cx.on_action(|_: &Hide, cx| {
+        // without the icon, there would be no way to show the windows again
+        if WorkspaceSettings::get_global(cx).show_tray_icon {
+            cx.hide();
+        }
+    });
+    // the menu of the icon is used while no window is open to handle this
+    cx.on_action(|_: &NewWindow, cx| open_new_window(cx));
+
+    let mut shown = false;
+    let mut update_tray_icon = move |cx: &mut AppContext| {
+        let show = WorkspaceSettings::get_global(cx).show_tray_icon;
+        if show == shown {
+            return;
+        }
+        shown = show;
+        if show {
+            cx.set_tray_icon(
+                ReleaseChannel::global(cx).display_name(),
+                vec![
+                    MenuItem::action("Show Windows", ShowWindows),
+                    MenuItem::action("Hide Windows", Hide),
+                    MenuItem::separator(),
+                    MenuItem::action("New Window", NewWindow),
+                    MenuItem::separator(),
+                    MenuItem::action("Quit", Quit),
+                ],
+            );
+        } else {
+            cx.remove_tray_icon();
+        }
+    };
+    update_tray_icon(cx);
+    cx.observe_global::<SettingsStore>(update_tray_icon)
+        .detach();
+}
+
+/// Shows the windows hidden in the notification area and brings one to the front, or opens a
+/// new one when they were all closed.
+pub fn show_windows(cx: &mut AppContext) {
+    let Some(window) = cx.windows().into_iter().next() else {
+        open_new_window(cx);
+        return;
+    };
+    cx.activate(true);
+    window
+        .update(cx, |_, cx| cx.request_window_activation())
+        .log_err();
+}
+
+fn open_new_window(cx: &mut AppContext) {
+    if let Some(app_state) = AppState::try_global(cx).and_then(|app_state| app_state.upgrade()) {
+        open_new(app_state, cx, |workspace, cx| {
+            Editor::new_file(workspace, &Default::default(), cx)
+        })
+        .detach();
+    }
+}